@@ -0,0 +1,47 @@
+'use strict';
+
+// Stamps the circuit's public `tag` constants into an exported verification key so the
+// backend can refuse to start with keys that belong to a different circuit.
+//
+//   snarkjs zkey export verificationkey private_deposit_final.zkey verification_key.json
+//   node scripts/stamp_vk_tags.js private_deposit.circom verification_key.json
+//
+// Tags are read from the `var VK_* = 0x...;` declarations of the entry circuit and the files
+// it includes, and written as decimal strings under `zylith_tags`.
+
+const fs = require('fs');
+const path = require('path');
+
+const VK_DECL = /var\s+VK_\w+\s*=\s*(0x[0-9a-fA-F]+)\s*;/g;
+const INCLUDE = /include\s+"(\.{1,2}\/[^"]+)"\s*;/g;
+
+function collectTags(file, seen = new Set()) {
+  const resolved = path.resolve(file);
+  if (seen.has(resolved)) return [];
+  seen.add(resolved);
+  const source = fs.readFileSync(resolved, 'utf8');
+  const tags = [...source.matchAll(VK_DECL)].map((match) => BigInt(match[1]));
+  for (const match of source.matchAll(INCLUDE)) {
+    tags.push(...collectTags(path.join(path.dirname(resolved), match[1]), seen));
+  }
+  return tags;
+}
+
+function main() {
+  const [circuit, vkPath] = process.argv.slice(2);
+  if (!circuit || !vkPath) {
+    console.error('usage: stamp_vk_tags.js <circuit.circom> <verification_key.json>');
+    process.exit(2);
+  }
+  const tags = [...new Set(collectTags(circuit))].map((tag) => tag.toString());
+  if (tags.length === 0) {
+    console.error(`${circuit} declares no VK_* tag constants`);
+    process.exit(1);
+  }
+  const vk = JSON.parse(fs.readFileSync(vkPath, 'utf8'));
+  vk.zylith_tags = tags;
+  fs.writeFileSync(vkPath, `${JSON.stringify(vk, null, 1)}\n`);
+  console.log(`${vkPath}: zylith_tags = [${tags.join(', ')}]`);
+}
+
+main();
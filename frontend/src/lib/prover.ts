@@ -128,6 +128,14 @@ export type PoolConfigResponse = {
   max_input_notes: number;
 };
 
+export type PoolStateResponse = {
+  sqrt_price: string;
+  tick: number;
+  liquidity: string;
+  fee_growth_global_0: string;
+  fee_growth_global_1: string;
+};
+
 export type SwapQuoteRequest = {
   amount: string;
   zero_for_one: boolean;
@@ -136,6 +144,8 @@ export type SwapQuoteRequest = {
 };
 
 export type SwapQuoteResponse = {
+  token_in: string;
+  token_out: string;
   amount_in: string;
   amount_out: string;
   sqrt_price_end: string;
@@ -157,6 +167,22 @@ export type LiquidityQuoteResponse = {
   sqrt_ratio_upper: string;
 };
 
+export type LiquidityRemoveQuoteRequest = {
+  position_note: PositionNoteInput;
+  liquidity_delta: string;
+};
+
+export type LiquidityRemoveQuoteResponse = {
+  amount0: string;
+  amount1: string;
+  protocol_fee_0: string;
+  protocol_fee_1: string;
+  fee_amount0: string;
+  fee_amount1: string;
+  out_amount0: string;
+  out_amount1: string;
+};
+
 const PROVER_URL =
   import.meta.env.VITE_PROVER_URL ?? "http://127.0.0.1:8081";
 const PROVER_API_KEY = import.meta.env.VITE_PROVER_API_KEY;
@@ -179,24 +205,37 @@ async function postJson<T>(path: string, body: unknown, signal?: AbortSignal): P
     signal,
   });
   if (!response.ok) {
-    const payload = await response.json().catch(() => ({}));
-    const message =
-      typeof payload.error === "string" ? payload.error : response.statusText;
-    throw new Error(message);
+    throw await proverError(response);
   }
   return response.json() as Promise<T>;
 }
 
+// `code` is the backend's stable error code (e.g. "note_token_mismatch"); branch on it rather
+// than on the message.
+export class ProverError extends Error {
+  constructor(
+    message: string,
+    readonly code?: string,
+  ) {
+    super(message);
+  }
+}
+
+async function proverError(response: Response): Promise<ProverError> {
+  const payload = await response.json().catch(() => ({}));
+  const message =
+    typeof payload.error === "string" ? payload.error : response.statusText;
+  const code = typeof payload.code === "string" ? payload.code : undefined;
+  return new ProverError(message, code);
+}
+
 async function getJson<T>(path: string): Promise<T> {
   const response = await fetch(`${PROVER_URL}${path}`, {
     method: "GET",
     headers: buildHeaders(),
   });
   if (!response.ok) {
-    const payload = await response.json().catch(() => ({}));
-    const message =
-      typeof payload.error === "string" ? payload.error : response.statusText;
-    throw new Error(message);
+    throw await proverError(response);
   }
   return response.json() as Promise<T>;
 }
@@ -222,8 +261,16 @@ export const proveDeposit = (payload: DepositProofRequest) =>
 export const fetchPoolConfig = () =>
   getJson<PoolConfigResponse>("/pool/config");
 
+export const fetchPoolState = () =>
+  getJson<PoolStateResponse>("/pool/state");
+
 export const quoteSwap = (payload: SwapQuoteRequest, signal?: AbortSignal) =>
   postJson<SwapQuoteResponse>("/quote/swap", payload, signal);
 
 export const quoteLiquidityAdd = (payload: LiquidityQuoteRequest, signal?: AbortSignal) =>
   postJson<LiquidityQuoteResponse>("/quote/liquidity/add", payload, signal);
+
+export const quoteLiquidityRemove = (
+  payload: LiquidityRemoveQuoteRequest,
+  signal?: AbortSignal,
+) => postJson<LiquidityRemoveQuoteResponse>("/quote/liquidity/remove", payload, signal);
@@ -2,6 +2,7 @@
 
 use std::collections::{HashMap, VecDeque};
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use tokio::sync::{mpsc, RwLock};
@@ -11,11 +12,12 @@ use starknet::core::types::{BlockId, EmittedEvent, EventFilter, FieldElement as
 use starknet::core::utils::get_selector_from_name;
 use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClient};
 use starknet::providers::Provider;
+use zylith_events::{CommitmentEvent, CommitmentKind, Word};
 
-use crate::merkle::{MerkleError, MerkleTree};
+use crate::merkle::{MerkleError, MerkleTree, ROOT_CACHE_LIMIT};
 use crate::storage::{CommitmentRecord, RootRecord, Storage, StorageError};
+use crate::subscription::{subscribe_new_heads, HeadEvent, IndexerMode};
 
-pub const ROOT_CACHE_LIMIT: usize = 256;
 const MAX_ROOT_LOOKUP_STEPS: u64 = 512;
 
 #[derive(Debug, Clone)]
@@ -24,6 +26,40 @@ pub struct RootCacheEntry {
     pub leaf_count: u64,
 }
 
+// What the indexer last saw, shared with the health endpoint. Both fields stay 0 until the
+// first observation.
+#[derive(Debug, Default)]
+pub struct SyncStatus {
+    chain_head: AtomicU64,
+    // Unix seconds when `sync_once` last finished without error.
+    synced_at: AtomicU64,
+}
+
+impl SyncStatus {
+    pub fn chain_head(&self) -> Option<u64> {
+        Some(self.chain_head.load(Ordering::Relaxed)).filter(|head| *head != 0)
+    }
+
+    pub fn seconds_since_sync(&self, now: u64) -> Option<u64> {
+        let synced_at = self.synced_at.load(Ordering::Relaxed);
+        (synced_at != 0).then(|| now.saturating_sub(synced_at))
+    }
+
+    fn record_head(&self, head: u64) {
+        self.chain_head.store(head, Ordering::Relaxed);
+    }
+
+    fn record_sync(&self) {
+        self.synced_at.store(unix_now(), Ordering::Relaxed);
+    }
+}
+
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
 #[derive(Debug)]
 pub enum IndexerCommand {
     Resync,
@@ -41,6 +77,8 @@ pub struct IndexerService {
     interval: Interval,
     command_rx: mpsc::Receiver<IndexerCommand>,
     known_tokens: Vec<String>,
+    mode: IndexerMode,
+    status: Arc<SyncStatus>,
 }
 
 impl IndexerService {
@@ -56,6 +94,8 @@ impl IndexerService {
         finality_depth: u64,
         command_rx: mpsc::Receiver<IndexerCommand>,
         known_tokens: Vec<String>,
+        mode: IndexerMode,
+        status: Arc<SyncStatus>,
     ) -> Self {
         Self {
             provider,
@@ -69,24 +109,41 @@ impl IndexerService {
             interval: tokio::time::interval(Duration::from_secs(12)),
             command_rx,
             known_tokens,
+            mode,
+            status,
         }
     }
 
     pub async fn run(mut self) {
+        let mut heads = match &self.mode {
+            IndexerMode::Poll => None,
+            IndexerMode::Subscribe(ws_url) => {
+                let (tx, rx) = mpsc::channel(16);
+                tokio::spawn(subscribe_new_heads(ws_url.clone(), tx));
+                Some(rx)
+            }
+        };
+        // Polling pauses while a subscription is live and resumes whenever it drops.
+        let mut subscribed = false;
         loop {
             tokio::select! {
                 _ = self.interval.tick() => {
-                    if let Err(err) = self.sync_once().await {
-                        match err {
-                            StorageError::Desync(reason) | StorageError::Invariant(reason) => {
-                                println!("[asp] desync detected: {reason}");
-                                if let Err(err) = self.resync().await {
-                                    println!("[asp] resync error: {:?}", err);
-                                }
-                            }
-                            other => {
-                                println!("[asp] sync error: {:?}", other);
-                            }
+                    if !subscribed {
+                        self.sync_or_resync().await;
+                    }
+                }
+                head = next_head(&mut heads) => {
+                    match head {
+                        Some(HeadEvent::Connected) => {
+                            println!("[asp] head subscription active");
+                            subscribed = true;
+                            self.sync_or_resync().await;
+                        }
+                        Some(HeadEvent::NewHead(_)) => self.sync_or_resync().await,
+                        Some(HeadEvent::Disconnected) => subscribed = false,
+                        None => {
+                            heads = None;
+                            subscribed = false;
                         }
                     }
                 }
@@ -105,6 +162,23 @@ impl IndexerService {
         }
     }
 
+    async fn sync_or_resync(&self) {
+        match self.sync_once().await {
+            Ok(()) => self.status.record_sync(),
+            Err(err) => match err {
+                StorageError::Desync(reason) | StorageError::Invariant(reason) => {
+                    println!("[asp] desync detected: {reason}");
+                    if let Err(err) = self.resync().await {
+                        println!("[asp] resync error: {:?}", err);
+                    }
+                }
+                other => {
+                    println!("[asp] sync error: {:?}", other);
+                }
+            },
+        }
+    }
+
     async fn resync(&self) -> Result<(), StorageError> {
         println!("[asp] resync requested");
         self.storage.reset().await?;
@@ -115,6 +189,7 @@ impl IndexerService {
         drop(roots_cache);
         drop(trees);
         self.sync_once().await?;
+        self.status.record_sync();
         Ok(())
     }
 
@@ -126,6 +201,7 @@ impl IndexerService {
             .block_number()
             .await
             .map_err(|err| StorageError::Db(format!("starknet block_number error: {err}")))?;
+        self.status.record_head(latest);
         let safe_latest = latest.saturating_sub(finality_depth);
         if let Some(block) = last_block {
             if block > safe_latest {
@@ -196,27 +272,32 @@ impl IndexerService {
     }
 
     async fn process_event(&self, emitted: EmittedEvent) -> Result<(), StorageError> {
-        let block_number = emitted.block_number.unwrap_or_default();
         if emitted.keys.is_empty() {
             return Ok(());
         }
+        // Only events from the pending block lack a block number. The sync range ends at a
+        // finalized block, so such an event is fetched again with its block once included;
+        // indexing it now would record it at block 0, below every finality check.
+        let Some(block_number) = emitted.block_number else {
+            println!("[asp] deferring event without a block number");
+            return Ok(());
+        };
 
-        let deposit_key = selector("Deposit");
         let root_key = selector("RootUpdated");
-        let position_commitment_key = selector("PositionCommitmentInserted");
         let position_root_key = selector("PositionRootUpdated");
         let nullifier_key = selector("NullifierUsed");
         let nullifier_marked_key = selector("NullifierMarked");
         let key = emitted.keys[0];
 
-        if key == deposit_key {
-            self.handle_deposit(&emitted.data, block_number).await?;
+        if let Some(kind) = CommitmentKind::ALL
+            .into_iter()
+            .find(|kind| selector(kind.event_name()) == key)
+        {
+            let event = decode_commitment_event(kind, &emitted)?;
+            self.handle_commitment(event, block_number).await?;
         } else if key == root_key {
             self.handle_root_updated(&emitted.data, block_number)
                 .await?;
-        } else if key == position_commitment_key {
-            self.handle_position_commitment(&emitted.data, block_number)
-                .await?;
         } else if key == position_root_key {
             self.handle_position_root_updated(&emitted.data, block_number)
                 .await?;
@@ -227,17 +308,19 @@ impl IndexerService {
         Ok(())
     }
 
-    async fn handle_deposit(&self, data: &[Felt], block_number: u64) -> Result<(), StorageError> {
-        if data.len() < 4 {
-            return Ok(());
-        }
-        let commitment = data[0];
-        let leaf_index = felt_to_u64(&data[1])?;
-        let token = data[2];
-        let timestamp = felt_to_u64(&data[3])?;
-
-        let token_hex = felt_to_hex(&token);
+    async fn handle_commitment(
+        &self,
+        event: CommitmentEvent,
+        block_number: u64,
+    ) -> Result<(), StorageError> {
+        let (label, token_hex) = match event.token {
+            Some(token) => ("deposit", felt_to_hex(&word_to_felt(token)?)),
+            None => ("position commitment", "position".to_string()),
+        };
+        let leaf_index = event.leaf_index;
+        let commitment = word_to_felt(event.commitment)?;
         let commitment_hex = felt_to_hex(&commitment);
+        let timestamp = event.timestamp;
         let record = CommitmentRecord {
             token: token_hex.clone(),
             leaf_index,
@@ -254,23 +337,22 @@ impl IndexerService {
             Ok((_, root)) => root,
             Err(MerkleError::LeafMismatch { .. }) => {
                 return Err(StorageError::Invariant(format!(
-                    "merkle leaf mismatch for deposit {}",
-                    commitment_hex
+                    "merkle leaf mismatch for {label} {commitment_hex}"
                 )));
             }
             Err(MerkleError::IndexGap { expected, got }) => {
                 return Err(StorageError::Invariant(format!(
-                    "merkle index gap for deposit expected {expected} got {got}"
+                    "merkle index gap for {label} expected {expected} got {got}"
                 )));
             }
             Err(MerkleError::TreeFull { max }) => {
                 return Err(StorageError::Invariant(format!(
-                    "merkle tree full for deposit max_leaves={max}"
+                    "merkle tree full for {label} max_leaves={max}"
                 )));
             }
             Err(MerkleError::InvalidLeaf { reason }) => {
                 return Err(StorageError::Invariant(format!(
-                    "merkle invalid leaf for deposit reason={reason}"
+                    "merkle invalid leaf for {label} reason={reason}"
                 )));
             }
         };
@@ -300,7 +382,7 @@ impl IndexerService {
         }
         if inserted {
             println!(
-                "[asp] deposit token={} index={} commitment={}",
+                "[asp] {label} token={} index={} commitment={}",
                 token_hex, leaf_index, commitment_hex
             );
         }
@@ -395,88 +477,6 @@ impl IndexerService {
         Ok(())
     }
 
-    async fn handle_position_commitment(
-        &self,
-        data: &[Felt],
-        block_number: u64,
-    ) -> Result<(), StorageError> {
-        if data.len() < 3 {
-            return Ok(());
-        }
-        let commitment = data[0];
-        let leaf_index = felt_to_u64(&data[1])?;
-        let timestamp = felt_to_u64(&data[2])?;
-        let token_hex = "position".to_string();
-        let commitment_hex = felt_to_hex(&commitment);
-        let record = CommitmentRecord {
-            token: token_hex.clone(),
-            leaf_index,
-            commitment: commitment_hex.clone(),
-            timestamp,
-            block_number,
-        };
-        let inserted = self.storage.insert_commitment(record).await?;
-        let mut trees = self.trees.write().await;
-        let tree = trees
-            .entry(token_hex.clone())
-            .or_insert_with(|| MerkleTree::new(self.tree_height));
-        let new_root = match tree.insert_at(leaf_index, commitment) {
-            Ok((_, root)) => root,
-            Err(MerkleError::LeafMismatch { .. }) => {
-                return Err(StorageError::Invariant(format!(
-                    "merkle leaf mismatch for position commitment {}",
-                    commitment_hex
-                )));
-            }
-            Err(MerkleError::IndexGap { expected, got }) => {
-                return Err(StorageError::Invariant(format!(
-                    "merkle index gap for position commitment expected {expected} got {got}"
-                )));
-            }
-            Err(MerkleError::TreeFull { max }) => {
-                return Err(StorageError::Invariant(format!(
-                    "merkle tree full for position commitment max_leaves={max}"
-                )));
-            }
-            Err(MerkleError::InvalidLeaf { reason }) => {
-                return Err(StorageError::Invariant(format!(
-                    "merkle invalid leaf for position commitment reason={reason}"
-                )));
-            }
-        };
-        let leaf_count = tree.next_index();
-        let new_root_hex = felt_to_hex(&new_root);
-        drop(trees);
-        self.storage
-            .update_root_leaf_count(&token_hex, &new_root_hex, leaf_count)
-            .await?;
-        self.update_root_cache_leaf_count(&token_hex, &new_root_hex, leaf_count)
-            .await;
-        if let Some(entry) = self
-            .roots_cache
-            .read()
-            .await
-            .get(&token_hex)
-            .and_then(|entries| entries.iter().find(|entry| entry.leaf_count == leaf_count))
-        {
-            if entry.root_hash != felt_to_hex(&new_root) {
-                return Err(StorageError::Desync(format!(
-                    "root mismatch token={} expected={} onchain={}",
-                    token_hex,
-                    felt_to_hex(&new_root),
-                    entry.root_hash
-                )));
-            }
-        }
-        if inserted {
-            println!(
-                "[asp] position commitment index={} commitment={}",
-                leaf_index, commitment_hex
-            );
-        }
-        Ok(())
-    }
-
     async fn handle_position_root_updated(
         &self,
         data: &[Felt],
@@ -609,6 +609,9 @@ fn resolve_leaf_count_for_root(tree: &MerkleTree, root: Felt, last_flushed: u64)
     if tree.root() == root {
         return Some(current);
     }
+    if let Some(leaf_count) = tree.leaf_count_for_root(root) {
+        return (leaf_count > last_flushed).then_some(leaf_count);
+    }
     if current <= last_flushed {
         return None;
     }
@@ -628,6 +631,13 @@ fn resolve_leaf_count_for_root(tree: &MerkleTree, root: Felt, last_flushed: u64)
     None
 }
 
+async fn next_head(heads: &mut Option<mpsc::Receiver<HeadEvent>>) -> Option<HeadEvent> {
+    match heads {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
 fn selector(name: &str) -> Felt {
     get_selector_from_name(name).expect("selector")
 }
@@ -636,6 +646,22 @@ fn felt_to_hex(value: &Felt) -> String {
     format!("0x{:x}", value)
 }
 
+// Commitment insertions share their decoder with the client through `zylith-events`, which
+// works on raw 32-byte big-endian words.
+fn decode_commitment_event(
+    kind: CommitmentKind,
+    emitted: &EmittedEvent,
+) -> Result<CommitmentEvent, StorageError> {
+    let data: Vec<Word> = emitted.data.iter().map(Felt::to_bytes_be).collect();
+    CommitmentEvent::decode(kind, &data, emitted.block_number)
+        .map_err(|err| StorageError::Invariant(err.to_string()))
+}
+
+fn word_to_felt(value: Word) -> Result<Felt, StorageError> {
+    Felt::from_bytes_be(&value)
+        .map_err(|_| StorageError::Invariant("felt out of range".to_string()))
+}
+
 fn felt_to_u64(value: &Felt) -> Result<u64, StorageError> {
     let bytes = value.to_bytes_be();
     if bytes[..24].iter().any(|b| *b != 0) {
@@ -4,6 +4,7 @@ mod events;
 mod generated_constants;
 mod merkle;
 mod storage;
+mod subscription;
 
 use std::collections::{HashMap, VecDeque};
 use std::env;
@@ -28,19 +29,23 @@ use starknet::providers::Provider;
 use url::Url;
 
 use crate::events::{
-    IndexerCommand, IndexerService, RateLimiter, RootCacheEntry, ROOT_CACHE_LIMIT,
+    unix_now, IndexerCommand, IndexerService, RateLimiter, RootCacheEntry, SyncStatus,
 };
-use crate::merkle::MerkleTree;
+use crate::merkle::{MerkleTree, ROOT_CACHE_LIMIT};
 use crate::storage::Storage;
+use crate::subscription::IndexerMode;
 
 #[derive(Clone)]
 struct AppState {
     storage: Arc<Storage>,
+    // The database the indexer writes to; `storage` may be a replica that trails it.
+    primary_storage: Arc<Storage>,
     trees: Arc<RwLock<HashMap<String, MerkleTree>>>,
     resync_tx: mpsc::Sender<IndexerCommand>,
     rate_limiter: Arc<RateLimiter>,
-    finality_depth: u64,
+    finality_depth: FinalityDepth,
     sync_token: Option<String>,
+    sync_status: Arc<SyncStatus>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -49,9 +54,101 @@ struct Config {
     shielded_notes_address: String,
     start_block: u64,
     postgres_url: String,
+    // Optional read-only replica serving the query endpoints; the indexer always writes to
+    // `postgres_url`.
+    postgres_read_url: Option<String>,
+    #[serde(default = "default_postgres_max_connections")]
+    postgres_max_connections: u32,
+    #[serde(default = "default_postgres_acquire_timeout_secs")]
+    postgres_acquire_timeout_secs: u64,
     server_port: u16,
-    finality_depth: u64,
+    finality_depth: FinalityDepth,
     sync_token: Option<String>,
+    #[serde(default = "default_indexer_mode")]
+    indexer_mode: String,
+    starknet_ws_url: Option<String>,
+}
+
+fn default_indexer_mode() -> String {
+    "poll".to_string()
+}
+
+fn default_postgres_max_connections() -> u32 {
+    storage::DEFAULT_MAX_CONNECTIONS
+}
+
+fn default_postgres_acquire_timeout_secs() -> u64 {
+    storage::DEFAULT_ACQUIRE_TIMEOUT.as_secs()
+}
+
+// `finality_depth = 5`, or a per-token table such as `finality_depth = { default = 5, position = 0 }`
+// where tokens not listed fall back to `default`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "FinalityDepthConfig")]
+struct FinalityDepth {
+    default: u64,
+    per_token: HashMap<String, u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FinalityDepthConfig {
+    Global(u64),
+    PerToken(HashMap<String, u64>),
+}
+
+impl TryFrom<FinalityDepthConfig> for FinalityDepth {
+    type Error = String;
+
+    fn try_from(value: FinalityDepthConfig) -> Result<Self, Self::Error> {
+        match value {
+            FinalityDepthConfig::Global(depth) => Ok(Self::uniform(depth)),
+            FinalityDepthConfig::PerToken(mut map) => {
+                let default = map
+                    .remove("default")
+                    .ok_or_else(|| "finality_depth table must set default".to_string())?;
+                let per_token = map
+                    .into_iter()
+                    .map(|(token, depth)| {
+                        normalize_token_key(&token)
+                            .map(|token| (token, depth))
+                            .map_err(|_| format!("invalid finality_depth token: {token}"))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Self { default, per_token })
+            }
+        }
+    }
+}
+
+impl FinalityDepth {
+    fn uniform(depth: u64) -> Self {
+        Self {
+            default: depth,
+            per_token: HashMap::new(),
+        }
+    }
+
+    fn for_token(&self, token: &str) -> u64 {
+        self.per_token.get(token).copied().unwrap_or(self.default)
+    }
+
+    // The indexer has to reach the shallowest configured depth for every token to be servable;
+    // deeper tokens are held back per request by `finality_block`.
+    fn shallowest(&self) -> u64 {
+        self.per_token
+            .values()
+            .copied()
+            .fold(self.default, u64::min)
+    }
+
+    // For requests that cannot name a token: nothing is served before every tree would serve it.
+    fn deepest(&self) -> u64 {
+        self.per_token
+            .values()
+            .copied()
+            .fold(self.default, u64::max)
+    }
 }
 
 #[derive(Serialize)]
@@ -59,6 +156,16 @@ struct HealthResponse {
     status: &'static str,
 }
 
+#[derive(Serialize)]
+struct DetailedHealthResponse {
+    status: &'static str,
+    last_indexed_block: Option<u64>,
+    chain_head_block: Option<u64>,
+    lag_blocks: Option<u64>,
+    seconds_since_sync: Option<u64>,
+    synced: bool,
+}
+
 #[derive(Serialize)]
 struct RootsResponse {
     roots: Vec<RootEntry>,
@@ -75,6 +182,12 @@ struct RootQuery {
     token: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct NullifierQuery {
+    // The spent note's token, or `position`; selects that tree's finality depth.
+    token: Option<String>,
+}
+
 #[derive(Serialize)]
 struct RootAtResponse {
     token: String,
@@ -98,6 +211,32 @@ struct PathResponse {
     indices: Vec<bool>,
 }
 
+#[derive(Deserialize)]
+struct PathsRequest {
+    commitments: Vec<String>,
+    root_index: Option<u64>,
+    root_hash: Option<String>,
+}
+
+// `status` is "ok" with a path, or why this commitment has none: "not_found", "token_mismatch",
+// "root_not_found", "invalid_root" or "not_in_root".
+#[derive(Serialize)]
+struct PathsEntry {
+    commitment: String,
+    status: &'static str,
+    path: Option<PathResponse>,
+}
+
+impl PathsEntry {
+    fn failed(commitment: String, status: &'static str) -> Self {
+        Self {
+            commitment,
+            status,
+            path: None,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct InsertPathRequest {
     token: String,
@@ -126,12 +265,35 @@ struct CommitmentIndexResponse {
     commitment: String,
 }
 
+#[derive(Deserialize)]
+struct CommitmentRangeQuery {
+    from: u64,
+    to: u64,
+}
+
+#[derive(Serialize)]
+struct CommitmentRangeEntry {
+    leaf_index: u64,
+    commitment: String,
+}
+
+#[derive(Serialize)]
+struct NullifierResponse {
+    spent: bool,
+    block_number: Option<u64>,
+    // Indexed, but not yet past the finality depth. A reorg could still undo the spend, yet a
+    // client about to spend the note should treat it as spent.
+    pending: bool,
+}
+
 #[derive(Serialize)]
 struct SyncResponse {
     status: &'static str,
 }
 
 const MAX_ROOT_LOOKUP_STEPS: u64 = 512;
+const MAX_PATHS_PER_REQUEST: usize = 256;
+const MAX_COMMITMENTS_PER_RANGE: u64 = 1000;
 
 #[tokio::main]
 async fn main() {
@@ -145,9 +307,27 @@ async fn run() -> Result<(), Box<dyn Error>> {
     let config_path = std::env::var("ZYLITH_ASP_CONFIG")
         .map(PathBuf::from)
         .unwrap_or_else(|_| PathBuf::from("config.toml"));
+    if env::args().skip(1).any(|arg| arg == "--check-config") {
+        return check_config(&config_path).map_err(|err| to_io_error(err).into());
+    }
     let config = load_config(&config_path).map_err(to_io_error)?;
 
-    let storage = Arc::new(Storage::new(&config.postgres_url).await?);
+    let acquire_timeout = std::time::Duration::from_secs(config.postgres_acquire_timeout_secs);
+    let storage = Arc::new(
+        Storage::new_with_options(
+            &config.postgres_url,
+            config.postgres_max_connections,
+            acquire_timeout,
+        )
+        .await?,
+    );
+    let read_storage = match config.postgres_read_url.as_deref() {
+        Some(url) => Arc::new(
+            Storage::new_read_replica(url, config.postgres_max_connections, acquire_timeout)
+                .await?,
+        ),
+        None => storage.clone(),
+    };
     let tree_height = MerkleTree::default_height();
     let trees = Arc::new(RwLock::new(storage.load_trees(tree_height).await?));
 
@@ -188,6 +368,7 @@ async fn run() -> Result<(), Box<dyn Error>> {
         ensure_known_trees(&mut trees, tree_height, &known_tokens);
     }
 
+    let sync_status = Arc::new(SyncStatus::default());
     let indexer = IndexerService::new(
         provider,
         storage.clone(),
@@ -196,34 +377,44 @@ async fn run() -> Result<(), Box<dyn Error>> {
         shielded_notes,
         config.start_block,
         tree_height,
-        config.finality_depth,
+        config.finality_depth.shallowest(),
         resync_rx,
         known_tokens,
+        parse_indexer_mode(&config.indexer_mode, config.starknet_ws_url.as_deref())
+            .map_err(to_io_error)?,
+        sync_status.clone(),
     );
     tokio::spawn(async move { indexer.run().await });
 
     let rate_limiter = Arc::new(RateLimiter::new(100, std::time::Duration::from_secs(60)));
     let state = AppState {
-        storage,
+        storage: read_storage,
+        primary_storage: storage,
         trees,
         resync_tx,
         rate_limiter,
-        finality_depth: config.finality_depth,
+        finality_depth: config.finality_depth.clone(),
         sync_token: config.sync_token.clone(),
+        sync_status,
     };
 
     let app = Router::new()
         .route("/health", get(health))
+        .route("/health/detailed", get(health_detailed))
         .route("/root", get(get_root))
         .route("/root/latest", get(get_root_latest))
         .route("/root/:index", get(get_root_at))
+        .route("/root/hash/:hash", get(get_root_by_hash))
         .route("/path", post(get_path))
+        .route("/paths", post(get_paths))
         .route("/insert_path", post(get_insert_path))
         .route("/commitment/:hash", get(get_commitment))
         .route(
             "/commitment_by_index/:token/:index",
             get(get_commitment_by_index),
         )
+        .route("/commitments/:token", get(get_commitments_range))
+        .route("/nullifier/:hash", get(get_nullifier))
         .route("/sync", get(trigger_sync))
         .with_state(state.clone())
         .layer(middleware::from_fn_with_state(state, rate_limit));
@@ -244,12 +435,69 @@ async fn health() -> Json<HealthResponse> {
     Json(HealthResponse { status: "ok" })
 }
 
+// Five missed 12s polls. The head is only refreshed by a sync, so once syncs stop succeeding
+// the reported lag can no longer be trusted.
+const SYNC_STALE_AFTER_SECS: u64 = 60;
+
+// Readiness for load balancers: 503 while the indexer trails the chain head by more than
+// `finality_depth` blocks, before it has completed a sync, or once its last sync is stale.
+// The indexed block comes from the primary, since this reports on the indexer itself.
+async fn health_detailed(
+    State(state): State<AppState>,
+) -> Result<(StatusCode, Json<DetailedHealthResponse>), StatusCode> {
+    let last_indexed_block = state
+        .primary_storage
+        .get_last_block()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let response = indexer_health(
+        last_indexed_block,
+        state.sync_status.chain_head(),
+        state.sync_status.seconds_since_sync(unix_now()),
+        state.finality_depth.shallowest(),
+    );
+    let status = if response.synced {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok((status, Json(response)))
+}
+
+fn indexer_health(
+    last_indexed_block: Option<u64>,
+    chain_head_block: Option<u64>,
+    seconds_since_sync: Option<u64>,
+    finality_depth: u64,
+) -> DetailedHealthResponse {
+    let lag_blocks = last_indexed_block
+        .zip(chain_head_block)
+        .map(|(last, head)| head.saturating_sub(last));
+    let stale = !seconds_since_sync.is_some_and(|secs| secs <= SYNC_STALE_AFTER_SECS);
+    let synced = !stale && lag_blocks.is_some_and(|lag| lag <= finality_depth);
+    let status = match (synced, stale) {
+        (true, _) => "ok",
+        (false, true) => "stale",
+        (false, false) => "lagging",
+    };
+    DetailedHealthResponse {
+        status,
+        last_indexed_block,
+        chain_head_block,
+        lag_blocks,
+        seconds_since_sync,
+        synced,
+    }
+}
+
 async fn get_root(State(state): State<AppState>) -> Result<Json<RootsResponse>, StatusCode> {
-    let max_block = finality_block(&state).await?;
+    let last_block = last_indexed_block(&state).await?;
     let trees = state.trees.read().await;
     let mut roots = Vec::new();
     for (token, tree) in trees.iter() {
-        let root_hash = if state.finality_depth == 0 {
+        let finality_depth = state.finality_depth.for_token(token);
+        let max_block = last_block.saturating_sub(finality_depth);
+        let root_hash = if finality_depth == 0 {
             match state
                 .storage
                 .get_latest_root(token)
@@ -290,8 +538,9 @@ async fn get_root_at(
     State(state): State<AppState>,
 ) -> Result<Json<RootAtResponse>, StatusCode> {
     let token = normalize_token_key(&query.token.ok_or(StatusCode::BAD_REQUEST)?)?;
-    let max_block = finality_block(&state).await?;
-    let (root_hash, root_leaf_count) = if state.finality_depth == 0 {
+    let finality_depth = state.finality_depth.for_token(&token);
+    let max_block = finality_block(&state, &token).await?;
+    let (root_hash, root_leaf_count) = if finality_depth == 0 {
         let root = state
             .storage
             .get_root_at(&token, index)
@@ -320,7 +569,7 @@ async fn get_root_at(
             &token,
             &root_hash,
             max_block,
-            state.finality_depth != 0,
+            finality_depth != 0,
         )
         .await?
         .ok_or(StatusCode::NOT_FOUND)?
@@ -343,82 +592,151 @@ async fn get_root_latest(
     let trees = state.trees.read().await;
     let tree = trees.get(&token).ok_or(StatusCode::NOT_FOUND)?;
     let tree_snapshot = tree.clone();
-    let mut leaf_count = tree_snapshot.next_index();
-    let root = tree_snapshot.root();
-    let root_hex = felt_to_hex(&root);
     drop(trees);
-    let max_block = finality_block(&state).await?;
-    let mut root_index = 0u64;
-    if state.finality_depth == 0 {
-        if let Some((latest_index, latest_hash, latest_leaf_count)) = state
+    let root = tree_snapshot.root();
+    let finality_depth = state.finality_depth.for_token(&token);
+    let max_block = finality_block(&state, &token).await?;
+    let latest = if finality_depth == 0 {
+        state
             .storage
             .get_latest_root(&token)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        {
-            let expected_root = parse_felt(&latest_hash)?;
-            if expected_root == root {
-                root_index = latest_index;
-                if latest_leaf_count != 0 {
-                    leaf_count = latest_leaf_count;
-                } else if leaf_count == 0 && root_index != 0 {
-                    if let Some(resolved) = resolve_leaf_count_for_root_hash(
-                        state.storage.as_ref(),
-                        &tree_snapshot,
-                        &token,
-                        &latest_hash,
-                        max_block,
-                        false,
-                    )
-                    .await?
-                    {
-                        leaf_count = resolved;
-                    }
-                }
-                if leaf_count == 0 && root_index != 0 {
-                    root_index = 0;
-                }
-            }
+    } else {
+        state
+            .storage
+            .get_latest_root_before(&token, max_block)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .map(|(root_index, root_hash, leaf_count, _block)| (root_index, root_hash, leaf_count))
+    };
+    let latest_felt = match &latest {
+        Some((root_index, root_hash, leaf_count)) => {
+            Some((*root_index, parse_felt(root_hash)?, *leaf_count))
         }
-    } else if let Some((latest_index, latest_hash, latest_leaf_count, _block)) = state
-        .storage
-        .get_latest_root_before(&token, max_block)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    {
-        let expected_root = parse_felt(&latest_hash)?;
-        if expected_root == root {
-            root_index = latest_index;
-            if latest_leaf_count != 0 {
-                leaf_count = latest_leaf_count;
-            } else if leaf_count == 0 && root_index != 0 {
-                if let Some(resolved) = resolve_leaf_count_for_root_hash(
-                    state.storage.as_ref(),
-                    &tree_snapshot,
-                    &token,
-                    &latest_hash,
-                    max_block,
-                    true,
-                )
-                .await?
-                {
-                    leaf_count = resolved;
-                }
-            }
-            if leaf_count == 0 && root_index != 0 {
-                root_index = 0;
-            }
+        None => None,
+    };
+    let resolution = RootResolution::new(root, tree_snapshot.next_index(), latest_felt);
+    let resolved_leaf_count = match &latest {
+        Some((_, root_hash, _)) if resolution.needs_leaf_count() => {
+            resolve_leaf_count_for_root_hash(
+                state.storage.as_ref(),
+                &tree_snapshot,
+                &token,
+                root_hash,
+                max_block,
+                finality_depth != 0,
+            )
+            .await?
         }
-    }
+        _ => None,
+    };
+    let resolution = resolution.finish(resolved_leaf_count);
 
+    Ok(Json(RootAtResponse {
+        token,
+        root_index: resolution.root_index,
+        root: felt_to_hex(&root),
+        leaf_count: resolution.leaf_count,
+    }))
+}
+
+// Index of a known root, so a client holding only the hash need not scan `/root/:index`. Roots
+// newer than the token's finality depth are reported as not found.
+async fn get_root_by_hash(
+    Path(hash): Path<String>,
+    Query(query): Query<RootQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<RootAtResponse>, StatusCode> {
+    let token = normalize_token_key(&query.token.ok_or(StatusCode::BAD_REQUEST)?)?;
+    let root_hash = felt_to_hex(&parse_felt(&hash)?);
+    let finality_depth = state.finality_depth.for_token(&token);
+    let max_block = finality_block(&state, &token).await?;
+    let root_index = if finality_depth == 0 {
+        state
+            .storage
+            .get_root_by_hash(&token, &root_hash)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?
+            .0
+    } else {
+        let root = state
+            .storage
+            .get_root_by_hash_with_block(&token, &root_hash)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+        if root.2 > max_block {
+            return Err(StatusCode::NOT_FOUND);
+        }
+        root.0
+    };
+    let trees = state.trees.read().await;
+    let tree = trees.get(&token).ok_or(StatusCode::NOT_FOUND)?;
+    let leaf_count = resolve_leaf_count_for_root_hash(
+        state.storage.as_ref(),
+        tree,
+        &token,
+        &root_hash,
+        max_block,
+        finality_depth != 0,
+    )
+    .await?
+    .ok_or(StatusCode::NOT_FOUND)?;
     Ok(Json(RootAtResponse {
         token,
         root_index,
-        root: root_hex,
+        root: root_hash,
         leaf_count,
     }))
 }
 
+// What /root/latest reports for a tree, given the newest stored (finalized) root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RootResolution {
+    root_index: u64,
+    leaf_count: u64,
+}
+
+impl RootResolution {
+    // `latest` is (root_index, root, stored leaf_count); a stored leaf_count of 0 means the row
+    // predates leaf-count tracking. A stored root that differs from the in-memory tree (not yet
+    // flushed, or newer than finality allows) cannot be cited, so the tree is reported as-is.
+    fn new(tree_root: Felt, tree_leaf_count: u64, latest: Option<(u64, Felt, u64)>) -> Self {
+        match latest {
+            Some((root_index, root, stored_leaf_count)) if root == tree_root => Self {
+                root_index,
+                leaf_count: if stored_leaf_count != 0 {
+                    stored_leaf_count
+                } else {
+                    tree_leaf_count
+                },
+            },
+            _ => Self {
+                root_index: 0,
+                leaf_count: tree_leaf_count,
+            },
+        }
+    }
+
+    fn needs_leaf_count(&self) -> bool {
+        self.leaf_count == 0 && self.root_index != 0
+    }
+
+    // A non-zero root index must come with its leaf count; when none can be resolved fall back
+    // to the genesis root index rather than pairing an index with an empty tree.
+    fn finish(mut self, resolved_leaf_count: Option<u64>) -> Self {
+        if self.needs_leaf_count() {
+            match resolved_leaf_count {
+                Some(leaf_count) if leaf_count != 0 => self.leaf_count = leaf_count,
+                _ => self.root_index = 0,
+            }
+        }
+        self
+    }
+}
+
 async fn get_path(
     State(state): State<AppState>,
     Json(payload): Json<PathRequest>,
@@ -426,7 +744,6 @@ async fn get_path(
     if payload.root_index.is_some() && payload.root_hash.is_some() {
         return Err(StatusCode::BAD_REQUEST);
     }
-    let mut max_block = finality_block(&state).await?;
     let commitment = payload.commitment;
     let found = state
         .storage
@@ -435,16 +752,131 @@ async fn get_path(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .ok_or(StatusCode::NOT_FOUND)?;
     let (token, leaf_index, commitment_block) = found;
-    if commitment_block > max_block {
-        max_block = commitment_block;
-    }
+    let max_block = finality_block(&state, &token).await?.max(commitment_block);
     let trees = state.trees.read().await;
     let tree = trees.get(&token).ok_or(StatusCode::NOT_FOUND)?;
-    let (expected_root, leaf_count) = if let Some(root_index) = payload.root_index {
-        if state.finality_depth == 0 {
+    let leaf_count = resolve_path_leaf_count(
+        &state,
+        tree,
+        &token,
+        payload.root_index,
+        payload.root_hash.as_deref(),
+        max_block,
+    )
+    .await?;
+    let leaf = parse_felt(&commitment)?;
+    path_response(tree, token, leaf, leaf_index, leaf_count).map(Json)
+}
+
+// Every commitment is proven against one root: the requested root when given (all commitments
+// must then share a token), otherwise each tree's current root under a single read lock.
+// Problems with one commitment or its root land in that entry's status; only storage
+// failures fail the whole batch.
+async fn get_paths(
+    State(state): State<AppState>,
+    Json(payload): Json<PathsRequest>,
+) -> Result<Json<Vec<PathsEntry>>, StatusCode> {
+    if payload.root_index.is_some() && payload.root_hash.is_some() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.commitments.len() > MAX_PATHS_PER_REQUEST {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let pinned_root = payload.root_index.is_some() || payload.root_hash.is_some();
+    let found = state
+        .storage
+        .get_commitments(&payload.commitments)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let last_block = last_indexed_block(&state).await?;
+    // A commitment in one tree says nothing about how final the other tree's roots are.
+    let mut newest_commitment_block: HashMap<&str, u64> = HashMap::new();
+    for (token, _leaf_index, block) in found.values() {
+        let newest = newest_commitment_block.entry(token.as_str()).or_default();
+        *newest = (*newest).max(*block);
+    }
+    let pinned_token = payload
+        .commitments
+        .iter()
+        .find_map(|commitment| found.get(commitment))
+        .map(|(token, _leaf_index, _block)| token.clone());
+
+    let trees = state.trees.read().await;
+    let mut leaf_counts: HashMap<String, Result<u64, &'static str>> = HashMap::new();
+    let mut entries = Vec::with_capacity(payload.commitments.len());
+    for commitment in payload.commitments {
+        let Some((token, leaf_index, _block)) = found.get(&commitment).cloned() else {
+            entries.push(PathsEntry::failed(commitment, "not_found"));
+            continue;
+        };
+        if pinned_root && pinned_token.as_ref() != Some(&token) {
+            entries.push(PathsEntry::failed(commitment, "token_mismatch"));
+            continue;
+        }
+        let Some(tree) = trees.get(&token) else {
+            entries.push(PathsEntry::failed(commitment, "not_found"));
+            continue;
+        };
+        let leaf_count = match leaf_counts.get(&token) {
+            Some(leaf_count) => *leaf_count,
+            None => {
+                let max_block = last_block
+                    .saturating_sub(state.finality_depth.for_token(&token))
+                    .max(newest_commitment_block[token.as_str()]);
+                let leaf_count = match resolve_path_leaf_count(
+                    &state,
+                    tree,
+                    &token,
+                    payload.root_index,
+                    payload.root_hash.as_deref(),
+                    max_block,
+                )
+                .await
+                {
+                    Ok(leaf_count) => Ok(leaf_count),
+                    Err(StatusCode::NOT_FOUND) => Err("root_not_found"),
+                    Err(StatusCode::BAD_REQUEST) => Err("invalid_root"),
+                    Err(status) => return Err(status),
+                };
+                leaf_counts.insert(token.clone(), leaf_count);
+                leaf_count
+            }
+        };
+        let leaf_count = match leaf_count {
+            Ok(leaf_count) => leaf_count,
+            Err(status) => {
+                entries.push(PathsEntry::failed(commitment, status));
+                continue;
+            }
+        };
+        let path = parse_felt(&commitment)
+            .and_then(|leaf| path_response(tree, token, leaf, leaf_index, leaf_count));
+        match path {
+            Ok(path) => entries.push(PathsEntry {
+                commitment,
+                status: "ok",
+                path: Some(path),
+            }),
+            Err(_) => entries.push(PathsEntry::failed(commitment, "not_in_root")),
+        }
+    }
+    Ok(Json(entries))
+}
+
+async fn resolve_path_leaf_count(
+    state: &AppState,
+    tree: &MerkleTree,
+    token: &str,
+    root_index: Option<u64>,
+    root_hash: Option<&str>,
+    max_block: u64,
+) -> Result<u64, StatusCode> {
+    let finality_depth = state.finality_depth.for_token(token);
+    let (expected_root, leaf_count) = if let Some(root_index) = root_index {
+        if finality_depth == 0 {
             let root = state
                 .storage
-                .get_root_at(&token, root_index)
+                .get_root_at(token, root_index)
                 .await
                 .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
                 .ok_or(StatusCode::NOT_FOUND)?;
@@ -453,7 +885,7 @@ async fn get_path(
                 resolve_leaf_count_for_root_hash(
                     state.storage.as_ref(),
                     tree,
-                    &token,
+                    token,
                     &root.0,
                     max_block,
                     false,
@@ -466,7 +898,7 @@ async fn get_path(
         } else {
             let root = state
                 .storage
-                .get_root_at_with_block(&token, root_index)
+                .get_root_at_with_block(token, root_index)
                 .await
                 .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
                 .ok_or(StatusCode::NOT_FOUND)?;
@@ -478,7 +910,7 @@ async fn get_path(
                 resolve_leaf_count_for_root_hash(
                     state.storage.as_ref(),
                     tree,
-                    &token,
+                    token,
                     &root.0,
                     max_block,
                     true,
@@ -489,15 +921,15 @@ async fn get_path(
             };
             (Some(expected_root), leaf_count)
         }
-    } else if let Some(root_hash) = payload.root_hash.as_deref() {
+    } else if let Some(root_hash) = root_hash {
         let expected_root = parse_felt(root_hash)?;
         let leaf_count = resolve_leaf_count_for_root_hash(
             state.storage.as_ref(),
             tree,
-            &token,
+            token,
             root_hash,
             max_block,
-            state.finality_depth != 0,
+            finality_depth != 0,
         )
         .await?;
         (Some(expected_root), leaf_count)
@@ -508,24 +940,38 @@ async fn get_path(
     if leaf_count == 0 {
         return Err(StatusCode::NOT_FOUND);
     }
-    let (path, indices) = {
-        if let Some(root) = expected_root {
-            if let Some(expected) = tree.root_at(leaf_count) {
-                if expected != root {
-                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                }
+    if let Some(root) = expected_root {
+        if let Some(expected) = tree.root_at(leaf_count) {
+            if expected != root {
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
             }
         }
-        tree.get_path_at(leaf_index, leaf_count)
-            .ok_or(StatusCode::NOT_FOUND)?
-    };
+    }
+    Ok(leaf_count)
+}
+
+// Refuses to serve a path that does not hash from `leaf` to the root it claims.
+fn path_response(
+    tree: &MerkleTree,
+    token: String,
+    leaf: Felt,
+    leaf_index: u64,
+    leaf_count: u64,
+) -> Result<PathResponse, StatusCode> {
+    let (path, indices) = tree
+        .get_path_at(leaf_index, leaf_count)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let root = tree.root_at(leaf_count).ok_or(StatusCode::NOT_FOUND)?;
+    if !MerkleTree::verify_path(leaf, leaf_index, &path, &indices, root) {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
     let path_hex = path.into_iter().map(|felt| felt_to_hex(&felt)).collect();
-    Ok(Json(PathResponse {
+    Ok(PathResponse {
         token,
         leaf_index,
         path: path_hex,
         indices,
-    }))
+    })
 }
 
 async fn get_insert_path(
@@ -609,6 +1055,9 @@ async fn resolve_leaf_count_for_root_hash(
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
             .unwrap_or(0)
     };
+    if let Some(leaf_count) = tree.leaf_count_for_root(root_felt) {
+        return Ok((leaf_count > last_flushed).then_some(leaf_count));
+    }
     let current_leaf_count = tree.next_index();
     let pending = current_leaf_count.saturating_sub(last_flushed);
     if pending > MAX_ROOT_LOOKUP_STEPS {
@@ -633,13 +1082,13 @@ async fn get_commitment(
     Path(hash): Path<String>,
     State(state): State<AppState>,
 ) -> Result<Json<CommitmentResponse>, StatusCode> {
-    let max_block = finality_block(&state).await?;
     let found = state
         .storage
         .get_commitment(&hash)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .ok_or(StatusCode::NOT_FOUND)?;
+    let max_block = finality_block(&state, &found.0).await?;
     if found.2 > max_block {
         return Err(StatusCode::NOT_FOUND);
     }
@@ -649,19 +1098,46 @@ async fn get_commitment(
     }))
 }
 
+// Spends newer than the finality block are reported as unspent, like unfinalized commitments.
+// Nullifiers are not stored per token, so without `?token=` the deepest depth applies.
+async fn get_nullifier(
+    Path(hash): Path<String>,
+    Query(query): Query<NullifierQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<NullifierResponse>, StatusCode> {
+    let nullifier = felt_to_hex(&parse_felt(&hash)?);
+    let max_block = match query.token {
+        Some(token) => finality_block(&state, &normalize_token_key(&token)?).await?,
+        None => last_indexed_block(&state)
+            .await?
+            .saturating_sub(state.finality_depth.deepest()),
+    };
+    let indexed_at_block = state
+        .storage
+        .get_nullifier(&nullifier)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let used_at_block = indexed_at_block.filter(|block| *block <= max_block);
+    Ok(Json(NullifierResponse {
+        spent: used_at_block.is_some(),
+        block_number: used_at_block,
+        pending: indexed_at_block.is_some() && used_at_block.is_none(),
+    }))
+}
+
 async fn get_commitment_by_index(
     Path((token, index)): Path<(String, u64)>,
     State(state): State<AppState>,
 ) -> Result<Json<CommitmentIndexResponse>, StatusCode> {
     let token = normalize_token_key(&token)?;
-    let max_block = finality_block(&state).await?;
+    let max_block = finality_block(&state, &token).await?;
     let found = state
         .storage
         .get_commitment_by_index(&token, index)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .ok_or(StatusCode::NOT_FOUND)?;
-    if state.finality_depth != 0 && found.1 > max_block {
+    if state.finality_depth.for_token(&token) != 0 && found.1 > max_block {
         return Err(StatusCode::NOT_FOUND);
     }
     Ok(Json(CommitmentIndexResponse {
@@ -671,6 +1147,42 @@ async fn get_commitment_by_index(
     }))
 }
 
+// Inclusive `from..=to`, truncated to MAX_COMMITMENTS_PER_RANGE entries; callers page by
+// resuming after the last returned leaf_index.
+async fn get_commitments_range(
+    Path(token): Path<String>,
+    Query(query): Query<CommitmentRangeQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<CommitmentRangeEntry>>, StatusCode> {
+    let token = normalize_token_key(&token)?;
+    let (from, to) = clamp_commitment_range(query.from, query.to)?;
+    let max_block = if state.finality_depth.for_token(&token) == 0 {
+        None
+    } else {
+        Some(finality_block(&state, &token).await?)
+    };
+    let rows = state
+        .storage
+        .get_commitments_range(&token, from, to, max_block)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(
+        rows.into_iter()
+            .map(|(leaf_index, commitment)| CommitmentRangeEntry {
+                leaf_index,
+                commitment,
+            })
+            .collect(),
+    ))
+}
+
+fn clamp_commitment_range(from: u64, to: u64) -> Result<(u64, u64), StatusCode> {
+    if from > to || to > i64::MAX as u64 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok((from, to.min(from + (MAX_COMMITMENTS_PER_RANGE - 1))))
+}
+
 fn normalize_token_key(token: &str) -> Result<String, StatusCode> {
     let token = token.trim();
     if token == "position" {
@@ -711,128 +1223,118 @@ async fn rate_limit(
     Ok(next.run(req).await)
 }
 
-async fn finality_block(state: &AppState) -> Result<u64, StatusCode> {
-    let latest = state
+async fn last_indexed_block(state: &AppState) -> Result<u64, StatusCode> {
+    Ok(state
         .storage
         .get_last_block()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .unwrap_or(0);
-    Ok(latest.saturating_sub(state.finality_depth))
+        .unwrap_or(0))
+}
+
+async fn finality_block(state: &AppState, token: &str) -> Result<u64, StatusCode> {
+    let latest = last_indexed_block(state).await?;
+    Ok(latest.saturating_sub(state.finality_depth.for_token(token)))
 }
 
 fn load_config(path: &PathBuf) -> Result<Config, String> {
     let contents = std::fs::read_to_string(path).map_err(|err| format!("read config: {err}"))?;
-    let mut map: HashMap<String, String> = HashMap::new();
-    for raw_line in contents.lines() {
-        let line = strip_comments(raw_line).trim().to_string();
-        if line.is_empty() {
-            continue;
-        }
-        let (key, value) = line
-            .split_once('=')
-            .ok_or_else(|| format!("invalid config line: {line}"))?;
-        let key = key.trim().to_string();
-        let mut value = value.trim().trim_end_matches(',').to_string();
-        if value.starts_with('"') && value.ends_with('"') && value.len() >= 2 {
-            value = value[1..value.len() - 1].to_string();
-        }
-        map.insert(key, value);
-    }
-
-    let starknet_rpc_url = get_required(&map, "starknet_rpc_url")?;
-    let shielded_notes_address = get_required(&map, "shielded_notes_address")?;
-    let start_block = parse_u64(&get_required(&map, "start_block")?, "start_block")?;
-    let postgres_url = get_required(&map, "postgres_url")?;
-    let server_port = parse_u16(&get_required(&map, "server_port")?, "server_port")?;
-    let finality_depth = parse_u64(&get_required(&map, "finality_depth")?, "finality_depth")?;
-    let sync_token = map.get("sync_token").cloned().and_then(|value| {
-        let trimmed = value.trim();
-        if trimmed.is_empty() {
-            None
-        } else {
-            Some(trimmed.to_string())
-        }
-    });
-    if sync_token.is_none() && !is_dev_mode() {
-        return Err("sync_token must be set unless ENV=dev or ENV=test".to_string());
-    }
-
-    Ok(Config {
-        starknet_rpc_url,
-        shielded_notes_address,
-        start_block,
-        postgres_url,
-        server_port,
-        finality_depth,
-        sync_token,
-    })
+    parse_config(&contents)
 }
 
-fn is_dev_mode() -> bool {
-    match env::var("ENV") {
-        Ok(value) => matches!(value.as_str(), "dev" | "test"),
-        Err(_) => false,
+fn parse_config(contents: &str) -> Result<Config, String> {
+    let mut config: Config =
+        toml::from_str(contents).map_err(|err| format!("parse config: {err}"))?;
+    config.sync_token = config
+        .sync_token
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    config.starknet_ws_url = config
+        .starknet_ws_url
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    config.postgres_read_url = config
+        .postgres_read_url
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+
+    let mut problems = Vec::new();
+    if config.postgres_max_connections == 0 {
+        problems.push("postgres_max_connections must be at least 1".to_string());
+    }
+    if config.postgres_acquire_timeout_secs == 0 {
+        problems.push("postgres_acquire_timeout_secs must be at least 1".to_string());
+    }
+    if config.sync_token.is_none() && !is_dev_mode() {
+        problems.push("sync_token must be set unless ENV=dev or ENV=test".to_string());
+    }
+    collect_problem(
+        &mut problems,
+        parse_indexer_mode(&config.indexer_mode, config.starknet_ws_url.as_deref()),
+    );
+    if !problems.is_empty() {
+        return Err(problems.join("; "));
     }
+    Ok(config)
 }
 
-fn strip_comments(raw_line: &str) -> String {
-    let mut out = String::new();
-    let mut chars = raw_line.chars().peekable();
-    let mut in_string = false;
-    let mut escaped = false;
-    while let Some(ch) = chars.next() {
-        if in_string {
-            if escaped {
-                escaped = false;
-                out.push(ch);
-                continue;
-            }
-            if ch == '\\' {
-                escaped = true;
-                out.push(ch);
-                continue;
-            }
-            if ch == '"' {
-                in_string = false;
-            }
-            out.push(ch);
-            continue;
+fn parse_indexer_mode(mode: &str, ws_url: Option<&str>) -> Result<IndexerMode, String> {
+    match mode {
+        "poll" => Ok(IndexerMode::Poll),
+        "subscribe" => {
+            let ws_url = ws_url.ok_or_else(|| {
+                "starknet_ws_url is required when indexer_mode = \"subscribe\"".to_string()
+            })?;
+            Url::parse(ws_url)
+                .map(IndexerMode::Subscribe)
+                .map_err(|err| format!("invalid starknet_ws_url: {err}"))
         }
-        if ch == '"' {
-            in_string = true;
-            out.push(ch);
-            continue;
-        }
-        if ch == '#' {
-            break;
-        }
-        if ch == '/' {
-            if let Some('/') = chars.peek() {
-                break;
-            }
-        }
-        out.push(ch);
+        other => Err(format!(
+            "indexer_mode must be \"subscribe\" or \"poll\", got \"{other}\""
+        )),
     }
-    out
 }
 
-fn get_required(map: &HashMap<String, String>, key: &str) -> Result<String, String> {
-    map.get(key)
-        .cloned()
-        .ok_or_else(|| format!("missing config key: {key}"))
+fn collect_problem<T>(problems: &mut Vec<String>, result: Result<T, String>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(err) => {
+            problems.push(err);
+            None
+        }
+    }
 }
 
-fn parse_u64(value: &str, key: &str) -> Result<u64, String> {
-    value
-        .parse::<u64>()
-        .map_err(|err| format!("invalid {key}: {err}"))
+// Validates values that run() would otherwise only check after connecting to storage.
+fn check_config(path: &PathBuf) -> Result<(), String> {
+    let mut problems = Vec::new();
+    match load_config(path) {
+        Ok(config) => {
+            if let Err(err) = Url::parse(&config.starknet_rpc_url) {
+                problems.push(format!("invalid starknet_rpc_url: {err}"));
+            }
+            if let Err(err) = Felt::from_hex_be(&config.shielded_notes_address) {
+                problems.push(format!("invalid shielded_notes_address: {err}"));
+            }
+        }
+        Err(err) => problems.push(err),
+    }
+    if !problems.is_empty() {
+        return Err(format!(
+            "invalid config {}: {}",
+            path.display(),
+            problems.join("; ")
+        ));
+    }
+    println!("[asp] config ok: {}", path.display());
+    Ok(())
 }
 
-fn parse_u16(value: &str, key: &str) -> Result<u16, String> {
-    value
-        .parse::<u16>()
-        .map_err(|err| format!("invalid {key}: {err}"))
+fn is_dev_mode() -> bool {
+    match env::var("ENV") {
+        Ok(value) => matches!(value.as_str(), "dev" | "test"),
+        Err(_) => false,
+    }
 }
 
 fn to_io_error(message: impl Into<String>) -> std::io::Error {
@@ -883,11 +1385,88 @@ fn parse_felt(value: &str) -> Result<Felt, StatusCode> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::CommitmentRecord;
-    use starknet_crypto::poseidon_hash;
+    use crate::storage::{CommitmentRecord, RootRecord};
     use std::env;
     use std::time::Duration;
 
+    #[test]
+    fn indexer_mode_parsing() {
+        assert_eq!(parse_indexer_mode("poll", None), Ok(IndexerMode::Poll));
+        assert_eq!(
+            parse_indexer_mode("subscribe", Some("wss://rpc.example/ws")),
+            Ok(IndexerMode::Subscribe(
+                Url::parse("wss://rpc.example/ws").unwrap()
+            ))
+        );
+        assert!(parse_indexer_mode("subscribe", None)
+            .unwrap_err()
+            .contains("starknet_ws_url"));
+        assert!(parse_indexer_mode("stream", None).is_err());
+    }
+
+    #[test]
+    fn root_resolution_empty_tree() {
+        let empty = MerkleTree::new(MerkleTree::default_height()).root();
+        let resolution = RootResolution::new(empty, 0, None).finish(None);
+        assert_eq!(
+            resolution,
+            RootResolution {
+                root_index: 0,
+                leaf_count: 0
+            }
+        );
+    }
+
+    #[test]
+    fn root_resolution_single_leaf() {
+        let root = Felt::from(7u64);
+        let resolution = RootResolution::new(root, 1, Some((1, root, 1))).finish(None);
+        assert_eq!(resolution.root_index, 1);
+        assert_eq!(resolution.leaf_count, 1);
+    }
+
+    #[test]
+    fn root_resolution_unflushed_root_reports_tree() {
+        let resolution =
+            RootResolution::new(Felt::from(9u64), 3, Some((2, Felt::from(8u64), 2))).finish(None);
+        assert_eq!(
+            resolution,
+            RootResolution {
+                root_index: 0,
+                leaf_count: 3
+            }
+        );
+    }
+
+    #[test]
+    fn root_resolution_flushed_root_without_leaf_count() {
+        let root = Felt::from(9u64);
+        let from_tree = RootResolution::new(root, 3, Some((3, root, 0)));
+        assert!(!from_tree.needs_leaf_count());
+        assert_eq!(from_tree.finish(None).leaf_count, 3);
+
+        let pending = RootResolution::new(root, 0, Some((3, root, 0)));
+        assert!(pending.needs_leaf_count());
+        assert_eq!(
+            pending.finish(Some(3)),
+            RootResolution {
+                root_index: 3,
+                leaf_count: 3
+            }
+        );
+        assert_eq!(pending.finish(None).root_index, 0);
+        assert_eq!(pending.finish(Some(0)).root_index, 0);
+    }
+
+    #[test]
+    fn root_resolution_finality_gated_root() {
+        // The newest finalized root lags the tree, so nothing citable matches yet.
+        let resolution =
+            RootResolution::new(Felt::from(5u64), 4, Some((3, Felt::from(4u64), 3))).finish(None);
+        assert_eq!(resolution.root_index, 0);
+        assert_eq!(resolution.leaf_count, 4);
+    }
+
     #[tokio::test]
     async fn root_latest_matches_path() {
         let db_url = match env::var("ZYLITH_ASP_TEST_DB") {
@@ -926,12 +1505,14 @@ mod tests {
         let trees = Arc::new(RwLock::new(trees));
         let (resync_tx, _resync_rx) = mpsc::channel(1);
         let state = AppState {
+            primary_storage: Arc::new(storage.clone()),
             storage: Arc::new(storage),
             trees,
             resync_tx,
             rate_limiter: Arc::new(RateLimiter::new(100, Duration::from_secs(60))),
-            finality_depth: 0,
+            finality_depth: FinalityDepth::uniform(0),
             sync_token: None,
+            sync_status: Arc::new(SyncStatus::default()),
         };
 
         let Json(root_response) = get_root_latest(
@@ -965,16 +1546,423 @@ mod tests {
             .iter()
             .map(|value| Felt::from_hex_be(value).expect("path element"))
             .collect();
-        let mut current = commitments[1];
-        for (sibling, is_right) in path.iter().zip(path_response.indices.iter()) {
-            let (left, right) = if *is_right {
-                (*sibling, current)
-            } else {
-                (current, *sibling)
+        assert!(MerkleTree::verify_path(
+            commitments[1],
+            path_response.leaf_index,
+            &path,
+            &path_response.indices,
+            root
+        ));
+    }
+
+    #[tokio::test]
+    async fn root_by_hash_reports_index_within_finality() {
+        let db_url = match env::var("ZYLITH_ASP_TEST_DB") {
+            Ok(value) => value,
+            Err(_) => {
+                eprintln!("ZYLITH_ASP_TEST_DB not set; skipping integration test");
+                return;
+            }
+        };
+
+        let storage = Storage::new(&db_url).await.expect("storage");
+        storage.reset().await.expect("reset");
+
+        let token = "0x1".to_string();
+        let mut tree = MerkleTree::new(MerkleTree::default_height());
+        let mut roots = Vec::new();
+        for (index, block_number) in [(0u64, 1u64), (1, 10)] {
+            tree.insert_at(index, Felt::from(100 + index))
+                .expect("insert");
+            let root_hash = felt_to_hex(&tree.root());
+            storage
+                .insert_root(RootRecord {
+                    token: token.clone(),
+                    root_index: index + 1,
+                    root_hash: root_hash.clone(),
+                    block_number,
+                    leaf_count: index + 1,
+                })
+                .await
+                .expect("insert root");
+            roots.push(root_hash);
+        }
+        storage.set_last_block(12).await.expect("set last block");
+
+        let (resync_tx, _resync_rx) = mpsc::channel(1);
+        let mut state = AppState {
+            primary_storage: Arc::new(storage.clone()),
+            storage: Arc::new(storage),
+            trees: Arc::new(RwLock::new(HashMap::from([(token.clone(), tree)]))),
+            resync_tx,
+            rate_limiter: Arc::new(RateLimiter::new(100, Duration::from_secs(60))),
+            finality_depth: FinalityDepth::uniform(0),
+            sync_token: None,
+            sync_status: Arc::new(SyncStatus::default()),
+        };
+        let lookup = |state: &AppState, hash: &str| {
+            get_root_by_hash(
+                Path(hash.to_string()),
+                Query(RootQuery {
+                    token: Some("0x01".to_string()),
+                }),
+                State(state.clone()),
+            )
+        };
+
+        let Json(first) = lookup(&state, &roots[0]).await.expect("first root");
+        assert_eq!((first.root_index, first.leaf_count), (1, 1));
+        assert_eq!(first.token, token);
+        let Json(second) = lookup(&state, &roots[1]).await.expect("second root");
+        assert_eq!((second.root_index, second.leaf_count), (2, 2));
+        assert_eq!(
+            lookup(&state, "0x5").await.err(),
+            Some(StatusCode::NOT_FOUND)
+        );
+
+        // Block 10 is within 5 blocks of the head, so the second root is not final yet.
+        state.finality_depth = FinalityDepth::uniform(5);
+        assert!(lookup(&state, &roots[0]).await.is_ok());
+        assert_eq!(
+            lookup(&state, &roots[1]).await.err(),
+            Some(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[tokio::test]
+    async fn paths_report_root_problems_per_commitment() {
+        let db_url = match env::var("ZYLITH_ASP_TEST_DB") {
+            Ok(value) => value,
+            Err(_) => {
+                eprintln!("ZYLITH_ASP_TEST_DB not set; skipping integration test");
+                return;
+            }
+        };
+
+        let storage = Storage::new(&db_url).await.expect("storage");
+        storage.reset().await.expect("reset");
+        let token = "0x1".to_string();
+        let commitment = Felt::from(123u64);
+        let mut tree = MerkleTree::new(MerkleTree::default_height());
+        tree.insert_at(0, commitment).expect("insert");
+        storage
+            .insert_commitment(CommitmentRecord {
+                token: token.clone(),
+                leaf_index: 0,
+                commitment: felt_to_hex(&commitment),
+                timestamp: 1,
+                block_number: 1,
+            })
+            .await
+            .expect("insert commitment");
+
+        let (resync_tx, _resync_rx) = mpsc::channel(1);
+        let state = AppState {
+            primary_storage: Arc::new(storage.clone()),
+            storage: Arc::new(storage),
+            trees: Arc::new(RwLock::new(HashMap::from([(token, tree)]))),
+            resync_tx,
+            rate_limiter: Arc::new(RateLimiter::new(100, Duration::from_secs(60))),
+            finality_depth: FinalityDepth::uniform(0),
+            sync_token: None,
+            sync_status: Arc::new(SyncStatus::default()),
+        };
+        let paths = |root_index: Option<u64>| {
+            get_paths(
+                State(state.clone()),
+                Json(PathsRequest {
+                    commitments: vec![felt_to_hex(&commitment), "0x999".to_string()],
+                    root_index,
+                    root_hash: None,
+                }),
+            )
+        };
+
+        let Json(entries) = paths(None).await.expect("current root");
+        let statuses: Vec<_> = entries.iter().map(|entry| entry.status).collect();
+        assert_eq!(statuses, ["ok", "not_found"]);
+
+        // An unknown root is reported against the commitment, not as a failed batch.
+        let Json(entries) = paths(Some(99)).await.expect("unknown root");
+        let statuses: Vec<_> = entries.iter().map(|entry| entry.status).collect();
+        assert_eq!(statuses, ["root_not_found", "not_found"]);
+        assert!(entries[0].path.is_none());
+    }
+
+    #[tokio::test]
+    async fn nullifier_respects_finality_depth() {
+        let db_url = match env::var("ZYLITH_ASP_TEST_DB") {
+            Ok(value) => value,
+            Err(_) => {
+                eprintln!("ZYLITH_ASP_TEST_DB not set; skipping integration test");
+                return;
+            }
+        };
+
+        let storage = Storage::new(&db_url).await.expect("storage");
+        storage.reset().await.expect("reset");
+        let nullifier = felt_to_hex(&Felt::from(777u64));
+        storage
+            .insert_nullifier(&nullifier, 10)
+            .await
+            .expect("insert nullifier");
+        storage.set_last_block(12).await.expect("set last block");
+
+        let (resync_tx, _resync_rx) = mpsc::channel(1);
+        let mut state = AppState {
+            primary_storage: Arc::new(storage.clone()),
+            storage: Arc::new(storage),
+            trees: Arc::new(RwLock::new(HashMap::new())),
+            resync_tx,
+            rate_limiter: Arc::new(RateLimiter::new(100, Duration::from_secs(60))),
+            finality_depth: FinalityDepth::uniform(5),
+            sync_token: None,
+            sync_status: Arc::new(SyncStatus::default()),
+        };
+
+        let lookup = |state: &AppState, hash: &str, token: Option<&str>| {
+            get_nullifier(
+                Path(hash.to_string()),
+                Query(NullifierQuery {
+                    token: token.map(str::to_string),
+                }),
+                State(state.clone()),
+            )
+        };
+
+        let Json(pending) = lookup(&state, "0x309", None).await.expect("nullifier");
+        assert!(!pending.spent);
+        assert_eq!(pending.block_number, None);
+        assert!(pending.pending);
+
+        state.finality_depth = FinalityDepth::uniform(0);
+        let Json(spent) = lookup(&state, "0x309", None).await.expect("nullifier");
+        assert!(spent.spent);
+        assert_eq!(spent.block_number, Some(10));
+
+        assert!(!spent.pending);
+
+        let Json(unknown) = lookup(&state, "0x1", None).await.expect("nullifier");
+        assert!(!unknown.spent && !unknown.pending);
+
+        // Per-token depths: the named tree's depth applies, the deepest one without a token.
+        state.finality_depth = FinalityDepth {
+            default: 0,
+            per_token: HashMap::from([("position".to_string(), 5)]),
+        };
+        let Json(token) = lookup(&state, "0x309", Some("0x01"))
+            .await
+            .expect("nullifier");
+        assert!(token.spent);
+        let Json(position) = lookup(&state, "0x309", Some("position"))
+            .await
+            .expect("nullifier");
+        assert!(!position.spent);
+        let Json(untagged) = lookup(&state, "0x309", None).await.expect("nullifier");
+        assert!(!untagged.spent);
+    }
+
+    #[test]
+    fn commitment_range_is_capped() {
+        assert_eq!(clamp_commitment_range(5, 5), Ok((5, 5)));
+        assert_eq!(clamp_commitment_range(0, 10_000), Ok((0, 999)));
+        assert_eq!(clamp_commitment_range(2_000, 2_500), Ok((2_000, 2_500)));
+        assert_eq!(clamp_commitment_range(3, 2), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(
+            clamp_commitment_range(0, u64::MAX),
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[tokio::test]
+    async fn commitments_range_respects_finality_depth() {
+        let db_url = match env::var("ZYLITH_ASP_TEST_DB") {
+            Ok(value) => value,
+            Err(_) => {
+                eprintln!("ZYLITH_ASP_TEST_DB not set; skipping integration test");
+                return;
+            }
+        };
+
+        let storage = Storage::new(&db_url).await.expect("storage");
+        storage.reset().await.expect("reset");
+        for index in 0..4u64 {
+            let record = CommitmentRecord {
+                token: "0x1".to_string(),
+                leaf_index: index,
+                commitment: felt_to_hex(&Felt::from(100 + index)),
+                timestamp: 1,
+                block_number: 5 + index,
             };
-            current = poseidon_hash(left, right);
+            storage
+                .insert_commitment(record)
+                .await
+                .expect("insert commitment");
+        }
+        storage.set_last_block(10).await.expect("set last block");
+
+        let (resync_tx, _resync_rx) = mpsc::channel(1);
+        let mut state = AppState {
+            primary_storage: Arc::new(storage.clone()),
+            storage: Arc::new(storage),
+            trees: Arc::new(RwLock::new(HashMap::new())),
+            resync_tx,
+            rate_limiter: Arc::new(RateLimiter::new(100, Duration::from_secs(60))),
+            finality_depth: FinalityDepth::uniform(4),
+            sync_token: None,
+            sync_status: Arc::new(SyncStatus::default()),
+        };
+
+        let range = |from, to| Query(CommitmentRangeQuery { from, to });
+        let Json(finalized) =
+            get_commitments_range(Path("0x1".to_string()), range(0, 10), State(state.clone()))
+                .await
+                .expect("range");
+        let indices: Vec<u64> = finalized.iter().map(|entry| entry.leaf_index).collect();
+        assert_eq!(indices, vec![0, 1]);
+
+        state.finality_depth = FinalityDepth::uniform(0);
+        let Json(all) = get_commitments_range(Path("0x1".to_string()), range(1, 3), State(state))
+            .await
+            .expect("range");
+        let indices: Vec<u64> = all.iter().map(|entry| entry.leaf_index).collect();
+        assert_eq!(indices, vec![1, 2, 3]);
+        assert_eq!(all[0].commitment, felt_to_hex(&Felt::from(101u64)));
+    }
+
+    #[test]
+    fn indexer_health_reports_lag_against_finality() {
+        let synced = indexer_health(Some(95), Some(100), Some(3), 5);
+        assert_eq!(synced.lag_blocks, Some(5));
+        assert!(synced.synced);
+        assert_eq!(synced.status, "ok");
+
+        let lagging = indexer_health(Some(50), Some(100), Some(3), 5);
+        assert_eq!(lagging.lag_blocks, Some(50));
+        assert!(!lagging.synced);
+
+        let unindexed = indexer_health(None, Some(3), Some(3), 5);
+        assert_eq!(unindexed.lag_blocks, None);
+        assert!(!unindexed.synced);
+
+        let no_head = indexer_health(Some(10), None, Some(3), 5);
+        assert_eq!(no_head.lag_blocks, None);
+        assert!(!no_head.synced);
+        assert_eq!(no_head.status, "lagging");
+    }
+
+    #[test]
+    fn indexer_health_goes_stale_when_syncs_stop() {
+        // The last head seen matched the index, but nothing has synced since.
+        let stale = indexer_health(Some(100), Some(100), Some(SYNC_STALE_AFTER_SECS + 1), 5);
+        assert_eq!(stale.lag_blocks, Some(0));
+        assert!(!stale.synced);
+        assert_eq!(stale.status, "stale");
+
+        let never_synced = indexer_health(Some(100), Some(100), None, 5);
+        assert!(!never_synced.synced);
+        assert_eq!(never_synced.status, "stale");
+
+        let status = SyncStatus::default();
+        assert_eq!(status.chain_head(), None);
+        assert_eq!(status.seconds_since_sync(unix_now()), None);
+    }
+
+    #[test]
+    fn config_parses_toml() {
+        let config = parse_config(
+            r#"
+            # ASP settings
+            starknet_rpc_url = "http://127.0.0.1:5050"
+            shielded_notes_address = "0x123"
+            start_block = 0
+            postgres_url = "postgres://zylith@127.0.0.1:5432/zylith"
+            server_port = 8080
+            finality_depth = 5
+            sync_token = " secret "
+            starknet_ws_url = ""
+            "#,
+        )
+        .expect("config");
+        assert_eq!(config.server_port, 8080);
+        assert_eq!(config.finality_depth, FinalityDepth::uniform(5));
+        assert_eq!(config.sync_token.as_deref(), Some("secret"));
+        assert_eq!(config.indexer_mode, "poll");
+        assert_eq!(config.starknet_ws_url, None);
+
+        let missing = parse_config("server_port = 8080").unwrap_err();
+        assert!(missing.contains("starknet_rpc_url"), "{missing}");
+        let subscribe = parse_config(
+            r#"
+            starknet_rpc_url = "http://127.0.0.1:5050"
+            shielded_notes_address = "0x123"
+            start_block = 0
+            postgres_url = "postgres://zylith@127.0.0.1:5432/zylith"
+            server_port = 8080
+            finality_depth = 5
+            sync_token = "secret"
+            indexer_mode = "subscribe"
+            "#,
+        )
+        .unwrap_err();
+        assert!(subscribe.contains("starknet_ws_url"), "{subscribe}");
+    }
+
+    #[test]
+    fn finality_depth_accepts_per_token_table() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            finality_depth: FinalityDepth,
         }
+        let parsed: Wrapper =
+            toml::from_str(r#"finality_depth = { default = 5, position = 0, "0x01" = 8 }"#)
+                .expect("finality depth");
+        let depth = parsed.finality_depth;
+        assert_eq!(depth.for_token("position"), 0);
+        assert_eq!(depth.for_token("0x1"), 8);
+        assert_eq!(depth.for_token("0x2"), 5);
+        assert_eq!(depth.shallowest(), 0);
+        assert_eq!(depth.deepest(), 8);
+        assert_eq!(FinalityDepth::uniform(3).shallowest(), 3);
+
+        let missing_default =
+            toml::from_str::<Wrapper>(r#"finality_depth = { position = 0 }"#).err();
+        assert!(missing_default.is_some());
+    }
 
-        assert_eq!(current, root);
+    #[test]
+    fn config_rejects_undersized_postgres_pool() {
+        let base = r#"
+            starknet_rpc_url = "http://127.0.0.1:5050"
+            shielded_notes_address = "0x123"
+            start_block = 0
+            postgres_url = "postgres://zylith@127.0.0.1:5432/zylith"
+            server_port = 8080
+            finality_depth = 5
+            sync_token = "secret"
+        "#;
+        let config = parse_config(base).expect("config");
+        assert_eq!(config.postgres_max_connections, 5);
+        assert_eq!(config.postgres_acquire_timeout_secs, 30);
+        assert_eq!(config.postgres_read_url, None);
+
+        let tuned = parse_config(&format!(
+            "{base}postgres_max_connections = 20\npostgres_acquire_timeout_secs = 5\n\
+             postgres_read_url = \"postgres://zylith@replica:5432/zylith\"\n"
+        ))
+        .expect("config");
+        assert_eq!(tuned.postgres_max_connections, 20);
+        assert_eq!(tuned.postgres_acquire_timeout_secs, 5);
+        assert_eq!(
+            tuned.postgres_read_url.as_deref(),
+            Some("postgres://zylith@replica:5432/zylith")
+        );
+
+        let err = parse_config(&format!(
+            "{base}postgres_max_connections = 0\npostgres_acquire_timeout_secs = 0\n"
+        ))
+        .unwrap_err();
+        assert!(err.contains("postgres_max_connections"), "{err}");
+        assert!(err.contains("postgres_acquire_timeout_secs"), "{err}");
     }
 }
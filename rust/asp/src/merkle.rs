@@ -1,7 +1,7 @@
 //! Merkle tree utilities for ASP storage.
 //! Mirrors ShieldedNotes.cairo poseidon parameters and tree depth.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::OnceLock;
 
 use starknet::core::types::FieldElement as Felt;
@@ -9,6 +9,9 @@ use starknet_crypto::poseidon_hash;
 
 use crate::generated_constants;
 
+// Recent roots kept per tree, both in a tree's history and in the indexer's root cache.
+pub const ROOT_CACHE_LIMIT: usize = 256;
+
 static TREE_HEIGHT: OnceLock<usize> = OnceLock::new();
 static ZERO_LEAF_HASH: OnceLock<Felt> = OnceLock::new();
 
@@ -19,6 +22,10 @@ pub struct MerkleTree {
     nodes: HashMap<(usize, u64), Felt>,
     next_index: u64,
     root: Felt,
+    // Root after each of the most recent insertions -> leaf count at that point.
+    root_history: HashMap<Felt, u64>,
+    root_history_order: VecDeque<Felt>,
+    root_history_limit: usize,
 }
 
 #[derive(Debug)]
@@ -39,13 +46,30 @@ impl MerkleTree {
             zero_hashes.push(poseidon_hash(prev, prev));
         }
         let root = poseidon_hash(zero_hashes[height - 1], zero_hashes[height - 1]);
-        Self {
+        let mut tree = Self {
             height,
             zero_hashes,
             nodes: HashMap::new(),
             next_index: 0,
             root,
+            root_history: HashMap::new(),
+            root_history_order: VecDeque::new(),
+            root_history_limit: ROOT_CACHE_LIMIT,
+        };
+        tree.record_root();
+        tree
+    }
+
+    // Rebuilds a tree from stored `(leaf_index, commitment)` pairs in any order; the indices
+    // must cover `0..leaves.len()` without gaps.
+    pub fn rebuild_from_leaves(height: usize, leaves: &[(u64, Felt)]) -> Result<Self, MerkleError> {
+        let mut ordered = leaves.to_vec();
+        ordered.sort_by_key(|(index, _leaf)| *index);
+        let mut tree = Self::new(height);
+        for (index, leaf) in ordered {
+            tree.insert_at(index, leaf)?;
         }
+        Ok(tree)
     }
 
     pub fn default_height() -> usize {
@@ -125,13 +149,65 @@ impl MerkleTree {
         }
 
         self.root = current;
+        self.record_root();
         Ok((index, self.root))
     }
 
+    fn record_root(&mut self) {
+        if self
+            .root_history
+            .insert(self.root, self.next_index)
+            .is_none()
+        {
+            self.root_history_order.push_back(self.root);
+        }
+        while self.root_history_order.len() > self.root_history_limit {
+            if let Some(evicted) = self.root_history_order.pop_front() {
+                self.root_history.remove(&evicted);
+            }
+        }
+    }
+
+    // Constant-time reverse lookup for roots within the last `ROOT_CACHE_LIMIT` insertions.
+    pub fn leaf_count_for_root(&self, root: Felt) -> Option<u64> {
+        self.root_history.get(&root).copied()
+    }
+
     pub fn root(&self) -> Felt {
         self.root
     }
 
+    // `indices[level]` is true when the running hash is the right child, which must agree with
+    // the corresponding bit of `leaf_index`.
+    pub fn verify_path(
+        leaf: Felt,
+        leaf_index: u64,
+        path: &[Felt],
+        indices: &[bool],
+        expected_root: Felt,
+    ) -> bool {
+        if path.len() != indices.len() {
+            return false;
+        }
+        if path.len() < 64 && leaf_index >> path.len() != 0 {
+            return false;
+        }
+        let mut hash = leaf;
+        for (level, (sibling, is_right)) in path.iter().zip(indices).enumerate() {
+            let bit = level < 64 && (leaf_index >> level) & 1 == 1;
+            if bit != *is_right {
+                return false;
+            }
+            let (left, right) = if *is_right {
+                (*sibling, hash)
+            } else {
+                (hash, *sibling)
+            };
+            hash = poseidon_hash(left, right);
+        }
+        hash == expected_root
+    }
+
     pub fn next_index(&self) -> u64 {
         self.next_index
     }
@@ -267,21 +343,6 @@ mod tests_extra {
     use starknet::core::types::FieldElement as Felt;
     use starknet_crypto::poseidon_hash;
 
-    fn compute_root_from_path(leaf: Felt, mut index: u64, path: &[Felt], indices: &[bool]) -> Felt {
-        let mut hash = leaf;
-        for (sibling, is_right) in path.iter().zip(indices.iter()) {
-            let (left, right) = if *is_right {
-                (*sibling, hash)
-            } else {
-                (hash, *sibling)
-            };
-            hash = poseidon_hash(left, right);
-            index /= 2;
-        }
-        let _ = index;
-        hash
-    }
-
     #[test]
     fn root_at_matches_historical_state() {
         let mut tree = MerkleTree::new(4);
@@ -301,9 +362,8 @@ mod tests_extra {
         tree.insert(leaf0).expect("insert leaf0");
         tree.insert(Felt::from(44u8)).expect("insert leaf1");
         let (path, indices) = tree.get_path_at(0, 1).expect("path");
-        let root = compute_root_from_path(leaf0, 0, &path, &indices);
         let expected = tree.root_at(1).expect("root_at");
-        assert_eq!(root, expected);
+        assert!(MerkleTree::verify_path(leaf0, 0, &path, &indices, expected));
     }
 
     #[test]
@@ -313,8 +373,58 @@ mod tests_extra {
         tree.insert(leaf0).expect("insert leaf0");
         tree.insert(Felt::from(66u8)).expect("insert leaf1");
         let (path, indices) = tree.get_path(0).expect("path");
-        let root = compute_root_from_path(leaf0, 0, &path, &indices);
-        assert_eq!(root, tree.root());
+        assert!(MerkleTree::verify_path(
+            leaf0,
+            0,
+            &path,
+            &indices,
+            tree.root()
+        ));
+    }
+
+    #[test]
+    fn verify_path_rejects_tampered_paths() {
+        let mut tree = MerkleTree::new(4);
+        tree.insert(Felt::from(7u8)).expect("insert leaf0");
+        let leaf1 = Felt::from(8u8);
+        tree.insert(leaf1).expect("insert leaf1");
+        tree.insert(Felt::from(9u8)).expect("insert leaf2");
+        let root = tree.root();
+        let (path, indices) = tree.get_path(1).expect("path");
+        assert!(MerkleTree::verify_path(leaf1, 1, &path, &indices, root));
+
+        let mut wrong_sibling = path.clone();
+        wrong_sibling[0] = Felt::from(12345u32);
+        assert!(!MerkleTree::verify_path(
+            leaf1,
+            1,
+            &wrong_sibling,
+            &indices,
+            root
+        ));
+        assert!(!MerkleTree::verify_path(
+            leaf1,
+            1,
+            &path[..3],
+            &indices,
+            root
+        ));
+        assert!(!MerkleTree::verify_path(
+            leaf1,
+            1,
+            &path,
+            &indices[..3],
+            root
+        ));
+        assert!(!MerkleTree::verify_path(leaf1, 0, &path, &indices, root));
+        assert!(!MerkleTree::verify_path(leaf1, 17, &path, &indices, root));
+        assert!(!MerkleTree::verify_path(
+            Felt::from(9u8),
+            1,
+            &path,
+            &indices,
+            root
+        ));
     }
 
     #[test]
@@ -373,6 +483,40 @@ mod tests {
         assert!(matches!(err, MerkleError::TreeFull { .. }));
     }
 
+    #[test]
+    fn root_history_maps_recent_roots_to_leaf_counts() {
+        let mut tree = MerkleTree::new(3);
+        let empty_root = tree.root();
+        assert_eq!(tree.leaf_count_for_root(empty_root), Some(0));
+        for leaf in 1..=4u8 {
+            tree.insert(Felt::from(leaf)).unwrap();
+        }
+        for leaf_count in 0..=4 {
+            let root = tree.root_at(leaf_count).unwrap();
+            assert_eq!(tree.leaf_count_for_root(root), Some(leaf_count));
+        }
+        assert_eq!(tree.leaf_count_for_root(Felt::from(99u8)), None);
+
+        // Re-inserting an existing leaf leaves the history untouched.
+        tree.insert_at(0, Felt::from(1u8)).unwrap();
+        assert_eq!(tree.leaf_count_for_root(tree.root()), Some(4));
+    }
+
+    #[test]
+    fn root_history_evicts_oldest_roots() {
+        let mut tree = MerkleTree::new(3);
+        tree.root_history_limit = 2;
+        let empty_root = tree.root();
+        for leaf in 1..=3u8 {
+            tree.insert(Felt::from(leaf)).unwrap();
+        }
+        assert_eq!(tree.leaf_count_for_root(empty_root), None);
+        assert_eq!(tree.leaf_count_for_root(tree.root_at(1).unwrap()), None);
+        assert_eq!(tree.leaf_count_for_root(tree.root_at(2).unwrap()), Some(2));
+        assert_eq!(tree.leaf_count_for_root(tree.root()), Some(3));
+        assert_eq!(tree.root_history.len(), 2);
+    }
+
     #[test]
     fn insertion_path_matches_at() {
         let mut tree = MerkleTree::new(3);
@@ -384,4 +528,30 @@ mod tests {
         assert_eq!(path, path_at);
         assert_eq!(indices, indices_at);
     }
+
+    #[test]
+    fn rebuild_from_leaves_matches_sequential_inserts() {
+        let leaves: Vec<(u64, Felt)> = (0..5u64).map(|i| (i, Felt::from(10 + i))).collect();
+        let mut sequential = MerkleTree::new(3);
+        for (index, leaf) in &leaves {
+            sequential.insert_at(*index, *leaf).unwrap();
+        }
+
+        let mut shuffled = leaves.clone();
+        shuffled.reverse();
+        let rebuilt = MerkleTree::rebuild_from_leaves(3, &shuffled).unwrap();
+        assert_eq!(rebuilt.root(), sequential.root());
+        assert_eq!(rebuilt.next_index(), 5);
+        assert_eq!(rebuilt.root_at(2), sequential.root_at(2));
+
+        let gapped = [(0, Felt::from(1u8)), (2, Felt::from(3u8))];
+        let err = MerkleTree::rebuild_from_leaves(3, &gapped).unwrap_err();
+        assert!(matches!(
+            err,
+            MerkleError::IndexGap {
+                expected: 1,
+                got: 2
+            }
+        ));
+    }
 }
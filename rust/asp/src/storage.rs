@@ -1,6 +1,7 @@
 //! Persistence layer for ASP.
 
 use std::collections::HashMap;
+use std::time::Duration;
 
 use sqlx::{PgPool, Row};
 use starknet::core::types::FieldElement as Felt;
@@ -55,15 +56,50 @@ pub struct RootRecord {
     pub leaf_count: u64,
 }
 
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+pub const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+
 impl Storage {
+    #[cfg(test)]
     pub async fn new(database_url: &str) -> Result<Self, StorageError> {
+        Self::new_with_options(
+            database_url,
+            DEFAULT_MAX_CONNECTIONS,
+            DEFAULT_ACQUIRE_TIMEOUT,
+        )
+        .await
+    }
+
+    pub async fn new_with_options(
+        database_url: &str,
+        max_connections: u32,
+        acquire_timeout: Duration,
+    ) -> Result<Self, StorageError> {
+        let storage = Self::connect(database_url, max_connections, acquire_timeout).await?;
+        storage.init_schema().await?;
+        Ok(storage)
+    }
+
+    // Replicas reject DDL, so schema creation is left to the primary.
+    pub async fn new_read_replica(
+        database_url: &str,
+        max_connections: u32,
+        acquire_timeout: Duration,
+    ) -> Result<Self, StorageError> {
+        Self::connect(database_url, max_connections, acquire_timeout).await
+    }
+
+    async fn connect(
+        database_url: &str,
+        max_connections: u32,
+        acquire_timeout: Duration,
+    ) -> Result<Self, StorageError> {
         let pool = sqlx::postgres::PgPoolOptions::new()
-            .max_connections(5)
+            .max_connections(max_connections)
+            .acquire_timeout(acquire_timeout)
             .connect(database_url)
             .await?;
-        let storage = Self { pool };
-        storage.init_schema().await?;
-        Ok(storage)
+        Ok(Self { pool })
     }
 
     pub async fn init_schema(&self) -> Result<(), StorageError> {
@@ -233,6 +269,18 @@ impl Storage {
         Ok(())
     }
 
+    pub async fn get_nullifier(&self, nullifier: &str) -> Result<Option<u64>, StorageError> {
+        let row = sqlx::query("SELECT used_at_block FROM nullifiers WHERE nullifier_hash = $1")
+            .bind(nullifier)
+            .fetch_optional(&self.pool)
+            .await?;
+        if let Some(row) = row {
+            let used_at_block: i64 = row.try_get("used_at_block")?;
+            return Ok(Some(used_at_block as u64));
+        }
+        Ok(None)
+    }
+
     pub async fn get_commitment(
         &self,
         commitment: &str,
@@ -252,6 +300,30 @@ impl Storage {
         Ok(None)
     }
 
+    pub async fn get_commitments(
+        &self,
+        commitments: &[String],
+    ) -> Result<HashMap<String, (String, u64, u64)>, StorageError> {
+        let rows = sqlx::query(
+            "SELECT commitment, token, leaf_index, block_number FROM commitments \
+             WHERE commitment = ANY($1)",
+        )
+        .bind(commitments)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut found = HashMap::with_capacity(rows.len());
+        for row in rows {
+            let commitment: String = row.try_get("commitment")?;
+            let token: String = row.try_get("token")?;
+            let leaf_index: i64 = row.try_get("leaf_index")?;
+            let block_number: i64 = row.try_get("block_number")?;
+            found
+                .entry(commitment)
+                .or_insert((token, leaf_index as u64, block_number as u64));
+        }
+        Ok(found)
+    }
+
     pub async fn get_commitment_by_index(
         &self,
         token: &str,
@@ -272,6 +344,48 @@ impl Storage {
         Ok(None)
     }
 
+    // Commitments of `token` with leaf_index in `from..=to`, ascending; rows from blocks after
+    // `max_block` are left out.
+    pub async fn get_commitments_range(
+        &self,
+        token: &str,
+        from: u64,
+        to: u64,
+        max_block: Option<u64>,
+    ) -> Result<Vec<(u64, String)>, StorageError> {
+        let rows = if let Some(max_block) = max_block {
+            sqlx::query(
+                "SELECT leaf_index, commitment FROM commitments \
+                 WHERE token = $1 AND leaf_index >= $2 AND leaf_index <= $3 AND block_number <= $4 \
+                 ORDER BY leaf_index ASC",
+            )
+            .bind(token)
+            .bind(from as i64)
+            .bind(to as i64)
+            .bind(max_block as i64)
+            .fetch_all(&self.pool)
+            .await?
+        } else {
+            sqlx::query(
+                "SELECT leaf_index, commitment FROM commitments \
+                 WHERE token = $1 AND leaf_index >= $2 AND leaf_index <= $3 \
+                 ORDER BY leaf_index ASC",
+            )
+            .bind(token)
+            .bind(from as i64)
+            .bind(to as i64)
+            .fetch_all(&self.pool)
+            .await?
+        };
+        let mut out = Vec::with_capacity(rows.len());
+        for row in rows {
+            let leaf_index: i64 = row.try_get("leaf_index")?;
+            let commitment: String = row.try_get("commitment")?;
+            out.push((leaf_index as u64, commitment));
+        }
+        Ok(out)
+    }
+
     pub async fn get_root_at(
         &self,
         token: &str,
@@ -494,18 +608,22 @@ impl Storage {
         )
         .fetch_all(&self.pool)
         .await?;
-        let mut trees: HashMap<String, MerkleTree> = HashMap::new();
+        let mut leaves: HashMap<String, Vec<(u64, Felt)>> = HashMap::new();
         for row in rows {
             let token: String = row.try_get("token")?;
             let leaf_index: i64 = row.try_get("leaf_index")?;
             let commitment: String = row.try_get("commitment")?;
-            let tree = trees
-                .entry(token.clone())
-                .or_insert_with(|| MerkleTree::new(height));
             let leaf = Felt::from_hex_be(&commitment)
                 .map_err(|e| StorageError::Invariant(e.to_string()))?;
-            tree.insert_at(leaf_index as u64, leaf)
-                .map_err(|err| match err {
+            leaves
+                .entry(token)
+                .or_default()
+                .push((leaf_index as u64, leaf));
+        }
+        let mut trees: HashMap<String, MerkleTree> = HashMap::new();
+        for (token, leaves) in leaves {
+            let tree =
+                MerkleTree::rebuild_from_leaves(height, &leaves).map_err(|err| match err {
                     MerkleError::IndexGap { expected, got } => StorageError::Invariant(format!(
                         "merkle index gap for {} expected {} got {}",
                         token, expected, got
@@ -523,6 +641,7 @@ impl Storage {
                         token, reason
                     )),
                 })?;
+            trees.insert(token, tree);
         }
         Ok(trees)
     }
@@ -0,0 +1,180 @@
+//! new-head subscription over the Starknet JSON-RPC WebSocket endpoint
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use url::Url;
+
+const SUBSCRIBE_REQUEST_ID: u64 = 1;
+const RECONNECT_DELAY_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexerMode {
+    Poll,
+    Subscribe(Url),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadEvent {
+    // Sent after every (re)connect so the indexer reconciles whatever it missed while down.
+    Connected,
+    NewHead(u64),
+    Disconnected,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SubscriptionMessage {
+    Subscribed,
+    NewHead(u64),
+    Rejected(String),
+    Ignored,
+}
+
+enum StreamEnd {
+    Dropped(String),
+    Unsupported(String),
+    IndexerGone,
+}
+
+// Keeps a `starknet_subscribeNewHeads` subscription open, reconnecting with backoff. Returns
+// only when the node rejects the method (the indexer then stays on polling) or the indexer
+// has shut down.
+pub async fn subscribe_new_heads(ws_url: Url, tx: mpsc::Sender<HeadEvent>) {
+    let mut delay = RECONNECT_DELAY_MIN;
+    loop {
+        match stream_heads(&ws_url, &tx, &mut delay).await {
+            StreamEnd::IndexerGone => return,
+            StreamEnd::Unsupported(reason) => {
+                println!("[asp] head subscription unsupported, staying on polling: {reason}");
+                let _ = tx.send(HeadEvent::Disconnected).await;
+                return;
+            }
+            StreamEnd::Dropped(reason) => {
+                println!("[asp] head subscription dropped: {reason}");
+                if tx.send(HeadEvent::Disconnected).await.is_err() {
+                    return;
+                }
+            }
+        }
+        sleep(delay).await;
+        delay = (delay * 2).min(RECONNECT_DELAY_MAX);
+    }
+}
+
+async fn stream_heads(
+    ws_url: &Url,
+    tx: &mpsc::Sender<HeadEvent>,
+    reconnect_delay: &mut Duration,
+) -> StreamEnd {
+    let (mut ws, _response) = match connect_async(ws_url.as_str()).await {
+        Ok(conn) => conn,
+        Err(err) => return StreamEnd::Dropped(format!("connect error: {err}")),
+    };
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": SUBSCRIBE_REQUEST_ID,
+        "method": "starknet_subscribeNewHeads",
+        "params": {},
+    });
+    if let Err(err) = ws.send(Message::Text(request.to_string())).await {
+        return StreamEnd::Dropped(format!("send error: {err}"));
+    }
+
+    while let Some(message) = ws.next().await {
+        let text = match message {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Ping(payload)) => {
+                if let Err(err) = ws.send(Message::Pong(payload)).await {
+                    return StreamEnd::Dropped(format!("send error: {err}"));
+                }
+                continue;
+            }
+            Ok(Message::Close(_)) => return StreamEnd::Dropped("closed by node".to_string()),
+            Ok(_) => continue,
+            Err(err) => return StreamEnd::Dropped(format!("read error: {err}")),
+        };
+        let event = match parse_subscription_message(&text) {
+            SubscriptionMessage::Subscribed => {
+                *reconnect_delay = RECONNECT_DELAY_MIN;
+                HeadEvent::Connected
+            }
+            SubscriptionMessage::NewHead(block_number) => HeadEvent::NewHead(block_number),
+            SubscriptionMessage::Rejected(reason) => return StreamEnd::Unsupported(reason),
+            SubscriptionMessage::Ignored => continue,
+        };
+        if tx.send(event).await.is_err() {
+            return StreamEnd::IndexerGone;
+        }
+    }
+    StreamEnd::Dropped("stream ended".to_string())
+}
+
+fn parse_subscription_message(text: &str) -> SubscriptionMessage {
+    let Ok(value) = serde_json::from_str::<Value>(text) else {
+        return SubscriptionMessage::Ignored;
+    };
+    if value.get("id").and_then(Value::as_u64) == Some(SUBSCRIBE_REQUEST_ID) {
+        if let Some(error) = value.get("error") {
+            return SubscriptionMessage::Rejected(error.to_string());
+        }
+        if value.get("result").is_some() {
+            return SubscriptionMessage::Subscribed;
+        }
+    }
+    if value.get("method").and_then(Value::as_str) == Some("starknet_subscriptionNewHeads") {
+        if let Some(block_number) = value
+            .pointer("/params/result/block_number")
+            .and_then(Value::as_u64)
+        {
+            return SubscriptionMessage::NewHead(block_number);
+        }
+    }
+    SubscriptionMessage::Ignored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_subscription_message, SubscriptionMessage};
+
+    #[test]
+    fn subscription_ack_and_heads_are_parsed() {
+        assert_eq!(
+            parse_subscription_message(r#"{"jsonrpc":"2.0","id":1,"result":"0x2a"}"#),
+            SubscriptionMessage::Subscribed
+        );
+        let head = r#"{"jsonrpc":"2.0","method":"starknet_subscriptionNewHeads",
+            "params":{"subscription_id":"0x2a","result":{"block_hash":"0x1","block_number":812}}}"#;
+        assert_eq!(
+            parse_subscription_message(head),
+            SubscriptionMessage::NewHead(812)
+        );
+    }
+
+    #[test]
+    fn subscription_rejection_is_reported() {
+        let reply =
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"Method not found"}}"#;
+        assert!(matches!(
+            parse_subscription_message(reply),
+            SubscriptionMessage::Rejected(reason) if reason.contains("Method not found")
+        ));
+    }
+
+    #[test]
+    fn unrelated_messages_are_ignored() {
+        assert_eq!(
+            parse_subscription_message("not json"),
+            SubscriptionMessage::Ignored
+        );
+        assert_eq!(
+            parse_subscription_message(
+                r#"{"jsonrpc":"2.0","method":"starknet_subscriptionReorg","params":{}}"#
+            ),
+            SubscriptionMessage::Ignored
+        );
+    }
+}
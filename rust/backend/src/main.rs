@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
 use std::fs;
+use std::future::IntoFuture;
 use std::io::Read;
 use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
@@ -8,8 +9,8 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use axum::body::Body;
-use axum::extract::{ConnectInfo, DefaultBodyLimit, State};
-use axum::http::{header, HeaderName, HeaderValue, Method, Request, StatusCode};
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Path as PathParam, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode};
 use axum::middleware::{from_fn_with_state, Next};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
@@ -23,22 +24,26 @@ use tokio::time::timeout;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 use url::Url;
+use uuid::Uuid;
 
 use async_trait::async_trait;
 use starknet::accounts::{Account, ConnectedAccount, ExecutionEncoder, ExecutionEncoding};
+use starknet::core::crypto::{compute_hash_on_elements, ecdsa_verify, Signature};
 use starknet::core::types::{BlockId, BlockTag, Call, Felt, FunctionCall, U256};
-use starknet::core::utils::get_selector_from_name;
+use starknet::core::utils::{get_selector_from_name, starknet_keccak};
 use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClient};
 use starknet::providers::Provider;
 
 use zylith_client::{
     compute_commitment, generate_note_with_token_id, generate_nullifier_hash, parse_felt,
-    quote_liquidity_amounts, ClientError, LiquidityAddProveRequest, LiquidityClaimProveRequest,
-    LiquidityProveResult, LiquidityRemoveProveRequest, MerklePath, Note, PoolConfig, PositionNote,
-    SignedAmount, SwapProveRequest, SwapStepQuote, ZylithClient, ZylithConfig, MAX_INPUT_NOTES,
+    plan_swap_circuit_steps, quote_liquidity_amounts, rpc_provider, ClientError, InsertionTree,
+    LiquidityAddProveRequest, LiquidityClaimProveRequest, LiquidityProveResult,
+    LiquidityRemoveProveRequest, LiquidityRemoveQuote, MerklePath, Note, PoolConfig, PoolState,
+    PositionNote, SignedAmount, SwapDirection, SwapProveRequest, SwapStepQuote, TokenMetadata,
+    ZylithClient, ZylithConfig, MAX_INPUT_NOTES,
 };
 use zylith_prover::{
     prove_deposit as prove_deposit_proof, prove_withdraw as prove_withdraw_proof,
@@ -49,8 +54,35 @@ use zylith_prover::{
 struct AppState {
     account: ReadOnlyAccount<JsonRpcClient<HttpTransport>>,
     config: Arc<AppConfig>,
-    limiter: Arc<Semaphore>,
+    limiter: Arc<ProofLimiter>,
     rate_limiter: Arc<RateLimiter>,
+    proof_stats: Arc<ProofStats>,
+    jobs: Arc<JobStore>,
+    metrics: Arc<Metrics>,
+    // [token0, token1] metadata, fetched on first use.
+    token_metadata: Arc<tokio::sync::OnceCell<[TokenMetadata; 2]>>,
+    // Seeded by `validate_onchain_config`; see `cached_pool_config`.
+    pool_config: Arc<RefreshingCache<PoolConfig>>,
+}
+
+impl AppState {
+    fn new(account: ReadOnlyAccount<JsonRpcClient<HttpTransport>>, config: AppConfig) -> Self {
+        Self {
+            account,
+            limiter: Arc::new(ProofLimiter::new(&config.max_concurrent_proofs)),
+            rate_limiter: Arc::new(RateLimiter::new(
+                config.rate_limit_per_minute,
+                config.rate_limit_burst,
+                &config.rate_limit_key_overrides,
+            )),
+            proof_stats: Arc::new(ProofStats::default()),
+            jobs: Arc::new(JobStore::new(MAX_PENDING_SWAP_JOBS)),
+            metrics: Arc::new(Metrics::default()),
+            token_metadata: Arc::new(tokio::sync::OnceCell::new()),
+            pool_config: Arc::new(RefreshingCache::new(POOL_CONFIG_REFRESH)),
+            config: Arc::new(config),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -64,14 +96,41 @@ struct RawConfig {
     chain_id: Option<String>,
     bind_addr: String,
     artifacts_dir: String,
-    max_concurrent_proofs: usize,
+    max_concurrent_proofs: RawCircuitLimits,
     request_timeout_secs: u64,
     max_body_bytes: usize,
+    max_quote_body_bytes: Option<usize>,
     cors_allow_origins: Vec<String>,
+    cors_allow_credentials: Option<bool>,
+    cors_allow_headers: Option<Vec<String>>,
     rate_limit_per_minute: u64,
     rate_limit_burst: u64,
+    rate_limit_keys: Option<HashMap<String, RawRateLimitOverride>>,
     api_key: Option<String>,
+    api_keys: Option<RawApiKeys>,
+    // Superseded by `trusted_proxies`; only read to reject stale configs.
     trust_proxy: Option<bool>,
+    trusted_proxies: Option<Vec<String>>,
+    // key id -> Stark public key for signed requests.
+    request_signing_keys: Option<HashMap<String, String>>,
+    request_signature_max_age_secs: Option<u64>,
+    max_proof_felts: Option<RawCircuitLimits>,
+    enabled_circuits: Option<Vec<String>>,
+}
+
+// Per-API-key rate limit; unset fields fall back to the global values.
+#[derive(Debug, Deserialize)]
+struct RawRateLimitOverride {
+    rate_limit_per_minute: Option<u64>,
+    rate_limit_burst: Option<u64>,
+}
+
+// `api_keys` accepts either a TOML array or a comma-separated string.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawApiKeys {
+    List(Vec<String>),
+    Csv(String),
 }
 
 #[derive(Clone)]
@@ -84,14 +143,38 @@ struct AppConfig {
     chain_id: Felt,
     bind_addr: SocketAddr,
     artifacts_dir: PathBuf,
-    max_concurrent_proofs: usize,
+    max_concurrent_proofs: HashMap<ProofCircuit, usize>,
     request_timeout: Duration,
+    // Ceiling for proof routes and anything without its own cap.
     max_body_bytes: usize,
+    max_quote_body_bytes: usize,
     cors_allow_origins: Vec<String>,
+    cors_allow_credentials: bool,
+    // Allowed on top of content-type and x-api-key.
+    cors_allow_headers: Vec<String>,
     rate_limit_per_minute: u64,
     rate_limit_burst: u64,
-    api_key: Option<String>,
-    trust_proxy: bool,
+    // (per minute, burst) for specific API keys.
+    rate_limit_key_overrides: HashMap<String, (u64, u64)>,
+    api_keys: Vec<String>,
+    // Peers whose forwarded-for headers are believed; empty means use the socket address.
+    trusted_proxies: Vec<IpNetwork>,
+    request_signing: RequestSigning,
+    max_proof_felts: HashMap<ProofCircuit, usize>,
+    enabled_circuits: Vec<CircuitSet>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RequestSigning {
+    keys: HashMap<String, Felt>,
+    // Accepted distance between the signed timestamp and server time, either direction.
+    max_age: Duration,
+}
+
+impl AppConfig {
+    fn circuit_enabled(&self, circuit: CircuitSet) -> bool {
+        self.enabled_circuits.contains(&circuit)
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -143,6 +226,8 @@ struct SwapProofRequest {
     zero_for_one: bool,
     exact_out: bool,
     amount_out: Option<String>,
+    amount_in_max: Option<String>,
+    amount_out_min: Option<String>,
     sqrt_ratio_limit: Option<String>,
     output_note: Option<NoteInput>,
     change_note: Option<NoteInput>,
@@ -160,6 +245,11 @@ struct SwapProofResponse {
     selected_swap_steps: usize,
 }
 
+#[derive(Debug, Serialize)]
+struct JobSubmitResponse {
+    job_id: Uuid,
+}
+
 #[derive(Debug, Deserialize)]
 struct LiquidityAddProofRequest {
     token0_notes: Vec<NoteInput>,
@@ -246,12 +336,29 @@ struct SwapQuoteRequest {
 
 #[derive(Debug, Serialize)]
 struct SwapQuoteResponse {
+    // Addresses of the tokens `amount_in` and `amount_out` are denominated in.
+    token_in: String,
+    token_out: String,
     amount_in: String,
     amount_out: String,
     sqrt_price_end: String,
     tick_end: i32,
     liquidity_end: String,
     is_limited: bool,
+    // `amount_in`/`amount_out` in whole tokens; omitted when token metadata is unavailable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount_in_display: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount_out_display: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SwapProofEstimateResponse {
+    circuit: String,
+    active_steps: usize,
+    selected_swap_steps: usize,
+    estimated_ms: Option<u64>,
+    samples: usize,
 }
 
 #[derive(Debug, Deserialize)]
@@ -267,6 +374,74 @@ struct LiquidityQuoteResponse {
     amount1: String,
     sqrt_ratio_lower: String,
     sqrt_ratio_upper: String,
+    // `amount0`/`amount1` in whole tokens; omitted when token metadata is unavailable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount0_display: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount1_display: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LiquidityRemoveQuoteRequest {
+    position_note: PositionNoteInput,
+    liquidity_delta: String,
+}
+
+#[derive(Debug, Serialize)]
+struct LiquidityRemoveQuoteResponse {
+    amount0: String,
+    amount1: String,
+    protocol_fee_0: String,
+    protocol_fee_1: String,
+    fee_amount0: String,
+    fee_amount1: String,
+    out_amount0: String,
+    out_amount1: String,
+    // `out_amount0`/`out_amount1` in whole tokens; omitted when token metadata is unavailable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    out_amount0_display: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    out_amount1_display: Option<String>,
+}
+
+fn liquidity_remove_quote_response(
+    quote: LiquidityRemoveQuote,
+    tokens: Option<&[TokenMetadata; 2]>,
+) -> LiquidityRemoveQuoteResponse {
+    let [out_amount0_display, out_amount1_display] =
+        display_amounts(tokens, [quote.out_amount0, quote.out_amount1]);
+    LiquidityRemoveQuoteResponse {
+        amount0: quote.amount0.to_string(),
+        amount1: quote.amount1.to_string(),
+        protocol_fee_0: quote.protocol_fee_0.to_string(),
+        protocol_fee_1: quote.protocol_fee_1.to_string(),
+        fee_amount0: quote.fee_amount0.to_string(),
+        fee_amount1: quote.fee_amount1.to_string(),
+        out_amount0: quote.out_amount0.to_string(),
+        out_amount1: quote.out_amount1.to_string(),
+        out_amount0_display,
+        out_amount1_display,
+    }
+}
+
+// Whole-token renderings of a (token0, token1) amount pair, or `None`s without metadata.
+fn display_amounts(tokens: Option<&[TokenMetadata; 2]>, amounts: [u128; 2]) -> [Option<String>; 2] {
+    match tokens {
+        Some([token0, token1]) => [
+            Some(token0.format_amount(amounts[0])),
+            Some(token1.format_amount(amounts[1])),
+        ],
+        None => [None, None],
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PoolStateResponse {
+    sqrt_price: String,
+    tick: i32,
+    liquidity: String,
+    fee_growth_global_0: String,
+    fee_growth_global_1: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -280,6 +455,10 @@ struct PoolConfigResponse {
     min_sqrt_ratio: String,
     max_sqrt_ratio: String,
     max_input_notes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token0_metadata: Option<TokenMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token1_metadata: Option<TokenMetadata>,
 }
 
 #[derive(Debug, Serialize)]
@@ -292,32 +471,582 @@ struct MerklePathResponse {
     indices: Vec<bool>,
 }
 
+// `code` is stable and meant for branching; `error` is for humans and may change.
 #[derive(Debug, Serialize)]
 struct ErrorResponse {
+    code: &'static str,
     error: String,
 }
 
 #[derive(Debug)]
 enum ApiError {
     BadRequest(String),
+    // A 400 with a specific code from `VALIDATION_ERROR_CODES`.
+    Invalid(&'static str, String),
     Upstream(String),
     Prover(String),
     QueueTimeout,
-    RateLimited,
+    // The async job queue already holds its limit of pending jobs.
+    QueueFull,
+    GatewayTimeout(String),
+    // Time until the caller's bucket holds a token again.
+    RateLimited(Duration),
     Unauthorized,
+    NotFound(String),
+    NotImplemented(String),
     Internal(String),
 }
 
+// Either one limit for every circuit, or a table keyed by circuit name. How unlisted circuits
+// are covered depends on the setting; see `proof_concurrency_limits` and `proof_felt_limits`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawCircuitLimits {
+    Global(usize),
+    PerCircuit(HashMap<String, usize>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ProofCircuit {
+    Deposit,
+    Withdraw,
+    Swap,
+    Liquidity,
+}
+
+// Garaga Groth16 calldata is ~2k felts plus two felts per public input; the
+// caps leave headroom for the largest public input sets of each circuit.
+impl ProofCircuit {
+    const ALL: [ProofCircuit; 4] = [
+        ProofCircuit::Deposit,
+        ProofCircuit::Withdraw,
+        ProofCircuit::Swap,
+        ProofCircuit::Liquidity,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ProofCircuit::Deposit => "deposit",
+            ProofCircuit::Withdraw => "withdraw",
+            ProofCircuit::Swap => "swap",
+            ProofCircuit::Liquidity => "liquidity",
+        }
+    }
+
+    fn default_max_felts(self) -> usize {
+        match self {
+            ProofCircuit::Deposit | ProofCircuit::Withdraw => 4096,
+            ProofCircuit::Swap | ProofCircuit::Liquidity => 6144,
+        }
+    }
+
+    // Only the public inputs of the smallest variant (4 swap steps for swaps), so a proof is
+    // always larger than this.
+    fn min_proof_felts(self) -> usize {
+        let public_inputs = match self {
+            ProofCircuit::Deposit => 4,
+            ProofCircuit::Withdraw => 6,
+            ProofCircuit::Swap => 46,
+            ProofCircuit::Liquidity => 47,
+        };
+        2 * public_inputs
+    }
+}
+
+// Artifact sets a deployment can ship independently. `enabled_circuits` picks the ones that
+// are validated at startup and served; the rest answer 404 (no route) or 501.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CircuitSet {
+    Deposit,
+    Withdraw,
+    Liquidity,
+    Swap,
+    SwapExactOut,
+}
+
+impl CircuitSet {
+    const ALL: [CircuitSet; 5] = [
+        CircuitSet::Deposit,
+        CircuitSet::Withdraw,
+        CircuitSet::Liquidity,
+        CircuitSet::Swap,
+        CircuitSet::SwapExactOut,
+    ];
+
+    fn for_swap(exact_out: bool) -> Self {
+        if exact_out {
+            CircuitSet::SwapExactOut
+        } else {
+            CircuitSet::Swap
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CircuitSet::Deposit => "deposit",
+            CircuitSet::Withdraw => "withdraw",
+            CircuitSet::Liquidity => "liquidity",
+            CircuitSet::Swap => "swap",
+            CircuitSet::SwapExactOut => "swap_exact_out",
+        }
+    }
+
+    fn artifact_dirs(self) -> &'static [&'static str] {
+        match self {
+            CircuitSet::Deposit => &["private_deposit"],
+            CircuitSet::Withdraw => &["private_withdraw"],
+            CircuitSet::Liquidity => &["private_liquidity"],
+            CircuitSet::Swap => &[
+                "private_swap_zero_for_one_4",
+                "private_swap_zero_for_one_8",
+                "private_swap_one_for_zero_4",
+                "private_swap_one_for_zero_8",
+            ],
+            CircuitSet::SwapExactOut => &[
+                "private_swap_exact_out_zero_for_one_4",
+                "private_swap_exact_out_zero_for_one_8",
+                "private_swap_exact_out_one_for_zero_4",
+                "private_swap_exact_out_one_for_zero_8",
+            ],
+        }
+    }
+
+    // The public `tag` values each circuit constrains (see the VK_* vars in circuits/*.circom).
+    fn vk_tags(self) -> &'static [u128] {
+        match self {
+            CircuitSet::Deposit => &[0x4445504f534954],
+            CircuitSet::Withdraw => &[0x5749544844524157],
+            CircuitSet::Liquidity => &[
+                0x4c49515f414444,
+                0x4c49515f52454d4f5645,
+                0x4c49515f434c41494d,
+            ],
+            CircuitSet::Swap => &[0x53574150],
+            CircuitSet::SwapExactOut => &[0x535741505f45584143545f4f5554],
+        }
+    }
+}
+
+const DEFAULT_MAX_CONCURRENT_PROOFS: usize = 2;
+const PROOF_QUEUE_WAIT: Duration = Duration::from_secs(30);
+// Retry-After hint for a full proof queue. The caller already waited `PROOF_QUEUE_WAIT`, and
+// permits free up as proofs finish, so a short fixed back-off is as good as any estimate.
+const PROOF_QUEUE_RETRY_AFTER: Duration = Duration::from_secs(5);
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(120);
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// One semaphore per circuit so a burst of cheap proofs cannot starve the expensive ones.
+#[derive(Debug)]
+struct ProofLimiter {
+    permits: HashMap<ProofCircuit, (Arc<Semaphore>, usize)>,
+}
+
+impl ProofLimiter {
+    fn new(limits: &HashMap<ProofCircuit, usize>) -> Self {
+        let permits = ProofCircuit::ALL
+            .into_iter()
+            .map(|circuit| {
+                let limit = limits
+                    .get(&circuit)
+                    .copied()
+                    .unwrap_or(DEFAULT_MAX_CONCURRENT_PROOFS);
+                (circuit, (Arc::new(Semaphore::new(limit)), limit))
+            })
+            .collect();
+        Self { permits }
+    }
+
+    fn available_permits(&self, circuit: ProofCircuit) -> usize {
+        self.permits
+            .get(&circuit)
+            .map_or(0, |(semaphore, _)| semaphore.available_permits())
+    }
+
+    fn in_flight(&self) -> usize {
+        self.permits
+            .values()
+            .map(|(semaphore, limit)| limit.saturating_sub(semaphore.available_permits()))
+            .sum()
+    }
+
+    // Waits until every permit is back or the deadline passes; returns the proofs still running.
+    async fn wait_idle(&self, deadline: tokio::time::Instant) -> usize {
+        loop {
+            let in_flight = self.in_flight();
+            if in_flight == 0 || tokio::time::Instant::now() >= deadline {
+                return in_flight;
+            }
+            tokio::time::sleep_until(
+                deadline.min(tokio::time::Instant::now() + SHUTDOWN_POLL_INTERVAL),
+            )
+            .await;
+        }
+    }
+
+    async fn acquire(
+        &self,
+        circuit: ProofCircuit,
+        wait: Duration,
+    ) -> Result<OwnedSemaphorePermit, ApiError> {
+        let (semaphore, _) =
+            self.permits.get(&circuit).cloned().ok_or_else(|| {
+                ApiError::Internal(format!("no proof queue for {}", circuit.name()))
+            })?;
+        timeout(wait, semaphore.acquire_owned())
+            .await
+            .map_err(|_| ApiError::QueueTimeout)?
+            .map_err(|_| ApiError::Internal("proof queue closed".to_string()))
+    }
+}
+
+// Upper bounds, in seconds, of the proof duration histogram buckets.
+const PROOF_DURATION_BUCKETS: [f64; 9] = [1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0];
+
+#[derive(Debug, Default, Clone)]
+struct DurationHistogram {
+    buckets: [u64; PROOF_DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl DurationHistogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(PROOF_DURATION_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += secs;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    errors: Mutex<HashMap<&'static str, u64>>,
+    proof_durations: Mutex<HashMap<ProofCircuit, DurationHistogram>>,
+}
+
+impl Metrics {
+    fn record_error(&self, kind: &'static str) {
+        *self
+            .errors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(kind)
+            .or_default() += 1;
+    }
+
+    fn observe_proof(&self, circuit: ProofCircuit, elapsed: Duration) {
+        self.proof_durations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(circuit)
+            .or_default()
+            .observe(elapsed);
+    }
+
+    // Prometheus text exposition format, version 0.0.4.
+    fn render(&self, limiter: &ProofLimiter) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP zylith_api_errors_total API error responses by kind.\n");
+        out.push_str("# TYPE zylith_api_errors_total counter\n");
+        let mut errors: Vec<_> = self
+            .errors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|(kind, count)| (*kind, *count))
+            .collect();
+        errors.sort_unstable();
+        for (kind, count) in errors {
+            out.push_str(&format!(
+                "zylith_api_errors_total{{kind=\"{kind}\"}} {count}\n"
+            ));
+        }
+
+        out.push_str("# HELP zylith_proof_duration_seconds Time a proof held its queue permit.\n");
+        out.push_str("# TYPE zylith_proof_duration_seconds histogram\n");
+        let durations = self
+            .proof_durations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        for circuit in ProofCircuit::ALL {
+            let Some(histogram) = durations.get(&circuit) else {
+                continue;
+            };
+            let name = circuit.name();
+            for (bound, count) in PROOF_DURATION_BUCKETS.iter().zip(histogram.buckets) {
+                out.push_str(&format!(
+                    "zylith_proof_duration_seconds_bucket{{circuit=\"{name}\",le=\"{bound}\"}} {count}\n"
+                ));
+            }
+            out.push_str(&format!(
+                "zylith_proof_duration_seconds_bucket{{circuit=\"{name}\",le=\"+Inf\"}} {}\n",
+                histogram.count
+            ));
+            out.push_str(&format!(
+                "zylith_proof_duration_seconds_sum{{circuit=\"{name}\"}} {}\n",
+                histogram.sum
+            ));
+            out.push_str(&format!(
+                "zylith_proof_duration_seconds_count{{circuit=\"{name}\"}} {}\n",
+                histogram.count
+            ));
+        }
+
+        out.push_str("# HELP zylith_proof_permits_available Free proof queue permits.\n");
+        out.push_str("# TYPE zylith_proof_permits_available gauge\n");
+        for circuit in ProofCircuit::ALL {
+            out.push_str(&format!(
+                "zylith_proof_permits_available{{circuit=\"{}\"}} {}\n",
+                circuit.name(),
+                limiter.available_permits(circuit)
+            ));
+        }
+        out
+    }
+}
+
+// Holds a proof queue permit and records how long it was held when dropped.
+struct ProofPermit {
+    _permit: OwnedSemaphorePermit,
+    circuit: ProofCircuit,
+    acquired_at: Instant,
+    metrics: Arc<Metrics>,
+}
+
+impl Drop for ProofPermit {
+    fn drop(&mut self) {
+        self.metrics
+            .observe_proof(self.circuit, self.acquired_at.elapsed());
+    }
+}
+
+const PROOF_STATS_WINDOW: usize = 32;
+
+#[derive(Debug, Default)]
+struct ProofStats {
+    durations: Mutex<HashMap<String, VecDeque<Duration>>>,
+}
+
+impl ProofStats {
+    fn record(&self, circuit: &str, elapsed: Duration) {
+        let mut durations = self
+            .durations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let window = durations.entry(circuit.to_string()).or_default();
+        window.push_back(elapsed);
+        if window.len() > PROOF_STATS_WINDOW {
+            window.pop_front();
+        }
+    }
+
+    fn estimate(&self, circuit: &str) -> (Option<Duration>, usize) {
+        let durations = self
+            .durations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match durations.get(circuit) {
+            Some(window) if !window.is_empty() => {
+                let total: Duration = window.iter().sum();
+                (Some(total / window.len() as u32), window.len())
+            }
+            _ => (None, 0),
+        }
+    }
+}
+
+// Pool config only changes through governance, so it is re-read rarely rather than per request.
+const POOL_CONFIG_REFRESH: Duration = Duration::from_secs(3600);
+
+// A single value that is re-fetched once it is older than `refresh_after`. Concurrent misses
+// queue on the write lock and reuse the first fetch instead of each hitting the RPC.
+#[derive(Debug)]
+struct RefreshingCache<T> {
+    value: tokio::sync::RwLock<Option<(Instant, T)>>,
+    refresh_after: Duration,
+}
+
+impl<T: Clone> RefreshingCache<T> {
+    fn new(refresh_after: Duration) -> Self {
+        Self {
+            value: tokio::sync::RwLock::new(None),
+            refresh_after,
+        }
+    }
+
+    async fn set(&self, value: T) {
+        *self.value.write().await = Some((Instant::now(), value));
+    }
+
+    async fn get_or_fetch<F, Fut, E>(&self, fetch: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+    {
+        if let Some(value) = self.fresh(&*self.value.read().await) {
+            return Ok(value);
+        }
+        let mut slot = self.value.write().await;
+        if let Some(value) = self.fresh(&slot) {
+            return Ok(value);
+        }
+        let value = fetch().await?;
+        *slot = Some((Instant::now(), value.clone()));
+        Ok(value)
+    }
+
+    fn fresh(&self, slot: &Option<(Instant, T)>) -> Option<T> {
+        slot.as_ref()
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.refresh_after)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+const JOB_TTL: Duration = Duration::from_secs(3600);
+// Pending async swap jobs accepted at once. Each holds a parsed request until a proof permit
+// frees up, so the bound keeps a burst of submissions from queueing without limit.
+const MAX_PENDING_SWAP_JOBS: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+enum JobStatus {
+    Pending,
+    Done { result: serde_json::Value },
+    // `code` is the same stable code a synchronous request would have returned.
+    Error { code: &'static str, error: String },
+}
+
+impl JobStatus {
+    fn from_error(err: ApiError) -> Self {
+        let code = err.code();
+        JobStatus::Error {
+            code,
+            error: err.into_parts().1,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct JobEntry {
+    status: JobStatus,
+    updated_at: Instant,
+}
+
+// Results of `POST /jobs` submissions, kept until JOB_TTL after their last update.
+#[derive(Debug)]
+struct JobStore {
+    jobs: Mutex<HashMap<Uuid, JobEntry>>,
+    max_pending: usize,
+}
+
+impl JobStore {
+    fn new(max_pending: usize) -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            max_pending,
+        }
+    }
+
+    fn submit(&self) -> Result<Uuid, ApiError> {
+        let now = Instant::now();
+        let id = Uuid::new_v4();
+        let mut jobs = self.lock();
+        jobs.retain(|_, job| now.duration_since(job.updated_at) <= JOB_TTL);
+        if pending_jobs(&jobs) >= self.max_pending {
+            return Err(ApiError::QueueFull);
+        }
+        jobs.insert(
+            id,
+            JobEntry {
+                status: JobStatus::Pending,
+                updated_at: now,
+            },
+        );
+        Ok(id)
+    }
+
+    fn pending(&self) -> usize {
+        pending_jobs(&self.lock())
+    }
+
+    // Waits until no job is pending or the deadline passes; returns the jobs left unfinished.
+    async fn wait_idle(&self, deadline: tokio::time::Instant) -> usize {
+        loop {
+            let pending = self.pending();
+            if pending == 0 || tokio::time::Instant::now() >= deadline {
+                return pending;
+            }
+            tokio::time::sleep_until(
+                deadline.min(tokio::time::Instant::now() + SHUTDOWN_POLL_INTERVAL),
+            )
+            .await;
+        }
+    }
+
+    fn finish(&self, id: Uuid, status: JobStatus) {
+        if let Some(job) = self.lock().get_mut(&id) {
+            job.status = status;
+            job.updated_at = Instant::now();
+        }
+    }
+
+    fn get(&self, id: Uuid) -> Option<JobStatus> {
+        self.lock().get(&id).map(|job| job.status.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, JobEntry>> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn pending_jobs(jobs: &HashMap<Uuid, JobEntry>) -> usize {
+    jobs.values()
+        .filter(|job| matches!(job.status, JobStatus::Pending))
+        .count()
+}
+
 const RATE_LIMIT_BUCKET_TTL: Duration = Duration::from_secs(600);
 const RATE_LIMIT_MAX_BUCKETS: usize = 10_000;
 
+// Authenticated callers get a bucket per API key so partners behind a shared NAT do not starve
+// each other; everyone else is bucketed by client IP. Signer key ids are public, so a signer's
+// bucket is also split by IP: forged requests naming a partner's key id only drain the forger's.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RateLimitKey {
+    Ip(IpAddr),
+    ApiKey(String),
+    Signer(String, IpAddr),
+}
+
 #[derive(Debug)]
 struct RateLimiter {
-    buckets: Mutex<HashMap<IpAddr, RateBucket>>,
+    buckets: Mutex<HashMap<RateLimitKey, RateBucket>>,
+    default_tier: RateTier,
+    key_tiers: HashMap<String, RateTier>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RateTier {
     rate_per_sec: f64,
     burst: f64,
 }
 
+impl RateTier {
+    fn new(per_minute: u64, burst: u64) -> Self {
+        Self {
+            rate_per_sec: per_minute as f64 / 60.0,
+            burst: burst as f64,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct RateBucket {
     tokens: f64,
@@ -325,16 +1054,25 @@ struct RateBucket {
 }
 
 impl RateLimiter {
-    fn new(per_minute: u64, burst: u64) -> Self {
-        let rate_per_sec = per_minute as f64 / 60.0;
+    fn new(per_minute: u64, burst: u64, key_overrides: &HashMap<String, (u64, u64)>) -> Self {
         Self {
             buckets: Mutex::new(HashMap::new()),
-            rate_per_sec,
-            burst: burst as f64,
+            default_tier: RateTier::new(per_minute, burst),
+            key_tiers: key_overrides
+                .iter()
+                .map(|(key, (per_minute, burst))| (key.clone(), RateTier::new(*per_minute, *burst)))
+                .collect(),
         }
     }
 
-    fn allow(&self, ip: IpAddr) -> bool {
+    // Takes a token from the caller's bucket, or reports how long until one is available.
+    fn check(&self, key: RateLimitKey) -> Result<(), Duration> {
+        let tier = match &key {
+            RateLimitKey::ApiKey(api_key) => self.key_tiers.get(api_key),
+            RateLimitKey::Ip(_) | RateLimitKey::Signer(..) => None,
+        }
+        .copied()
+        .unwrap_or(self.default_tier);
         let now = Instant::now();
         let mut buckets = self
             .buckets
@@ -343,34 +1081,36 @@ impl RateLimiter {
         if buckets.len() >= RATE_LIMIT_MAX_BUCKETS {
             prune_rate_limit_buckets(&mut buckets, now);
         }
-        let bucket = buckets.entry(ip).or_insert_with(|| RateBucket {
-            tokens: self.burst,
+        let bucket = buckets.entry(key).or_insert_with(|| RateBucket {
+            tokens: tier.burst,
             last_refill: now,
         });
         let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
-        bucket.tokens = (bucket.tokens + elapsed * self.rate_per_sec).min(self.burst);
+        bucket.tokens = (bucket.tokens + elapsed * tier.rate_per_sec).min(tier.burst);
         bucket.last_refill = now;
         if bucket.tokens < 1.0 {
-            return false;
+            return Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / tier.rate_per_sec,
+            ));
         }
         bucket.tokens -= 1.0;
-        true
+        Ok(())
     }
 }
 
-fn prune_rate_limit_buckets(buckets: &mut HashMap<IpAddr, RateBucket>, now: Instant) {
+fn prune_rate_limit_buckets(buckets: &mut HashMap<RateLimitKey, RateBucket>, now: Instant) {
     buckets.retain(|_, bucket| now.duration_since(bucket.last_refill) <= RATE_LIMIT_BUCKET_TTL);
     if buckets.len() <= RATE_LIMIT_MAX_BUCKETS {
         return;
     }
-    let mut entries: Vec<(IpAddr, Instant)> = buckets
+    let mut entries: Vec<(RateLimitKey, Instant)> = buckets
         .iter()
-        .map(|(ip, bucket)| (*ip, bucket.last_refill))
+        .map(|(key, bucket)| (key.clone(), bucket.last_refill))
         .collect();
     entries.sort_by_key(|(_, instant)| *instant);
     let over = buckets.len().saturating_sub(RATE_LIMIT_MAX_BUCKETS);
-    for (ip, _) in entries.into_iter().take(over) {
-        buckets.remove(&ip);
+    for (key, _) in entries.into_iter().take(over) {
+        buckets.remove(&key);
     }
 }
 
@@ -385,28 +1125,120 @@ impl From<ClientError> for ApiError {
             ClientError::Prover(msg) => ApiError::Prover(msg),
             ClientError::Io(msg) => ApiError::Internal(msg),
             ClientError::NotImplemented(msg) => ApiError::Internal(msg),
+            ClientError::Timeout(msg) => ApiError::GatewayTimeout(msg),
         }
     }
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let (status, message) = match self {
-            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+// Codes carried by `ApiError::Invalid`. Every other error uses its kind ("bad_request",
+// "upstream", "prover", "queue_timeout", "queue_full", "gateway_timeout", "rate_limited",
+// "unauthorized", "not_found", "not_implemented", "internal"). Codes are never renamed once
+// published.
+const VALIDATION_ERROR_CODES: [&str; 10] = [
+    "note_token_mismatch",
+    "invalid_tick_range",
+    "zero_amount",
+    "liquidity_exceeds_position",
+    "no_input_notes",
+    "too_many_notes",
+    "invalid_number",
+    "invalid_hex",
+    "body_too_large",
+    "proof_too_large",
+];
+
+// Attached to error responses so the metrics middleware can count them by variant.
+#[derive(Debug, Clone, Copy)]
+struct ApiErrorKind(&'static str);
+
+impl ApiError {
+    fn kind(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) | ApiError::Invalid(..) => "bad_request",
+            ApiError::Upstream(_) => "upstream",
+            ApiError::Prover(_) => "prover",
+            ApiError::QueueTimeout => "queue_timeout",
+            ApiError::QueueFull => "queue_full",
+            ApiError::GatewayTimeout(_) => "gateway_timeout",
+            ApiError::RateLimited(_) => "rate_limited",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::NotImplemented(_) => "not_implemented",
+            ApiError::Internal(_) => "internal",
+        }
+    }
+
+    // The `code` in error bodies: the variant's kind, refined for specific validation failures.
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::Invalid(code, _) => {
+                debug_assert!(
+                    VALIDATION_ERROR_CODES.contains(code),
+                    "unlisted code {code}"
+                );
+                code
+            }
+            _ => self.kind(),
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::RateLimited(wait) => Some(*wait),
+            ApiError::QueueTimeout | ApiError::QueueFull => Some(PROOF_QUEUE_RETRY_AFTER),
+            _ => None,
+        }
+    }
+
+    fn into_parts(self) -> (StatusCode, String) {
+        match self {
+            ApiError::BadRequest(msg) | ApiError::Invalid(_, msg) => (StatusCode::BAD_REQUEST, msg),
             ApiError::Upstream(msg) => (StatusCode::BAD_GATEWAY, msg),
             ApiError::Prover(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             ApiError::QueueTimeout => (
                 StatusCode::TOO_MANY_REQUESTS,
                 "proof queue timeout".to_string(),
             ),
-            ApiError::RateLimited => (
+            ApiError::QueueFull => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "proof job queue is full".to_string(),
+            ),
+            ApiError::GatewayTimeout(msg) => (StatusCode::GATEWAY_TIMEOUT, msg),
+            ApiError::RateLimited(_) => (
                 StatusCode::TOO_MANY_REQUESTS,
                 "rate limit exceeded".to_string(),
             ),
             ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized".to_string()),
+            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            ApiError::NotImplemented(msg) => (StatusCode::NOT_IMPLEMENTED, msg),
             ApiError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-        };
-        (status, Json(ErrorResponse { error: message })).into_response()
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let kind = ApiErrorKind(self.kind());
+        let code = self.code();
+        let retry_after = self.retry_after();
+        let (status, message) = self.into_parts();
+        let mut response = (
+            status,
+            Json(ErrorResponse {
+                code,
+                error: message,
+            }),
+        )
+            .into_response();
+        response.extensions_mut().insert(kind);
+        if let Some(wait) = retry_after {
+            // Whole seconds, rounded up so a client retrying on time finds a token.
+            let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs.max(1)));
+        }
+        response
     }
 }
 
@@ -542,9 +1374,15 @@ async fn run() -> Result<(), Box<dyn Error>> {
         .map(PathBuf::from)
         .unwrap_or_else(|_| PathBuf::from("config.toml"));
     let raw = load_config(&config_path)?;
+    if std::env::args().skip(1).any(|arg| arg == "--check-config") {
+        return check_config(raw, &config_path);
+    }
 
     let rpc_url = Url::parse(&raw.rpc_url)?;
-    let provider = JsonRpcClient::new(HttpTransport::new(rpc_url.clone()));
+    // A zero timeout is rejected by `finalize_config` below with a clearer message.
+    let rpc_timeout =
+        (raw.request_timeout_secs > 0).then_some(Duration::from_secs(raw.request_timeout_secs));
+    let provider = rpc_provider(rpc_url, rpc_timeout)?;
     let chain_id = match &raw.chain_id {
         Some(value) => parse_felt(value).map_err(|e| format!("invalid chain_id: {e}"))?,
         None => provider
@@ -553,37 +1391,64 @@ async fn run() -> Result<(), Box<dyn Error>> {
             .map_err(|e| format!("failed to fetch chain_id: {e}"))?,
     };
 
-    let config = finalize_config(raw, &config_path, rpc_url, chain_id)?;
-    validate_artifacts(&config.artifacts_dir)?;
+    let config = finalize_config(raw, &config_path, chain_id)?;
+    validate_artifacts(&config.artifacts_dir, &config.enabled_circuits)?;
     log_swap_variant_status(&config.artifacts_dir);
     maybe_warm_artifacts(&config.artifacts_dir)?;
 
     let account = ReadOnlyAccount::new(provider, config.chain_id, ExecutionEncoding::New);
-    let state = AppState {
-        account,
-        config: Arc::new(config.clone()),
-        limiter: Arc::new(Semaphore::new(config.max_concurrent_proofs)),
-        rate_limiter: Arc::new(RateLimiter::new(
-            config.rate_limit_per_minute,
-            config.rate_limit_burst,
-        )),
-    };
+    let state = AppState::new(account, config.clone());
     validate_onchain_config(&state).await?;
+    let limiter = state.limiter.clone();
+    let jobs = state.jobs.clone();
 
-    let cors = build_cors(&config.cors_allow_origins)?;
-    let app = Router::new()
+    let cors = build_cors(
+        &config.cors_allow_origins,
+        &config.cors_allow_headers,
+        config.cors_allow_credentials,
+    )?;
+    let mut app = Router::new()
         .route("/health", get(health))
         .route("/pool/config", get(pool_config))
-        .route("/quote/swap", post(quote_swap))
-        .route("/quote/liquidity/add", post(quote_liquidity_add))
-        .route("/proofs/swap", post(prove_swap))
-        .route("/proofs/deposit", post(prove_deposit))
-        .route("/proofs/liquidity/add", post(prove_liquidity_add))
-        .route("/proofs/liquidity/remove", post(prove_liquidity_remove))
-        .route("/proofs/liquidity/claim", post(prove_liquidity_claim))
-        .route("/proofs/withdraw", post(prove_withdraw))
-        .layer(from_fn_with_state(state.clone(), rate_limit))
-        .layer(from_fn_with_state(state.clone(), require_api_key))
+        .route("/pool/state", get(pool_state))
+        .route(
+            "/quote/swap",
+            post(quote_swap).layer(DefaultBodyLimit::max(config.max_quote_body_bytes)),
+        )
+        .route(
+            "/quote/liquidity/add",
+            post(quote_liquidity_add).layer(DefaultBodyLimit::max(config.max_quote_body_bytes)),
+        )
+        .route(
+            "/quote/liquidity/remove",
+            post(quote_liquidity_remove).layer(DefaultBodyLimit::max(config.max_quote_body_bytes)),
+        );
+    // Exact-in and exact-out share the swap routes; `parse_swap_proof_request` rejects the
+    // disabled one.
+    if config.circuit_enabled(CircuitSet::Swap) || config.circuit_enabled(CircuitSet::SwapExactOut)
+    {
+        app = app
+            .route("/proofs/swap", post(prove_swap))
+            .route("/jobs", post(submit_swap_job))
+            // The original name of `POST /jobs`, kept for existing callers.
+            .route("/proofs/swap/async", post(submit_swap_job))
+            .route("/jobs/:id", get(get_job))
+            .route("/proofs/swap/estimate", post(estimate_swap_proof));
+    }
+    if config.circuit_enabled(CircuitSet::Deposit) {
+        app = app.route("/proofs/deposit", post(prove_deposit));
+    }
+    if config.circuit_enabled(CircuitSet::Liquidity) {
+        app = app
+            .route("/proofs/liquidity/add", post(prove_liquidity_add))
+            .route("/proofs/liquidity/remove", post(prove_liquidity_remove))
+            .route("/proofs/liquidity/claim", post(prove_liquidity_claim));
+    }
+    if config.circuit_enabled(CircuitSet::Withdraw) {
+        app = app.route("/proofs/withdraw", post(prove_withdraw));
+    }
+    let app = caller_layers(app, &state)
+        .route("/metrics", get(metrics))
         .with_state(state)
         .layer(DefaultBodyLimit::max(config.max_body_bytes))
         .layer(TraceLayer::new_for_http())
@@ -592,14 +1457,73 @@ async fn run() -> Result<(), Box<dyn Error>> {
 
     info!("zylith backend listening on {}", config.bind_addr);
     let listener = tokio::net::TcpListener::bind(config.bind_addr).await?;
-    axum::serve(
+    let (shutdown_tx, mut shutdown_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        let _ = shutdown_tx.send(true);
+    });
+    let mut graceful_rx = shutdown_rx.clone();
+    let server = axum::serve(
         listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
     )
-    .await?;
+    .with_graceful_shutdown(async move {
+        let _ = graceful_rx.wait_for(|requested| *requested).await;
+    });
+    let mut server = std::pin::pin!(server.into_future());
+
+    tokio::select! {
+        result = &mut server => return result.map_err(Into::into),
+        _ = shutdown_rx.wait_for(|requested| *requested) => {}
+    }
+    // Stop accepting connections, then give in-flight proofs and queued async jobs until the
+    // deadline to finish. Jobs still waiting for a permit at that point are dropped with the
+    // process; their ids were never persisted, so callers see them as unknown and resubmit.
+    let deadline = tokio::time::Instant::now() + SHUTDOWN_DRAIN_TIMEOUT;
+    let in_flight = limiter.in_flight();
+    let queued = jobs.pending();
+    info!("shutdown requested; draining {in_flight} in-flight proofs and {queued} async jobs");
+    if let Ok(result) = tokio::time::timeout_at(deadline, &mut server).await {
+        result?;
+    }
+    let remaining_jobs = jobs.wait_idle(deadline).await;
+    let remaining = limiter.wait_idle(deadline).await;
+    info!(
+        "shutdown complete: {} proofs drained, {remaining} force-killed, {remaining_jobs} async \
+         jobs abandoned",
+        in_flight.saturating_sub(remaining)
+    );
     Ok(())
 }
 
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            error!("failed to listen for ctrl-c: {err}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                error!("failed to listen for SIGTERM: {err}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
 fn load_config(path: &Path) -> Result<RawConfig, Box<dyn Error>> {
     let contents = fs::read_to_string(path)?;
     let config: RawConfig = toml::from_str(&contents)?;
@@ -609,67 +1533,124 @@ fn load_config(path: &Path) -> Result<RawConfig, Box<dyn Error>> {
 fn finalize_config(
     raw: RawConfig,
     config_path: &Path,
-    _rpc_url: Url,
     chain_id: Felt,
 ) -> Result<AppConfig, Box<dyn Error>> {
+    let mut problems = Vec::new();
     let base_dir = config_path
         .parent()
         .map(Path::to_path_buf)
         .unwrap_or_else(|| PathBuf::from("."));
     let artifacts_dir = resolve_path(&base_dir, &raw.artifacts_dir);
-    let asp_url = Url::parse(&raw.asp_url).map_err(|e| format!("asp_url: {e}"))?;
-
-    let bind_addr = raw
-        .bind_addr
-        .parse::<SocketAddr>()
-        .map_err(|e| format!("invalid bind_addr: {e}"))?;
-    let pool_address = parse_felt(&raw.pool_address).map_err(|e| format!("pool_address: {e}"))?;
-    let shielded_notes_address = parse_felt(&raw.shielded_notes_address)
-        .map_err(|e| format!("shielded_notes_address: {e}"))?;
-    let token0 = parse_felt(&raw.token0).map_err(|e| format!("token0: {e}"))?;
-    let token1 = parse_felt(&raw.token1).map_err(|e| format!("token1: {e}"))?;
+    let asp_url = config_value(&mut problems, "asp_url", Url::parse(&raw.asp_url));
+    let bind_addr = config_value(
+        &mut problems,
+        "invalid bind_addr",
+        raw.bind_addr.parse::<SocketAddr>(),
+    );
+    let pool_address = config_value(&mut problems, "pool_address", parse_felt(&raw.pool_address));
+    let shielded_notes_address = config_value(
+        &mut problems,
+        "shielded_notes_address",
+        parse_felt(&raw.shielded_notes_address),
+    );
+    let token0 = config_value(&mut problems, "token0", parse_felt(&raw.token0));
+    let token1 = config_value(&mut problems, "token1", parse_felt(&raw.token1));
 
-    if pool_address == Felt::ZERO {
-        return Err("pool_address cannot be zero".into());
+    if pool_address == Some(Felt::ZERO) {
+        problems.push("pool_address cannot be zero".to_string());
     }
-    if shielded_notes_address == Felt::ZERO {
-        return Err("shielded_notes_address cannot be zero".into());
+    if shielded_notes_address == Some(Felt::ZERO) {
+        problems.push("shielded_notes_address cannot be zero".to_string());
     }
-    if token0 == Felt::ZERO || token1 == Felt::ZERO {
-        return Err("token0/token1 cannot be zero".into());
+    if token0 == Some(Felt::ZERO) || token1 == Some(Felt::ZERO) {
+        problems.push("token0/token1 cannot be zero".to_string());
     }
-    if token0 == token1 {
-        return Err("token0 and token1 must be different".into());
-    }
-    if raw.max_concurrent_proofs == 0 {
-        return Err("max_concurrent_proofs must be >= 1".into());
+    if token0.is_some() && token0 == token1 {
+        problems.push("token0 and token1 must be different".to_string());
     }
+    let max_concurrent_proofs = proof_concurrency_limits(&raw.max_concurrent_proofs, &mut problems);
     if raw.request_timeout_secs == 0 {
-        return Err("request_timeout_secs must be >= 1".into());
+        problems.push("request_timeout_secs must be >= 1".to_string());
     }
     if raw.max_body_bytes == 0 {
-        return Err("max_body_bytes must be >= 1".into());
+        problems.push("max_body_bytes must be >= 1".to_string());
+    }
+    let max_quote_body_bytes = raw.max_quote_body_bytes.unwrap_or(raw.max_body_bytes);
+    if max_quote_body_bytes == 0 {
+        problems.push("max_quote_body_bytes must be >= 1".to_string());
     }
     if raw.cors_allow_origins.is_empty() {
-        return Err("cors_allow_origins must not be empty".into());
+        problems.push("cors_allow_origins must not be empty".to_string());
     }
     if raw.rate_limit_per_minute == 0 {
-        return Err("rate_limit_per_minute must be >= 1".into());
+        problems.push("rate_limit_per_minute must be >= 1".to_string());
     }
     if raw.rate_limit_burst == 0 {
-        return Err("rate_limit_burst must be >= 1".into());
+        problems.push("rate_limit_burst must be >= 1".to_string());
     }
-    let api_key = raw.api_key.and_then(|value| {
-        let trimmed = value.trim();
-        if trimmed.is_empty() {
-            None
-        } else {
-            Some(trimmed.to_string())
+    let api_keys = collect_api_keys(raw.api_key, raw.api_keys);
+    let rate_limit_key_overrides = rate_limit_key_overrides(
+        raw.rate_limit_keys.unwrap_or_default(),
+        &api_keys,
+        (raw.rate_limit_per_minute, raw.rate_limit_burst),
+        &mut problems,
+    );
+    if raw.trust_proxy == Some(true) {
+        problems.push(
+            "trust_proxy was replaced by trusted_proxies; list the proxy CIDRs instead".to_string(),
+        );
+    }
+    let mut trusted_proxies = Vec::new();
+    for entry in raw.trusted_proxies.unwrap_or_default() {
+        match entry.parse::<IpNetwork>() {
+            Ok(network) => trusted_proxies.push(network),
+            Err(err) => problems.push(format!("trusted_proxies: {err}")),
         }
-    });
-    let trust_proxy = raw.trust_proxy.unwrap_or(false);
-    if api_key.is_none() && !is_dev_mode() {
-        return Err("api_key must be set unless ENV=dev or ENV=test".into());
+    }
+    let max_proof_felts = proof_felt_limits(raw.max_proof_felts.as_ref(), &mut problems);
+    let mut signing_keys = HashMap::new();
+    for (key_id, public_key) in raw.request_signing_keys.unwrap_or_default() {
+        match parse_felt(&public_key) {
+            Ok(key) if key != Felt::ZERO => {
+                signing_keys.insert(key_id, key);
+            }
+            _ => problems.push(format!("request_signing_keys.{key_id}: invalid public key")),
+        }
+    }
+    let request_signature_max_age = raw
+        .request_signature_max_age_secs
+        .unwrap_or(DEFAULT_SIGNATURE_MAX_AGE_SECS);
+    if request_signature_max_age == 0 {
+        problems.push("request_signature_max_age_secs must be >= 1".to_string());
+    }
+    if api_keys.is_empty() && signing_keys.is_empty() && !is_dev_mode() {
+        problems.push(
+            "api_key, api_keys or request_signing_keys must be set unless ENV=dev or ENV=test"
+                .to_string(),
+        );
+    }
+    let enabled_circuits = enabled_circuit_sets(raw.enabled_circuits.as_deref(), &mut problems);
+
+    let (
+        Some(asp_url),
+        Some(bind_addr),
+        Some(pool_address),
+        Some(shielded_notes_address),
+        Some(token0),
+        Some(token1),
+    ) = (
+        asp_url,
+        bind_addr,
+        pool_address,
+        shielded_notes_address,
+        token0,
+        token1,
+    )
+    else {
+        return Err(problems.join("; ").into());
+    };
+    if !problems.is_empty() {
+        return Err(problems.join("; ").into());
     }
 
     Ok(AppConfig {
@@ -681,17 +1662,210 @@ fn finalize_config(
         chain_id,
         bind_addr,
         artifacts_dir,
-        max_concurrent_proofs: raw.max_concurrent_proofs,
+        max_concurrent_proofs,
         request_timeout: Duration::from_secs(raw.request_timeout_secs),
         max_body_bytes: raw.max_body_bytes,
+        max_quote_body_bytes,
         cors_allow_origins: raw.cors_allow_origins,
+        cors_allow_credentials: raw.cors_allow_credentials.unwrap_or(false),
+        cors_allow_headers: raw.cors_allow_headers.unwrap_or_default(),
         rate_limit_per_minute: raw.rate_limit_per_minute,
         rate_limit_burst: raw.rate_limit_burst,
-        api_key,
-        trust_proxy,
+        rate_limit_key_overrides,
+        api_keys,
+        trusted_proxies,
+        request_signing: RequestSigning {
+            keys: signing_keys,
+            max_age: Duration::from_secs(request_signature_max_age),
+        },
+        max_proof_felts,
+        enabled_circuits,
     })
 }
 
+// Unset means every circuit; names match `CircuitSet::name`.
+fn enabled_circuit_sets(raw: Option<&[String]>, problems: &mut Vec<String>) -> Vec<CircuitSet> {
+    let Some(names) = raw else {
+        return CircuitSet::ALL.to_vec();
+    };
+    let mut enabled = Vec::new();
+    for name in names {
+        match CircuitSet::ALL
+            .into_iter()
+            .find(|c| c.name() == name.trim())
+        {
+            Some(circuit) if !enabled.contains(&circuit) => enabled.push(circuit),
+            Some(_) => {}
+            None => problems.push(format!("enabled_circuits: unknown circuit {name}")),
+        }
+    }
+    if names.is_empty() {
+        problems.push("enabled_circuits must not be empty".to_string());
+    }
+    enabled
+}
+
+fn proof_concurrency_limits(
+    raw: &RawCircuitLimits,
+    problems: &mut Vec<String>,
+) -> HashMap<ProofCircuit, usize> {
+    let (default, overrides) = match raw {
+        RawCircuitLimits::Global(limit) => {
+            if *limit == 0 {
+                problems.push("max_concurrent_proofs must be >= 1".to_string());
+            }
+            (*limit, None)
+        }
+        RawCircuitLimits::PerCircuit(table) => {
+            for key in table.keys() {
+                if key != "default" && !ProofCircuit::ALL.iter().any(|c| c.name() == key) {
+                    problems.push(format!("max_concurrent_proofs: unknown circuit {key}"));
+                }
+            }
+            let default = table
+                .get("default")
+                .copied()
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_PROOFS);
+            (default, Some(table))
+        }
+    };
+    ProofCircuit::ALL
+        .into_iter()
+        .map(|circuit| {
+            let limit = overrides
+                .and_then(|table| table.get(circuit.name()).copied())
+                .unwrap_or(default);
+            if limit == 0 && overrides.is_some() {
+                problems.push(format!(
+                    "max_concurrent_proofs.{} must be >= 1",
+                    circuit.name()
+                ));
+            }
+            (circuit, limit)
+        })
+        .collect()
+}
+
+// A single number only lowers the per-circuit defaults, since deposit and withdraw proofs are far
+// smaller than swap and liquidity ones; a table sets the listed circuits outright.
+fn proof_felt_limits(
+    raw: Option<&RawCircuitLimits>,
+    problems: &mut Vec<String>,
+) -> HashMap<ProofCircuit, usize> {
+    if let Some(RawCircuitLimits::PerCircuit(table)) = raw {
+        for key in table.keys() {
+            if !ProofCircuit::ALL.iter().any(|c| c.name() == key) {
+                problems.push(format!("max_proof_felts: unknown circuit {key}"));
+            }
+        }
+    }
+    ProofCircuit::ALL
+        .into_iter()
+        .map(|circuit| {
+            let default = circuit.default_max_felts();
+            let limit = match raw {
+                None => default,
+                Some(RawCircuitLimits::Global(limit)) => (*limit).min(default),
+                Some(RawCircuitLimits::PerCircuit(table)) => {
+                    table.get(circuit.name()).copied().unwrap_or(default)
+                }
+            };
+            if limit == 0 {
+                problems.push(format!("max_proof_felts.{} must be >= 1", circuit.name()));
+            }
+            (circuit, limit)
+        })
+        .collect()
+}
+
+// A single legacy `api_key` is merged with `api_keys` so both old and new keys are
+// accepted while a rotation is in progress.
+fn collect_api_keys(api_key: Option<String>, api_keys: Option<RawApiKeys>) -> Vec<String> {
+    let listed = match api_keys {
+        Some(RawApiKeys::List(keys)) => keys,
+        Some(RawApiKeys::Csv(keys)) => keys.split(',').map(str::to_string).collect(),
+        None => Vec::new(),
+    };
+    let mut keys: Vec<String> = Vec::new();
+    for key in api_key.into_iter().chain(listed) {
+        let trimmed = key.trim();
+        if !trimmed.is_empty() && !keys.iter().any(|existing| existing == trimmed) {
+            keys.push(trimmed.to_string());
+        }
+    }
+    keys
+}
+
+// Entries are keyed by the API key itself, so problems never echo the key back.
+fn rate_limit_key_overrides(
+    raw: HashMap<String, RawRateLimitOverride>,
+    api_keys: &[String],
+    (per_minute, burst): (u64, u64),
+    problems: &mut Vec<String>,
+) -> HashMap<String, (u64, u64)> {
+    let mut overrides = HashMap::new();
+    for (key, limits) in raw {
+        let key = key.trim().to_string();
+        if !api_keys.contains(&key) {
+            problems
+                .push("rate_limit_keys entry does not match any configured api key".to_string());
+            continue;
+        }
+        let per_minute = limits.rate_limit_per_minute.unwrap_or(per_minute);
+        let burst = limits.rate_limit_burst.unwrap_or(burst);
+        if per_minute == 0 || burst == 0 {
+            problems.push("rate_limit_keys limits must be >= 1".to_string());
+            continue;
+        }
+        overrides.insert(key, (per_minute, burst));
+    }
+    overrides
+}
+
+fn config_value<T, E: std::fmt::Display>(
+    problems: &mut Vec<String>,
+    label: &str,
+    result: Result<T, E>,
+) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(err) => {
+            problems.push(format!("{label}: {err}"));
+            None
+        }
+    }
+}
+
+// Validates config and local artifacts without touching the network; used by --check-config.
+fn check_config(raw: RawConfig, config_path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut problems = Vec::new();
+    config_value(&mut problems, "rpc_url", Url::parse(&raw.rpc_url));
+    let chain_id = match &raw.chain_id {
+        Some(value) => {
+            config_value(&mut problems, "invalid chain_id", parse_felt(value)).unwrap_or(Felt::ZERO)
+        }
+        None => Felt::ZERO,
+    };
+    match finalize_config(raw, config_path, chain_id) {
+        Ok(config) => {
+            if let Err(err) = validate_artifacts(&config.artifacts_dir, &config.enabled_circuits) {
+                problems.push(err.to_string());
+            }
+        }
+        Err(err) => problems.push(err.to_string()),
+    }
+    if !problems.is_empty() {
+        return Err(format!(
+            "invalid config {}: {}",
+            config_path.display(),
+            problems.join("; ")
+        )
+        .into());
+    }
+    info!("config ok: {}", config_path.display());
+    Ok(())
+}
+
 fn is_dev_mode() -> bool {
     match std::env::var("ENV") {
         Ok(value) => matches!(value.as_str(), "dev" | "test"),
@@ -708,22 +1882,60 @@ fn resolve_path(base: &Path, value: &str) -> PathBuf {
     }
 }
 
-fn validate_artifacts(root: &Path) -> Result<(), Box<dyn Error>> {
-    let required_circuits = ["private_deposit", "private_liquidity", "private_withdraw"];
-    for circuit in required_circuits {
-        validate_circuit_artifacts(root, circuit)?;
+fn validate_artifacts(root: &Path, enabled: &[CircuitSet]) -> Result<(), Box<dyn Error>> {
+    for circuit in enabled {
+        for dir in circuit.artifact_dirs() {
+            validate_circuit_artifacts(root, dir)?;
+            validate_vk_tags(
+                &root.join(dir).join("verification_key.json"),
+                circuit.vk_tags(),
+            )?;
+        }
     }
-    for circuit in [
-        "private_swap_zero_for_one_4",
-        "private_swap_zero_for_one_8",
-        "private_swap_one_for_zero_4",
-        "private_swap_one_for_zero_8",
-        "private_swap_exact_out_zero_for_one_4",
-        "private_swap_exact_out_zero_for_one_8",
-        "private_swap_exact_out_one_for_zero_4",
-        "private_swap_exact_out_one_for_zero_8",
-    ] {
-        validate_circuit_artifacts(root, circuit)?;
+    Ok(())
+}
+
+// Artifact builds stamp `zylith_tags` (decimal strings of the circuit's tag constants) into
+// verification_key.json with circuits/scripts/stamp_vk_tags.js. A missing or mismatched stamp
+// means we cannot tell which circuit the keys belong to, and a wrong one would only surface once
+// proofs reach the verifier contract, so both refuse startup.
+fn validate_vk_tags(path: &Path, expected: &[u128]) -> Result<(), Box<dyn Error>> {
+    let vk: serde_json::Value = serde_json::from_slice(&fs::read(path)?).map_err(|err| {
+        format!(
+            "verification key {} is not valid json: {err}",
+            path.display()
+        )
+    })?;
+    let tags = vk.get("zylith_tags").ok_or_else(|| {
+        format!(
+            "verification key {} has no zylith_tags; stamp it with \
+             circuits/scripts/stamp_vk_tags.js after exporting the key",
+            path.display()
+        )
+    })?;
+    let mut found = tags
+        .as_array()
+        .and_then(|tags| {
+            tags.iter()
+                .map(|tag| tag.as_str()?.parse::<u128>().ok())
+                .collect::<Option<Vec<_>>>()
+        })
+        .ok_or_else(|| {
+            format!(
+                "verification key {} has malformed zylith_tags",
+                path.display()
+            )
+        })?;
+    found.sort_unstable();
+    let mut expected = expected.to_vec();
+    expected.sort_unstable();
+    if found != expected {
+        return Err(format!(
+            "verification key {} is tagged {found:?} but the circuit expects {expected:?}; \
+             the artifacts were built from a different circuit",
+            path.display()
+        )
+        .into());
     }
     Ok(())
 }
@@ -867,11 +2079,39 @@ fn swap_artifact_dir_name(exact_out: bool, zero_for_one: bool) -> &'static str {
     }
 }
 
-fn build_cors(origins: &[String]) -> Result<CorsLayer, Box<dyn Error>> {
+fn swap_circuit_key(exact_out: bool, zero_for_one: bool, steps: usize) -> String {
+    format!(
+        "{}_{steps}",
+        swap_artifact_dir_name(exact_out, zero_for_one)
+    )
+}
+
+fn build_cors(
+    origins: &[String],
+    extra_headers: &[String],
+    allow_credentials: bool,
+) -> Result<CorsLayer, Box<dyn Error>> {
+    let wildcard = origins.iter().any(|o| o == "*");
+    // Browsers refuse credentialed responses with `Access-Control-Allow-Origin: *`, and
+    // tower-http panics on the combination, so reject it as a config error up front.
+    if wildcard && allow_credentials {
+        return Err(
+            "cors_allow_credentials requires explicit cors_allow_origins, not \"*\"".into(),
+        );
+    }
+    let mut headers = vec![header::CONTENT_TYPE, HeaderName::from_static("x-api-key")];
+    for name in extra_headers {
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|err| format!("invalid cors_allow_headers entry {name:?}: {err}"))?;
+        if !headers.contains(&name) {
+            headers.push(name);
+        }
+    }
     let base = CorsLayer::new()
         .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
-        .allow_headers([header::CONTENT_TYPE, HeaderName::from_static("x-api-key")]);
-    if origins.iter().any(|o| o == "*") {
+        .allow_headers(headers)
+        .allow_credentials(allow_credentials);
+    if wildcard {
         return Ok(base.allow_origin(Any));
     }
     let mut values = Vec::with_capacity(origins.len());
@@ -886,6 +2126,14 @@ async fn health() -> Json<HealthResponse> {
     Json(HealthResponse { status: "ok" })
 }
 
+// The layer added last runs first: errors are counted, then the caller is rate limited, and only
+// then authenticated, so failed authentication still costs a token.
+fn caller_layers(app: Router<AppState>, state: &AppState) -> Router<AppState> {
+    app.layer(from_fn_with_state(state.clone(), authenticate))
+        .layer(from_fn_with_state(state.clone(), rate_limit))
+        .layer(from_fn_with_state(state.clone(), track_errors))
+}
+
 async fn rate_limit(
     State(state): State<AppState>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
@@ -895,79 +2143,332 @@ async fn rate_limit(
     if req.method() == Method::OPTIONS {
         return next.run(req).await;
     }
-    let ip = client_ip(&req, addr, state.config.trust_proxy);
-    if !state.rate_limiter.allow(ip) {
-        return ApiError::RateLimited.into_response();
+    if let Err(wait) = state
+        .rate_limiter
+        .check(rate_limit_key(&req, addr, &state.config))
+    {
+        return ApiError::RateLimited(wait).into_response();
     }
     next.run(req).await
 }
 
-fn client_ip(req: &Request<Body>, addr: SocketAddr, trust_proxy: bool) -> IpAddr {
-    if !trust_proxy {
-        return addr.ip();
+// Runs before `authenticate`, so callers that fail it are still limited, by IP, before their
+// body is buffered or their signature checked. Only credentials `authenticate` could accept
+// earn their own bucket: an issued API key, or a registered signer key id. In dev mode nothing
+// is issued, so every request stays on the IP.
+fn rate_limit_key(req: &Request<Body>, addr: SocketAddr, config: &AppConfig) -> RateLimitKey {
+    let ip = client_ip(req, addr, &config.trusted_proxies);
+    let headers = req.headers();
+    let signer = headers
+        .get(SIGNATURE_KEY_ID_HEADER)
+        .and_then(|value| value.to_str().ok());
+    if let Some(key_id) = signer {
+        if config.request_signing.keys.contains_key(key_id) {
+            return RateLimitKey::Signer(key_id.to_string(), ip);
+        }
+        return RateLimitKey::Ip(ip);
+    }
+    match headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(key)
+            if !config.api_keys.is_empty() && api_key_authorized(&config.api_keys, Some(key)) =>
+        {
+            RateLimitKey::ApiKey(key.to_string())
+        }
+        _ => RateLimitKey::Ip(ip),
+    }
+}
+
+// A CIDR block such as `10.0.0.0/8` or `fd00::/8`; a bare address is a single-host block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    fn contains(&self, ip: IpAddr) -> bool {
+        let (net, ip, width) = match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                (u32::from(net) as u128, u32::from(ip) as u128, 32)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => (u128::from(net), u128::from(ip), 128),
+            _ => return false,
+        };
+        let shift = width - u32::from(self.prefix);
+        shift == width || (net >> shift) == (ip >> shift)
+    }
+}
+
+impl std::str::FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|_| format!("invalid address in {value:?}"))?
+            .to_canonical();
+        let width = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= width)
+                .ok_or_else(|| format!("invalid prefix length in {value:?}"))?,
+            None => width,
+        };
+        Ok(Self { addr, prefix })
+    }
+}
+
+// Forwarded headers are only believed when the socket peer is a trusted proxy. X-Forwarded-For
+// is walked right to left because each proxy appends the address it saw: the first hop outside
+// the trusted set is the client, and anything further left is client-supplied. A malformed hop
+// falls back to the peer address rather than guessing.
+fn client_ip(req: &Request<Body>, addr: SocketAddr, trusted_proxies: &[IpNetwork]) -> IpAddr {
+    let peer = addr.ip().to_canonical();
+    let trusted = |ip: IpAddr| trusted_proxies.iter().any(|network| network.contains(ip));
+    if !trusted(peer) {
+        return peer;
     }
     if let Some(value) = req.headers().get("x-forwarded-for") {
-        if let Ok(value) = value.to_str() {
-            for part in value.split(',') {
-                let trimmed = part.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                if let Ok(ip) = trimmed.parse::<IpAddr>() {
-                    return ip;
-                }
-                if let Ok(sock) = trimmed.parse::<SocketAddr>() {
-                    return sock.ip();
-                }
+        let Ok(value) = value.to_str() else {
+            return peer;
+        };
+        let mut client = peer;
+        for part in value.rsplit(',') {
+            let Some(ip) = parse_forwarded_ip(part) else {
+                return peer;
+            };
+            client = ip;
+            if !trusted(ip) {
+                break;
             }
         }
+        return client;
     }
     if let Some(value) = req.headers().get("x-real-ip") {
-        if let Ok(value) = value.to_str() {
-            if let Ok(ip) = value.trim().parse::<IpAddr>() {
-                return ip;
-            }
-            if let Ok(sock) = value.trim().parse::<SocketAddr>() {
-                return sock.ip();
-            }
+        if let Some(ip) = value.to_str().ok().and_then(parse_forwarded_ip) {
+            return ip;
         }
     }
-    addr.ip()
+    peer
 }
 
-async fn acquire_proof_permit(state: &AppState) -> Result<OwnedSemaphorePermit, ApiError> {
-    let wait = Duration::from_secs(30);
-    let permit = timeout(wait, state.limiter.clone().acquire_owned())
-        .await
-        .map_err(|_| ApiError::QueueTimeout)?
-        .map_err(|_| ApiError::Internal("proof queue closed".to_string()))?;
-    Ok(permit)
+fn parse_forwarded_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    value
+        .parse::<IpAddr>()
+        .or_else(|_| value.parse::<SocketAddr>().map(|sock| sock.ip()))
+        .ok()
+        .map(|ip| ip.to_canonical())
 }
 
-async fn require_api_key(
-    State(state): State<AppState>,
-    req: Request<Body>,
-    next: Next,
-) -> Response {
+async fn acquire_proof_permit(
+    state: &AppState,
+    circuit: ProofCircuit,
+) -> Result<ProofPermit, ApiError> {
+    acquire_proof_permit_within(state, circuit, PROOF_QUEUE_WAIT).await
+}
+
+async fn acquire_proof_permit_within(
+    state: &AppState,
+    circuit: ProofCircuit,
+    wait: Duration,
+) -> Result<ProofPermit, ApiError> {
+    ensure_proof_can_fit(&state.config, circuit)?;
+    let permit = state.limiter.acquire(circuit, wait).await?;
+    Ok(ProofPermit {
+        _permit: permit,
+        circuit,
+        acquired_at: Instant::now(),
+        metrics: state.metrics.clone(),
+    })
+}
+
+async fn track_errors(State(state): State<AppState>, req: Request<Body>, next: Next) -> Response {
+    let response = next.run(req).await;
+    if let Some(ApiErrorKind(kind)) = response.extensions().get::<ApiErrorKind>() {
+        state.metrics.record_error(kind);
+    }
+    response
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(&state.limiter),
+    )
+}
+
+const DEFAULT_SIGNATURE_MAX_AGE_SECS: u64 = 300;
+const SIGNATURE_KEY_ID_HEADER: &str = "x-zylith-key-id";
+const SIGNATURE_TIMESTAMP_HEADER: &str = "x-zylith-timestamp";
+const SIGNATURE_HEADER: &str = "x-zylith-signature";
+
+// Accepts either a signed request (when the key-id header is present) or an API key.
+async fn authenticate(State(state): State<AppState>, req: Request<Body>, next: Next) -> Response {
     if req.method() == Method::OPTIONS {
         return next.run(req).await;
     }
-    if let Some(expected) = state.config.api_key.as_deref() {
-        let provided = req
-            .headers()
-            .get("x-api-key")
-            .and_then(|value| value.to_str().ok());
-        if provided != Some(expected) {
+    let config = &state.config;
+    if req.headers().contains_key(SIGNATURE_KEY_ID_HEADER) {
+        let (parts, body) = req.into_parts();
+        let body = match axum::body::to_bytes(body, config.max_body_bytes).await {
+            Ok(body) => body,
+            Err(_) => {
+                return ApiError::Invalid("body_too_large", "request body too large".to_string())
+                    .into_response()
+            }
+        };
+        let path = parts
+            .uri
+            .path_and_query()
+            .map_or(parts.uri.path(), |path| path.as_str());
+        if let Err(reason) = verify_signed_request(
+            &config.request_signing,
+            &parts.method,
+            path,
+            &body,
+            &parts.headers,
+            unix_now(),
+        ) {
+            warn!("rejected signed request: {reason}");
             return ApiError::Unauthorized.into_response();
         }
+        return next.run(Request::from_parts(parts, Body::from(body))).await;
+    }
+    if config.api_keys.is_empty() && config.request_signing.keys.is_empty() {
+        return next.run(req).await;
+    }
+    let provided = req
+        .headers()
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok());
+    if config.api_keys.is_empty() || !api_key_authorized(&config.api_keys, provided) {
+        return ApiError::Unauthorized.into_response();
     }
     next.run(req).await
 }
 
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// Signed requests carry a key id, a unix timestamp and a Stark ECDSA signature `r,s` over
+// `signed_request_digest`. The freshness window bounds how long a captured request can be
+// replayed; it does not make requests single-use.
+fn verify_signed_request(
+    signing: &RequestSigning,
+    method: &Method,
+    path: &str,
+    body: &[u8],
+    headers: &HeaderMap,
+    now: u64,
+) -> Result<String, &'static str> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .ok_or("missing signature header")
+    };
+    let key_id = header(SIGNATURE_KEY_ID_HEADER)?;
+    let public_key = signing.keys.get(key_id).ok_or("unknown key id")?;
+    let timestamp: u64 = header(SIGNATURE_TIMESTAMP_HEADER)?
+        .parse()
+        .map_err(|_| "invalid timestamp")?;
+    if now.abs_diff(timestamp) > signing.max_age.as_secs() {
+        return Err("stale timestamp");
+    }
+    let (r, s) = header(SIGNATURE_HEADER)?
+        .split_once(',')
+        .ok_or("signature must be r,s")?;
+    let signature = Signature {
+        r: Felt::from_hex(r.trim()).map_err(|_| "invalid signature")?,
+        s: Felt::from_hex(s.trim()).map_err(|_| "invalid signature")?,
+    };
+    let digest = signed_request_digest(method, path, body, timestamp);
+    match ecdsa_verify(public_key, &digest, &signature) {
+        Ok(true) => Ok(key_id.to_string()),
+        _ => Err("bad signature"),
+    }
+}
+
+// pedersen(domain, keccak(method), keccak(path and query), keccak(body), timestamp), with the
+// same element hashing Starknet uses for transaction hashes.
+fn signed_request_digest(method: &Method, path: &str, body: &[u8], timestamp: u64) -> Felt {
+    compute_hash_on_elements(&[
+        starknet_keccak(b"zylith-request-v1"),
+        starknet_keccak(method.as_str().as_bytes()),
+        starknet_keccak(path.as_bytes()),
+        starknet_keccak(body),
+        Felt::from(timestamp),
+    ])
+}
+
+// No configured keys means auth is disabled (only allowed in dev/test). Every key is
+// compared so the response time does not reveal which one matched.
+fn api_key_authorized(keys: &[String], provided: Option<&str>) -> bool {
+    if keys.is_empty() {
+        return true;
+    }
+    let Some(provided) = provided else {
+        return false;
+    };
+    keys.iter().fold(false, |matched, key| {
+        matched | constant_time_eq(key.as_bytes(), provided.as_bytes())
+    })
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
 async fn pool_config(State(state): State<AppState>) -> Result<Json<PoolConfigResponse>, ApiError> {
+    let config = cached_pool_config(&state).await?;
+    let tokens = pool_token_metadata(&state).await;
+    Ok(Json(pool_config_response(config, &state.config, tokens)))
+}
+
+async fn pool_state(State(state): State<AppState>) -> Result<Json<PoolStateResponse>, ApiError> {
     let client = build_client(&state);
-    let config = client.get_pool_config().await?;
-    Ok(Json(pool_config_response(config, &state.config)))
+    Ok(Json(pool_state_response(client.get_pool_state().await?)))
+}
+
+// Metadata only feeds optional display fields, so a failed read is logged and the response
+// goes out without them; the next request tries again.
+async fn pool_token_metadata(state: &AppState) -> Option<&[TokenMetadata; 2]> {
+    let fetched = state
+        .token_metadata
+        .get_or_try_init(|| async {
+            let client = build_client(state);
+            let (token0, token1) = tokio::try_join!(
+                client.get_token_metadata(state.config.token0),
+                client.get_token_metadata(state.config.token1),
+            )?;
+            Ok::<_, ClientError>([token0, token1])
+        })
+        .await;
+    match fetched {
+        Ok(tokens) => Some(tokens),
+        Err(err) => {
+            warn!("token metadata unavailable: {err}");
+            None
+        }
+    }
 }
 
 async fn quote_swap(
@@ -976,22 +2477,20 @@ async fn quote_swap(
 ) -> Result<Json<SwapQuoteResponse>, ApiError> {
     let amount = parse_u128(&request.amount)?;
     if amount == 0 {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "zero_amount",
             "amount must be greater than zero".to_string(),
         ));
     }
     let client = build_client(&state);
-    let pool_config = client.get_pool_config().await?;
+    let pool_config = cached_pool_config(&state).await?;
     let sqrt_ratio_limit = request
         .sqrt_ratio_limit
         .map(|value| parse_u256(&value))
         .transpose()?
         .unwrap_or_else(|| default_sqrt_ratio_limit(&pool_config, request.zero_for_one));
-    let is_token1 = if request.exact_out {
-        request.zero_for_one
-    } else {
-        !request.zero_for_one
-    };
+    let direction = SwapDirection::from(request.zero_for_one);
+    let is_token1 = direction.quote_is_token1(request.exact_out);
     let quote = client
         .swap_client()
         .quote_swap_steps(zylith_client::SwapQuoteRequest {
@@ -1005,13 +2504,25 @@ async fn quote_swap(
         })
         .await?;
     let (amount_in, amount_out) = summarize_quote_amounts(&quote.steps)?;
+    let (token_in_address, token_out_address) = swap_tokens(&pool_config, direction);
+    let (token_in, token_out) = match pool_token_metadata(&state).await {
+        Some(tokens) => (
+            Some(&tokens[usize::from(direction.input_token_id())]),
+            Some(&tokens[usize::from(direction.output_token_id())]),
+        ),
+        None => (None, None),
+    };
     Ok(Json(SwapQuoteResponse {
+        token_in: felt_to_hex(token_in_address),
+        token_out: felt_to_hex(token_out_address),
         amount_in: amount_in.to_string(),
         amount_out: amount_out.to_string(),
         sqrt_price_end: u256_to_hex(quote.sqrt_price_end),
         tick_end: quote.tick_end,
         liquidity_end: quote.liquidity_end.to_string(),
         is_limited: quote.is_limited,
+        amount_in_display: token_in.map(|token| token.format_amount(amount_in)),
+        amount_out_display: token_out.map(|token| token.format_amount(amount_out)),
     }))
 }
 
@@ -1021,23 +2532,26 @@ async fn quote_liquidity_add(
 ) -> Result<Json<LiquidityQuoteResponse>, ApiError> {
     let liquidity_delta = parse_u128(&request.liquidity_delta)?;
     if liquidity_delta == 0 {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "zero_amount",
             "liquidity_delta must be greater than zero".to_string(),
         ));
     }
     if request.tick_lower >= request.tick_upper {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "invalid_tick_range",
             "tick_lower must be less than tick_upper".to_string(),
         ));
     }
     let client = build_client(&state);
-    let pool_config = client.get_pool_config().await?;
+    let pool_config = cached_pool_config(&state).await?;
     let tick_spacing = pool_config.tick_spacing as i32;
     if tick_spacing == 0 {
         return Err(ApiError::BadRequest("tick_spacing is zero".to_string()));
     }
     if request.tick_lower % tick_spacing != 0 || request.tick_upper % tick_spacing != 0 {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "invalid_tick_range",
             "ticks must align to tick spacing".to_string(),
         ));
     }
@@ -1061,21 +2575,110 @@ async fn quote_liquidity_add(
         liquidity_delta,
         true,
     )?;
+    let [amount0_display, amount1_display] =
+        display_amounts(pool_token_metadata(&state).await, [amount0, amount1]);
     Ok(Json(LiquidityQuoteResponse {
         amount0: amount0.to_string(),
         amount1: amount1.to_string(),
         sqrt_ratio_lower: u256_to_hex(sqrt_ratio_lower),
         sqrt_ratio_upper: u256_to_hex(sqrt_ratio_upper),
+        amount0_display,
+        amount1_display,
     }))
 }
 
+async fn quote_liquidity_remove(
+    State(state): State<AppState>,
+    Json(request): Json<LiquidityRemoveQuoteRequest>,
+) -> Result<Json<LiquidityRemoveQuoteResponse>, ApiError> {
+    let (position_note, liquidity_delta) = parse_liquidity_remove_quote(request)?;
+    let client = build_client(&state);
+    let quote = client
+        .quote_liquidity_remove(&position_note, liquidity_delta)
+        .await?;
+    Ok(Json(liquidity_remove_quote_response(
+        quote,
+        pool_token_metadata(&state).await,
+    )))
+}
+
+fn parse_liquidity_remove_quote(
+    request: LiquidityRemoveQuoteRequest,
+) -> Result<(PositionNote, u128), ApiError> {
+    let position_note = parse_position_note(request.position_note)?;
+    let liquidity_delta = parse_u128(&request.liquidity_delta)?;
+    if liquidity_delta == 0 {
+        return Err(ApiError::Invalid(
+            "zero_amount",
+            "liquidity_delta must be greater than zero".to_string(),
+        ));
+    }
+    if liquidity_delta > position_note.liquidity {
+        return Err(ApiError::Invalid(
+            "liquidity_exceeds_position",
+            "liquidity_delta exceeds position liquidity".to_string(),
+        ));
+    }
+    Ok((position_note, liquidity_delta))
+}
+
 async fn prove_swap(
     State(state): State<AppState>,
     Json(request): Json<SwapProofRequest>,
 ) -> Result<Json<SwapProofResponse>, ApiError> {
-    let _permit = acquire_proof_permit(&state).await?;
+    let request = parse_swap_proof_request(&state, request)?;
+    Ok(Json(
+        run_swap_proof(&state, request, SwapProofMode::Sync).await?,
+    ))
+}
+
+// Malformed requests are rejected here, before a job id is handed out, so a 202 only ever
+// means the request is waiting for the prover.
+async fn submit_swap_job(
+    State(state): State<AppState>,
+    Json(request): Json<SwapProofRequest>,
+) -> Result<(StatusCode, Json<JobSubmitResponse>), ApiError> {
+    let request = parse_swap_proof_request(&state, request)?;
+    let job_id = state.jobs.submit()?;
+    tokio::spawn(async move {
+        let status = match run_swap_proof(&state, request, SwapProofMode::Job).await {
+            Ok(response) => match serde_json::to_value(response) {
+                Ok(result) => JobStatus::Done { result },
+                Err(err) => {
+                    let err = ApiError::Internal(format!("failed to encode proof: {err}"));
+                    state.metrics.record_error(err.kind());
+                    JobStatus::from_error(err)
+                }
+            },
+            Err(err) => JobStatus::from_error(err),
+        };
+        state.jobs.finish(job_id, status);
+    });
+    Ok((StatusCode::ACCEPTED, Json(JobSubmitResponse { job_id })))
+}
+
+async fn get_job(
+    State(state): State<AppState>,
+    PathParam(id): PathParam<Uuid>,
+) -> Result<Json<JobStatus>, ApiError> {
+    state
+        .jobs
+        .get(id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("unknown job {id}")))
+}
+
+// Everything that can be checked without the chain or the prover.
+fn parse_swap_proof_request(
+    state: &AppState,
+    request: SwapProofRequest,
+) -> Result<SwapProveRequest, ApiError> {
+    require_circuit(&state.config, CircuitSet::for_swap(request.exact_out))?;
     if request.notes.is_empty() {
-        return Err(ApiError::BadRequest("notes cannot be empty".to_string()));
+        return Err(ApiError::Invalid(
+            "no_input_notes",
+            "notes cannot be empty".to_string(),
+        ));
     }
     let notes = parse_notes(request.notes, &state.config)?;
     let output_note = parse_note_option(request.output_note, &state.config)?;
@@ -1087,7 +2690,8 @@ async fn prove_swap(
     if request.exact_out {
         match amount_out {
             Some(0) => {
-                return Err(ApiError::BadRequest(
+                return Err(ApiError::Invalid(
+                    "zero_amount",
                     "amount_out must be greater than zero".to_string(),
                 ));
             }
@@ -1103,6 +2707,14 @@ async fn prove_swap(
             "amount_out is only valid for exact_out".to_string(),
         ));
     }
+    let amount_in_max = request
+        .amount_in_max
+        .map(|value| parse_u128(&value))
+        .transpose()?;
+    let amount_out_min = request
+        .amount_out_min
+        .map(|value| parse_u128(&value))
+        .transpose()?;
     let sqrt_ratio_limit = request
         .sqrt_ratio_limit
         .map(|value| parse_u256(&value))
@@ -1112,22 +2724,68 @@ async fn prove_swap(
         request.exact_out,
         request.zero_for_one,
     ));
-    let client = build_client(&state);
-    let result = client
-        .prove_swap(SwapProveRequest {
-            notes,
-            zero_for_one: request.zero_for_one,
-            exact_out: request.exact_out,
-            amount_out,
-            sqrt_ratio_limit,
-            output_note,
-            change_note,
-            circuit_dir: Some(circuit_dir),
-        })
-        .await?;
+    Ok(SwapProveRequest {
+        notes,
+        direction: request.zero_for_one.into(),
+        exact_out: request.exact_out,
+        amount_out,
+        amount_in_max,
+        amount_out_min,
+        sqrt_ratio_limit,
+        output_note,
+        change_note,
+        circuit_dir: Some(circuit_dir),
+        require_active_pool: false,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SwapProofMode {
+    // The caller holds the connection, so the queue wait is the usual PROOF_QUEUE_WAIT.
+    Sync,
+    // Admission is already bounded by MAX_PENDING_SWAP_JOBS, so an accepted job waits for a
+    // permit as long as its result would be kept. Its errors never reach `track_errors`.
+    Job,
+}
+
+async fn run_swap_proof(
+    state: &AppState,
+    request: SwapProveRequest,
+    mode: SwapProofMode,
+) -> Result<SwapProofResponse, ApiError> {
+    let result = prove_swap_response(state, request, mode).await;
+    if let (SwapProofMode::Job, Err(err)) = (mode, &result) {
+        state.metrics.record_error(err.kind());
+    }
+    result
+}
+
+async fn prove_swap_response(
+    state: &AppState,
+    request: SwapProveRequest,
+    mode: SwapProofMode,
+) -> Result<SwapProofResponse, ApiError> {
+    let queue_wait = match mode {
+        SwapProofMode::Sync => PROOF_QUEUE_WAIT,
+        SwapProofMode::Job => JOB_TTL,
+    };
+    let _permit = acquire_proof_permit_within(state, ProofCircuit::Swap, queue_wait).await?;
+    let exact_out = request.exact_out;
+    let zero_for_one = request.direction.is_zero_for_one();
+    let client = build_client(state);
+    let started = Instant::now();
+    let result = client.prove_swap(request).await?;
+    state.proof_stats.record(
+        &swap_circuit_key(exact_out, zero_for_one, result.selected_swap_steps),
+        started.elapsed(),
+    );
 
-    Ok(Json(SwapProofResponse {
-        proof: result.proof.to_calldata(),
+    Ok(SwapProofResponse {
+        proof: checked_proof_calldata(
+            &state.config,
+            ProofCircuit::Swap,
+            result.proof.to_calldata(),
+        )?,
         input_proofs: result
             .input_proofs
             .into_iter()
@@ -1143,6 +2801,59 @@ async fn prove_swap(
         amount_out: result.amount_out.to_string(),
         amount_in_consumed: result.amount_in_consumed.to_string(),
         selected_swap_steps: result.selected_swap_steps,
+    })
+}
+
+async fn estimate_swap_proof(
+    State(state): State<AppState>,
+    Json(request): Json<SwapQuoteRequest>,
+) -> Result<Json<SwapProofEstimateResponse>, ApiError> {
+    require_circuit(&state.config, CircuitSet::for_swap(request.exact_out))?;
+    let amount = parse_u128(&request.amount)?;
+    if amount == 0 {
+        return Err(ApiError::Invalid(
+            "zero_amount",
+            "amount must be greater than zero".to_string(),
+        ));
+    }
+    let client = build_client(&state);
+    let pool_config = cached_pool_config(&state).await?;
+    let sqrt_ratio_limit = request
+        .sqrt_ratio_limit
+        .map(|value| parse_u256(&value))
+        .transpose()?
+        .unwrap_or_else(|| default_sqrt_ratio_limit(&pool_config, request.zero_for_one));
+    let is_token1 = SwapDirection::from(request.zero_for_one).quote_is_token1(request.exact_out);
+    let quote = client
+        .swap_client()
+        .quote_swap_steps(zylith_client::SwapQuoteRequest {
+            amount: SignedAmount {
+                mag: amount,
+                sign: request.exact_out,
+            },
+            is_token1,
+            sqrt_ratio_limit,
+            skip_ahead: 0,
+        })
+        .await?;
+    let circuit_dir = state.config.artifacts_dir.join(swap_artifact_dir_name(
+        request.exact_out,
+        request.zero_for_one,
+    ));
+    let (active_steps, selected_swap_steps) = plan_swap_circuit_steps(
+        Some(circuit_dir),
+        request.exact_out,
+        request.zero_for_one,
+        &quote,
+    )?;
+    let circuit = swap_circuit_key(request.exact_out, request.zero_for_one, selected_swap_steps);
+    let (estimate, samples) = state.proof_stats.estimate(&circuit);
+    Ok(Json(SwapProofEstimateResponse {
+        circuit,
+        active_steps,
+        selected_swap_steps,
+        estimated_ms: estimate.map(|value| value.as_millis() as u64),
+        samples,
     }))
 }
 
@@ -1150,7 +2861,6 @@ async fn prove_deposit(
     State(state): State<AppState>,
     Json(request): Json<DepositProofRequest>,
 ) -> Result<Json<DepositProofResponse>, ApiError> {
-    let _permit = acquire_proof_permit(&state).await?;
     if request.token_id > 1 {
         return Err(ApiError::BadRequest("token_id must be 0 or 1".to_string()));
     }
@@ -1163,26 +2873,35 @@ async fn prove_deposit(
     let (note, response_note) = if auto_generate {
         let amount = parse_u128(&request.note.amount)?;
         if amount == 0 {
-            return Err(ApiError::BadRequest(
+            return Err(ApiError::Invalid(
+                "zero_amount",
                 "note amount must be greater than zero".to_string(),
             ));
         }
         let token =
             parse_felt(&request.note.token).map_err(|e| ApiError::BadRequest(e.to_string()))?;
         if token != expected_token {
-            return Err(ApiError::BadRequest("note token mismatch".to_string()));
+            return Err(ApiError::Invalid(
+                "note_token_mismatch",
+                "note token mismatch".to_string(),
+            ));
         }
         let note = generate_note_with_token_id(amount, expected_token, request.token_id)?;
         (note.clone(), Some(note_output(note)))
     } else {
         let note = parse_note(request.note, &state.config)?;
         if note.token != expected_token {
-            return Err(ApiError::BadRequest("note token mismatch".to_string()));
+            return Err(ApiError::Invalid(
+                "note_token_mismatch",
+                "note token mismatch".to_string(),
+            ));
         }
         (note, None)
     };
 
-    let commitment = compute_commitment(&note, request.token_id)?;
+    let client = build_client(&state);
+    let commitment = client.simulate_deposit(&note, request.token_id)?;
+    let _permit = acquire_proof_permit(&state, ProofCircuit::Deposit).await?;
     let tag = vk_tag("DEPOSIT")?;
     let mut values = HashMap::new();
     values.insert("tag".to_string(), WitnessValue::Scalar(tag.to_string()));
@@ -1210,14 +2929,13 @@ async fn prove_deposit(
         .await
         .map_err(|e| ApiError::Prover(e.to_string()))?;
 
-    let client = build_client(&state);
     let insertion_proof = client
         .swap_client()
         .fetch_insertion_path(expected_token)
         .await?;
 
     Ok(Json(DepositProofResponse {
-        proof: proof.to_calldata(),
+        proof: checked_proof_calldata(&state.config, ProofCircuit::Deposit, proof.to_calldata())?,
         insertion_proof: merkle_path_response(insertion_proof),
         commitment: felt_to_hex(commitment),
         note: response_note,
@@ -1228,7 +2946,6 @@ async fn prove_liquidity_add(
     State(state): State<AppState>,
     Json(request): Json<LiquidityAddProofRequest>,
 ) -> Result<Json<LiquidityProofResponse>, ApiError> {
-    let _permit = acquire_proof_permit(&state).await?;
     let token0_notes =
         parse_notes_for_token(request.token0_notes, &state.config, state.config.token0)?;
     let token1_notes =
@@ -1247,40 +2964,46 @@ async fn prove_liquidity_add(
     )?;
     let liquidity_delta = parse_u128(&request.liquidity_delta)?;
     if liquidity_delta == 0 {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "zero_amount",
             "liquidity_delta must be greater than zero".to_string(),
         ));
     }
     if request.tick_lower >= request.tick_upper {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "invalid_tick_range",
             "tick_lower must be less than tick_upper".to_string(),
         ));
     }
-    let pool_config = build_client(&state).get_pool_config().await?;
+    let pool_config = cached_pool_config(&state).await?;
     let tick_spacing = pool_config.tick_spacing as i32;
     if tick_spacing == 0 {
         return Err(ApiError::BadRequest("tick_spacing is zero".to_string()));
     }
     if request.tick_lower % tick_spacing != 0 || request.tick_upper % tick_spacing != 0 {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "invalid_tick_range",
             "ticks must align to tick spacing".to_string(),
         ));
     }
     if let Some(note) = &position_note {
         if note.tick_lower != request.tick_lower || note.tick_upper != request.tick_upper {
-            return Err(ApiError::BadRequest(
+            return Err(ApiError::Invalid(
+                "invalid_tick_range",
                 "position_note tick bounds must match request".to_string(),
             ));
         }
     }
     if let Some(note) = &output_position_note {
         if note.tick_lower != request.tick_lower || note.tick_upper != request.tick_upper {
-            return Err(ApiError::BadRequest(
+            return Err(ApiError::Invalid(
+                "invalid_tick_range",
                 "output_position_note tick bounds must match request".to_string(),
             ));
         }
     }
 
+    let _permit = acquire_proof_permit(&state, ProofCircuit::Liquidity).await?;
     let client = build_client(&state);
     let result = client
         .prove_liquidity_add(LiquidityAddProveRequest {
@@ -1294,16 +3017,16 @@ async fn prove_liquidity_add(
             output_note_token0,
             output_note_token1,
             circuit_dir: Some(state.config.artifacts_dir.join("private_liquidity")),
+            require_active_pool: false,
         })
         .await?;
-    Ok(Json(liquidity_response(result)))
+    Ok(Json(liquidity_response(&state.config, result)?))
 }
 
 async fn prove_liquidity_remove(
     State(state): State<AppState>,
     Json(request): Json<LiquidityRemoveProofRequest>,
 ) -> Result<Json<LiquidityProofResponse>, ApiError> {
-    let _permit = acquire_proof_permit(&state).await?;
     let position_note = parse_position_note(request.position_note)?;
     let output_position_note = parse_position_note_option(request.output_position_note)?;
     let output_note_token0 = parse_note_option_for_token(
@@ -1320,18 +3043,21 @@ async fn prove_liquidity_remove(
         if note.tick_lower != position_note.tick_lower
             || note.tick_upper != position_note.tick_upper
         {
-            return Err(ApiError::BadRequest(
+            return Err(ApiError::Invalid(
+                "invalid_tick_range",
                 "output_position_note tick bounds must match position_note".to_string(),
             ));
         }
     }
     let liquidity_delta = parse_u128(&request.liquidity_delta)?;
     if liquidity_delta == 0 {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "zero_amount",
             "liquidity_delta must be greater than zero".to_string(),
         ));
     }
 
+    let _permit = acquire_proof_permit(&state, ProofCircuit::Liquidity).await?;
     let client = build_client(&state);
     let result = client
         .prove_liquidity_remove(LiquidityRemoveProveRequest {
@@ -1341,16 +3067,16 @@ async fn prove_liquidity_remove(
             output_note_token0,
             output_note_token1,
             circuit_dir: Some(state.config.artifacts_dir.join("private_liquidity")),
+            require_active_pool: false,
         })
         .await?;
-    Ok(Json(liquidity_response(result)))
+    Ok(Json(liquidity_response(&state.config, result)?))
 }
 
 async fn prove_liquidity_claim(
     State(state): State<AppState>,
     Json(request): Json<LiquidityClaimProofRequest>,
 ) -> Result<Json<LiquidityProofResponse>, ApiError> {
-    let _permit = acquire_proof_permit(&state).await?;
     let position_note = parse_position_note(request.position_note)?;
     let output_position_note = parse_position_note_option(request.output_position_note)?;
     let output_note_token0 = parse_note_option_for_token(
@@ -1367,12 +3093,14 @@ async fn prove_liquidity_claim(
         if note.tick_lower != position_note.tick_lower
             || note.tick_upper != position_note.tick_upper
         {
-            return Err(ApiError::BadRequest(
+            return Err(ApiError::Invalid(
+                "invalid_tick_range",
                 "output_position_note tick bounds must match position_note".to_string(),
             ));
         }
     }
 
+    let _permit = acquire_proof_permit(&state, ProofCircuit::Liquidity).await?;
     let client = build_client(&state);
     let result = client
         .prove_liquidity_claim(LiquidityClaimProveRequest {
@@ -1381,16 +3109,16 @@ async fn prove_liquidity_claim(
             output_note_token0,
             output_note_token1,
             circuit_dir: Some(state.config.artifacts_dir.join("private_liquidity")),
+            require_active_pool: false,
         })
         .await?;
-    Ok(Json(liquidity_response(result)))
+    Ok(Json(liquidity_response(&state.config, result)?))
 }
 
 async fn prove_withdraw(
     State(state): State<AppState>,
     Json(request): Json<WithdrawProofRequest>,
 ) -> Result<Json<WithdrawProofResponse>, ApiError> {
-    let _permit = acquire_proof_permit(&state).await?;
     if request.token_id > 1 {
         return Err(ApiError::BadRequest("token_id must be 0 or 1".to_string()));
     }
@@ -1401,7 +3129,10 @@ async fn prove_withdraw(
         state.config.token1
     };
     if note.token != token_expected {
-        return Err(ApiError::BadRequest("note token mismatch".to_string()));
+        return Err(ApiError::Invalid(
+            "note_token_mismatch",
+            "note token mismatch".to_string(),
+        ));
     }
     if request.root_index.is_some() && request.root_hash.is_some() {
         return Err(ApiError::BadRequest(
@@ -1410,8 +3141,17 @@ async fn prove_withdraw(
     }
     let recipient =
         parse_felt(&request.recipient).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let root_hash = request
+        .root_hash
+        .map(|value| parse_felt(&value).map_err(|e| ApiError::BadRequest(e.to_string())))
+        .transpose()?;
     let commitment = compute_commitment(&note, request.token_id)?;
     let nullifier = generate_nullifier_hash(&note, request.token_id)?;
+    let client = build_client(&state);
+    client
+        .ensure_nullifiers_unspent(InsertionTree::Token(note.token), &[nullifier])
+        .await?;
+    let _permit = acquire_proof_permit(&state, ProofCircuit::Withdraw).await?;
     let tag = vk_tag("WITHDRAW")?;
     let mut values = HashMap::new();
     values.insert("tag".to_string(), WitnessValue::Scalar(tag.to_string()));
@@ -1447,24 +3187,30 @@ async fn prove_withdraw(
         .await
         .map_err(|e| ApiError::Prover(e.to_string()))?;
 
-    let client = build_client(&state);
-    let root_hash = request
-        .root_hash
-        .map(|value| parse_felt(&value).map_err(|e| ApiError::BadRequest(e.to_string())))
-        .transpose()?;
     let merkle_proof = client
         .swap_client()
         .fetch_merkle_path(commitment, request.root_index, root_hash)
         .await?;
 
     Ok(Json(WithdrawProofResponse {
-        proof: proof.to_calldata(),
+        proof: checked_proof_calldata(&state.config, ProofCircuit::Withdraw, proof.to_calldata())?,
         merkle_proof: merkle_path_response(merkle_proof),
         commitment: felt_to_hex(commitment),
         nullifier: felt_to_hex(nullifier),
     }))
 }
 
+fn require_circuit(config: &AppConfig, circuit: CircuitSet) -> Result<(), ApiError> {
+    if config.circuit_enabled(circuit) {
+        Ok(())
+    } else {
+        Err(ApiError::NotImplemented(format!(
+            "{} proofs are disabled on this deployment",
+            circuit.name()
+        )))
+    }
+}
+
 fn build_client(state: &AppState) -> ZylithClient<ReadOnlyAccount<JsonRpcClient<HttpTransport>>> {
     ZylithClient::new(ZylithConfig {
         account: state.account.clone(),
@@ -1473,9 +3219,20 @@ fn build_client(state: &AppState) -> ZylithClient<ReadOnlyAccount<JsonRpcClient<
         shielded_notes_address: state.config.shielded_notes_address,
         token0: state.config.token0,
         token1: state.config.token1,
+        rpc_timeout: Some(state.config.request_timeout),
+        request_timeout: state.config.request_timeout,
+        pool_state_ttl: None,
+        check_nullifiers: true,
     })
 }
 
+async fn cached_pool_config(state: &AppState) -> Result<PoolConfig, ClientError> {
+    state
+        .pool_config
+        .get_or_fetch(|| async { build_client(state).get_pool_config().await })
+        .await
+}
+
 async fn validate_onchain_config(state: &AppState) -> Result<(), Box<dyn Error>> {
     let client = build_client(state);
     let pool_config = client
@@ -1492,6 +3249,7 @@ async fn validate_onchain_config(state: &AppState) -> Result<(), Box<dyn Error>>
         )
         .into());
     }
+    state.pool_config.set(pool_config).await;
 
     let provider = state.account.provider();
     let notes_token0 =
@@ -1539,9 +3297,10 @@ async fn call_contract_felt<P: Provider + Sync>(
 
 fn parse_notes(inputs: Vec<NoteInput>, config: &AppConfig) -> Result<Vec<Note>, ApiError> {
     if inputs.len() > MAX_INPUT_NOTES {
-        return Err(ApiError::BadRequest(format!(
-            "notes length exceeds max {MAX_INPUT_NOTES}"
-        )));
+        return Err(ApiError::Invalid(
+            "too_many_notes",
+            format!("notes length exceeds max {MAX_INPUT_NOTES}"),
+        ));
     }
     inputs
         .into_iter()
@@ -1555,15 +3314,17 @@ fn parse_notes_for_token(
     expected_token: Felt,
 ) -> Result<Vec<Note>, ApiError> {
     if inputs.len() > MAX_INPUT_NOTES {
-        return Err(ApiError::BadRequest(format!(
-            "notes length exceeds max {MAX_INPUT_NOTES}"
-        )));
+        return Err(ApiError::Invalid(
+            "too_many_notes",
+            format!("notes length exceeds max {MAX_INPUT_NOTES}"),
+        ));
     }
     let mut parsed = Vec::with_capacity(inputs.len());
     for input in inputs {
         let note = parse_note(input, config)?;
         if note.token != expected_token {
-            return Err(ApiError::BadRequest(
+            return Err(ApiError::Invalid(
+                "note_token_mismatch",
                 "note token does not match expected token".to_string(),
             ));
         }
@@ -1591,7 +3352,8 @@ fn parse_note_option_for_token(
         Some(note) => {
             let parsed = parse_note(note, config)?;
             if parsed.token != expected_token {
-                return Err(ApiError::BadRequest(
+                return Err(ApiError::Invalid(
+                    "note_token_mismatch",
                     "note token does not match expected token".to_string(),
                 ));
             }
@@ -1602,27 +3364,22 @@ fn parse_note_option_for_token(
 }
 
 fn parse_note(input: NoteInput, config: &AppConfig) -> Result<Note, ApiError> {
-    let secret = parse_bytes32(&input.secret)?;
-    let nullifier = parse_bytes32(&input.nullifier)?;
-    let amount = parse_u128(&input.amount)?;
-    if amount == 0 {
-        return Err(ApiError::BadRequest(
+    let note =
+        Note::try_from_hex_fields(&input.secret, &input.nullifier, &input.amount, &input.token)?;
+    if note.amount == 0 {
+        return Err(ApiError::Invalid(
+            "zero_amount",
             "note amount must be greater than zero".to_string(),
         ));
     }
-    let token = parse_felt(&input.token).map_err(|e| ApiError::BadRequest(e.to_string()))?;
-    validate_note_token(token, config)?;
-    Ok(Note {
-        secret,
-        nullifier,
-        amount,
-        token,
-    })
+    validate_note_token(note.token, config)?;
+    Ok(note)
 }
 
 fn validate_note_token(token: Felt, config: &AppConfig) -> Result<(), ApiError> {
     if token != config.token0 && token != config.token1 {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "note_token_mismatch",
             "note token must be token0 or token1".to_string(),
         ));
     }
@@ -1642,7 +3399,8 @@ fn parse_position_note(input: PositionNoteInput) -> Result<PositionNote, ApiErro
     let secret = parse_bytes32(&input.secret)?;
     let nullifier = parse_bytes32(&input.nullifier)?;
     if input.tick_lower >= input.tick_upper {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "invalid_tick_range",
             "tick_lower must be less than tick_upper".to_string(),
         ));
     }
@@ -1692,9 +3450,12 @@ fn merkle_path_response(path: MerklePath) -> MerklePathResponse {
     }
 }
 
-fn liquidity_response(result: LiquidityProveResult) -> LiquidityProofResponse {
-    LiquidityProofResponse {
-        proof: result.proof.to_calldata(),
+fn liquidity_response(
+    config: &AppConfig,
+    result: LiquidityProveResult,
+) -> Result<LiquidityProofResponse, ApiError> {
+    Ok(LiquidityProofResponse {
+        proof: checked_proof_calldata(config, ProofCircuit::Liquidity, result.proof.to_calldata())?,
         proofs_token0: result
             .proofs_token0
             .into_iter()
@@ -1712,10 +3473,54 @@ fn liquidity_response(result: LiquidityProveResult) -> LiquidityProofResponse {
         output_note_token0: result.output_note_token0.map(note_output),
         output_note_token1: result.output_note_token1.map(note_output),
         output_position_note: result.output_position_note.map(position_note_output),
+    })
+}
+
+// Refuses before a prover slot is spent when no proof of this circuit could pass
+// `checked_proof_calldata`.
+fn ensure_proof_can_fit(config: &AppConfig, circuit: ProofCircuit) -> Result<(), ApiError> {
+    let max = config.max_proof_felts[&circuit];
+    let min = circuit.min_proof_felts();
+    if min > max {
+        return Err(ApiError::Invalid(
+            "proof_too_large",
+            format!(
+                "{} proofs need more than {min} felts (max {max})",
+                circuit.name()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn checked_proof_calldata(
+    config: &AppConfig,
+    circuit: ProofCircuit,
+    calldata: Vec<String>,
+) -> Result<Vec<String>, ApiError> {
+    let max = config.max_proof_felts[&circuit];
+    if calldata.len() > max {
+        return Err(ApiError::Invalid(
+            "proof_too_large",
+            format!(
+                "{} proof too large: {} felts (max {max})",
+                circuit.name(),
+                calldata.len()
+            ),
+        ));
     }
+    Ok(calldata)
 }
 
-fn pool_config_response(config: PoolConfig, app_config: &AppConfig) -> PoolConfigResponse {
+fn pool_config_response(
+    config: PoolConfig,
+    app_config: &AppConfig,
+    tokens: Option<&[TokenMetadata; 2]>,
+) -> PoolConfigResponse {
+    let [token0_metadata, token1_metadata] = match tokens {
+        Some([token0, token1]) => [Some(token0.clone()), Some(token1.clone())],
+        None => [None, None],
+    };
     PoolConfigResponse {
         pool_address: felt_to_hex(app_config.pool_address),
         shielded_notes_address: felt_to_hex(app_config.shielded_notes_address),
@@ -1726,9 +3531,34 @@ fn pool_config_response(config: PoolConfig, app_config: &AppConfig) -> PoolConfi
         min_sqrt_ratio: u256_to_hex(config.min_sqrt_ratio),
         max_sqrt_ratio: u256_to_hex(config.max_sqrt_ratio),
         max_input_notes: MAX_INPUT_NOTES,
+        token0_metadata,
+        token1_metadata,
+    }
+}
+
+// Fee growth words arrive as (low, high) limbs, like the contract's u256.
+fn pool_state_response(pool_state: PoolState) -> PoolStateResponse {
+    let (fee0_low, fee0_high) = pool_state.fee_growth_global_0;
+    let (fee1_low, fee1_high) = pool_state.fee_growth_global_1;
+    PoolStateResponse {
+        sqrt_price: u256_to_hex(pool_state.sqrt_price),
+        tick: pool_state.tick,
+        liquidity: pool_state.liquidity.to_string(),
+        fee_growth_global_0: u256_to_hex(U256::from_words(fee0_low, fee0_high)),
+        fee_growth_global_1: u256_to_hex(U256::from_words(fee1_low, fee1_high)),
     }
 }
 
+// (input, output) token addresses. These do not depend on exact-in vs exact-out; only which
+// side the quoted amount refers to does.
+fn swap_tokens(config: &PoolConfig, direction: SwapDirection) -> (Felt, Felt) {
+    let tokens = [config.token0, config.token1];
+    (
+        tokens[usize::from(direction.input_token_id())],
+        tokens[usize::from(direction.output_token_id())],
+    )
+}
+
 fn default_sqrt_ratio_limit(config: &PoolConfig, zero_for_one: bool) -> U256 {
     if zero_for_one {
         config.min_sqrt_ratio
@@ -1753,11 +3583,12 @@ fn summarize_quote_amounts(steps: &[SwapStepQuote]) -> Result<(u128, u128), ApiE
 
 fn parse_u128(value: &str) -> Result<u128, ApiError> {
     if let Some(hex) = value.strip_prefix("0x") {
-        u128::from_str_radix(hex, 16).map_err(|_| ApiError::BadRequest("invalid u128".to_string()))
+        u128::from_str_radix(hex, 16)
+            .map_err(|_| ApiError::Invalid("invalid_number", "invalid u128".to_string()))
     } else {
         value
             .parse::<u128>()
-            .map_err(|_| ApiError::BadRequest("invalid u128".to_string()))
+            .map_err(|_| ApiError::Invalid("invalid_number", "invalid u128".to_string()))
     }
 }
 
@@ -1768,10 +3599,13 @@ fn parse_u256(value: &str) -> Result<U256, ApiError> {
         (10, value)
     };
     let parsed = BigUint::from_str_radix(digits, radix)
-        .map_err(|_| ApiError::BadRequest("invalid u256".to_string()))?;
+        .map_err(|_| ApiError::Invalid("invalid_number", "invalid u256".to_string()))?;
     let bytes = parsed.to_bytes_be();
     if bytes.len() > 32 {
-        return Err(ApiError::BadRequest("u256 overflow".to_string()));
+        return Err(ApiError::Invalid(
+            "invalid_number",
+            "u256 overflow".to_string(),
+        ));
     }
     let mut padded = [0u8; 32];
     padded[32 - bytes.len()..].copy_from_slice(&bytes);
@@ -1783,11 +3617,13 @@ fn parse_u256(value: &str) -> Result<U256, ApiError> {
 fn parse_bytes32(value: &str) -> Result<[u8; 32], ApiError> {
     let hex = value.strip_prefix("0x").unwrap_or(value);
     if hex.len() != 64 {
-        return Err(ApiError::BadRequest(
+        return Err(ApiError::Invalid(
+            "invalid_hex",
             "expected 32-byte hex string".to_string(),
         ));
     }
-    let bytes = hex::decode(hex).map_err(|_| ApiError::BadRequest("invalid hex".to_string()))?;
+    let bytes = hex::decode(hex)
+        .map_err(|_| ApiError::Invalid("invalid_hex", "invalid hex".to_string()))?;
     let mut out = [0u8; 32];
     out.copy_from_slice(&bytes);
     Ok(out)
@@ -1832,3 +3668,900 @@ fn vk_tag(tag: &str) -> Result<u64, ApiError> {
         _ => Err(ApiError::BadRequest("unknown verifier tag".to_string())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::signers::SigningKey;
+
+    #[tokio::test]
+    async fn saturated_deposits_do_not_block_swaps() {
+        let limits = HashMap::from([(ProofCircuit::Deposit, 1), (ProofCircuit::Swap, 1)]);
+        let limiter = ProofLimiter::new(&limits);
+        let wait = Duration::from_millis(20);
+
+        let _deposit = limiter
+            .acquire(ProofCircuit::Deposit, wait)
+            .await
+            .expect("first deposit permit");
+        assert!(matches!(
+            limiter.acquire(ProofCircuit::Deposit, wait).await,
+            Err(ApiError::QueueTimeout)
+        ));
+        limiter
+            .acquire(ProofCircuit::Swap, wait)
+            .await
+            .expect("swap permit while deposits are saturated");
+    }
+
+    #[test]
+    fn jobs_report_pending_then_result() {
+        let store = JobStore::new(2);
+        let id = store.submit().expect("job");
+        assert!(matches!(store.get(id), Some(JobStatus::Pending)));
+
+        store.finish(id, JobStatus::from_error(ApiError::QueueTimeout));
+        let body = serde_json::to_value(store.get(id).expect("job")).expect("json");
+        assert_eq!(body["status"], "error");
+        assert_eq!(body["code"], "queue_timeout");
+        assert_eq!(body["error"], "proof queue timeout");
+        assert!(store.get(Uuid::new_v4()).is_none());
+
+        // Finished jobs do not count against the bound; pending ones do.
+        store.submit().expect("second job");
+        store.submit().expect("third job");
+        assert!(matches!(store.submit(), Err(ApiError::QueueFull)));
+        assert_eq!(store.pending(), 2);
+    }
+
+    // Nothing listens on port 1, so chain and ASP calls fail fast.
+    const UNREACHABLE_URL: &str = "http://127.0.0.1:1";
+
+    fn test_config() -> AppConfig {
+        AppConfig {
+            asp_url: UNREACHABLE_URL.to_string(),
+            pool_address: Felt::from(0x100u64),
+            shielded_notes_address: Felt::from(0x200u64),
+            token0: Felt::from(0x10u64),
+            token1: Felt::from(0x20u64),
+            chain_id: Felt::ONE,
+            bind_addr: "127.0.0.1:0".parse().expect("bind addr"),
+            artifacts_dir: std::env::temp_dir().join("zylith-backend-test-artifacts"),
+            max_concurrent_proofs: ProofCircuit::ALL.into_iter().map(|c| (c, 1)).collect(),
+            request_timeout: Duration::from_secs(30),
+            max_body_bytes: 1024 * 1024,
+            max_quote_body_bytes: 16 * 1024,
+            cors_allow_origins: Vec::new(),
+            cors_allow_credentials: false,
+            cors_allow_headers: Vec::new(),
+            rate_limit_per_minute: 600,
+            rate_limit_burst: 100,
+            rate_limit_key_overrides: HashMap::new(),
+            api_keys: Vec::new(),
+            trusted_proxies: Vec::new(),
+            request_signing: RequestSigning::default(),
+            max_proof_felts: proof_felt_limits(None, &mut Vec::new()),
+            enabled_circuits: CircuitSet::ALL.to_vec(),
+        }
+    }
+
+    fn test_state(config: AppConfig) -> AppState {
+        let url = Url::parse(UNREACHABLE_URL).expect("url");
+        let provider = JsonRpcClient::new(HttpTransport::new(url));
+        AppState::new(
+            ReadOnlyAccount::new(provider, config.chain_id, ExecutionEncoding::New),
+            config,
+        )
+    }
+
+    fn test_note(token: Felt) -> NoteInput {
+        NoteInput {
+            secret: "0x1".to_string(),
+            nullifier: "0x2".to_string(),
+            amount: "1000".to_string(),
+            token: format!("{token:#x}"),
+        }
+    }
+
+    fn test_swap_request(state: &AppState) -> SwapProveRequest {
+        let request = SwapProofRequest {
+            notes: vec![test_note(state.config.token0)],
+            zero_for_one: true,
+            exact_out: false,
+            amount_out: None,
+            amount_in_max: None,
+            amount_out_min: None,
+            sqrt_ratio_limit: None,
+            output_note: None,
+            change_note: None,
+        };
+        parse_swap_proof_request(state, request).expect("valid swap request")
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn invalid_withdraw_is_rejected_while_the_queue_is_full() {
+        let state = test_state(test_config());
+        let _held = state
+            .limiter
+            .acquire(ProofCircuit::Withdraw, PROOF_QUEUE_WAIT)
+            .await
+            .expect("only withdraw permit");
+        let request = WithdrawProofRequest {
+            note: test_note(state.config.token1),
+            token_id: 0,
+            recipient: "0x1".to_string(),
+            root_index: None,
+            root_hash: None,
+        };
+        let err = prove_withdraw(State(state.clone()), Json(request))
+            .await
+            .err()
+            .expect("token mismatch");
+        assert_eq!(err.code(), "note_token_mismatch");
+    }
+
+    fn recorded_errors(metrics: &Metrics) -> u64 {
+        metrics
+            .errors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .sum()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn swap_jobs_outwait_the_sync_queue_and_count_their_errors() {
+        let state = test_state(test_config());
+        let held = state
+            .limiter
+            .acquire(ProofCircuit::Swap, PROOF_QUEUE_WAIT)
+            .await
+            .expect("only swap permit");
+
+        // A synchronous caller gives up after PROOF_QUEUE_WAIT; `track_errors` counts that one.
+        let sync = run_swap_proof(&state, test_swap_request(&state), SwapProofMode::Sync).await;
+        assert!(matches!(sync, Err(ApiError::QueueTimeout)));
+        assert_eq!(recorded_errors(&state.metrics), 0);
+
+        let job = tokio::spawn({
+            let state = state.clone();
+            let request = test_swap_request(&state);
+            async move { run_swap_proof(&state, request, SwapProofMode::Job).await }
+        });
+        tokio::time::sleep(PROOF_QUEUE_WAIT * 4).await;
+        assert!(!job.is_finished(), "job gave up on the queue");
+
+        // Once admitted the proof fails on the unreachable chain, and the job counts it itself.
+        drop(held);
+        let err = job
+            .await
+            .expect("job task")
+            .expect_err("no chain to prove against");
+        assert!(!matches!(err, ApiError::QueueTimeout), "{err:?}");
+        assert_eq!(recorded_errors(&state.metrics), 1);
+    }
+
+    #[test]
+    fn no_api_keys_allows_all_requests() {
+        assert!(collect_api_keys(Some("  ".to_string()), None).is_empty());
+        assert!(api_key_authorized(&[], None));
+        assert!(api_key_authorized(&[], Some("anything")));
+    }
+
+    #[test]
+    fn single_api_key_must_match() {
+        let keys = collect_api_keys(Some(" secret ".to_string()), None);
+        assert_eq!(keys, vec!["secret".to_string()]);
+        assert!(api_key_authorized(&keys, Some("secret")));
+        assert!(!api_key_authorized(&keys, Some("secreT")));
+        assert!(!api_key_authorized(&keys, Some("secret2")));
+        assert!(!api_key_authorized(&keys, None));
+    }
+
+    #[test]
+    fn rotating_api_keys_accept_old_and_new() {
+        let keys = collect_api_keys(None, Some(RawApiKeys::Csv("old-key, new-key".to_string())));
+        assert_eq!(keys, vec!["old-key".to_string(), "new-key".to_string()]);
+        assert!(api_key_authorized(&keys, Some("old-key")));
+        assert!(api_key_authorized(&keys, Some("new-key")));
+        assert!(!api_key_authorized(&keys, Some("retired-key")));
+
+        let merged = collect_api_keys(
+            Some("old-key".to_string()),
+            Some(RawApiKeys::List(vec![
+                "new-key".to_string(),
+                "old-key".to_string(),
+            ])),
+        );
+        assert_eq!(merged, keys);
+    }
+
+    #[tokio::test]
+    async fn wait_idle_reports_proofs_still_running() {
+        let limiter = ProofLimiter::new(&HashMap::from([(ProofCircuit::Swap, 2)]));
+        let wait = Duration::from_millis(20);
+        let first = limiter
+            .acquire(ProofCircuit::Swap, wait)
+            .await
+            .expect("permit");
+        let _second = limiter
+            .acquire(ProofCircuit::Swap, wait)
+            .await
+            .expect("permit");
+        assert_eq!(limiter.in_flight(), 2);
+
+        drop(first);
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(50);
+        assert_eq!(limiter.wait_idle(deadline).await, 1);
+    }
+
+    #[test]
+    fn metrics_render_errors_durations_and_permits() {
+        let limiter = ProofLimiter::new(&HashMap::from([(ProofCircuit::Swap, 3)]));
+        let metrics = Metrics::default();
+        metrics.record_error(ApiError::RateLimited(Duration::ZERO).kind());
+        metrics.record_error(ApiError::RateLimited(Duration::ZERO).kind());
+        metrics.observe_proof(ProofCircuit::Swap, Duration::from_secs(4));
+
+        let text = metrics.render(&limiter);
+        assert!(text.contains("zylith_api_errors_total{kind=\"rate_limited\"} 2\n"));
+        assert!(
+            text.contains("zylith_proof_duration_seconds_bucket{circuit=\"swap\",le=\"2.5\"} 0\n")
+        );
+        assert!(
+            text.contains("zylith_proof_duration_seconds_bucket{circuit=\"swap\",le=\"5\"} 1\n")
+        );
+        assert!(text.contains("zylith_proof_duration_seconds_count{circuit=\"swap\"} 1\n"));
+        assert!(text.contains("zylith_proof_permits_available{circuit=\"swap\"} 3\n"));
+    }
+
+    #[test]
+    fn error_responses_carry_their_kind() {
+        let response = ApiError::QueueTimeout.into_response();
+        assert_eq!(
+            response.extensions().get::<ApiErrorKind>().map(|k| k.0),
+            Some("queue_timeout")
+        );
+    }
+
+    #[test]
+    fn client_timeouts_map_to_gateway_timeout() {
+        let response =
+            ApiError::from(ClientError::Timeout("asp path request".to_string())).into_response();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(
+            response.extensions().get::<ApiErrorKind>().map(|k| k.0),
+            Some("gateway_timeout")
+        );
+    }
+
+    #[test]
+    fn per_circuit_limits_fall_back_to_default() {
+        let raw: RawCircuitLimits =
+            toml::from_str::<HashMap<String, RawCircuitLimits>>("m = { default = 3, swap = 1 }")
+                .expect("parse")
+                .remove("m")
+                .expect("key");
+        let mut problems = Vec::new();
+        let limits = proof_concurrency_limits(&raw, &mut problems);
+        assert!(problems.is_empty());
+        assert_eq!(limits[&ProofCircuit::Swap], 1);
+        assert_eq!(limits[&ProofCircuit::Deposit], 3);
+
+        let mut problems = Vec::new();
+        proof_concurrency_limits(
+            &RawCircuitLimits::PerCircuit(HashMap::from([("swpa".to_string(), 1)])),
+            &mut problems,
+        );
+        assert_eq!(problems.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn proof_felt_caps_are_per_circuit_and_reject_with_400() {
+        let mut problems = Vec::new();
+        let limits = proof_felt_limits(Some(&RawCircuitLimits::Global(5000)), &mut problems);
+        assert!(problems.is_empty());
+        assert_eq!(limits[&ProofCircuit::Swap], 5000);
+        assert_eq!(limits[&ProofCircuit::Deposit], 4096);
+
+        let table = HashMap::from([("swap".to_string(), 8192), ("withdraw".to_string(), 50)]);
+        let limits = proof_felt_limits(Some(&RawCircuitLimits::PerCircuit(table)), &mut problems);
+        assert!(problems.is_empty());
+        assert_eq!(limits[&ProofCircuit::Swap], 8192);
+        assert_eq!(limits[&ProofCircuit::Liquidity], 6144);
+        let table = HashMap::from([("swpa".to_string(), 8192)]);
+        proof_felt_limits(Some(&RawCircuitLimits::PerCircuit(table)), &mut problems);
+        assert_eq!(problems.len(), 1);
+
+        let mut config = test_config();
+        config.max_proof_felts = limits;
+        let err = checked_proof_calldata(&config, ProofCircuit::Withdraw, vec![String::new(); 51])
+            .expect_err("over the withdraw cap");
+        assert_eq!(err.code(), "proof_too_large");
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+        assert!(
+            checked_proof_calldata(&config, ProofCircuit::Deposit, vec![String::new(); 51]).is_ok()
+        );
+
+        // No withdraw proof fits in 10 felts, so the request is refused without taking a permit.
+        config.max_proof_felts.insert(ProofCircuit::Withdraw, 10);
+        let state = test_state(config);
+        let err = acquire_proof_permit(&state, ProofCircuit::Withdraw)
+            .await
+            .err()
+            .expect("cap below any withdraw proof");
+        assert_eq!(err.code(), "proof_too_large");
+        assert_eq!(state.limiter.available_permits(ProofCircuit::Withdraw), 1);
+    }
+
+    fn tagged_vk(circuit: CircuitSet) -> String {
+        let tags: Vec<String> = circuit.vk_tags().iter().map(u128::to_string).collect();
+        serde_json::json!({ "zylith_tags": tags }).to_string()
+    }
+
+    fn write_artifacts(root: &Path, circuit: CircuitSet, vk: &str) {
+        for dir in circuit.artifact_dirs() {
+            let base = base_circuit_name(dir);
+            let dir = root.join(dir);
+            fs::create_dir_all(&dir).expect("dir");
+            for file in [
+                format!("{base}.wasm"),
+                format!("{base}_final.zkey"),
+                base.to_string(),
+                format!("{base}.dat"),
+            ] {
+                fs::write(dir.join(file), [0u8; 1024]).expect("artifact");
+            }
+            // Trailing whitespace keeps the json valid while clearing the truncation check.
+            let vk = format!("{vk}{}", " ".repeat(1024));
+            fs::write(dir.join("verification_key.json"), vk).expect("vk");
+        }
+    }
+
+    #[test]
+    fn disabled_exact_out_skips_its_artifact_checks() {
+        let root = std::env::temp_dir().join(format!("zylith-artifacts-{}", std::process::id()));
+        let enabled = enabled_circuit_sets(
+            Some(&["deposit", "withdraw", "liquidity", "swap"].map(String::from)[..]),
+            &mut Vec::new(),
+        );
+        for circuit in &enabled {
+            write_artifacts(&root, *circuit, &tagged_vk(*circuit));
+        }
+
+        validate_artifacts(&root, &enabled).expect("exact-out files are not required");
+        let err = validate_artifacts(&root, &CircuitSet::ALL).expect_err("exact-out missing");
+        assert!(err.to_string().contains("private_swap_exact_out"));
+        fs::remove_dir_all(&root).ok();
+
+        let mut problems = Vec::new();
+        enabled_circuit_sets(Some(&["swap_exactout".to_string()][..]), &mut problems);
+        assert_eq!(problems.len(), 1);
+    }
+
+    #[test]
+    fn mismatched_vk_tag_fails_artifact_validation() {
+        let root = std::env::temp_dir().join(format!("zylith-vk-tags-{}", std::process::id()));
+        let withdraw_tag = 0x5749544844524157u128;
+        write_artifacts(
+            &root,
+            CircuitSet::Deposit,
+            &format!(r#"{{"zylith_tags":["{withdraw_tag}"]}}"#),
+        );
+        let err = validate_artifacts(&root, &[CircuitSet::Deposit]).expect_err("wrong circuit");
+        let message = err.to_string();
+        assert!(message.contains("private_deposit"), "{message}");
+        assert!(message.contains("different circuit"), "{message}");
+
+        let deposit_tag = 0x4445504f534954u128;
+        write_artifacts(
+            &root,
+            CircuitSet::Deposit,
+            &format!(r#"{{"zylith_tags":["{deposit_tag}"]}}"#),
+        );
+        validate_artifacts(&root, &[CircuitSet::Deposit]).expect("matching tag");
+
+        // Liquidity accepts three tags; the stamp may list them in any order.
+        let mut liquidity_tags: Vec<String> = CircuitSet::Liquidity
+            .vk_tags()
+            .iter()
+            .map(u128::to_string)
+            .collect();
+        liquidity_tags.reverse();
+        write_artifacts(
+            &root,
+            CircuitSet::Liquidity,
+            &serde_json::json!({ "zylith_tags": liquidity_tags }).to_string(),
+        );
+        validate_artifacts(&root, &[CircuitSet::Liquidity]).expect("liquidity tags");
+
+        write_artifacts(&root, CircuitSet::Withdraw, "{}");
+        let err = validate_artifacts(&root, &[CircuitSet::Withdraw]).expect_err("unstamped vk");
+        assert!(err.to_string().contains("no zylith_tags"), "{err}");
+        fs::remove_dir_all(&root).ok();
+    }
+
+    async fn preflight(cors: CorsLayer, origin: &str, request_headers: &str) -> Response {
+        use tower::ServiceExt;
+        let app = Router::new().route("/health", get(health)).layer(cors);
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/health")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, request_headers)
+            .body(Body::empty())
+            .expect("request");
+        app.oneshot(request).await.expect("response")
+    }
+
+    fn response_header(response: &Response, name: HeaderName) -> Option<&str> {
+        response
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().expect("ascii header"))
+    }
+
+    #[tokio::test]
+    async fn cors_wildcard_allows_any_origin_without_credentials() {
+        let cors = build_cors(&["*".to_string()], &[], false).expect("cors");
+        let response = preflight(cors, "https://app.example", "content-type").await;
+        assert_eq!(
+            response_header(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("*")
+        );
+        assert_eq!(
+            response_header(&response, header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            None
+        );
+
+        let err = build_cors(&["*".to_string()], &[], true).expect_err("wildcard + credentials");
+        assert!(err.to_string().contains("cors_allow_credentials"));
+    }
+
+    #[tokio::test]
+    async fn cors_explicit_origins_echo_only_listed_origins() {
+        let origins = ["https://app.example".to_string()];
+        let cors = build_cors(&origins, &[], false).expect("cors");
+        let response = preflight(cors.clone(), "https://app.example", "x-api-key").await;
+        assert_eq!(
+            response_header(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("https://app.example")
+        );
+        let response = preflight(cors, "https://evil.example", "x-api-key").await;
+        assert_eq!(
+            response_header(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn cors_credentials_and_extra_headers_are_advertised() {
+        let origins = ["https://app.example".to_string()];
+        let extra = ["Authorization".to_string(), " content-type ".to_string()];
+        let cors = build_cors(&origins, &extra, true).expect("cors");
+        let response = preflight(cors, "https://app.example", "authorization").await;
+        assert_eq!(
+            response_header(&response, header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            Some("true")
+        );
+        let allowed = response_header(&response, header::ACCESS_CONTROL_ALLOW_HEADERS)
+            .expect("allow-headers")
+            .to_string();
+        assert_eq!(allowed, "content-type,x-api-key,authorization");
+
+        let err = build_cors(&origins, &["bad header".to_string()], false).expect_err("bad name");
+        assert!(err.to_string().contains("cors_allow_headers"));
+    }
+
+    #[tokio::test]
+    async fn quote_body_cap_is_tighter_than_proof_ceiling() {
+        use tower::ServiceExt;
+        // Same layering as `main`: per-route cap on quotes under the global proof ceiling.
+        let app = Router::new()
+            .route(
+                "/quote/swap",
+                post(|body: String| async move { body.len().to_string() })
+                    .layer(DefaultBodyLimit::max(64)),
+            )
+            .route(
+                "/proofs/swap",
+                post(|body: String| async move { body.len().to_string() }),
+            )
+            .layer(DefaultBodyLimit::max(1024));
+        let send = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = Request::builder()
+                    .method(Method::POST)
+                    .uri(uri)
+                    .body(Body::from("x".repeat(128)))
+                    .expect("request");
+                app.oneshot(request).await.expect("response").status()
+            }
+        };
+        assert_eq!(send("/quote/swap").await, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(send("/proofs/swap").await, StatusCode::OK);
+    }
+
+    fn forwarded_request(xff: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/quote/swap")
+            .header("x-forwarded-for", xff)
+            .header("x-real-ip", "198.51.100.9")
+            .body(Body::empty())
+            .expect("request")
+    }
+
+    #[test]
+    fn trusted_proxy_peer_uses_first_untrusted_forwarded_hop() {
+        let proxies = ["10.0.0.0/8".parse::<IpNetwork>().expect("cidr")];
+        let peer: SocketAddr = "10.1.2.3:443".parse().expect("addr");
+        // The client prepended a spoofed hop; the proxy appended what it actually saw.
+        let request = forwarded_request("1.1.1.1, 203.0.113.7, 10.9.9.9");
+        assert_eq!(
+            client_ip(&request, peer, &proxies),
+            "203.0.113.7".parse::<IpAddr>().expect("ip")
+        );
+
+        let request = Request::builder()
+            .header("x-real-ip", "198.51.100.9")
+            .body(Body::empty())
+            .expect("request");
+        assert_eq!(
+            client_ip(&request, peer, &proxies),
+            "198.51.100.9".parse::<IpAddr>().expect("ip")
+        );
+    }
+
+    #[test]
+    fn untrusted_peer_forwarded_headers_are_ignored() {
+        let proxies = ["10.0.0.0/8".parse::<IpNetwork>().expect("cidr")];
+        let peer: SocketAddr = "192.0.2.50:5000".parse().expect("addr");
+        let request = forwarded_request("203.0.113.7");
+        assert_eq!(client_ip(&request, peer, &proxies), peer.ip());
+        assert_eq!(client_ip(&request, peer, &[]), peer.ip());
+    }
+
+    #[test]
+    fn malformed_forwarded_for_falls_back_to_peer() {
+        let proxies = ["127.0.0.1".parse::<IpNetwork>().expect("addr")];
+        let peer: SocketAddr = "[::ffff:127.0.0.1]:8080".parse().expect("mapped addr");
+        let request = forwarded_request("203.0.113.7, not-an-ip");
+        assert_eq!(
+            client_ip(&request, peer, &proxies),
+            "127.0.0.1".parse::<IpAddr>().expect("ip")
+        );
+
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("10.0.0/8".parse::<IpNetwork>().is_err());
+        let v6 = "fd00::/8".parse::<IpNetwork>().expect("v6 cidr");
+        assert!(v6.contains("fd12::1".parse().expect("ip")));
+        assert!(!v6.contains("10.0.0.1".parse().expect("ip")));
+    }
+
+    #[test]
+    fn api_keys_from_one_ip_get_independent_buckets() {
+        let overrides = HashMap::from([("partner-key".to_string(), (60, 3))]);
+        let limiter = RateLimiter::new(60, 1, &overrides);
+        let shared_ip: IpAddr = "203.0.113.7".parse().expect("ip");
+
+        assert!(limiter
+            .check(RateLimitKey::ApiKey("team-key".to_string()))
+            .is_ok());
+        assert!(limiter
+            .check(RateLimitKey::ApiKey("team-key".to_string()))
+            .is_err());
+        for _ in 0..3 {
+            assert!(limiter
+                .check(RateLimitKey::ApiKey("partner-key".to_string()))
+                .is_ok());
+        }
+        assert!(limiter
+            .check(RateLimitKey::ApiKey("partner-key".to_string()))
+            .is_err());
+        assert!(limiter.check(RateLimitKey::Ip(shared_ip)).is_ok());
+
+        let mut problems = Vec::new();
+        let raw = HashMap::from([(
+            "unknown".to_string(),
+            RawRateLimitOverride {
+                rate_limit_per_minute: Some(10),
+                rate_limit_burst: None,
+            },
+        )]);
+        assert!(
+            rate_limit_key_overrides(raw, &["k".to_string()], (60, 1), &mut problems).is_empty()
+        );
+        assert_eq!(problems.len(), 1);
+        assert!(!problems[0].contains("unknown"));
+    }
+
+    #[tokio::test]
+    async fn failed_authentication_is_rate_limited_by_ip() {
+        use tower::ServiceExt;
+        let mut config = test_config();
+        config.api_keys = vec!["secret".to_string()];
+        config.rate_limit_burst = 2;
+        let state = test_state(config);
+        let app = caller_layers(Router::new().route("/health", get(health)), &state)
+            .with_state(state.clone());
+        let send = |api_key: &'static str| {
+            let app = app.clone();
+            async move {
+                let mut request = Request::builder()
+                    .uri("/health")
+                    .header("x-api-key", api_key)
+                    .body(Body::empty())
+                    .expect("request");
+                let peer: SocketAddr = "198.51.100.7:4000".parse().expect("addr");
+                request.extensions_mut().insert(ConnectInfo(peer));
+                app.oneshot(request).await.expect("response").status()
+            }
+        };
+
+        assert_eq!(send("guess-1").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send("guess-2").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send("guess-3").await, StatusCode::TOO_MANY_REQUESTS);
+        // The issued key has its own bucket, so the same IP is still served.
+        assert_eq!(send("secret").await, StatusCode::OK);
+        let errors = state.metrics.errors.lock().expect("errors").clone();
+        assert_eq!(errors.get("unauthorized"), Some(&2));
+        assert_eq!(errors.get("rate_limited"), Some(&1));
+    }
+
+    #[test]
+    fn throttled_responses_say_when_to_retry() {
+        // 6 per minute refills one token every 10 seconds.
+        let limiter = RateLimiter::new(6, 1, &HashMap::new());
+        let ip = RateLimitKey::Ip("192.0.2.1".parse().expect("ip"));
+        assert!(limiter.check(ip.clone()).is_ok());
+        let wait = limiter.check(ip).expect_err("bucket empty");
+        assert!(wait > Duration::from_millis(9_900) && wait <= Duration::from_secs(10));
+
+        let response = ApiError::RateLimited(wait).into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "10");
+        let response = ApiError::RateLimited(Duration::from_millis(10)).into_response();
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+
+        let response = ApiError::QueueTimeout.into_response();
+        assert_eq!(response.headers()[header::RETRY_AFTER], "5");
+        let response = ApiError::Unauthorized.into_response();
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+    }
+
+    #[test]
+    fn remove_quote_request_checks_ticks_and_liquidity() {
+        let request = |tick_lower, tick_upper, liquidity_delta: &str| LiquidityRemoveQuoteRequest {
+            position_note: PositionNoteInput {
+                secret: format!("0x{}", "11".repeat(32)),
+                nullifier: format!("0x{}", "22".repeat(32)),
+                tick_lower,
+                tick_upper,
+                liquidity: "1000".to_string(),
+                fee_growth_inside_0: "0".to_string(),
+                fee_growth_inside_1: "0".to_string(),
+            },
+            liquidity_delta: liquidity_delta.to_string(),
+        };
+        let (note, delta) = parse_liquidity_remove_quote(request(-60, 60, "1000")).expect("valid");
+        assert_eq!((note.liquidity, delta), (1000, 1000));
+
+        for (tick_lower, tick_upper, delta) in [(60, -60, "10"), (-60, 60, "0"), (-60, 60, "1001")]
+        {
+            assert!(matches!(
+                parse_liquidity_remove_quote(request(tick_lower, tick_upper, delta)),
+                Err(ApiError::Invalid(..))
+            ));
+        }
+
+        let quote = LiquidityRemoveQuote {
+            amount0: 1,
+            amount1: 2,
+            protocol_fee_0: 0,
+            protocol_fee_1: 0,
+            fee_amount0: 3,
+            fee_amount1: 4,
+            out_amount0: 4,
+            out_amount1: 1_500_006,
+        };
+        let body = serde_json::to_value(liquidity_remove_quote_response(quote.clone(), None))
+            .expect("json");
+        assert_eq!(body["out_amount1"], "1500006");
+        assert!(body.get("out_amount1_display").is_none());
+
+        let token = |decimals| TokenMetadata {
+            address: Felt::ONE,
+            decimals,
+            symbol: None,
+            name: None,
+        };
+        let tokens = [token(0), token(6)];
+        let body = serde_json::to_value(liquidity_remove_quote_response(quote, Some(&tokens)))
+            .expect("json");
+        assert_eq!(body["out_amount0_display"], "4");
+        assert_eq!(body["out_amount1_display"], "1.500006");
+    }
+
+    fn signed_headers(key_id: &str, timestamp: u64, key: &SigningKey, body: &[u8]) -> HeaderMap {
+        let digest = signed_request_digest(&Method::POST, "/quote/swap", body, timestamp);
+        let signature = key.sign(&digest).expect("sign");
+        let mut headers = HeaderMap::new();
+        headers.insert(SIGNATURE_KEY_ID_HEADER, key_id.parse().expect("header"));
+        headers.insert(
+            SIGNATURE_TIMESTAMP_HEADER,
+            timestamp.to_string().parse().expect("header"),
+        );
+        headers.insert(
+            SIGNATURE_HEADER,
+            format!("{:#x},{:#x}", signature.r, signature.s)
+                .parse()
+                .expect("header"),
+        );
+        headers
+    }
+
+    fn partner_signing() -> (RequestSigning, SigningKey) {
+        let key = SigningKey::from_secret_scalar(Felt::from(0xabcdefu64));
+        let signing = RequestSigning {
+            keys: HashMap::from([("partner".to_string(), key.verifying_key().scalar())]),
+            max_age: Duration::from_secs(300),
+        };
+        (signing, key)
+    }
+
+    #[test]
+    fn signed_request_with_registered_key_is_accepted() {
+        let (signing, key) = partner_signing();
+        let body = br#"{"amount":"10"}"#;
+        let now = 1_700_000_000;
+        let headers = signed_headers("partner", now - 30, &key, body);
+        let verify = |body: &[u8]| {
+            verify_signed_request(&signing, &Method::POST, "/quote/swap", body, &headers, now)
+        };
+        assert_eq!(verify(body), Ok("partner".to_string()));
+        assert_eq!(verify(br#"{"amount":"11"}"#), Err("bad signature"));
+    }
+
+    #[test]
+    fn signed_request_outside_freshness_window_is_rejected() {
+        let (signing, key) = partner_signing();
+        let now = 1_700_000_000;
+        for timestamp in [now - 301, now + 301] {
+            let headers = signed_headers("partner", timestamp, &key, b"");
+            assert_eq!(
+                verify_signed_request(&signing, &Method::POST, "/quote/swap", b"", &headers, now),
+                Err("stale timestamp")
+            );
+        }
+    }
+
+    #[test]
+    fn signed_request_with_wrong_key_is_rejected() {
+        let (signing, _) = partner_signing();
+        let other = SigningKey::from_secret_scalar(Felt::from(0x1234u64));
+        let now = 1_700_000_000;
+        let headers = signed_headers("partner", now, &other, b"");
+        assert_eq!(
+            verify_signed_request(&signing, &Method::POST, "/quote/swap", b"", &headers, now),
+            Err("bad signature")
+        );
+        let headers = signed_headers("stranger", now, &other, b"");
+        assert_eq!(
+            verify_signed_request(&signing, &Method::POST, "/quote/swap", b"", &headers, now),
+            Err("unknown key id")
+        );
+    }
+
+    #[tokio::test]
+    async fn every_error_maps_to_a_stable_code() {
+        let cases = [
+            (ApiError::BadRequest(String::new()), "bad_request"),
+            (ApiError::Upstream(String::new()), "upstream"),
+            (ApiError::Prover(String::new()), "prover"),
+            (ApiError::QueueTimeout, "queue_timeout"),
+            (ApiError::QueueFull, "queue_full"),
+            (ApiError::GatewayTimeout(String::new()), "gateway_timeout"),
+            (ApiError::RateLimited(Duration::ZERO), "rate_limited"),
+            (ApiError::Unauthorized, "unauthorized"),
+            (ApiError::NotFound(String::new()), "not_found"),
+            (ApiError::NotImplemented(String::new()), "not_implemented"),
+            (ApiError::Internal(String::new()), "internal"),
+        ];
+        for (err, code) in cases {
+            assert_eq!(err.code(), code);
+        }
+        for code in VALIDATION_ERROR_CODES {
+            let err = ApiError::Invalid(code, "detail".to_string());
+            assert_eq!((err.code(), err.kind()), (code, "bad_request"));
+        }
+
+        let err = parse_bytes32("0x12").expect_err("short hex");
+        assert_eq!(err.code(), "invalid_hex");
+        let response = ApiError::Invalid("note_token_mismatch", "note token mismatch".to_string())
+            .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        let body: serde_json::Value = serde_json::from_slice(&body).expect("json");
+        assert_eq!(body["code"], "note_token_mismatch");
+        assert_eq!(body["error"], "note token mismatch");
+    }
+
+    #[test]
+    fn pool_state_response_encodes_fields() {
+        let body = serde_json::to_value(pool_state_response(PoolState {
+            sqrt_price: U256::from_words(0, 1),
+            tick: -887_220,
+            liquidity: 5_000,
+            fee_growth_global_0: (0xff, 0),
+            fee_growth_global_1: (0, 2),
+        }))
+        .expect("json");
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "sqrt_price": "0x100000000000000000000000000000000",
+                "tick": -887_220,
+                "liquidity": "5000",
+                "fee_growth_global_0": "0xff",
+                "fee_growth_global_1": "0x200000000000000000000000000000000",
+            })
+        );
+    }
+
+    #[test]
+    fn swap_quote_tokens_follow_direction_for_both_modes() {
+        let config = PoolConfig {
+            token0: Felt::from(0xa0u64),
+            token1: Felt::from(0xa1u64),
+            fee: 3000,
+            tick_spacing: 60,
+            min_sqrt_ratio: U256::from(1u8),
+            max_sqrt_ratio: U256::from(2u8),
+        };
+        for exact_out in [false, true] {
+            for (zero_for_one, token_in, token_out) in [
+                (true, config.token0, config.token1),
+                (false, config.token1, config.token0),
+            ] {
+                let direction = SwapDirection::from(zero_for_one);
+                assert_eq!(swap_tokens(&config, direction), (token_in, token_out));
+                // The amount sent to the pool is the input for exact-in and the output for
+                // exact-out; it must line up with the token reported for that side.
+                let specified = if exact_out { token_out } else { token_in };
+                let quoted = if direction.quote_is_token1(exact_out) {
+                    config.token1
+                } else {
+                    config.token0
+                };
+                assert_eq!(
+                    quoted, specified,
+                    "zero_for_one={zero_for_one} exact_out={exact_out}"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn pool_config_is_fetched_once_across_requests() {
+        let cache = RefreshingCache::new(Duration::from_secs(3600));
+        let fetches = std::sync::atomic::AtomicUsize::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok::<_, ClientError>(7u32)
+        };
+        let (first, second) = tokio::join!(cache.get_or_fetch(fetch), cache.get_or_fetch(fetch));
+        assert_eq!((first.expect("first"), second.expect("second")), (7, 7));
+        assert_eq!(cache.get_or_fetch(fetch).await.expect("cached"), 7);
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Once older than the refresh interval, even a seeded value is fetched again.
+        let seeded = RefreshingCache::new(Duration::ZERO);
+        seeded.set(1u32).await;
+        assert_eq!(seeded.get_or_fetch(fetch).await.expect("refetched"), 7);
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,130 @@
+use std::ops::{Add, Neg, Sub};
+
+use crate::error::ClientError;
+
+/// Sign-magnitude amount matching the pool's i129 encoding (`sign == true` is negative).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignedAmount {
+    pub mag: u128,
+    pub sign: bool,
+}
+
+impl SignedAmount {
+    pub fn new(mag: u128, sign: bool) -> Self {
+        Self {
+            mag,
+            sign: sign && mag != 0,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mag == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.sign && self.mag != 0
+    }
+
+    pub fn from_i128(value: i128) -> Self {
+        Self::new(value.unsigned_abs(), value < 0)
+    }
+
+    pub fn to_i128(&self) -> Result<i128, ClientError> {
+        if self.is_negative() {
+            0i128
+                .checked_sub_unsigned(self.mag)
+                .ok_or_else(|| ClientError::InvalidInput("signed amount overflow".to_string()))
+        } else {
+            i128::try_from(self.mag)
+                .map_err(|_| ClientError::InvalidInput("signed amount overflow".to_string()))
+        }
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        if self.is_negative() == other.is_negative() {
+            let mag = self.mag.checked_add(other.mag)?;
+            return Some(Self::new(mag, self.is_negative()));
+        }
+        if self.mag >= other.mag {
+            Some(Self::new(self.mag - other.mag, self.is_negative()))
+        } else {
+            Some(Self::new(other.mag - self.mag, other.is_negative()))
+        }
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(-other)
+    }
+}
+
+impl PartialEq for SignedAmount {
+    fn eq(&self, other: &Self) -> bool {
+        self.mag == other.mag && self.is_negative() == other.is_negative()
+    }
+}
+
+impl Eq for SignedAmount {}
+
+impl Neg for SignedAmount {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(self.mag, !self.is_negative())
+    }
+}
+
+impl Add for SignedAmount {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.checked_add(other).expect("signed amount overflow")
+    }
+}
+
+impl Sub for SignedAmount {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.checked_sub(other).expect("signed amount overflow")
+    }
+}
+
+impl From<i128> for SignedAmount {
+    fn from(value: i128) -> Self {
+        Self::from_i128(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SignedAmount;
+
+    #[test]
+    fn add_and_sub_cross_zero() {
+        let a = SignedAmount::from_i128(5);
+        let b = SignedAmount::from_i128(-8);
+        assert_eq!((a + b).to_i128().unwrap(), -3);
+        assert_eq!((a - b).to_i128().unwrap(), 13);
+        assert_eq!((b - b), SignedAmount::default());
+        assert!(!(b - b).is_negative());
+    }
+
+    #[test]
+    fn negative_zero_equals_zero() {
+        let neg_zero = SignedAmount { mag: 0, sign: true };
+        assert_eq!(neg_zero, SignedAmount::default());
+        assert!(neg_zero.is_zero());
+        assert!(!neg_zero.is_negative());
+    }
+
+    #[test]
+    fn i128_round_trip_and_overflow() {
+        for value in [0, 1, -1, i128::MAX, i128::MIN] {
+            assert_eq!(SignedAmount::from_i128(value).to_i128().unwrap(), value);
+        }
+        assert!(SignedAmount::new(u128::MAX, false).to_i128().is_err());
+        assert!(SignedAmount::new(u128::MAX, false)
+            .checked_add(SignedAmount::new(1, false))
+            .is_none());
+    }
+}
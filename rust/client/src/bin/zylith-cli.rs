@@ -1,37 +1,49 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use clap::{Args, Parser, Subcommand};
 use num_bigint::BigUint;
 use num_traits::Num;
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use starknet::accounts::{ExecutionEncoding, SingleOwnerAccount};
-use starknet::core::types::{BlockId, BlockTag, Felt, U256};
+use starknet::accounts::{ConnectedAccount, SingleOwnerAccount};
+use starknet::core::types::{Call, Felt, U256};
 use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClient};
 use starknet::providers::Provider;
 use starknet::signers::{LocalWallet, SigningKey};
 use url::Url;
+use zeroize::Zeroizing;
 
 use zylith_client::{
     compute_commitment, compute_position_commitment, generate_note_with_token_id,
-    generate_nullifier_hash, generate_position_note, generate_position_nullifier_hash, parse_felt,
-    ChunkedSwapExecuteRequest, DepositClient, DepositRequest, LiquidityAddProveRequest,
+    generate_nullifier_hash, generate_position_note, generate_position_nullifier_hash,
+    load_keystore_signer, parse_felt, rpc_provider, signer_account, ChunkedSwapExecuteRequest,
+    DepositOutcome, DepositRequest, InsertionTree, LiquidityAddProveRequest,
     LiquidityClaimProveRequest, LiquidityClaimRequest, LiquidityProveResult,
     LiquidityRemoveProveRequest, LiquidityRequest, MerklePath, Note, PositionNote, SignedAmount,
-    SwapClient, SwapProveRequest, SwapProveResult, SwapQuoteRequest, WithdrawClient,
-    WithdrawRequest, ZylithClient, ZylithConfig,
+    SwapClient, SwapDirection, SwapProveRequest, SwapProveResult, SwapQuoteRequest, SwapRecord,
+    TokenMetadata, TxReceipt, WithdrawRequest, ZylithClient, ZylithConfig, MAX_INPUT_NOTES,
 };
 use zylith_prover::{
     prove_deposit, prove_withdraw, DepositWitnessInputs, ProofCalldata, WithdrawWitnessInputs,
     WitnessValue,
 };
 
+const WALLET_VERSION: u32 = 1;
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+
 #[derive(Parser)]
 #[command(name = "zylith-cli")]
 #[command(about = "zylith cli for local dev flows", long_about = None)]
 struct Cli {
+    #[arg(long, global = true)]
+    json: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -79,6 +91,12 @@ enum Commands {
         asp_url: String,
         #[arg(long)]
         circuit_dir: Option<PathBuf>,
+        #[arg(long, default_value_t = false)]
+        skip_if_deposited: bool,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long)]
+        dry_run: bool,
         #[command(flatten)]
         network: NetworkArgs,
         #[command(flatten)]
@@ -97,8 +115,16 @@ enum Commands {
         recipient: String,
         #[arg(long)]
         asp_url: String,
+        #[arg(long, conflicts_with = "root_hash")]
+        root_index: Option<u64>,
+        #[arg(long)]
+        root_hash: Option<String>,
         #[arg(long)]
         circuit_dir: Option<PathBuf>,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long)]
+        dry_run: bool,
         #[command(flatten)]
         network: NetworkArgs,
         #[command(flatten)]
@@ -118,6 +144,10 @@ enum Commands {
         #[arg(long)]
         amount_out: Option<u128>,
         #[arg(long)]
+        amount_in_max: Option<u128>,
+        #[arg(long)]
+        amount_out_min: Option<u128>,
+        #[arg(long)]
         output_note: Option<PathBuf>,
         #[arg(long)]
         change_note: Option<PathBuf>,
@@ -127,6 +157,12 @@ enum Commands {
         change_note_out: Option<PathBuf>,
         #[arg(long)]
         circuit_dir: Option<PathBuf>,
+        #[arg(long)]
+        record_csv: Option<PathBuf>,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long)]
+        dry_run: bool,
         #[command(flatten)]
         network: NetworkArgs,
         #[command(flatten)]
@@ -152,6 +188,32 @@ enum Commands {
         #[command(flatten)]
         account: AccountArgs,
     },
+    Consolidate {
+        #[arg(long, num_args = 1.., value_delimiter = ',')]
+        note_in: Vec<PathBuf>,
+        #[arg(long)]
+        pool_address: String,
+        #[arg(long)]
+        asp_url: String,
+        #[arg(long)]
+        amount_in_max: Option<u128>,
+        #[arg(long)]
+        consolidated_note: Option<PathBuf>,
+        #[arg(long)]
+        consolidated_note_out: Option<PathBuf>,
+        #[arg(long)]
+        dust_note_out: Option<PathBuf>,
+        #[arg(long)]
+        circuit_dir: Option<PathBuf>,
+        // The merge costs a swap fee plus the input spent on the dust output; without --yes
+        // the command proves, prints both and stops before submitting.
+        #[arg(long)]
+        yes: bool,
+        #[command(flatten)]
+        network: NetworkArgs,
+        #[command(flatten)]
+        account: AccountArgs,
+    },
     SwapQuote {
         #[arg(long)]
         pool_address: String,
@@ -201,6 +263,8 @@ enum Commands {
         circuit_dir: Option<PathBuf>,
         #[arg(long)]
         pool_address: String,
+        #[arg(long)]
+        dry_run: bool,
         #[command(flatten)]
         network: NetworkArgs,
         #[command(flatten)]
@@ -229,6 +293,8 @@ enum Commands {
         circuit_dir: Option<PathBuf>,
         #[arg(long)]
         pool_address: String,
+        #[arg(long)]
+        dry_run: bool,
         #[command(flatten)]
         network: NetworkArgs,
         #[command(flatten)]
@@ -255,6 +321,8 @@ enum Commands {
         circuit_dir: Option<PathBuf>,
         #[arg(long)]
         pool_address: String,
+        #[arg(long)]
+        dry_run: bool,
         #[command(flatten)]
         network: NetworkArgs,
         #[command(flatten)]
@@ -268,12 +336,71 @@ enum Commands {
         #[command(flatten)]
         account: AccountArgs,
     },
+    PoolConfig {
+        #[arg(long)]
+        pool_address: String,
+        #[command(flatten)]
+        network: NetworkArgs,
+        #[command(flatten)]
+        account: AccountArgs,
+    },
     AspRootLatest {
         #[arg(long)]
         asp_url: String,
         #[arg(long)]
         token: String,
     },
+    VerifyPath {
+        #[arg(long)]
+        note: PathBuf,
+        #[arg(long)]
+        token_id: u8,
+        #[arg(long)]
+        asp_url: String,
+    },
+    Wallet {
+        #[command(subcommand)]
+        command: WalletCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum WalletCommand {
+    Init {
+        #[command(flatten)]
+        wallet: WalletArgs,
+    },
+    Add {
+        #[arg(long, conflicts_with = "position_note", requires = "token_id")]
+        note: Option<PathBuf>,
+        #[arg(long)]
+        token_id: Option<u8>,
+        #[arg(long, required_unless_present = "note")]
+        position_note: Option<PathBuf>,
+        #[command(flatten)]
+        wallet: WalletArgs,
+    },
+    List {
+        #[arg(long)]
+        asp_url: Option<String>,
+        #[command(flatten)]
+        wallet: WalletArgs,
+    },
+    Balance {
+        #[arg(long)]
+        asp_url: String,
+        #[command(flatten)]
+        wallet: WalletArgs,
+    },
+}
+
+#[derive(Args, Clone)]
+struct WalletArgs {
+    #[arg(long, default_value = "zylith-wallet.json")]
+    wallet: PathBuf,
+    // Name of the env var holding the wallet password, so it never lands in shell history.
+    #[arg(long, default_value = "ZYLITH_WALLET_PASSWORD")]
+    password_env: String,
 }
 
 #[derive(Args, Clone)]
@@ -282,14 +409,28 @@ struct NetworkArgs {
     rpc_url: String,
     #[arg(long)]
     chain_id: Option<String>,
+    #[arg(long)]
+    rpc_timeout_secs: Option<u64>,
+    #[arg(long, default_value_t = 120)]
+    request_timeout_secs: u64,
 }
 
 #[derive(Args, Clone)]
 struct AccountArgs {
     #[arg(long)]
     account_address: String,
+    // Prefer --keystore: a raw key on the command line ends up in shell history and `ps`.
+    #[arg(
+        long,
+        required_unless_present = "keystore",
+        conflicts_with = "keystore"
+    )]
+    private_key: Option<String>,
     #[arg(long)]
-    private_key: String,
+    keystore: Option<PathBuf>,
+    // Name of the env var holding the keystore password.
+    #[arg(long, default_value = "ZYLITH_KEYSTORE_PASSWORD")]
+    keystore_password_env: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -311,6 +452,33 @@ struct PositionNoteFile {
     fee_growth_inside_1: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct WalletFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WalletContents {
+    notes: Vec<WalletNote>,
+    position_notes: Vec<PositionNoteFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WalletNote {
+    token_id: u8,
+    note: NoteFile,
+}
+
+#[derive(Debug, Deserialize)]
+struct NullifierResponse {
+    spent: bool,
+    #[serde(default)]
+    pending: bool,
+}
+
 #[derive(Debug, Deserialize)]
 struct PathResponse {
     token: String,
@@ -341,7 +509,15 @@ struct InsertPathResponse {
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
-    match cli.command {
+    let mut output = Output::new(cli.json);
+    // Emitted even on failure so a submitted tx_hash is never lost to a later error.
+    let result = run(cli.command, &mut output).await;
+    output.emit();
+    result
+}
+
+async fn run(command: Commands, output: &mut Output) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
         Commands::NoteNew {
             amount,
             token,
@@ -350,17 +526,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         } => {
             let token_felt = parse_felt_arg(&token)?;
             let note = generate_note_with_token_id(amount, token_felt, token_id)?;
-            let note_file = NoteFile {
-                secret: bytes32_to_hex(&note.secret),
-                nullifier: bytes32_to_hex(&note.nullifier),
-                amount: note.amount.to_string(),
-                token: felt_to_hex(note.token),
-            };
-            let serialized = serde_json::to_string_pretty(&note_file)?;
+            let note_file = note_file(&note);
             if let Some(path) = out {
-                fs::write(path, serialized)?;
+                fs::write(path, serde_json::to_string_pretty(&note_file)?)?;
             } else {
-                println!("{serialized}");
+                output.document(serde_json::to_value(&note_file)?);
             }
         }
         Commands::PositionNoteNew {
@@ -380,28 +550,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 fee_growth_inside_0,
                 fee_growth_inside_1,
             )?;
-            let note_file = PositionNoteFile {
-                secret: bytes32_to_hex(&note.secret),
-                nullifier: bytes32_to_hex(&note.nullifier),
-                tick_lower: note.tick_lower,
-                tick_upper: note.tick_upper,
-                liquidity: note.liquidity.to_string(),
-                fee_growth_inside_0: u256_to_hex(&note.fee_growth_inside_0),
-                fee_growth_inside_1: u256_to_hex(&note.fee_growth_inside_1),
-            };
-            let serialized = serde_json::to_string_pretty(&note_file)?;
+            let note_file = position_note_file(&note);
             if let Some(path) = out {
-                fs::write(path, serialized)?;
+                fs::write(path, serde_json::to_string_pretty(&note_file)?)?;
             } else {
-                println!("{serialized}");
+                output.document(serde_json::to_value(&note_file)?);
             }
         }
         Commands::PositionNoteCommitment { note } => {
             let note = load_position_note(&note)?;
             let commitment = compute_position_commitment(&note).map_err(|e| e.to_string())?;
             let nullifier = generate_position_nullifier_hash(&note).map_err(|e| e.to_string())?;
-            println!("commitment={}", felt_to_hex(commitment));
-            println!("nullifier={}", felt_to_hex(nullifier));
+            output.field("commitment", felt_to_hex(commitment));
+            output.field("nullifier", felt_to_hex(nullifier));
         }
         Commands::Deposit {
             note,
@@ -410,6 +571,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             notes_address,
             asp_url,
             circuit_dir,
+            skip_if_deposited,
+            wait,
+            dry_run,
             network,
             account,
         } => {
@@ -423,21 +587,44 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let insertion = asp_fetch_insertion_path(&asp_url, token_address).await?;
 
             let account = build_account(&network, &account).await?;
-            let client = DepositClient::new(account, notes_address);
+            let client = ZylithClient::new(client_config(
+                &network,
+                account,
+                asp_url,
+                Felt::ZERO,
+                notes_address,
+            ));
             let request = DepositRequest {
                 note,
                 token_id,
                 token_address,
                 proof,
                 insertion_proof: insertion,
+                skip_if_deposited,
             };
-            let result = match token_id {
-                0 => client.deposit_token0(request).await?,
-                1 => client.deposit_token1(request).await?,
-                _ => return Err("token_id must be 0 or 1".into()),
-            };
-            println!("tx_hash={}", result.tx_hash);
-            println!("commitment=0x{:x}", result.commitment);
+            if dry_run {
+                let commitment = compute_commitment(&request.note, token_id)?;
+                let calls = client.deposit_client().deposit_calls(&request)?;
+                output.field("commitment", felt_to_hex(commitment));
+                write_calls_output(output, &calls);
+            } else {
+                let result = client.deposit(request).await?;
+                match result.outcome {
+                    DepositOutcome::Submitted(tx_hash) => {
+                        output.field("tx_hash", tx_hash.to_string())
+                    }
+                    DepositOutcome::AlreadyDeposited { leaf_index } => {
+                        output.field("already_deposited", true);
+                        output.field("leaf_index", leaf_index);
+                    }
+                }
+                output.field("commitment", format!("0x{:x}", result.commitment));
+                if wait {
+                    if let Some(receipt) = result.wait(&client).await? {
+                        write_receipt_output(output, &receipt)?;
+                    }
+                }
+            }
         }
         Commands::Withdraw {
             note,
@@ -446,34 +633,62 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             notes_address,
             recipient,
             asp_url,
+            root_index,
+            root_hash,
             circuit_dir,
+            wait,
+            dry_run,
             network,
             account,
         } => {
             let token_address = parse_felt_arg(&token_address)?;
             let notes_address = parse_felt_arg(&notes_address)?;
             let recipient = parse_felt_arg(&recipient)?;
+            let pinned_root = root_hash.as_deref().map(parse_felt_arg).transpose()?;
             let note = load_note(&note)?;
             if note.token != token_address {
                 return Err("note token does not match token_address".into());
             }
+            let account = build_account(&network, &account).await?;
+            let client = ZylithClient::new(client_config(
+                &network,
+                account,
+                asp_url.clone(),
+                Felt::ZERO,
+                notes_address,
+            ));
+            client
+                .ensure_nullifiers_unspent(
+                    InsertionTree::Token(note.token),
+                    &[generate_nullifier_hash(&note, token_id)?],
+                )
+                .await?;
             let proof = prove_withdraw_proof(&note, token_id, recipient, circuit_dir).await?;
             let commitment = compute_commitment(&note, token_id)?;
-            let merkle_proof = asp_fetch_merkle_path(&asp_url, commitment, None, None).await?;
+            let merkle_proof =
+                asp_fetch_merkle_path(&asp_url, commitment, root_index, pinned_root).await?;
 
-            let account = build_account(&network, &account).await?;
-            let client = WithdrawClient::new(account, notes_address);
-            let tx_hash = client
-                .withdraw(WithdrawRequest {
-                    note,
-                    token_id,
-                    token_address,
-                    recipient,
-                    proof,
-                    merkle_proof,
-                })
-                .await?;
-            println!("tx_hash={tx_hash}");
+            let request = WithdrawRequest {
+                note,
+                token_id,
+                token_address,
+                recipient,
+                proof,
+                merkle_proof: Some(merkle_proof),
+                root_index,
+                root_hash,
+            };
+            if dry_run {
+                let call = client.withdraw_client().withdraw_call(request).await?;
+                write_calls_output(output, &[call]);
+            } else {
+                let tx_hash = client.withdraw(request).await?;
+                output.field("tx_hash", tx_hash.to_string());
+                if wait {
+                    let receipt = client.wait_for_receipt(tx_hash, RECEIPT_TIMEOUT).await?;
+                    write_receipt_output(output, &receipt)?;
+                }
+            }
         }
         Commands::Swap {
             note_in,
@@ -482,11 +697,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             zero_for_one,
             exact_out,
             amount_out,
+            amount_in_max,
+            amount_out_min,
             output_note,
             change_note,
             output_note_out,
             change_note_out,
             circuit_dir,
+            record_csv,
+            wait,
+            dry_run,
             network,
             account,
         } => {
@@ -513,39 +733,64 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             };
 
             let account = build_account(&network, &account).await?;
-            let client = ZylithClient::new(ZylithConfig {
+            let client = ZylithClient::new(client_config(
+                &network,
                 account,
-                asp_url: asp_url.clone(),
+                asp_url.clone(),
                 pool_address,
-                shielded_notes_address: Felt::ZERO,
-                token0: Felt::ZERO,
-                token1: Felt::ZERO,
-            });
+                Felt::ZERO,
+            ));
             let request = SwapProveRequest {
                 notes,
-                zero_for_one,
+                direction: zero_for_one.into(),
                 exact_out,
                 amount_out,
+                amount_in_max,
+                amount_out_min,
                 sqrt_ratio_limit: None,
                 output_note,
                 change_note,
                 circuit_dir,
+                require_active_pool: false,
             };
             let result = client.prove_swap(request).await?;
+            let record = if dry_run {
+                let call = client.swap_client().swap_call(
+                    &result.proof,
+                    &result.input_proofs,
+                    &result.output_proofs,
+                    exact_out,
+                )?;
+                write_calls_output(output, &[call]);
+                None
+            } else {
+                Some(
+                    client
+                        .swap_client()
+                        .execute_swap_recorded(&result, zero_for_one.into(), exact_out)
+                        .await?,
+                )
+            };
             let SwapProveResult {
-                proof,
-                input_proofs,
-                output_proofs,
                 output_note,
                 change_note,
                 ..
             } = result;
-            let tx_hash = client
-                .swap(proof, &input_proofs, &output_proofs, exact_out)
-                .await?;
-            println!("tx_hash={tx_hash}");
-            write_note_output("output_note", output_note, output_note_out)?;
-            write_note_output("change_note", change_note, change_note_out)?;
+            if let Some(record) = &record {
+                output.field("tx_hash", record.tx_hash.clone().unwrap_or_default());
+                if let Some(path) = record_csv {
+                    append_swap_record(&path, record)?;
+                }
+            }
+            write_note_output(output, "output_note", output_note, output_note_out)?;
+            write_note_output(output, "change_note", change_note, change_note_out)?;
+            let tx_hash = record.as_ref().and_then(|record| record.tx_hash.as_deref());
+            if let (true, Some(tx_hash)) = (wait, tx_hash) {
+                let receipt = client
+                    .wait_for_receipt(parse_felt_arg(tx_hash)?, RECEIPT_TIMEOUT)
+                    .await?;
+                write_receipt_output(output, &receipt)?;
+            }
         }
         Commands::SwapChunked {
             note_in,
@@ -569,48 +814,156 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             };
 
             let account = build_account(&network, &account).await?;
-            let client = ZylithClient::new(ZylithConfig {
+            let client = ZylithClient::new(client_config(
+                &network,
                 account,
-                asp_url: asp_url.clone(),
+                asp_url.clone(),
                 pool_address,
-                shielded_notes_address: Felt::ZERO,
-                token0: Felt::ZERO,
-                token1: Felt::ZERO,
-            });
+                Felt::ZERO,
+            ));
             let result = client
                 .execute_chunked_swap_exact_in(ChunkedSwapExecuteRequest {
                     notes,
-                    zero_for_one,
+                    direction: zero_for_one.into(),
                     sqrt_ratio_limit,
                     circuit_dir,
                 })
                 .await?;
 
-            println!("chunk_count={}", result.chunks.len());
-            println!("total_amount_out={}", result.total_amount_out);
-            println!(
-                "total_amount_in_consumed={}",
-                result.total_amount_in_consumed
+            output.field("chunk_count", result.chunks.len());
+            output.field("total_amount_out", result.total_amount_out.to_string());
+            output.field(
+                "total_amount_in_consumed",
+                result.total_amount_in_consumed.to_string(),
             );
-            for (idx, chunk) in result.chunks.iter().enumerate() {
-                println!("chunk[{idx}].tx_hash={}", chunk.tx_hash);
-                println!("chunk[{idx}].amount_out={}", chunk.amount_out);
-                println!(
-                    "chunk[{idx}].amount_in_consumed={}",
-                    chunk.amount_in_consumed
-                );
-                println!(
-                    "chunk[{idx}].selected_swap_steps={}",
-                    chunk.selected_swap_steps
-                );
-            }
-            write_note_list_output("output_note", &result.output_notes)?;
+            let chunks = result
+                .chunks
+                .iter()
+                .map(|chunk| {
+                    serde_json::json!({
+                        "tx_hash": chunk.tx_hash.to_string(),
+                        "amount_out": chunk.amount_out.to_string(),
+                        "amount_in_consumed": chunk.amount_in_consumed.to_string(),
+                        "selected_swap_steps": chunk.selected_swap_steps,
+                    })
+                })
+                .collect();
+            output.records("chunks", chunks, |idx, chunk| {
+                format!(
+                    "chunk[{idx}].tx_hash={}\n\
+                     chunk[{idx}].amount_out={}\n\
+                     chunk[{idx}].amount_in_consumed={}\n\
+                     chunk[{idx}].selected_swap_steps={}",
+                    chunk["tx_hash"].as_str().unwrap_or_default(),
+                    chunk["amount_out"].as_str().unwrap_or_default(),
+                    chunk["amount_in_consumed"].as_str().unwrap_or_default(),
+                    chunk["selected_swap_steps"],
+                )
+            });
+            write_note_list_output(output, "output_note", &result.output_notes)?;
             write_note_output(
+                output,
                 "final_change_note",
                 result.final_change_note,
                 final_change_note_out,
             )?;
         }
+        Commands::Consolidate {
+            note_in,
+            pool_address,
+            asp_url,
+            amount_in_max,
+            consolidated_note,
+            consolidated_note_out,
+            dust_note_out,
+            circuit_dir,
+            yes,
+            network,
+            account,
+        } => {
+            let pool_address = parse_felt_arg(&pool_address)?;
+
+            let mut notes = Vec::new();
+            for path in &note_in {
+                notes.push(load_note(path)?);
+            }
+            let token = check_consolidation_inputs(&notes)?;
+            let consolidated_note = match consolidated_note {
+                Some(path) => Some(load_note(&path)?),
+                None => None,
+            };
+
+            let account = build_account(&network, &account).await?;
+            let client = ZylithClient::new(client_config(
+                &network,
+                account,
+                asp_url.clone(),
+                pool_address,
+                Felt::ZERO,
+            ));
+            let pool_config = client.swap_client().get_pool_config().await?;
+            let zero_for_one = if token == pool_config.token0 {
+                true
+            } else if token == pool_config.token1 {
+                false
+            } else {
+                return Err("note token does not belong to this pool".into());
+            };
+            // The pool has no note-to-note transfer, so the merge is a self-swap buying the
+            // smallest unit of the other token: the change note carries the rest of the balance.
+            let request = SwapProveRequest {
+                notes,
+                direction: zero_for_one.into(),
+                exact_out: true,
+                amount_out: Some(1),
+                amount_in_max,
+                amount_out_min: None,
+                sqrt_ratio_limit: None,
+                output_note: None,
+                change_note: consolidated_note,
+                circuit_dir,
+                require_active_pool: true,
+            };
+            let result = client.prove_swap(request).await?;
+            let fee = client
+                .estimate_fee_for_swap(
+                    &result.proof,
+                    &result.input_proofs,
+                    &result.output_proofs,
+                    true,
+                )
+                .await?;
+            output.field("estimated_fee", fee.overall_fee.to_string());
+            output.field("dust_cost", result.amount_in_consumed.to_string());
+            output.field("dust_amount_out", result.amount_out.to_string());
+            if !yes {
+                return Err(
+                    "consolidation not submitted; rerun with --yes to pay the fee and dust above"
+                        .into(),
+                );
+            }
+            let tx_hash = client
+                .swap(
+                    result.proof.clone(),
+                    &result.input_proofs,
+                    &result.output_proofs,
+                    true,
+                )
+                .await?;
+            let SwapProveResult {
+                output_note,
+                change_note,
+                ..
+            } = result;
+            output.field("tx_hash", tx_hash.to_string());
+            write_note_output(
+                output,
+                "consolidated_note",
+                change_note,
+                consolidated_note_out,
+            )?;
+            write_note_output(output, "dust_note", output_note, dust_note_out)?;
+        }
         Commands::SwapQuote {
             pool_address,
             amount,
@@ -623,11 +976,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         } => {
             let pool_address = parse_felt_arg(&pool_address)?;
             let sqrt_ratio_limit = parse_u256_arg(&sqrt_ratio_limit)?;
-            let is_token1 = if exact_out {
-                zero_for_one
-            } else {
-                !zero_for_one
-            };
+            let is_token1 = SwapDirection::from(zero_for_one).quote_is_token1(exact_out);
             let request = SwapQuoteRequest {
                 amount: SignedAmount {
                     mag: amount,
@@ -640,13 +989,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let account = build_account(&network, &account).await?;
             let client = SwapClient::new(account, pool_address, "");
             let quote = client.simulate_swap(request).await?;
-            println!("delta_amount0_mag={}", quote.delta_amount0.mag);
-            println!("delta_amount0_sign={}", quote.delta_amount0.sign);
-            println!("delta_amount1_mag={}", quote.delta_amount1.mag);
-            println!("delta_amount1_sign={}", quote.delta_amount1.sign);
-            println!("sqrt_price_after={}", u256_to_hex(&quote.sqrt_price_after));
-            println!("tick_after={}", quote.tick_after);
-            println!("liquidity_after={}", quote.liquidity_after);
+            let tokens = pool_token_metadata(&client).await;
+            output.field("delta_amount0", signed_amount_display(&quote.delta_amount0));
+            output.field("delta_amount0_mag", quote.delta_amount0.mag.to_string());
+            output.field("delta_amount0_sign", quote.delta_amount0.sign);
+            output.field("delta_amount1", signed_amount_display(&quote.delta_amount1));
+            output.field("delta_amount1_mag", quote.delta_amount1.mag.to_string());
+            output.field("delta_amount1_sign", quote.delta_amount1.sign);
+            if let Some([token0, token1]) = &tokens {
+                output.field(
+                    "delta_amount0_display",
+                    signed_token_display(token0, &quote.delta_amount0),
+                );
+                output.field(
+                    "delta_amount1_display",
+                    signed_token_display(token1, &quote.delta_amount1),
+                );
+            }
+            output.field("sqrt_price_after", u256_to_hex(&quote.sqrt_price_after));
+            output.field("tick_after", quote.tick_after);
+            output.field("liquidity_after", quote.liquidity_after.to_string());
         }
         Commands::LiquidityAdd {
             asp_url,
@@ -664,19 +1026,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             output_note_token1_out,
             circuit_dir,
             pool_address,
+            dry_run,
             network,
             account,
         } => {
             let pool_address = parse_felt_arg(&pool_address)?;
             let account = build_account(&network, &account).await?;
-            let client = ZylithClient::new(ZylithConfig {
+            let client = ZylithClient::new(client_config(
+                &network,
                 account,
-                asp_url: asp_url.clone(),
+                asp_url.clone(),
                 pool_address,
-                shielded_notes_address: Felt::ZERO,
-                token0: Felt::ZERO,
-                token1: Felt::ZERO,
-            });
+                Felt::ZERO,
+            ));
             let token0_notes = load_notes(&token0_notes)?;
             let token1_notes = load_notes(&token1_notes)?;
             let position_note = match position_note {
@@ -706,6 +1068,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 output_note_token0,
                 output_note_token1,
                 circuit_dir,
+                require_active_pool: false,
             };
             let result = client.prove_liquidity_add(request).await?;
             let LiquidityProveResult {
@@ -720,29 +1083,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 output_note_token1,
                 output_position_note,
             } = result;
-            let tx_hash = client
-                .add_liquidity(LiquidityRequest {
-                    proof,
-                    proofs_token0,
-                    proofs_token1,
-                    proof_position,
-                    insert_proof_position,
-                    output_proof_token0,
-                    output_proof_token1,
-                })
-                .await?;
-            println!("tx_hash={tx_hash}");
+            let request = LiquidityRequest {
+                proof,
+                proofs_token0,
+                proofs_token1,
+                proof_position,
+                insert_proof_position,
+                output_proof_token0,
+                output_proof_token1,
+            };
+            if dry_run {
+                let call = client.liquidity_client().add_liquidity_call(&request)?;
+                write_calls_output(output, &[call]);
+            } else {
+                let tx_hash = client.add_liquidity(request).await?;
+                output.field("tx_hash", tx_hash.to_string());
+            }
             write_position_note_output(
+                output,
                 "position_note",
                 output_position_note,
                 output_position_note_out,
             )?;
             write_note_output(
+                output,
                 "output_note_token0",
                 output_note_token0,
                 output_note_token0_out,
             )?;
             write_note_output(
+                output,
                 "output_note_token1",
                 output_note_token1,
                 output_note_token1_out,
@@ -760,19 +1130,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             output_note_token1_out,
             circuit_dir,
             pool_address,
+            dry_run,
             network,
             account,
         } => {
             let pool_address = parse_felt_arg(&pool_address)?;
             let account = build_account(&network, &account).await?;
-            let client = ZylithClient::new(ZylithConfig {
+            let client = ZylithClient::new(client_config(
+                &network,
                 account,
-                asp_url: asp_url.clone(),
+                asp_url.clone(),
                 pool_address,
-                shielded_notes_address: Felt::ZERO,
-                token0: Felt::ZERO,
-                token1: Felt::ZERO,
-            });
+                Felt::ZERO,
+            ));
             let position_note = load_position_note(&position_note)?;
             let output_position_note = match output_position_note {
                 Some(path) => Some(load_position_note(&path)?),
@@ -793,6 +1163,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 output_note_token0,
                 output_note_token1,
                 circuit_dir,
+                require_active_pool: false,
             };
             let result = client.prove_liquidity_remove(request).await?;
             let LiquidityProveResult {
@@ -807,27 +1178,44 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 ..
             } = result;
             let proof_position = proof_position.ok_or("missing proof_position")?;
-            let tx_hash = client
-                .remove_liquidity(
-                    proof,
-                    proof_position,
-                    insert_proof_position,
-                    output_proof_token0,
-                    output_proof_token1,
-                )
-                .await?;
-            println!("tx_hash={tx_hash}");
+            if dry_run {
+                let call =
+                    client
+                        .liquidity_client()
+                        .remove_liquidity_call(&LiquidityClaimRequest {
+                            proof,
+                            proof_position,
+                            insert_proof_position,
+                            output_proof_token0,
+                            output_proof_token1,
+                        })?;
+                write_calls_output(output, &[call]);
+            } else {
+                let tx_hash = client
+                    .remove_liquidity(
+                        proof,
+                        proof_position,
+                        insert_proof_position,
+                        output_proof_token0,
+                        output_proof_token1,
+                    )
+                    .await?;
+                output.field("tx_hash", tx_hash.to_string());
+            }
             write_position_note_output(
+                output,
                 "position_note",
                 output_position_note,
                 output_position_note_out,
             )?;
             write_note_output(
+                output,
                 "output_note_token0",
                 output_note_token0,
                 output_note_token0_out,
             )?;
             write_note_output(
+                output,
                 "output_note_token1",
                 output_note_token1,
                 output_note_token1_out,
@@ -844,19 +1232,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             output_note_token1_out,
             circuit_dir,
             pool_address,
+            dry_run,
             network,
             account,
         } => {
             let pool_address = parse_felt_arg(&pool_address)?;
             let account = build_account(&network, &account).await?;
-            let client = ZylithClient::new(ZylithConfig {
+            let client = ZylithClient::new(client_config(
+                &network,
                 account,
-                asp_url: asp_url.clone(),
+                asp_url.clone(),
                 pool_address,
-                shielded_notes_address: Felt::ZERO,
-                token0: Felt::ZERO,
-                token1: Felt::ZERO,
-            });
+                Felt::ZERO,
+            ));
             let position_note = load_position_note(&position_note)?;
             let output_position_note = match output_position_note {
                 Some(path) => Some(load_position_note(&path)?),
@@ -876,6 +1264,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 output_note_token0,
                 output_note_token1,
                 circuit_dir,
+                require_active_pool: false,
             };
             let result = client.prove_liquidity_claim(request).await?;
             let LiquidityProveResult {
@@ -897,19 +1286,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 output_proof_token0,
                 output_proof_token1,
             };
-            let tx_hash = client.claim_liquidity_fees(claim).await?;
-            println!("tx_hash={tx_hash}");
+            if dry_run {
+                let call = client.liquidity_client().claim_fees_call(&claim)?;
+                write_calls_output(output, &[call]);
+            } else {
+                let tx_hash = client.claim_liquidity_fees(claim).await?;
+                output.field("tx_hash", tx_hash.to_string());
+            }
             write_position_note_output(
+                output,
                 "position_note",
                 output_position_note,
                 output_position_note_out,
             )?;
             write_note_output(
+                output,
                 "output_note_token0",
                 output_note_token0,
                 output_note_token0_out,
             )?;
             write_note_output(
+                output,
                 "output_note_token1",
                 output_note_token1,
                 output_note_token1_out,
@@ -924,9 +1321,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let account = build_account(&network, &account).await?;
             let client = SwapClient::new(account, pool_address, "");
             let state = client.get_pool_state().await?;
-            println!("sqrt_price={}", state.0);
-            println!("tick={}", state.1);
-            println!("liquidity={}", state.2);
+            output.field("sqrt_price", state.0.to_string());
+            output.field("tick", state.1);
+            output.field("liquidity", state.2.to_string());
+        }
+        Commands::PoolConfig {
+            pool_address,
+            network,
+            account,
+        } => {
+            let pool_address = parse_felt_arg(&pool_address)?;
+            let account = build_account(&network, &account).await?;
+            let client = SwapClient::new(account, pool_address, "");
+            let config = client.get_pool_config().await?;
+            output.field("token0", felt_to_hex(config.token0));
+            output.field("token1", felt_to_hex(config.token1));
+            for (label, token) in [("token0", config.token0), ("token1", config.token1)] {
+                if let Ok(metadata) = client.get_token_metadata(token).await {
+                    output.field(&format!("{label}_decimals"), metadata.decimals);
+                    if let Some(symbol) = metadata.symbol {
+                        output.field(&format!("{label}_symbol"), symbol);
+                    }
+                }
+            }
+            output.field("fee", config.fee.to_string());
+            output.field("tick_spacing", config.tick_spacing.to_string());
+            output.field("min_sqrt_ratio", config.min_sqrt_ratio.to_string());
+            output.field("max_sqrt_ratio", config.max_sqrt_ratio.to_string());
         }
         Commands::AspRootLatest { asp_url, token } => {
             let token_label = if token == "position" {
@@ -936,37 +1357,250 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             };
             let (root, root_index, leaf_count) =
                 asp_fetch_latest_root(&asp_url, &token_label).await?;
-            println!("token={token_label}");
-            println!("root_index={root_index}");
-            println!("leaf_count={leaf_count}");
-            println!("root={}", felt_to_hex(root));
+            output.field("token", token_label);
+            output.field("root_index", root_index);
+            output.field("leaf_count", leaf_count);
+            output.field("root", felt_to_hex(root));
+        }
+        Commands::VerifyPath {
+            note,
+            token_id,
+            asp_url,
+        } => {
+            let note = load_note(&note)?;
+            let commitment = compute_commitment(&note, token_id)?;
+            let path = asp_fetch_unverified_path(&asp_url, commitment, None, None).await?;
+            let computed_root = path.compute_root()?;
+            let valid = path.verify();
+            output.field("commitment", felt_to_hex(commitment));
+            output.field("leaf_index", path.leaf_index);
+            output.field("asp_root", felt_to_hex(path.root));
+            output.field("computed_root", felt_to_hex(computed_root));
+            output.field("valid", valid);
+            if !valid {
+                return Err("recomputed root does not match the asp root".into());
+            }
         }
+        Commands::Wallet { command } => run_wallet_command(command, output).await?,
     }
     Ok(())
 }
 
-async fn build_account(
-    network: &NetworkArgs,
-    account: &AccountArgs,
-) -> Result<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>, String> {
+async fn run_wallet_command(command: WalletCommand, output: &mut Output) -> Result<(), String> {
+    match command {
+        WalletCommand::Init { wallet } => {
+            if wallet.wallet.exists() {
+                return Err(format!(
+                    "wallet already exists: {}",
+                    wallet.wallet.display()
+                ));
+            }
+            save_wallet(&wallet, &WalletContents::default())?;
+            output.field("wallet", wallet.wallet.display().to_string());
+        }
+        WalletCommand::Add {
+            note,
+            token_id,
+            position_note,
+            wallet,
+        } => {
+            let mut contents = load_wallet(&wallet)?;
+            if let Some(path) = note {
+                let token_id = token_id.ok_or("--token-id is required with --note")?;
+                if token_id > 1 {
+                    return Err("token_id must be 0 or 1".to_string());
+                }
+                let note = load_note(&path)?;
+                let commitment = compute_commitment(&note, token_id).map_err(|e| e.to_string())?;
+                for existing in &contents.notes {
+                    if wallet_note_commitment(existing)? == commitment {
+                        return Err("note already in wallet".to_string());
+                    }
+                }
+                contents.notes.push(WalletNote {
+                    token_id,
+                    note: note_file(&note),
+                });
+                output.field("commitment", felt_to_hex(commitment));
+            } else if let Some(path) = position_note {
+                let note = load_position_note(&path)?;
+                let commitment = compute_position_commitment(&note).map_err(|e| e.to_string())?;
+                for existing in &contents.position_notes {
+                    let existing = position_note_from_file(existing)?;
+                    if compute_position_commitment(&existing).map_err(|e| e.to_string())?
+                        == commitment
+                    {
+                        return Err("position note already in wallet".to_string());
+                    }
+                }
+                contents.position_notes.push(position_note_file(&note));
+                output.field("commitment", felt_to_hex(commitment));
+            }
+            save_wallet(&wallet, &contents)?;
+        }
+        WalletCommand::List { asp_url, wallet } => {
+            let contents = load_wallet(&wallet)?;
+            let mut notes = Vec::new();
+            for entry in &contents.notes {
+                let note = wallet_note(entry)?;
+                let nullifier =
+                    generate_nullifier_hash(&note, entry.token_id).map_err(|e| e.to_string())?;
+                let status = match &asp_url {
+                    Some(asp_url) => {
+                        let tree = InsertionTree::Token(note.token);
+                        spent_label(asp_nullifier_spent(asp_url, nullifier, tree).await?)
+                    }
+                    None => "unknown",
+                };
+                notes.push(serde_json::json!({
+                    "token": felt_to_hex(note.token),
+                    "amount": note.amount.to_string(),
+                    "commitment": felt_to_hex(wallet_note_commitment(entry)?),
+                    "status": status,
+                }));
+            }
+            let mut positions = Vec::new();
+            for entry in &contents.position_notes {
+                let note = position_note_from_file(entry)?;
+                let nullifier =
+                    generate_position_nullifier_hash(&note).map_err(|e| e.to_string())?;
+                let status = match &asp_url {
+                    Some(asp_url) => {
+                        let tree = InsertionTree::Position;
+                        spent_label(asp_nullifier_spent(asp_url, nullifier, tree).await?)
+                    }
+                    None => "unknown",
+                };
+                positions.push(serde_json::json!({
+                    "tick_lower": note.tick_lower,
+                    "tick_upper": note.tick_upper,
+                    "liquidity": note.liquidity.to_string(),
+                    "commitment": felt_to_hex(
+                        compute_position_commitment(&note).map_err(|e| e.to_string())?
+                    ),
+                    "status": status,
+                }));
+            }
+            output.records("notes", notes, |_, note| {
+                format!(
+                    "note token={} amount={} commitment={} status={}",
+                    note["token"].as_str().unwrap_or_default(),
+                    note["amount"].as_str().unwrap_or_default(),
+                    note["commitment"].as_str().unwrap_or_default(),
+                    note["status"].as_str().unwrap_or_default(),
+                )
+            });
+            output.records("positions", positions, |_, position| {
+                format!(
+                    "position ticks=[{},{}] liquidity={} commitment={} status={}",
+                    position["tick_lower"],
+                    position["tick_upper"],
+                    position["liquidity"].as_str().unwrap_or_default(),
+                    position["commitment"].as_str().unwrap_or_default(),
+                    position["status"].as_str().unwrap_or_default(),
+                )
+            });
+        }
+        WalletCommand::Balance { asp_url, wallet } => {
+            let contents = load_wallet(&wallet)?;
+            let mut balances: BTreeMap<String, (u128, usize)> = BTreeMap::new();
+            for entry in &contents.notes {
+                let note = wallet_note(entry)?;
+                let nullifier =
+                    generate_nullifier_hash(&note, entry.token_id).map_err(|e| e.to_string())?;
+                if asp_nullifier_spent(&asp_url, nullifier, InsertionTree::Token(note.token))
+                    .await?
+                {
+                    continue;
+                }
+                let balance = balances.entry(felt_to_hex(note.token)).or_default();
+                balance.0 = balance
+                    .0
+                    .checked_add(note.amount)
+                    .ok_or("balance overflow")?;
+                balance.1 += 1;
+            }
+            let balances = balances
+                .into_iter()
+                .map(|(token, (amount, notes))| {
+                    serde_json::json!({
+                        "token": token,
+                        "unspent": amount.to_string(),
+                        "notes": notes,
+                    })
+                })
+                .collect();
+            output.records("balances", balances, |_, balance| {
+                format!(
+                    "token={} unspent={} notes={}",
+                    balance["token"].as_str().unwrap_or_default(),
+                    balance["unspent"].as_str().unwrap_or_default(),
+                    balance["notes"],
+                )
+            });
+        }
+    }
+    Ok(())
+}
+
+type CliAccount = SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>;
+
+async fn build_account(network: &NetworkArgs, account: &AccountArgs) -> Result<CliAccount, String> {
     let rpc_url = Url::parse(&network.rpc_url).map_err(|e| e.to_string())?;
-    let provider = JsonRpcClient::new(HttpTransport::new(rpc_url));
+    let provider = rpc_provider(rpc_url, network.rpc_timeout_secs.map(Duration::from_secs))
+        .map_err(|e| e.to_string())?;
     let chain_id = match &network.chain_id {
         Some(chain_id) => parse_felt_arg(chain_id)?,
         None => provider.chain_id().await.map_err(|e| e.to_string())?,
     };
     let account_address = parse_felt_arg(&account.account_address)?;
-    let private_key = parse_felt_arg(&account.private_key)?;
-    let signing_key = SigningKey::from_secret_scalar(private_key);
-    let mut account = SingleOwnerAccount::new(
-        provider,
-        LocalWallet::from(signing_key),
-        account_address,
-        chain_id,
-        ExecutionEncoding::New,
-    );
-    account.set_block_id(BlockId::Tag(BlockTag::Latest));
-    Ok(account)
+    let signer = account_signer(account)?;
+    Ok(signer_account(provider, signer, account_address, chain_id))
+}
+
+// Each command drives either the pool or the shielded-notes contract, so the other address is
+// left zero; the pool tokens are read from the chain when needed.
+fn client_config(
+    network: &NetworkArgs,
+    account: CliAccount,
+    asp_url: String,
+    pool_address: Felt,
+    shielded_notes_address: Felt,
+) -> ZylithConfig<CliAccount> {
+    ZylithConfig {
+        account,
+        asp_url,
+        pool_address,
+        shielded_notes_address,
+        token0: Felt::ZERO,
+        token1: Felt::ZERO,
+        rpc_timeout: network.rpc_timeout_secs.map(Duration::from_secs),
+        request_timeout: Duration::from_secs(network.request_timeout_secs),
+        pool_state_ttl: None,
+        check_nullifiers: true,
+    }
+}
+
+fn account_signer(account: &AccountArgs) -> Result<LocalWallet, String> {
+    match (&account.private_key, &account.keystore) {
+        (Some(private_key), None) => {
+            let private_key = parse_felt_arg(private_key)?;
+            Ok(LocalWallet::from(SigningKey::from_secret_scalar(
+                private_key,
+            )))
+        }
+        (None, Some(path)) => {
+            let password =
+                Zeroizing::new(std::env::var(&account.keystore_password_env).map_err(|_| {
+                    format!(
+                        "set {} to the keystore password",
+                        account.keystore_password_env
+                    )
+                })?);
+            load_keystore_signer(path, &password).map_err(|e| e.to_string())
+        }
+        _ => Err("pass exactly one of --private-key or --keystore".to_string()),
+    }
 }
 
 async fn prove_deposit_proof(
@@ -1054,16 +1688,8 @@ fn default_circuit_dir(circuit: &str) -> PathBuf {
 fn load_note(path: &Path) -> Result<Note, String> {
     let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
     let note: NoteFile = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
-    let secret = parse_bytes32(&note.secret)?;
-    let nullifier = parse_bytes32(&note.nullifier)?;
-    let amount = note.amount.parse::<u128>().map_err(|e| e.to_string())?;
-    let token = parse_felt_arg(&note.token)?;
-    Ok(Note {
-        secret,
-        nullifier,
-        amount,
-        token,
-    })
+    Note::try_from_hex_fields(&note.secret, &note.nullifier, &note.amount, &note.token)
+        .map_err(|e| e.to_string())
 }
 
 fn load_notes(paths: &[PathBuf]) -> Result<Vec<Note>, String> {
@@ -1077,6 +1703,10 @@ fn load_notes(paths: &[PathBuf]) -> Result<Vec<Note>, String> {
 fn load_position_note(path: &Path) -> Result<PositionNote, String> {
     let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
     let note: PositionNoteFile = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    position_note_from_file(&note)
+}
+
+fn position_note_from_file(note: &PositionNoteFile) -> Result<PositionNote, String> {
     let secret = parse_bytes32(&note.secret)?;
     let nullifier = parse_bytes32(&note.nullifier)?;
     let liquidity = note.liquidity.parse::<u128>().map_err(|e| e.to_string())?;
@@ -1093,33 +1723,197 @@ fn load_position_note(path: &Path) -> Result<PositionNote, String> {
     })
 }
 
-fn write_note_output(label: &str, note: Option<Note>, out: Option<PathBuf>) -> Result<(), String> {
-    let Some(note) = note else {
-        return Ok(());
-    };
-    let file = NoteFile {
+fn note_file(note: &Note) -> NoteFile {
+    NoteFile {
         secret: bytes32_to_hex(&note.secret),
         nullifier: bytes32_to_hex(&note.nullifier),
         amount: note.amount.to_string(),
         token: felt_to_hex(note.token),
+    }
+}
+
+fn position_note_file(note: &PositionNote) -> PositionNoteFile {
+    PositionNoteFile {
+        secret: bytes32_to_hex(&note.secret),
+        nullifier: bytes32_to_hex(&note.nullifier),
+        tick_lower: note.tick_lower,
+        tick_upper: note.tick_upper,
+        liquidity: note.liquidity.to_string(),
+        fee_growth_inside_0: u256_to_hex(&note.fee_growth_inside_0),
+        fee_growth_inside_1: u256_to_hex(&note.fee_growth_inside_1),
+    }
+}
+
+fn wallet_note(entry: &WalletNote) -> Result<Note, String> {
+    let note = &entry.note;
+    Note::try_from_hex_fields(&note.secret, &note.nullifier, &note.amount, &note.token)
+        .map_err(|e| e.to_string())
+}
+
+fn wallet_note_commitment(entry: &WalletNote) -> Result<Felt, String> {
+    compute_commitment(&wallet_note(entry)?, entry.token_id).map_err(|e| e.to_string())
+}
+
+fn wallet_password(args: &WalletArgs) -> Result<String, String> {
+    let password = std::env::var(&args.password_env)
+        .map_err(|_| format!("set {} to the wallet password", args.password_env))?;
+    if password.is_empty() {
+        return Err(format!("{} is empty", args.password_env));
+    }
+    Ok(password)
+}
+
+fn wallet_key(password: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("wallet key derivation failed: {e}"))?;
+    Ok(key)
+}
+
+fn load_wallet(args: &WalletArgs) -> Result<WalletContents, String> {
+    let password = wallet_password(args)?;
+    let contents = fs::read_to_string(&args.wallet)
+        .map_err(|e| format!("read wallet {}: {e}", args.wallet.display()))?;
+    let file: WalletFile = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    if file.version != WALLET_VERSION {
+        return Err(format!("unsupported wallet version {}", file.version));
+    }
+    let salt = hex_to_bytes(&file.salt)?;
+    let nonce = hex_to_bytes(&file.nonce)?;
+    if nonce.len() != 24 {
+        return Err("invalid wallet nonce".to_string());
+    }
+    let key = Zeroizing::new(wallet_key(&password, &salt)?);
+    let cipher = XChaCha20Poly1305::new_from_slice(key.as_slice()).map_err(|e| e.to_string())?;
+    let plaintext = Zeroizing::new(
+        cipher
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                hex_to_bytes(&file.ciphertext)?.as_slice(),
+            )
+            .map_err(|_| "wrong wallet password or corrupted wallet".to_string())?,
+    );
+    serde_json::from_slice(&plaintext).map_err(|e| e.to_string())
+}
+
+// Re-encrypts with a fresh salt and nonce on every save and swaps the file in atomically.
+fn save_wallet(args: &WalletArgs, contents: &WalletContents) -> Result<(), String> {
+    let password = wallet_password(args)?;
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+    let key = Zeroizing::new(wallet_key(&password, &salt)?);
+    let cipher = XChaCha20Poly1305::new_from_slice(key.as_slice()).map_err(|e| e.to_string())?;
+    let plaintext = Zeroizing::new(serde_json::to_vec(contents).map_err(|e| e.to_string())?);
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| "wallet encryption failed".to_string())?;
+    let file = WalletFile {
+        version: WALLET_VERSION,
+        salt: bytes_to_hex(&salt),
+        nonce: bytes_to_hex(&nonce),
+        ciphertext: bytes_to_hex(&ciphertext),
     };
     let serialized = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
-    if let Some(path) = out {
-        fs::write(path, serialized).map_err(|e| e.to_string())?;
+    let tmp_path = args.wallet.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut tmp = options.open(&tmp_path).map_err(|e| e.to_string())?;
+    {
+        use std::io::Write;
+        tmp.write_all(serialized.as_bytes())
+            .and_then(|_| tmp.sync_all())
+            .map_err(|e| e.to_string())?;
+    }
+    fs::rename(&tmp_path, &args.wallet).map_err(|e| e.to_string())
+}
+
+fn spent_label(spent: bool) -> &'static str {
+    if spent {
+        "spent"
     } else {
-        println!("{label}={serialized}");
+        "unspent"
+    }
+}
+
+// A reverted transaction still reports its block, then fails the command.
+fn write_receipt_output(output: &mut Output, receipt: &TxReceipt) -> Result<(), String> {
+    output.field("block_number", receipt.block_number);
+    if let Some(reason) = &receipt.revert_reason {
+        output.field("revert_reason", reason.clone());
+        return Err(format!("transaction reverted: {reason}"));
     }
     Ok(())
 }
 
-fn write_note_list_output(label: &str, notes: &[Note]) -> Result<(), String> {
-    for (idx, note) in notes.iter().cloned().enumerate() {
-        write_note_output(&format!("{label}[{idx}]"), Some(note), None)?;
+// Consolidation inputs must fit a single swap proof and share one token; returns that token.
+fn check_consolidation_inputs(notes: &[Note]) -> Result<Felt, String> {
+    if notes.len() < 2 {
+        return Err("consolidate needs at least two --note-in notes".to_string());
+    }
+    if notes.len() > MAX_INPUT_NOTES {
+        return Err(format!(
+            "consolidate accepts at most {MAX_INPUT_NOTES} notes per run (got {}); merge them in batches of {MAX_INPUT_NOTES}",
+            notes.len()
+        ));
     }
+    let token = notes[0].token;
+    if let Some(note) = notes.iter().find(|note| note.token != token) {
+        return Err(format!(
+            "cannot consolidate notes of different tokens: {} and {}",
+            felt_to_hex(token),
+            felt_to_hex(note.token)
+        ));
+    }
+    Ok(token)
+}
+
+fn write_note_output(
+    output: &mut Output,
+    label: &str,
+    note: Option<Note>,
+    out: Option<PathBuf>,
+) -> Result<(), String> {
+    let Some(note) = note else {
+        return Ok(());
+    };
+    write_note_file(output, label, &note_file(&note), out)
+}
+
+// `--dry-run` output: the calls that would have been submitted, in submission order.
+fn write_calls_output(output: &mut Output, calls: &[Call]) {
+    let calls = calls
+        .iter()
+        .map(|call| {
+            serde_json::json!({
+                "to": felt_to_hex(call.to),
+                "selector": felt_to_hex(call.selector),
+                "calldata": call.calldata.iter().copied().map(felt_to_hex).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    output.field("dry_run", true);
+    output.list("calls", calls);
+}
+
+fn write_note_list_output(output: &mut Output, label: &str, notes: &[Note]) -> Result<(), String> {
+    let files = notes
+        .iter()
+        .map(|note| serde_json::to_value(note_file(note)).map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    output.list(label, files);
     Ok(())
 }
 
 fn write_position_note_output(
+    output: &mut Output,
     label: &str,
     note: Option<PositionNote>,
     out: Option<PathBuf>,
@@ -1127,29 +1921,132 @@ fn write_position_note_output(
     let Some(note) = note else {
         return Ok(());
     };
-    let file = PositionNoteFile {
-        secret: bytes32_to_hex(&note.secret),
-        nullifier: bytes32_to_hex(&note.nullifier),
-        tick_lower: note.tick_lower,
-        tick_upper: note.tick_upper,
-        liquidity: note.liquidity.to_string(),
-        fee_growth_inside_0: u256_to_hex(&note.fee_growth_inside_0),
-        fee_growth_inside_1: u256_to_hex(&note.fee_growth_inside_1),
-    };
-    let serialized = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    write_note_file(output, label, &position_note_file(&note), out)
+}
+
+fn write_note_file<T: Serialize>(
+    output: &mut Output,
+    label: &str,
+    file: &T,
+    out: Option<PathBuf>,
+) -> Result<(), String> {
     if let Some(path) = out {
+        let serialized = serde_json::to_string_pretty(file).map_err(|e| e.to_string())?;
         fs::write(path, serialized).map_err(|e| e.to_string())?;
     } else {
-        println!("{label}={serialized}");
+        output.field(
+            label,
+            serde_json::to_value(file).map_err(|e| e.to_string())?,
+        );
     }
     Ok(())
 }
 
+// Collects what a command reports: `key=value` lines by default, or a single JSON object on
+// stdout with --json.
+struct Output {
+    json: bool,
+    fields: serde_json::Map<String, serde_json::Value>,
+    lines: Vec<String>,
+}
+
+impl Output {
+    fn new(json: bool) -> Self {
+        Self {
+            json,
+            fields: serde_json::Map::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    fn field(&mut self, key: &str, value: impl Into<serde_json::Value>) {
+        let value = value.into();
+        if self.json {
+            self.fields.insert(key.to_string(), value);
+        } else {
+            self.lines.push(format!("{key}={}", text_value(&value)));
+        }
+    }
+
+    // A JSON array, printed in text mode as one `key[idx]=...` entry per element.
+    fn list(&mut self, key: &str, values: Vec<serde_json::Value>) {
+        self.records(key, values, |idx, value| {
+            format!("{key}[{idx}]={}", text_value(value))
+        });
+    }
+
+    // A JSON array whose text form is rendered entry by entry by `line`.
+    fn records(
+        &mut self,
+        key: &str,
+        values: Vec<serde_json::Value>,
+        line: impl Fn(usize, &serde_json::Value) -> String,
+    ) {
+        if self.json {
+            self.fields
+                .insert(key.to_string(), serde_json::Value::Array(values));
+        } else {
+            self.lines.extend(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, value)| line(idx, value)),
+            );
+        }
+    }
+
+    // A complete JSON document such as a freshly generated note; with --json its fields become
+    // the output object.
+    fn document(&mut self, value: serde_json::Value) {
+        match value {
+            serde_json::Value::Object(fields) if self.json => self.fields.extend(fields),
+            value => self.lines.push(text_value(&value)),
+        }
+    }
+
+    fn emit(&self) {
+        if self.json {
+            if !self.fields.is_empty() {
+                println!("{}", serde_json::Value::Object(self.fields.clone()));
+            }
+        } else {
+            for line in &self.lines {
+                println!("{line}");
+            }
+        }
+    }
+}
+
+fn text_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Object(_) | serde_json::Value::Array(_) => {
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        }
+        value => value.to_string(),
+    }
+}
+
 async fn asp_fetch_merkle_path(
     asp_url: &str,
     commitment: Felt,
     root_index: Option<u64>,
     root_hash: Option<Felt>,
+) -> Result<MerklePath, String> {
+    let path = asp_fetch_unverified_path(asp_url, commitment, root_index, root_hash).await?;
+    if !path.verify() {
+        return Err("asp merkle path does not match root".to_string());
+    }
+    Ok(path)
+}
+
+// The ASP's path for `commitment` paired with the root it should prove: the pinned root, or
+// the ASP's latest root for the commitment's tree. Nothing checks that the two agree.
+async fn asp_fetch_unverified_path(
+    asp_url: &str,
+    commitment: Felt,
+    root_index: Option<u64>,
+    root_hash: Option<Felt>,
 ) -> Result<MerklePath, String> {
     if root_index.is_some() && root_hash.is_some() {
         return Err("root_index and root_hash are mutually exclusive".to_string());
@@ -1293,6 +2190,27 @@ async fn asp_fetch_root_at(asp_url: &str, token: &str, index: u64) -> Result<Fel
     parse_felt_arg(&body.root)
 }
 
+// Unfinalized spends count, as in `SwapClient::is_nullifier_spent`.
+async fn asp_nullifier_spent(
+    asp_url: &str,
+    nullifier: Felt,
+    tree: InsertionTree,
+) -> Result<bool, String> {
+    let url = format!(
+        "{}/nullifier/{}?token={}",
+        asp_url.trim_end_matches('/'),
+        felt_to_hex(nullifier),
+        tree.label()
+    );
+    let client = asp_client()?;
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("asp nullifier error: {}", response.status()));
+    }
+    let body: NullifierResponse = response.json().await.map_err(|e| e.to_string())?;
+    Ok(body.spent || body.pending)
+}
+
 fn asp_timeout() -> Result<Duration, String> {
     if let Ok(value) = std::env::var("ZYLITH_ASP_TIMEOUT_SECS") {
         let secs = value
@@ -1307,6 +2225,22 @@ fn asp_timeout() -> Result<Duration, String> {
     }
 }
 
+fn append_swap_record(path: &Path, record: &SwapRecord) -> Result<(), String> {
+    use std::io::Write;
+    let needs_header = fs::metadata(path)
+        .map(|meta| meta.len() == 0)
+        .unwrap_or(true);
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    if needs_header {
+        writeln!(file, "{}", SwapRecord::CSV_HEADER).map_err(|e| e.to_string())?;
+    }
+    writeln!(file, "{}", record.to_csv_row()).map_err(|e| e.to_string())
+}
+
 fn asp_client() -> Result<reqwest::Client, String> {
     let timeout = asp_timeout()?;
     reqwest::Client::builder()
@@ -1363,10 +2297,56 @@ fn bytes32_to_hex(value: &[u8; 32]) -> String {
     out
 }
 
+fn bytes_to_hex(value: &[u8]) -> String {
+    value.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn hex_to_bytes(value: &str) -> Result<Vec<u8>, String> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    if hex.len() % 2 != 0 {
+        return Err("odd-length hex string".to_string());
+    }
+    // Slicing below is by byte offset, so a multi-byte character would panic instead of failing.
+    if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err("invalid hex string".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|start| u8::from_str_radix(&hex[start..start + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
 fn felt_to_hex(value: Felt) -> String {
     format!("0x{:x}", value)
 }
 
+// Metadata only adds display fields, so a token that cannot be read just leaves them out.
+async fn pool_token_metadata<A: ConnectedAccount + Sync>(
+    client: &SwapClient<A>,
+) -> Option<[TokenMetadata; 2]> {
+    let config = client.get_pool_config().await.ok()?;
+    let token0 = client.get_token_metadata(config.token0).await.ok()?;
+    let token1 = client.get_token_metadata(config.token1).await.ok()?;
+    Some([token0, token1])
+}
+
+fn signed_token_display(token: &TokenMetadata, value: &SignedAmount) -> String {
+    let amount = token.format_amount(value.mag);
+    if value.is_negative() {
+        format!("-{amount}")
+    } else {
+        amount
+    }
+}
+
+fn signed_amount_display(value: &SignedAmount) -> String {
+    if value.is_negative() {
+        format!("-{}", value.mag)
+    } else {
+        value.mag.to_string()
+    }
+}
+
 fn u256_to_hex(value: &U256) -> String {
     let mut buf = [0u8; 32];
     buf[0..16].copy_from_slice(&value.high().to_be_bytes());
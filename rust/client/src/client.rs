@@ -1,31 +1,77 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use num_bigint::BigUint;
-use starknet::accounts::ConnectedAccount;
-use starknet::core::types::{BlockId, BlockTag, FunctionCall, U256};
+use starknet::accounts::{ConnectedAccount, ExecutionEncoding, SingleOwnerAccount};
+use starknet::core::types::{BlockId, BlockTag, FeeEstimate, Felt, FunctionCall, U256};
 use starknet::core::utils::get_selector_from_name;
+use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClient};
 use starknet::providers::Provider;
+use starknet::signers::Signer;
+use url::Url;
 
 use crate::deposit::{DepositClient, DepositRequest, DepositResult};
 use crate::error::ClientError;
 use crate::generated_constants;
 use crate::liquidity::{LiquidityClaimRequest, LiquidityClient, LiquidityRequest};
-use crate::swap::{with_retry, MerklePath, SwapClient, SwapQuoteRequest, SwapResult, TxHash};
-use crate::utils::{felt_to_i32, felt_to_u128, Address};
+use crate::notes::{compute_commitment, Note};
+use crate::pool_cache::TtlCache;
+use crate::prover_backend::{ProverBackend, SnarkjsProver};
+use crate::swap::{
+    estimate_fee_with_retry, wait_for_receipt, with_retry, MerklePath, SwapClient,
+    SwapQuoteRequest, SwapResult, TokenMetadata, TxHash, TxReceipt,
+};
+use crate::utils::{felt_to_tick, felt_to_u128, Address};
 use crate::withdraw::{WithdrawClient, WithdrawRequest};
 use zylith_prover::ProofCalldata;
 
 #[derive(Debug, Clone)]
 pub struct RetryConfig {
     pub max_attempts: usize,
-    pub delay_ms: u64,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    // Full jitter: each delay is drawn uniformly from [0, capped backoff].
+    pub jitter: bool,
+    pub attempt_timeout: Option<Duration>,
+    // Bounds the whole operation, every attempt and backoff included.
+    pub deadline: Option<Duration>,
 }
 
 impl Default for RetryConfig {
     fn default() -> Self {
         Self {
             max_attempts: 3,
-            delay_ms: 500,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: false,
+            attempt_timeout: None,
+            deadline: None,
+        }
+    }
+}
+
+impl RetryConfig {
+    // Delay before retrying after the given failed attempt (1-based).
+    pub fn delay_for_attempt<R: rand::Rng + ?Sized>(
+        &self,
+        attempt: usize,
+        rng: &mut R,
+    ) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let factor = self.multiplier.max(1.0).powi(exponent);
+        let max_secs = self.max_delay.as_secs_f64();
+        let backoff = (self.base_delay.as_secs_f64() * factor).min(max_secs);
+        let capped = if backoff.is_finite() {
+            backoff
+        } else {
+            max_secs
+        };
+        if self.jitter && capped > 0.0 {
+            Duration::from_secs_f64(rng.gen_range(0.0..=capped))
+        } else {
+            Duration::from_secs_f64(capped)
         }
     }
 }
@@ -49,6 +95,20 @@ pub struct PoolConfig {
     pub max_sqrt_ratio: U256,
 }
 
+// One consolidation: spend `notes` plus the outputs of earlier steps into a single note.
+#[derive(Debug, Clone)]
+pub struct DefragmentStep {
+    pub token: Address,
+    pub notes: Vec<Note>,
+    pub merged_steps: Vec<usize>,
+    pub output_amount: u128,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DefragmentPlan {
+    pub steps: Vec<DefragmentStep>,
+}
+
 pub struct ZylithConfig<A: ConnectedAccount + Sync + Send> {
     pub account: A,
     pub asp_url: String,
@@ -56,6 +116,44 @@ pub struct ZylithConfig<A: ConnectedAccount + Sync + Send> {
     pub shielded_notes_address: Address,
     pub token0: Address,
     pub token1: Address,
+    // Per-attempt bound on contract calls. It cannot reach a transport that is already built,
+    // so construct the account's provider with `rpc_provider(url, rpc_timeout)` as well.
+    pub rpc_timeout: Option<Duration>,
+    // Deadline for each ASP fetch or contract call, retries included.
+    pub request_timeout: Duration,
+    // Reuse pool config/state reads for this long; `None` reads the chain on every call.
+    pub pool_state_ttl: Option<Duration>,
+    // Ask the ASP whether each input note is already spent before proving; disable for
+    // offline or test flows without an ASP.
+    pub check_nullifiers: bool,
+}
+
+/// Wraps any [`Signer`] in the account type `ZylithClient` is built around. `ZylithClient` is
+/// generic over `ConnectedAccount`, so the key can live in a keystore (see
+/// `load_keystore_signer`), on a hardware wallet, or behind a remote signing service instead
+/// of in process memory.
+///
+/// ```ignore
+/// use starknet::signers::ledger::LedgerSigner;
+///
+/// let signer = LedgerSigner::new(derivation_path).await?;
+/// let account = signer_account(provider, signer, account_address, chain_id);
+/// let client = ZylithClient::new(ZylithConfig { account, ..config });
+/// ```
+pub fn signer_account<P, S>(
+    provider: P,
+    signer: S,
+    address: Address,
+    chain_id: Felt,
+) -> SingleOwnerAccount<P, S>
+where
+    P: Provider + Send + Sync,
+    S: Signer + Send + Sync,
+{
+    let mut account =
+        SingleOwnerAccount::new(provider, signer, address, chain_id, ExecutionEncoding::New);
+    account.set_block_id(BlockId::Tag(BlockTag::Latest));
+    account
 }
 
 pub struct ZylithClient<A: ConnectedAccount + Sync + Send> {
@@ -66,6 +164,11 @@ pub struct ZylithClient<A: ConnectedAccount + Sync + Send> {
     pub token0: Address,
     pub token1: Address,
     pub retry: RetryConfig,
+    pub check_nullifiers: bool,
+    pub(crate) prover: Arc<dyn ProverBackend>,
+    pool_configs: Arc<TtlCache<PoolConfig>>,
+    pool_states: Arc<TtlCache<PoolState>>,
+    token_metadata: Arc<TtlCache<TokenMetadata>>,
 }
 
 impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
@@ -77,10 +180,25 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             shielded_notes_address: config.shielded_notes_address,
             token0: config.token0,
             token1: config.token1,
-            retry: RetryConfig::default(),
+            retry: RetryConfig {
+                attempt_timeout: config.rpc_timeout,
+                deadline: Some(config.request_timeout),
+                ..RetryConfig::default()
+            },
+            check_nullifiers: config.check_nullifiers,
+            prover: Arc::new(SnarkjsProver),
+            pool_configs: Arc::new(TtlCache::new(config.pool_state_ttl)),
+            pool_states: Arc::new(TtlCache::new(config.pool_state_ttl)),
+            token_metadata: Arc::new(TtlCache::new(Some(Duration::MAX))),
         }
     }
 
+    #[cfg(any(test, feature = "test-stubs"))]
+    pub fn with_prover(mut self, prover: Arc<dyn ProverBackend>) -> Self {
+        self.prover = prover;
+        self
+    }
+
     pub fn swap_client(&self) -> SwapClient<Arc<A>> {
         let mut client = SwapClient::new(
             self.account.clone(),
@@ -88,17 +206,20 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             self.asp_url.clone(),
         );
         client.retry = self.retry.clone();
+        client.token_metadata = self.token_metadata.clone();
         client
     }
 
     pub fn deposit_client(&self) -> DepositClient<Arc<A>> {
         let mut client = DepositClient::new(self.account.clone(), self.shielded_notes_address);
+        client.asp_url = Some(self.asp_url.clone());
         client.retry = self.retry.clone();
         client
     }
 
     pub fn withdraw_client(&self) -> WithdrawClient<Arc<A>> {
         let mut client = WithdrawClient::new(self.account.clone(), self.shielded_notes_address);
+        client.asp_url = Some(self.asp_url.clone());
         client.retry = self.retry.clone();
         client
     }
@@ -119,12 +240,105 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         }
     }
 
-    pub async fn deposit(&self, request: DepositRequest) -> Result<DepositResult, ClientError> {
-        match request.token_id {
-            0 => self.deposit_client().deposit_token0(request).await,
-            1 => self.deposit_client().deposit_token1(request).await,
-            _ => Err(ClientError::InvalidInput("invalid token id".to_string())),
+    pub fn spendable_combinations(
+        &self,
+        notes: &[Note],
+        token: Address,
+        target: u128,
+    ) -> Result<Vec<Vec<Note>>, ClientError> {
+        self.token_id(token)?;
+        if target == 0 {
+            return Err(ClientError::InvalidInput(
+                "target must be greater than zero".to_string(),
+            ));
+        }
+        let eligible: Vec<&Note> = notes
+            .iter()
+            .filter(|note| note.token == token && note.amount > 0)
+            .collect();
+        // The largest MAX_INPUT_NOTES notes bound every combination, so an unreachable target
+        // is rejected here instead of by an exhaustive search.
+        let mut amounts: Vec<u128> = eligible.iter().map(|note| note.amount).collect();
+        amounts.sort_unstable_by(|a, b| b.cmp(a));
+        let best = amounts
+            .iter()
+            .take(generated_constants::MAX_INPUT_NOTES)
+            .fold(0u128, |acc, amount| acc.saturating_add(*amount));
+        let combinations = if best < target {
+            Vec::new()
+        } else {
+            note_combinations(&eligible, target)
+        };
+        if combinations.is_empty() {
+            let total = amounts
+                .iter()
+                .fold(0u128, |acc, amount| acc.saturating_add(*amount));
+            let skipped = notes.len() - eligible.len();
+            let reason = if total < target {
+                "insufficient balance"
+            } else {
+                "balance fragmented across too many notes"
+            };
+            return Err(ClientError::InvalidInput(format!(
+                "no spendable combination: {reason} (target {target}, best {best} with max {} notes, total {total}, {skipped} notes skipped for token mismatch or zero amount)",
+                generated_constants::MAX_INPUT_NOTES
+            )));
+        }
+        Ok(combinations)
+    }
+
+    // The swap circuit has a fixed output arity (one swap output, one change note), so
+    // fragmentation cannot be undone inside a swap. Instead this plans consolidations:
+    // per token, the smallest notes are merged (at most MAX_INPUT_NOTES inputs per step)
+    // until the whole balance fits in a single spend. The plan is pure data. The pool has no
+    // note-to-note transfer, so a step is executed as an exact-out self-swap of one unit
+    // (`zylith-cli consolidate`), which pays the swap fee and gives up a little input as dust.
+    pub fn defragment_notes(&self, notes: &[Note]) -> DefragmentPlan {
+        let mut steps = Vec::new();
+        for token in [self.token0, self.token1] {
+            let eligible: Vec<&Note> = notes
+                .iter()
+                .filter(|note| note.token == token && note.amount > 0)
+                .collect();
+            plan_defragmentation(&eligible, token, &mut steps);
+        }
+        DefragmentPlan { steps }
+    }
+
+    // Checks a deposit locally and returns its commitment; nothing is proven or sent.
+    pub fn simulate_deposit(&self, note: &Note, token_id: u8) -> Result<Felt, ClientError> {
+        let expected_token = match token_id {
+            0 => self.token0,
+            1 => self.token1,
+            _ => {
+                return Err(ClientError::InvalidInput(
+                    "token_id must be 0 or 1".to_string(),
+                ))
+            }
+        };
+        if note.amount == 0 {
+            return Err(ClientError::InvalidInput(
+                "note amount must be greater than zero".to_string(),
+            ));
+        }
+        if note.token != expected_token {
+            return Err(ClientError::InvalidInput("note token mismatch".to_string()));
         }
+        compute_commitment(note, token_id)
+    }
+
+    pub async fn deposit(&self, request: DepositRequest) -> Result<DepositResult, ClientError> {
+        self.deposit_client().deposit(request).await
+    }
+
+    // Polls the provider until `tx_hash` is included in a block. A reverted transaction is
+    // still a receipt; check `TxReceipt::succeeded` or its `revert_reason`.
+    pub async fn wait_for_receipt(
+        &self,
+        tx_hash: TxHash,
+        timeout: Duration,
+    ) -> Result<TxReceipt, ClientError> {
+        wait_for_receipt(self.account.provider(), tx_hash, timeout).await
     }
 
     pub async fn swap(
@@ -139,6 +353,20 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             .await
     }
 
+    // Estimates the same call `swap` would submit, so UIs can show the fee before signing.
+    pub async fn estimate_fee_for_swap(
+        &self,
+        proof: &ProofCalldata,
+        input_proofs: &[MerklePath],
+        output_proofs: &[MerklePath],
+        exact_out: bool,
+    ) -> Result<FeeEstimate, ClientError> {
+        let call = self
+            .swap_client()
+            .swap_call(proof, input_proofs, output_proofs, exact_out)?;
+        estimate_fee_with_retry(self.account.as_ref(), vec![call], self.retry.clone()).await
+    }
+
     pub async fn quote_swap(&self, request: SwapQuoteRequest) -> Result<SwapResult, ClientError> {
         self.swap_client().simulate_swap(request).await
     }
@@ -177,7 +405,49 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         self.withdraw_client().withdraw(request).await
     }
 
+    pub async fn estimate_fee_for_withdraw(
+        &self,
+        request: &WithdrawRequest,
+    ) -> Result<FeeEstimate, ClientError> {
+        let call = self
+            .withdraw_client()
+            .withdraw_call(request.clone())
+            .await?;
+        estimate_fee_with_retry(self.account.as_ref(), vec![call], self.retry.clone()).await
+    }
+
+    // Estimates the approve-and-deposit multicall `deposit` submits.
+    pub async fn estimate_fee_for_deposit(
+        &self,
+        request: &DepositRequest,
+    ) -> Result<FeeEstimate, ClientError> {
+        let calls = self.deposit_client().deposit_calls(request)?;
+        estimate_fee_with_retry(self.account.as_ref(), calls, self.retry.clone()).await
+    }
+
     pub async fn get_pool_state(&self) -> Result<PoolState, ClientError> {
+        self.pool_states
+            .get_or_fetch(self.pool_address, || self.fetch_pool_state())
+            .await
+    }
+
+    pub async fn get_token_metadata(&self, token: Address) -> Result<TokenMetadata, ClientError> {
+        self.swap_client().get_token_metadata(token).await
+    }
+
+    pub async fn get_pool_config(&self) -> Result<PoolConfig, ClientError> {
+        self.pool_configs
+            .get_or_fetch(self.pool_address, || self.fetch_pool_config())
+            .await
+    }
+
+    // Drops every cached pool read, e.g. after submitting a transaction that moved the pool.
+    pub fn invalidate_pool_cache(&self) {
+        self.pool_configs.clear();
+        self.pool_states.clear();
+    }
+
+    async fn fetch_pool_state(&self) -> Result<PoolState, ClientError> {
         let selector = get_selector_from_name("get_pool_state")
             .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
         let call = FunctionCall {
@@ -194,32 +464,10 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         })
         .await?;
 
-        if result.len() < 8 {
-            return Err(ClientError::Rpc("invalid pool state".to_string()));
-        }
-        let sqrt_price = U256::from_words(felt_to_u128(&result[0])?, felt_to_u128(&result[1])?);
-        let tick = felt_to_i32(&result[2])?;
-        let liquidity = felt_to_u128(&result[3])?;
-        let fee0_low = felt_to_u128(&result[4])?;
-        let fee0_high = felt_to_u128(&result[5])?;
-        let fee1_low = felt_to_u128(&result[6])?;
-        let fee1_high = felt_to_u128(&result[7])?;
-        let max_fee_growth = max_fee_growth()?;
-        let fee_growth_0 = biguint_from_words(fee0_low, fee0_high);
-        let fee_growth_1 = biguint_from_words(fee1_low, fee1_high);
-        if fee_growth_0 > max_fee_growth || fee_growth_1 > max_fee_growth {
-            return Err(ClientError::Rpc("fee growth exceeds max".to_string()));
-        }
-        Ok(PoolState {
-            sqrt_price,
-            tick,
-            liquidity,
-            fee_growth_global_0: (fee0_low, fee0_high),
-            fee_growth_global_1: (fee1_low, fee1_high),
-        })
+        decode_pool_state(&result)
     }
 
-    pub async fn get_pool_config(&self) -> Result<PoolConfig, ClientError> {
+    async fn fetch_pool_config(&self) -> Result<PoolConfig, ClientError> {
         let selector = get_selector_from_name("get_pool_config")
             .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
         let call = FunctionCall {
@@ -235,28 +483,176 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
                 .map_err(|err| ClientError::Rpc(err.to_string()))
         })
         .await?;
-        if result.len() < 8 {
-            return Err(ClientError::Rpc("invalid pool config".to_string()));
+        decode_pool_config(&result)
+    }
+}
+
+pub(crate) fn decode_pool_state(result: &[Felt]) -> Result<PoolState, ClientError> {
+    if result.len() < 8 {
+        return Err(ClientError::Rpc("invalid pool state".to_string()));
+    }
+    let sqrt_price = U256::from_words(felt_to_u128(&result[0])?, felt_to_u128(&result[1])?);
+    let tick = felt_to_tick(&result[2])?;
+    let liquidity = felt_to_u128(&result[3])?;
+    let fee0_low = felt_to_u128(&result[4])?;
+    let fee0_high = felt_to_u128(&result[5])?;
+    let fee1_low = felt_to_u128(&result[6])?;
+    let fee1_high = felt_to_u128(&result[7])?;
+    let max_fee_growth = max_fee_growth()?;
+    let fee_growth_0 = biguint_from_words(fee0_low, fee0_high);
+    let fee_growth_1 = biguint_from_words(fee1_low, fee1_high);
+    if fee_growth_0 > max_fee_growth || fee_growth_1 > max_fee_growth {
+        return Err(ClientError::Rpc("fee growth exceeds max".to_string()));
+    }
+    Ok(PoolState {
+        sqrt_price,
+        tick,
+        liquidity,
+        fee_growth_global_0: (fee0_low, fee0_high),
+        fee_growth_global_1: (fee1_low, fee1_high),
+    })
+}
+
+pub(crate) fn decode_pool_config(result: &[Felt]) -> Result<PoolConfig, ClientError> {
+    if result.len() < 8 {
+        return Err(ClientError::Rpc("invalid pool config".to_string()));
+    }
+    let token0 = result[0];
+    let token1 = result[1];
+    let fee = felt_to_u128(&result[2])?;
+    let tick_spacing = felt_to_u128(&result[3])?;
+    if tick_spacing == 0 || tick_spacing > generated_constants::MAX_TICK_SPACING {
+        return Err(ClientError::InvalidInput(
+            "invalid tick spacing".to_string(),
+        ));
+    }
+    let min_sqrt_ratio = U256::from_words(felt_to_u128(&result[4])?, felt_to_u128(&result[5])?);
+    let max_sqrt_ratio = U256::from_words(felt_to_u128(&result[6])?, felt_to_u128(&result[7])?);
+    Ok(PoolConfig {
+        token0,
+        token1,
+        fee,
+        tick_spacing,
+        min_sqrt_ratio,
+        max_sqrt_ratio,
+    })
+}
+
+const RPC_CONNECT_TIMEOUT_SECS: u64 = 10;
+const RPC_KEEPALIVE_SECS: u64 = 60;
+
+// Keeps pooled connections alive so repeated calls skip reconnects, and resolves through
+// hickory's caching resolver so new connections reuse lookups for the record's TTL. `timeout`
+// bounds each HTTP request; pass `ZylithConfig::rpc_timeout` here.
+pub fn rpc_provider(
+    rpc_url: Url,
+    timeout: Option<Duration>,
+) -> Result<JsonRpcClient<HttpTransport>, ClientError> {
+    let mut builder = reqwest::Client::builder()
+        .hickory_dns(true)
+        .connect_timeout(Duration::from_secs(RPC_CONNECT_TIMEOUT_SECS))
+        .tcp_keepalive(Duration::from_secs(RPC_KEEPALIVE_SECS))
+        .pool_idle_timeout(Duration::from_secs(RPC_KEEPALIVE_SECS));
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    let http = builder
+        .build()
+        .map_err(|err| ClientError::Rpc(err.to_string()))?;
+    Ok(JsonRpcClient::new(HttpTransport::new_with_client(
+        rpc_url, http,
+    )))
+}
+
+const MAX_SPENDABLE_COMBINATIONS: usize = 256;
+
+// Returns minimal combinations (no note can be dropped) ordered by note count, then overshoot.
+fn note_combinations(notes: &[&Note], target: u128) -> Vec<Vec<Note>> {
+    // Largest first, so the search can stop a branch once the remaining slots filled with the
+    // next note cannot reach the target.
+    let mut notes = notes.to_vec();
+    notes.sort_by(|a, b| b.amount.cmp(&a.amount));
+    let notes = notes.as_slice();
+    let mut found: Vec<(Vec<usize>, u128)> = Vec::new();
+    let mut current = Vec::new();
+    collect_combinations(notes, target, 0, 0, &mut current, &mut found);
+    found.sort_by(|(a, sum_a), (b, sum_b)| a.len().cmp(&b.len()).then(sum_a.cmp(sum_b)));
+    found.truncate(MAX_SPENDABLE_COMBINATIONS);
+    found
+        .into_iter()
+        .map(|(indices, _)| indices.into_iter().map(|idx| notes[idx].clone()).collect())
+        .collect()
+}
+
+fn collect_combinations(
+    notes: &[&Note],
+    target: u128,
+    start: usize,
+    sum: u128,
+    current: &mut Vec<usize>,
+    found: &mut Vec<(Vec<usize>, u128)>,
+) {
+    if sum >= target {
+        let min_amount = current
+            .iter()
+            .map(|idx| notes[*idx].amount)
+            .min()
+            .unwrap_or(0);
+        if sum - min_amount < target {
+            found.push((current.clone(), sum));
         }
-        let token0 = result[0];
-        let token1 = result[1];
-        let fee = felt_to_u128(&result[2])?;
-        let tick_spacing = felt_to_u128(&result[3])?;
-        if tick_spacing == 0 || tick_spacing > generated_constants::MAX_TICK_SPACING {
-            return Err(ClientError::InvalidInput(
-                "invalid tick spacing".to_string(),
-            ));
+        return;
+    }
+    if current.len() == generated_constants::MAX_INPUT_NOTES
+        || found.len() >= MAX_SPENDABLE_COMBINATIONS * 4
+    {
+        return;
+    }
+    let slots = (generated_constants::MAX_INPUT_NOTES - current.len()) as u128;
+    for (idx, note) in notes.iter().enumerate().skip(start) {
+        if sum.saturating_add(note.amount.saturating_mul(slots)) < target {
+            break;
         }
-        let min_sqrt_ratio = U256::from_words(felt_to_u128(&result[4])?, felt_to_u128(&result[5])?);
-        let max_sqrt_ratio = U256::from_words(felt_to_u128(&result[6])?, felt_to_u128(&result[7])?);
-        Ok(PoolConfig {
-            token0,
-            token1,
-            fee,
-            tick_spacing,
-            min_sqrt_ratio,
-            max_sqrt_ratio,
-        })
+        let Some(next) = sum.checked_add(note.amount) else {
+            continue;
+        };
+        current.push(idx);
+        collect_combinations(notes, target, idx + 1, next, current, found);
+        current.pop();
+    }
+}
+
+#[derive(Clone, Copy)]
+enum DefragmentSource {
+    Note(usize),
+    Step(usize),
+}
+
+fn plan_defragmentation(notes: &[&Note], token: Address, steps: &mut Vec<DefragmentStep>) {
+    let max_inputs = generated_constants::MAX_INPUT_NOTES;
+    let mut pending: Vec<(DefragmentSource, u128)> = notes
+        .iter()
+        .enumerate()
+        .map(|(idx, note)| (DefragmentSource::Note(idx), note.amount))
+        .collect();
+    while pending.len() > max_inputs {
+        pending.sort_by_key(|(_, amount)| *amount);
+        let take = max_inputs.min(pending.len() - max_inputs + 1);
+        let mut step = DefragmentStep {
+            token,
+            notes: Vec::new(),
+            merged_steps: Vec::new(),
+            output_amount: 0,
+        };
+        for (source, amount) in pending.drain(..take) {
+            match source {
+                DefragmentSource::Note(idx) => step.notes.push(notes[idx].clone()),
+                DefragmentSource::Step(idx) => step.merged_steps.push(idx),
+            }
+            step.output_amount = step.output_amount.saturating_add(amount);
+        }
+        pending.push((DefragmentSource::Step(steps.len()), step.output_amount));
+        steps.push(step);
     }
 }
 
@@ -269,3 +665,121 @@ fn max_fee_growth() -> Result<BigUint, ClientError> {
     BigUint::parse_bytes(hex.as_bytes(), 16)
         .ok_or_else(|| ClientError::Crypto("invalid max fee growth".to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{note_combinations, plan_defragmentation, RetryConfig};
+    use crate::generated_constants;
+    use crate::notes::Note;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use starknet::core::types::Felt;
+    use std::time::Duration;
+
+    fn note(amount: u128) -> Note {
+        Note {
+            secret: [1u8; 32],
+            nullifier: [2u8; 32],
+            amount,
+            token: Felt::from(1u8),
+        }
+    }
+
+    #[test]
+    fn combinations_are_minimal_and_ordered() {
+        let notes = [note(5), note(10), note(3), note(7)];
+        let refs: Vec<&Note> = notes.iter().collect();
+        let combos = note_combinations(&refs, 10);
+        assert_eq!(combos[0].len(), 1);
+        assert_eq!(combos[0][0].amount, 10);
+        for combo in &combos {
+            let sum: u128 = combo.iter().map(|note| note.amount).sum();
+            let min = combo.iter().map(|note| note.amount).min().unwrap();
+            assert!(sum >= 10);
+            assert!(sum - min < 10);
+        }
+    }
+
+    #[test]
+    fn combinations_respect_max_input_notes() {
+        let notes: Vec<Note> = (0..generated_constants::MAX_INPUT_NOTES + 1)
+            .map(|_| note(1))
+            .collect();
+        let refs: Vec<&Note> = notes.iter().collect();
+        let target = (generated_constants::MAX_INPUT_NOTES + 1) as u128;
+        assert!(note_combinations(&refs, target).is_empty());
+    }
+
+    #[test]
+    fn combinations_prune_branches_that_cannot_reach_target() {
+        // Unpruned, this walks every 4-subset of 600 dust notes.
+        let mut notes: Vec<Note> = (0..600).map(|_| note(1)).collect();
+        notes.push(note(100));
+        let refs: Vec<&Note> = notes.iter().collect();
+        let combos = note_combinations(&refs, 100);
+        assert_eq!(combos.len(), 1);
+        assert_eq!(combos[0][0].amount, 100);
+    }
+
+    #[test]
+    fn defragmentation_leaves_balance_spendable_at_once() {
+        let max_inputs = generated_constants::MAX_INPUT_NOTES;
+        let notes: Vec<Note> = (1..=(max_inputs * 3) as u128).map(note).collect();
+        let refs: Vec<&Note> = notes.iter().collect();
+        let mut steps = Vec::new();
+        plan_defragmentation(&refs, Felt::from(1u8), &mut steps);
+        let spent: usize = steps
+            .iter()
+            .map(|step| step.notes.len() + step.merged_steps.len())
+            .sum();
+        assert!(steps
+            .iter()
+            .all(|step| step.notes.len() + step.merged_steps.len() <= max_inputs));
+        assert!(notes.len() + steps.len() - spent <= max_inputs);
+        let total: u128 = notes.iter().map(|note| note.amount).sum();
+        let merged: u128 = steps.last().map(|step| step.output_amount).unwrap();
+        assert!(merged < total);
+
+        let mut none = Vec::new();
+        plan_defragmentation(&refs[..max_inputs], Felt::from(1u8), &mut none);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn retry_delay_grows_exponentially_and_caps() {
+        let retry = RetryConfig {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            multiplier: 2.0,
+            jitter: false,
+            ..RetryConfig::default()
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        let delays: Vec<u128> = (1..=6)
+            .map(|attempt| retry.delay_for_attempt(attempt, &mut rng).as_millis())
+            .collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(
+            retry.delay_for_attempt(usize::MAX, &mut rng),
+            Duration::from_millis(1000)
+        );
+    }
+
+    #[test]
+    fn retry_delay_with_jitter_stays_within_backoff() {
+        let retry = RetryConfig {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            multiplier: 3.0,
+            jitter: true,
+            ..RetryConfig::default()
+        };
+        let mut rng = StdRng::seed_from_u64(42);
+        for attempt in 1..=8 {
+            let ceiling = Duration::from_millis((100 * 3u64.pow(attempt as u32 - 1)).min(500));
+            for _ in 0..50 {
+                assert!(retry.delay_for_attempt(attempt, &mut rng) <= ceiling);
+            }
+        }
+    }
+}
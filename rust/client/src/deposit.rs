@@ -2,10 +2,13 @@ use starknet::accounts::ConnectedAccount;
 use starknet::core::types::{Call, Felt};
 use starknet::core::utils::get_selector_from_name;
 
-use crate::client::RetryConfig;
+use crate::client::{RetryConfig, ZylithClient};
 use crate::error::ClientError;
 use crate::notes::{compute_commitment, Note};
-use crate::swap::{execute_with_retry, serialize_merkle_proof, MerklePath};
+use crate::swap::{
+    execute_calls_with_retry, fetch_commitment_leaf, serialize_merkle_proof, with_deadline,
+    MerklePath, TxReceipt, TX_RECEIPT_TIMEOUT,
+};
 use crate::utils::{parse_felt, Address};
 use zylith_prover::ProofCalldata;
 
@@ -18,18 +21,52 @@ pub struct DepositRequest {
     pub token_address: Address,
     pub proof: ProofCalldata,
     pub insertion_proof: MerklePath,
+    // Skip the deposit when the ASP has already indexed the commitment. Only the ASP is
+    // consulted, so a commitment that is on chain but not indexed yet is submitted again.
+    pub skip_if_deposited: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepositOutcome {
+    Submitted(TxHash),
+    // `skip_if_deposited` found the commitment already indexed at `leaf_index`.
+    AlreadyDeposited { leaf_index: u64 },
 }
 
 #[derive(Debug, Clone)]
 pub struct DepositResult {
     pub commitment: Felt,
-    pub tx_hash: TxHash,
+    pub outcome: DepositOutcome,
+}
+
+impl DepositResult {
+    pub fn tx_hash(&self) -> Option<TxHash> {
+        match self.outcome {
+            DepositOutcome::Submitted(tx_hash) => Some(tx_hash),
+            DepositOutcome::AlreadyDeposited { .. } => None,
+        }
+    }
+
+    // Waits for the deposit transaction to be included; `None` when it was skipped.
+    pub async fn wait<A: ConnectedAccount + Sync + Send>(
+        &self,
+        client: &ZylithClient<A>,
+    ) -> Result<Option<TxReceipt>, ClientError> {
+        match self.tx_hash() {
+            Some(tx_hash) => client
+                .wait_for_receipt(tx_hash, TX_RECEIPT_TIMEOUT)
+                .await
+                .map(Some),
+            None => Ok(None),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct DepositClient<A: ConnectedAccount + Sync> {
     pub account: A,
     pub shielded_notes_address: Address,
+    pub asp_url: Option<String>,
     pub retry: RetryConfig,
 }
 
@@ -38,29 +75,92 @@ impl<A: ConnectedAccount + Sync> DepositClient<A> {
         Self {
             account,
             shielded_notes_address,
+            asp_url: None,
             retry: RetryConfig::default(),
         }
     }
 
+    // Routes to `deposit_token0` or `deposit_token1` based on `request.token_id`.
+    pub async fn deposit(&self, request: DepositRequest) -> Result<DepositResult, ClientError> {
+        let entrypoint = deposit_entrypoint(request.token_id)?;
+        self.deposit_via(entrypoint, request).await
+    }
+
     pub async fn deposit_token0(
         &self,
         request: DepositRequest,
     ) -> Result<DepositResult, ClientError> {
-        self.deposit("deposit_token0", request).await
+        self.deposit_via("deposit_token0", request).await
     }
 
     pub async fn deposit_token1(
         &self,
         request: DepositRequest,
     ) -> Result<DepositResult, ClientError> {
-        self.deposit("deposit_token1", request).await
+        self.deposit_via("deposit_token1", request).await
     }
 
-    async fn deposit(
+    // The approve and deposit calls `deposit` submits as one multicall, without the ASP
+    // lookup behind `skip_if_deposited`.
+    pub fn deposit_calls(&self, request: &DepositRequest) -> Result<Vec<Call>, ClientError> {
+        let entrypoint = deposit_entrypoint(request.token_id)?;
+        self.calls_via(entrypoint, request)
+    }
+
+    async fn deposit_via(
         &self,
         entrypoint: &str,
         request: DepositRequest,
     ) -> Result<DepositResult, ClientError> {
+        let calls = self.calls_via(entrypoint, &request)?;
+        let commitment = compute_commitment(&request.note, request.token_id)?;
+        if request.skip_if_deposited {
+            let asp_url = self.asp_url.as_deref().ok_or_else(|| {
+                ClientError::InvalidInput("skip_if_deposited requires asp_url".to_string())
+            })?;
+            let indexed = with_deadline(
+                self.retry.deadline,
+                "asp commitment lookup",
+                fetch_commitment_leaf(asp_url, commitment),
+            )
+            .await?;
+            if let Some((token, leaf_index)) = indexed {
+                if parse_felt(&token).ok() != Some(request.token_address) {
+                    return Err(ClientError::Asp(
+                        "commitment indexed under a different token".to_string(),
+                    ));
+                }
+                return Ok(DepositResult {
+                    commitment,
+                    outcome: DepositOutcome::AlreadyDeposited { leaf_index },
+                });
+            }
+        }
+
+        let tx_hash = execute_calls_with_retry(&self.account, calls, self.retry.clone()).await?;
+        Ok(DepositResult {
+            commitment,
+            outcome: DepositOutcome::Submitted(tx_hash),
+        })
+    }
+
+    fn calls_via(
+        &self,
+        entrypoint: &str,
+        request: &DepositRequest,
+    ) -> Result<Vec<Call>, ClientError> {
+        self.check_request(entrypoint, request)?;
+        Ok(vec![
+            approve_call(
+                request.token_address,
+                self.shielded_notes_address,
+                request.note.amount,
+            )?,
+            self.deposit_call(entrypoint, request)?,
+        ])
+    }
+
+    fn check_request(&self, entrypoint: &str, request: &DepositRequest) -> Result<(), ClientError> {
         if request.note.amount == 0 {
             return Err(ClientError::InvalidInput("amount is zero".to_string()));
         }
@@ -85,16 +185,14 @@ impl<A: ConnectedAccount + Sync> DepositClient<A> {
                 "insertion proof token mismatch".to_string(),
             ));
         }
-        let commitment = compute_commitment(&request.note, request.token_id)?;
-        approve_erc20(
-            &self.account,
-            request.token_address,
-            self.shielded_notes_address,
-            request.note.amount,
-            self.retry.clone(),
-        )
-        .await?;
+        Ok(())
+    }
 
+    fn deposit_call(
+        &self,
+        entrypoint: &str,
+        request: &DepositRequest,
+    ) -> Result<Call, ClientError> {
         let mut calldata = request
             .proof
             .to_calldata()
@@ -108,33 +206,99 @@ impl<A: ConnectedAccount + Sync> DepositClient<A> {
 
         let selector = get_selector_from_name(entrypoint)
             .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
-        let call = Call {
+        Ok(Call {
             to: self.shielded_notes_address,
             selector,
             calldata,
-        };
-        let tx_hash = execute_with_retry(&self.account, call, self.retry.clone()).await?;
-        Ok(DepositResult {
-            commitment,
-            tx_hash,
         })
     }
 }
 
-async fn approve_erc20<A: ConnectedAccount + Sync>(
-    account: &A,
-    token: Address,
-    spender: Address,
-    amount: u128,
-    retry: RetryConfig,
-) -> Result<TxHash, ClientError> {
+fn deposit_entrypoint(token_id: u8) -> Result<&'static str, ClientError> {
+    match token_id {
+        0 => Ok("deposit_token0"),
+        1 => Ok("deposit_token1"),
+        _ => Err(ClientError::InvalidInput(
+            "token_id must be 0 or 1".to_string(),
+        )),
+    }
+}
+
+fn approve_call(token: Address, spender: Address, amount: u128) -> Result<Call, ClientError> {
     let selector = get_selector_from_name("approve")
         .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
     let calldata = vec![spender, Felt::from(amount), Felt::ZERO];
-    let call = Call {
+    Ok(Call {
         to: token,
         selector,
         calldata,
-    };
-    execute_with_retry(account, call, retry).await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{dummy_account, UNREACHABLE_URL};
+
+    #[test]
+    fn deposit_dispatches_on_token_id() {
+        for (token_id, name) in [(0, "deposit_token0"), (1, "deposit_token1")] {
+            let entrypoint = deposit_entrypoint(token_id).unwrap();
+            assert_eq!(
+                get_selector_from_name(entrypoint).unwrap(),
+                get_selector_from_name(name).unwrap()
+            );
+        }
+        assert!(matches!(
+            deposit_entrypoint(2),
+            Err(ClientError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn deposit_calls_approve_before_depositing() {
+        let account = dummy_account(UNREACHABLE_URL);
+        let notes_address = Felt::from(0x50u8);
+        let token = Felt::from(0x70u8);
+        let client = DepositClient::new(account, notes_address);
+        let request = DepositRequest {
+            note: crate::notes::generate_note_with_token_id(25, token, 1).unwrap(),
+            token_id: 1,
+            token_address: token,
+            proof: ProofCalldata::new(vec!["0x1".to_string(), "0x2".to_string()]),
+            insertion_proof: MerklePath {
+                token,
+                root: Felt::ZERO,
+                commitment: Felt::ZERO,
+                leaf_index: 0,
+                path: vec![Felt::ZERO; crate::generated_constants::TREE_HEIGHT],
+                indices: vec![false; crate::generated_constants::TREE_HEIGHT],
+            },
+            skip_if_deposited: false,
+        };
+
+        let calls = client.deposit_calls(&request).unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].to, token);
+        assert_eq!(
+            calls[0].selector,
+            get_selector_from_name("approve").unwrap()
+        );
+        assert_eq!(
+            calls[0].calldata,
+            [notes_address, Felt::from(25u8), Felt::ZERO]
+        );
+        assert_eq!(calls[1].to, notes_address);
+        assert_eq!(
+            calls[1].selector,
+            get_selector_from_name("deposit_token1").unwrap()
+        );
+        assert_eq!(calls[1].calldata[..3], [Felt::TWO, Felt::ONE, Felt::TWO]);
+
+        let mismatched = DepositRequest {
+            token_address: Felt::from(0x71u8),
+            ..request
+        };
+        assert!(client.deposit_calls(&mismatched).is_err());
+    }
 }
@@ -10,6 +10,23 @@ pub enum ClientError {
     Prover(String),
     Io(String),
     NotImplemented(String),
+    Timeout(String),
+}
+
+impl ClientError {
+    // Transient upstream failures are worth retrying; everything else will fail the same way
+    // again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Rpc(_) | ClientError::Asp(_) | ClientError::Timeout(_) => true,
+            ClientError::InvalidInput(_)
+            | ClientError::Crypto(_)
+            | ClientError::Serde(_)
+            | ClientError::Prover(_)
+            | ClientError::Io(_)
+            | ClientError::NotImplemented(_) => false,
+        }
+    }
 }
 
 impl fmt::Display for ClientError {
@@ -23,6 +40,7 @@ impl fmt::Display for ClientError {
             ClientError::Prover(msg) => write!(f, "prover error: {msg}"),
             ClientError::Io(msg) => write!(f, "io error: {msg}"),
             ClientError::NotImplemented(msg) => write!(f, "not implemented: {msg}"),
+            ClientError::Timeout(msg) => write!(f, "timeout: {msg}"),
         }
     }
 }
@@ -41,8 +59,31 @@ impl From<std::io::Error> for ClientError {
     }
 }
 
+#[cfg(feature = "rpc")]
 impl From<reqwest::Error> for ClientError {
     fn from(err: reqwest::Error) -> Self {
         ClientError::Asp(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ClientError;
+
+    #[test]
+    fn upstream_errors_are_retryable() {
+        assert!(ClientError::Rpc("down".to_string()).is_retryable());
+        assert!(ClientError::Asp("down".to_string()).is_retryable());
+        assert!(ClientError::Timeout("slow".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn local_errors_are_not_retryable() {
+        assert!(!ClientError::InvalidInput("bad".to_string()).is_retryable());
+        assert!(!ClientError::Crypto("bad".to_string()).is_retryable());
+        assert!(!ClientError::Serde("bad".to_string()).is_retryable());
+        assert!(!ClientError::Prover("bad".to_string()).is_retryable());
+        assert!(!ClientError::Io("bad".to_string()).is_retryable());
+        assert!(!ClientError::NotImplemented("bad".to_string()).is_retryable());
+    }
+}
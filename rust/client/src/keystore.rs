@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use starknet::signers::{LocalWallet, SigningKey};
+
+use crate::error::ClientError;
+
+// Decrypts an encrypted JSON keystore (the Web3 secret-storage format written by starkli and
+// `SigningKey::save_as_keystore`) into a signer, so the private key never has to appear on a
+// command line or in an environment variable.
+pub fn load_keystore_signer(
+    path: impl AsRef<Path>,
+    password: &str,
+) -> Result<LocalWallet, ClientError> {
+    let path = path.as_ref();
+    if password.is_empty() {
+        return Err(ClientError::InvalidInput(
+            "keystore password is empty".to_string(),
+        ));
+    }
+    if !path.is_file() {
+        return Err(ClientError::InvalidInput(format!(
+            "keystore {} not found",
+            path.display()
+        )));
+    }
+    let key = SigningKey::from_keystore(path, password).map_err(|err| {
+        ClientError::Crypto(format!(
+            "failed to decrypt keystore {}: {err}",
+            path.display()
+        ))
+    })?;
+    Ok(LocalWallet::from(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::load_keystore_signer;
+    use crate::error::ClientError;
+    use starknet::core::types::Felt;
+    use starknet::signers::{Signer, SigningKey};
+
+    #[tokio::test]
+    async fn keystore_round_trips_signing_key() {
+        let dir = std::env::temp_dir().join(format!("zylith-keystore-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let path = dir.join("account.json");
+        let key = SigningKey::from_secret_scalar(Felt::from(0x1234u64));
+        key.save_as_keystore(&path, "correct horse").expect("save");
+
+        let signer = load_keystore_signer(&path, "correct horse").expect("load");
+        let public_key = signer.get_public_key().await.expect("public key");
+        assert_eq!(public_key.scalar(), key.verifying_key().scalar());
+
+        let err = load_keystore_signer(&path, "wrong").expect_err("wrong password");
+        assert!(matches!(err, ClientError::Crypto(_)));
+        let err = load_keystore_signer(dir.join("missing.json"), "correct horse")
+            .expect_err("missing file");
+        assert!(matches!(err, ClientError::InvalidInput(_)));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,36 +1,78 @@
 //! Zylith client SDK.
 
+mod amount;
+#[cfg(feature = "rpc")]
 mod client;
+#[cfg(feature = "rpc")]
 mod deposit;
 mod error;
+// Most constants only feed the RPC and proving paths.
+#[cfg_attr(not(feature = "rpc"), allow(dead_code))]
 mod generated_constants;
+#[cfg(all(feature = "rpc", not(target_arch = "wasm32")))]
+mod keystore;
+#[cfg(feature = "rpc")]
 mod liquidity;
 mod notes;
+#[cfg(feature = "rpc")]
+mod pool_cache;
+#[cfg(feature = "rpc")]
 mod proofs;
+#[cfg(feature = "rpc")]
+mod prover_backend;
+#[cfg(feature = "rpc")]
 mod swap;
+#[cfg(all(test, feature = "rpc"))]
+mod test_support;
 mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "rpc")]
 mod withdraw;
 
-pub use client::{PoolConfig, PoolState, RetryConfig, ZylithClient, ZylithConfig};
-pub use deposit::{DepositClient, DepositRequest, DepositResult};
+pub use amount::SignedAmount;
+#[cfg(feature = "rpc")]
+pub use client::{
+    rpc_provider, signer_account, DefragmentPlan, DefragmentStep, PoolConfig, PoolState,
+    RetryConfig, ZylithClient, ZylithConfig,
+};
+#[cfg(feature = "rpc")]
+pub use deposit::{DepositClient, DepositOutcome, DepositRequest, DepositResult};
 pub use error::ClientError;
-pub use generated_constants::MAX_INPUT_NOTES;
+pub use generated_constants::{MAX_INPUT_NOTES, MAX_SWAP_STEPS};
+#[cfg(all(feature = "rpc", not(target_arch = "wasm32")))]
+pub use keystore::load_keystore_signer;
+#[cfg(feature = "rpc")]
 pub use liquidity::{LiquidityClaimRequest, LiquidityClient, LiquidityRequest};
+#[cfg(feature = "parallel")]
+pub use notes::decrypt_notes_par;
 pub use notes::{
-    compute_commitment, compute_position_commitment, decrypt_note, encrypt_note, generate_note,
-    generate_note_with_token_id, generate_nullifier_hash, generate_position_note,
-    generate_position_nullifier_hash, EncryptedNote, Note, PositionNote,
+    compute_commitment, compute_position_commitment, decrypt_note, decrypt_notes, encrypt_note,
+    generate_note, generate_note_deterministic, generate_note_with_token_id,
+    generate_nullifier_hash, generate_position_note, generate_position_note_deterministic,
+    generate_position_nullifier_hash, scan_feed_multi, split_amount_into_notes, viewing_public_key,
+    DecryptedNote, EncryptedNote, Note, PositionNote, ViewingKey,
 };
+#[cfg(feature = "rpc")]
 pub use proofs::{
-    quote_liquidity_amounts, ChunkedSwapChunkResult, ChunkedSwapExecuteRequest,
-    ChunkedSwapExecuteResult, LiquidityAddProveRequest, LiquidityClaimProveRequest,
-    LiquidityProveResult, LiquidityRemoveProveRequest, SwapProveRequest, SwapProveResult,
+    plan_swap_circuit_steps, quote_liquidity_amounts, ChunkedSwapChunkResult,
+    ChunkedSwapExecuteRequest, ChunkedSwapExecuteResult, LiquidityAddPlan,
+    LiquidityAddProveRequest, LiquidityClaimProveRequest, LiquidityProveResult,
+    LiquidityRemoveProveRequest, LiquidityRemoveQuote, SwapProveRequest, SwapProveResult,
 };
+#[cfg(all(feature = "rpc", any(test, feature = "test-stubs")))]
+pub use prover_backend::StubProver;
+#[cfg(feature = "rpc")]
+pub use prover_backend::{LiquidityCircuit, ProofFuture, ProverBackend, SnarkjsProver};
+#[cfg(feature = "rpc")]
 pub use swap::{
-    MerklePath, SignedAmount, SwapClient, SwapQuoteRequest, SwapRequest, SwapResult, SwapStepQuote,
-    SwapStepsQuote,
+    assert_swap_steps_len, verify_merkle_path, InsertionTree, LiquidityContext, MerklePath,
+    SwapClient, SwapDirection, SwapQuoteRequest, SwapReport, SwapRequest, SwapResult,
+    SwapStepQuote, SwapStepsQuote, TokenMetadata, TxReceipt,
 };
 pub use utils::{
-    felt252_to_u256, parse_event, parse_felt, poseidon_hash, u256_to_felt252, Address,
+    felt252_to_u256, felt_to_tick, parse_event, parse_felt, parse_felt_shortstring, poseidon_hash,
+    poseidon_hash_many, tick_to_felt, u256_to_felt252, Address, CommitmentEvent, StarknetEvent,
 };
+#[cfg(feature = "rpc")]
 pub use withdraw::{WithdrawClient, WithdrawRequest};
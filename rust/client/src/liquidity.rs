@@ -48,6 +48,11 @@ impl<A: ConnectedAccount + Sync> LiquidityClient<A> {
     }
 
     pub async fn add_liquidity(&self, request: LiquidityRequest) -> Result<TxHash, ClientError> {
+        let call = self.add_liquidity_call(&request)?;
+        execute_with_retry(&self.account, call, self.retry.clone()).await
+    }
+
+    pub fn add_liquidity_call(&self, request: &LiquidityRequest) -> Result<Call, ClientError> {
         if self.pool_address == Felt::ZERO {
             return Err(ClientError::InvalidInput(
                 "pool address is zero".to_string(),
@@ -75,12 +80,11 @@ impl<A: ConnectedAccount + Sync> LiquidityClient<A> {
 
         let selector = get_selector_from_name("add_liquidity_private")
             .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
-        let call = Call {
+        Ok(Call {
             to: self.pool_address,
             selector,
             calldata,
-        };
-        execute_with_retry(&self.account, call, self.retry.clone()).await
+        })
     }
 
     pub async fn remove_liquidity(
@@ -91,41 +95,38 @@ impl<A: ConnectedAccount + Sync> LiquidityClient<A> {
         output_proof_token0: Option<MerklePath>,
         output_proof_token1: Option<MerklePath>,
     ) -> Result<TxHash, ClientError> {
-        if self.pool_address == Felt::ZERO {
-            return Err(ClientError::InvalidInput(
-                "pool address is zero".to_string(),
-            ));
-        }
-        let mut calldata: Vec<Felt> = proof
-            .to_calldata()
-            .into_iter()
-            .map(|value| {
-                parse_felt(&value)
-                    .map_err(|_| ClientError::InvalidInput("invalid proof calldata".to_string()))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-        calldata.extend(serialize_merkle_proof(&proof_position)?);
-        calldata.extend(serialize_merkle_proofs(opt_proof_slice(
-            &insert_proof_position,
-        ))?);
-        calldata.extend(serialize_merkle_proofs(opt_proof_slice(
-            &output_proof_token0,
-        ))?);
-        calldata.extend(serialize_merkle_proofs(opt_proof_slice(
-            &output_proof_token1,
-        ))?);
-
-        let selector = get_selector_from_name("remove_liquidity_private")
-            .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
-        let call = Call {
-            to: self.pool_address,
-            selector,
-            calldata,
-        };
+        let call = self.remove_liquidity_call(&LiquidityClaimRequest {
+            proof,
+            proof_position,
+            insert_proof_position,
+            output_proof_token0,
+            output_proof_token1,
+        })?;
         execute_with_retry(&self.account, call, self.retry.clone()).await
     }
 
+    // Removal and fee claims carry the same proofs; only the entrypoint differs.
+    pub fn remove_liquidity_call(
+        &self,
+        request: &LiquidityClaimRequest,
+    ) -> Result<Call, ClientError> {
+        self.position_call("remove_liquidity_private", request)
+    }
+
     pub async fn claim_fees(&self, request: LiquidityClaimRequest) -> Result<TxHash, ClientError> {
+        let call = self.claim_fees_call(&request)?;
+        execute_with_retry(&self.account, call, self.retry.clone()).await
+    }
+
+    pub fn claim_fees_call(&self, request: &LiquidityClaimRequest) -> Result<Call, ClientError> {
+        self.position_call("claim_liquidity_fees_private", request)
+    }
+
+    fn position_call(
+        &self,
+        entrypoint: &str,
+        request: &LiquidityClaimRequest,
+    ) -> Result<Call, ClientError> {
         if self.pool_address == Felt::ZERO {
             return Err(ClientError::InvalidInput(
                 "pool address is zero".to_string(),
@@ -151,14 +152,13 @@ impl<A: ConnectedAccount + Sync> LiquidityClient<A> {
             &request.output_proof_token1,
         ))?);
 
-        let selector = get_selector_from_name("claim_liquidity_fees_private")
+        let selector = get_selector_from_name(entrypoint)
             .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
-        let call = Call {
+        Ok(Call {
             to: self.pool_address,
             selector,
             calldata,
-        };
-        execute_with_retry(&self.account, call, self.retry.clone()).await
+        })
     }
 }
 
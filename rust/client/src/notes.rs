@@ -2,19 +2,30 @@ use chacha20poly1305::aead::{Aead, KeyInit, Payload};
 use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use rand::rngs::OsRng;
 use rand::RngCore;
+use sha2::{Digest, Sha256};
 use starknet::core::types::{Felt, U256};
 use std::sync::OnceLock;
+use x25519_dalek::{PublicKey, StaticSecret};
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use crate::error::ClientError;
 use crate::generated_constants;
-use crate::utils::{bn254_to_felt, poseidon_hash_bn254, Address};
+use crate::utils::{bn254_to_felt, parse_felt, poseidon_hash_bn254, Address};
 
 const DOMAIN_TAG: u64 = 0x5a594c495448; // "ZYLITH"
 const NOTE_TYPE_TOKEN: u8 = 1;
 const NOTE_TYPE_POSITION: u8 = 2;
 const POSITION_TOKEN_ID: u8 = 2;
 const MAX_NOTE_GEN_ATTEMPTS: usize = 8192;
+const DERIVE_TAG: u64 = 0x4e4f54455f4b4559; // "NOTE_KEY"
+const DERIVE_SECRET: u8 = 1;
+const DERIVE_NULLIFIER: u8 = 2;
+const DERIVE_POSITION_SECRET: u8 = 3;
+const DERIVE_POSITION_NULLIFIER: u8 = 4;
+const MAX_SPLIT_NOTES: usize = 64;
+const NOTE_AAD: &[u8] = b"zylith-note-v2";
+const NOTE_KDF_TAG: &[u8] = b"zylith-note-v2-key";
+const LEGACY_NOTE_AAD: &[u8] = b"zylith-note-v1";
 static ZERO_LEAF_HASH: OnceLock<Felt> = OnceLock::new();
 
 #[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
@@ -26,6 +37,23 @@ pub struct Note {
     pub token: Address,
 }
 
+impl Note {
+    // 32-byte fields take 64 hex digits with or without `0x`; amount is decimal or `0x` hex.
+    pub fn try_from_hex_fields(
+        secret: &str,
+        nullifier: &str,
+        amount: &str,
+        token: &str,
+    ) -> Result<Note, ClientError> {
+        Ok(Note {
+            secret: parse_bytes32_field(secret, "secret")?,
+            nullifier: parse_bytes32_field(nullifier, "nullifier")?,
+            amount: parse_amount_field(amount)?,
+            token: parse_felt(token)?,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct PositionNote {
     pub secret: [u8; 32],
@@ -42,8 +70,17 @@ pub struct PositionNote {
     pub fee_growth_inside_1: U256,
 }
 
+pub type ViewingKey = [u8; 32];
+
+#[derive(Debug, Clone)]
+pub struct DecryptedNote {
+    pub feed_index: usize,
+    pub note: Note,
+}
+
 #[derive(Debug, Clone)]
 pub struct EncryptedNote {
+    pub ephemeral_pubkey: [u8; 32],
     pub nonce: [u8; 24],
     pub tag: [u8; 16],
     pub ciphertext: Zeroizing<Vec<u8>>,
@@ -106,6 +143,117 @@ pub fn generate_note_with_token_id(
     ))
 }
 
+// Recoverable counterpart of `generate_note_with_token_id`: `secret` and `nullifier` come from a
+// domain-separated Poseidon hash of the master key, token id and index, so the same seed always
+// rebuilds the same notes.
+pub fn generate_note_deterministic(
+    master_key: &[u8; 32],
+    token: Felt,
+    token_id: u8,
+    amount: u128,
+    index: u64,
+) -> Result<Note, ClientError> {
+    if token_id > 1 {
+        return Err(ClientError::InvalidInput(
+            "token id must be 0 or 1".to_string(),
+        ));
+    }
+    for attempt in 0..MAX_NOTE_GEN_ATTEMPTS {
+        let note = Note {
+            secret: derive_note_field(master_key, DERIVE_SECRET, token_id, index, attempt)?,
+            nullifier: derive_note_field(master_key, DERIVE_NULLIFIER, token_id, index, attempt)?,
+            amount,
+            token,
+        };
+        if compute_commitment(&note, token_id).is_ok() {
+            return Ok(note);
+        }
+    }
+    Err(ClientError::Crypto(
+        "note generation failed after max attempts".to_string(),
+    ))
+}
+
+// Position counterpart of `generate_note_deterministic`. The tick bounds are part of the
+// derivation, so positions over different ranges never share a secret at the same index.
+pub fn generate_position_note_deterministic(
+    master_key: &[u8; 32],
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+    fee_growth_inside_0: U256,
+    fee_growth_inside_1: U256,
+    index: u64,
+) -> Result<PositionNote, ClientError> {
+    let scope = [
+        biguint_from_u64(u64::from(tick_lower as u32)),
+        biguint_from_u64(u64::from(tick_upper as u32)),
+    ];
+    for attempt in 0..MAX_NOTE_GEN_ATTEMPTS {
+        let note = PositionNote {
+            secret: derive_field(master_key, DERIVE_POSITION_SECRET, &scope, index, attempt)?,
+            nullifier: derive_field(
+                master_key,
+                DERIVE_POSITION_NULLIFIER,
+                &scope,
+                index,
+                attempt,
+            )?,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            fee_growth_inside_0,
+            fee_growth_inside_1,
+        };
+        if compute_position_commitment(&note).is_ok() {
+            return Ok(note);
+        }
+    }
+    Err(ClientError::Crypto(
+        "position note generation failed after max attempts".to_string(),
+    ))
+}
+
+fn derive_note_field(
+    master_key: &[u8; 32],
+    purpose: u8,
+    token_id: u8,
+    index: u64,
+    attempt: usize,
+) -> Result<[u8; 32], ClientError> {
+    derive_field(
+        master_key,
+        purpose,
+        &[biguint_from_u8(token_id)],
+        index,
+        attempt,
+    )
+}
+
+fn derive_field(
+    master_key: &[u8; 32],
+    purpose: u8,
+    scope: &[num_bigint::BigUint],
+    index: u64,
+    attempt: usize,
+) -> Result<[u8; 32], ClientError> {
+    // Split the key so both halves stay below the BN254 modulus and no key bits are reduced away.
+    let (high, low) = master_key.split_at(16);
+    let mut inputs = vec![
+        biguint_from_u64(DERIVE_TAG),
+        biguint_from_u8(purpose),
+        num_bigint::BigUint::from_bytes_be(high),
+        num_bigint::BigUint::from_bytes_be(low),
+    ];
+    inputs.extend_from_slice(scope);
+    inputs.push(biguint_from_u64(index));
+    inputs.push(biguint_from_u64(attempt as u64));
+    let derived = Zeroizing::new(poseidon_hash_bn254(&inputs)?.to_bytes_be());
+    let mut out = [0u8; 32];
+    out[32 - derived.len()..].copy_from_slice(&derived);
+    Ok(out)
+}
+
 fn random_note(amount: u128, token: Address) -> Note {
     let mut secret = [0u8; 32];
     let mut nullifier = [0u8; 32];
@@ -141,6 +289,59 @@ fn random_position_note(
     }
 }
 
+// Splits `amount` greedily into the largest denominations first; anything the denominations
+// cannot cover exactly ends up in one final remainder note.
+pub fn split_amount_into_notes(
+    amount: u128,
+    denominations: &[u128],
+    token: Felt,
+    token_id: u8,
+) -> Result<Vec<Note>, ClientError> {
+    split_amounts(amount, denominations)?
+        .into_iter()
+        .map(|part| generate_note_with_token_id(part, token, token_id))
+        .collect()
+}
+
+fn split_amounts(amount: u128, denominations: &[u128]) -> Result<Vec<u128>, ClientError> {
+    if amount == 0 {
+        return Err(ClientError::InvalidInput(
+            "amount must be greater than zero".to_string(),
+        ));
+    }
+    if denominations.is_empty() || denominations.contains(&0) {
+        return Err(ClientError::InvalidInput(
+            "denominations must be non-empty and nonzero".to_string(),
+        ));
+    }
+    let mut sorted = denominations.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    sorted.dedup();
+
+    let mut parts = Vec::new();
+    let mut remaining = amount;
+    for denomination in sorted {
+        while remaining >= denomination {
+            if parts.len() == MAX_SPLIT_NOTES {
+                return Err(ClientError::InvalidInput(format!(
+                    "split would need more than {MAX_SPLIT_NOTES} notes"
+                )));
+            }
+            parts.push(denomination);
+            remaining -= denomination;
+        }
+    }
+    if remaining > 0 {
+        if parts.len() == MAX_SPLIT_NOTES {
+            return Err(ClientError::InvalidInput(format!(
+                "split would need more than {MAX_SPLIT_NOTES} notes"
+            )));
+        }
+        parts.push(remaining);
+    }
+    Ok(parts)
+}
+
 pub fn compute_commitment(note: &Note, token_id: u8) -> Result<Felt, ClientError> {
     if token_id > 1 {
         return Err(ClientError::InvalidInput(
@@ -241,11 +442,23 @@ pub fn generate_position_nullifier_hash(note: &PositionNote) -> Result<Felt, Cli
     bn254_to_felt(&nullifier)
 }
 
-// Uses a symmetric 32-byte key, the same key must be supplied for decryption.
-pub fn encrypt_note(note: &Note, shared_key: &[u8; 32]) -> Result<EncryptedNote, ClientError> {
+// ECIES-style: a fresh X25519 ephemeral key agrees a secret with `recipient_pubkey`, and both
+// public keys are bound as associated data so the ciphertext only opens for that recipient.
+pub fn encrypt_note(
+    note: &Note,
+    recipient_pubkey: &[u8; 32],
+) -> Result<EncryptedNote, ClientError> {
     let plaintext = Zeroizing::new(serialize_note(note)?);
-    let cipher = XChaCha20Poly1305::new_from_slice(shared_key)
-        .map_err(|_| ClientError::Crypto("invalid shared key".to_string()))?;
+    let ephemeral = StaticSecret::random_from_rng(OsRng);
+    let ephemeral_pubkey = PublicKey::from(&ephemeral).to_bytes();
+    let shared = ephemeral.diffie_hellman(&PublicKey::from(*recipient_pubkey));
+    if !shared.was_contributory() {
+        return Err(ClientError::Crypto(
+            "invalid recipient public key".to_string(),
+        ));
+    }
+    let cipher = note_cipher(shared.as_bytes(), &ephemeral_pubkey, recipient_pubkey)?;
+    let aad = note_aad(&ephemeral_pubkey, recipient_pubkey);
     let mut nonce = [0u8; 24];
     OsRng.fill_bytes(&mut nonce);
     let mut ciphertext = cipher
@@ -253,7 +466,7 @@ pub fn encrypt_note(note: &Note, shared_key: &[u8; 32]) -> Result<EncryptedNote,
             XNonce::from_slice(&nonce),
             Payload {
                 msg: &plaintext,
-                aad: b"zylith-note-v1",
+                aad: &aad,
             },
         )
         .map_err(|_| ClientError::Crypto("note encryption failed".to_string()))?;
@@ -266,15 +479,138 @@ pub fn encrypt_note(note: &Note, shared_key: &[u8; 32]) -> Result<EncryptedNote,
         .map_err(|_| ClientError::Crypto("invalid tag length".to_string()))?;
     ciphertext.truncate(tag_offset);
     Ok(EncryptedNote {
+        ephemeral_pubkey,
         nonce,
         tag,
         ciphertext: Zeroizing::new(ciphertext),
     })
 }
 
-pub fn decrypt_note(encrypted: &EncryptedNote, shared_key: &[u8; 32]) -> Result<Note, ClientError> {
-    let cipher = XChaCha20Poly1305::new_from_slice(shared_key)
-        .map_err(|_| ClientError::Crypto("invalid shared key".to_string()))?;
+pub fn viewing_public_key(viewing_key: &ViewingKey) -> [u8; 32] {
+    PublicKey::from(&StaticSecret::from(*viewing_key)).to_bytes()
+}
+
+// Ciphertexts from before recipient binding were sealed directly under a shared key with a
+// fixed associated data; they are reported with a distinct error so callers can re-encrypt.
+pub fn decrypt_note(
+    encrypted: &EncryptedNote,
+    viewing_key: &ViewingKey,
+) -> Result<Note, ClientError> {
+    decrypt_note_with(&PreparedViewingKey::new(viewing_key), encrypted)
+}
+
+// Per-key state that does not depend on the ciphertext, built once per batch or scan rather
+// than once per note.
+struct PreparedViewingKey {
+    secret: StaticSecret,
+    recipient_pubkey: [u8; 32],
+    legacy: Option<XChaCha20Poly1305>,
+}
+
+impl PreparedViewingKey {
+    fn new(viewing_key: &ViewingKey) -> Self {
+        let secret = StaticSecret::from(*viewing_key);
+        let recipient_pubkey = PublicKey::from(&secret).to_bytes();
+        Self {
+            secret,
+            recipient_pubkey,
+            legacy: legacy_cipher(viewing_key).ok(),
+        }
+    }
+}
+
+fn decrypt_note_with(
+    key: &PreparedViewingKey,
+    encrypted: &EncryptedNote,
+) -> Result<Note, ClientError> {
+    if encrypted.ephemeral_pubkey == [0u8; 32] {
+        return Err(legacy_note_error());
+    }
+    let shared = key
+        .secret
+        .diffie_hellman(&PublicKey::from(encrypted.ephemeral_pubkey));
+    let cipher = note_cipher(
+        shared.as_bytes(),
+        &encrypted.ephemeral_pubkey,
+        &key.recipient_pubkey,
+    )?;
+    let aad = note_aad(&encrypted.ephemeral_pubkey, &key.recipient_pubkey);
+    match open_note(&cipher, encrypted, &aad) {
+        Ok(note) => Ok(note),
+        Err(err) => match &key.legacy {
+            Some(legacy) if open_note(legacy, encrypted, LEGACY_NOTE_AAD).is_ok() => {
+                Err(legacy_note_error())
+            }
+            _ => Err(err),
+        },
+    }
+}
+
+// Each entry fails independently and results keep input order.
+pub fn decrypt_notes(
+    viewing_key: &ViewingKey,
+    notes: &[EncryptedNote],
+) -> Vec<Result<Note, ClientError>> {
+    let key = PreparedViewingKey::new(viewing_key);
+    notes
+        .iter()
+        .map(|encrypted| decrypt_note_with(&key, encrypted))
+        .collect()
+}
+
+#[cfg(feature = "parallel")]
+pub fn decrypt_notes_par(
+    viewing_key: &ViewingKey,
+    notes: &[EncryptedNote],
+) -> Vec<Result<Note, ClientError>> {
+    use rayon::prelude::*;
+    let key = PreparedViewingKey::new(viewing_key);
+    notes
+        .par_iter()
+        .map(|encrypted| decrypt_note_with(&key, encrypted))
+        .collect()
+}
+
+fn note_cipher(
+    shared: &[u8; 32],
+    ephemeral_pubkey: &[u8; 32],
+    recipient_pubkey: &[u8; 32],
+) -> Result<XChaCha20Poly1305, ClientError> {
+    let mut hasher = Sha256::new();
+    hasher.update(NOTE_KDF_TAG);
+    hasher.update(shared);
+    hasher.update(ephemeral_pubkey);
+    hasher.update(recipient_pubkey);
+    let key = Zeroizing::new(<[u8; 32]>::from(hasher.finalize()));
+    XChaCha20Poly1305::new_from_slice(&key)
+        .map_err(|_| ClientError::Crypto("invalid note key".to_string()))
+}
+
+fn legacy_cipher(shared_key: &[u8; 32]) -> Result<XChaCha20Poly1305, ClientError> {
+    XChaCha20Poly1305::new_from_slice(shared_key)
+        .map_err(|_| ClientError::Crypto("invalid shared key".to_string()))
+}
+
+fn legacy_note_error() -> ClientError {
+    ClientError::Crypto(
+        "legacy v1 note ciphertext is not bound to a recipient; re-encrypt it with encrypt_note"
+            .to_string(),
+    )
+}
+
+fn note_aad(ephemeral_pubkey: &[u8; 32], recipient_pubkey: &[u8; 32]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(NOTE_AAD.len() + 64);
+    aad.extend_from_slice(NOTE_AAD);
+    aad.extend_from_slice(ephemeral_pubkey);
+    aad.extend_from_slice(recipient_pubkey);
+    aad
+}
+
+fn open_note(
+    cipher: &XChaCha20Poly1305,
+    encrypted: &EncryptedNote,
+    aad: &[u8],
+) -> Result<Note, ClientError> {
     let mut combined = encrypted.ciphertext.clone();
     combined.extend_from_slice(&encrypted.tag);
     let plaintext = cipher
@@ -282,7 +618,7 @@ pub fn decrypt_note(encrypted: &EncryptedNote, shared_key: &[u8; 32]) -> Result<
             XNonce::from_slice(&encrypted.nonce),
             Payload {
                 msg: &combined,
-                aad: b"zylith-note-v1",
+                aad,
             },
         )
         .map_err(|_| ClientError::Crypto("note decryption failed".to_string()))?;
@@ -290,6 +626,48 @@ pub fn decrypt_note(encrypted: &EncryptedNote, shared_key: &[u8; 32]) -> Result<
     deserialize_note(&plaintext)
 }
 
+// Returns (key index, note) for every entry some key decrypts; entries no key opens are skipped.
+pub fn scan_feed_multi(enc: &[EncryptedNote], keys: &[ViewingKey]) -> Vec<(usize, DecryptedNote)> {
+    let keys: Vec<PreparedViewingKey> = keys.iter().map(PreparedViewingKey::new).collect();
+    let mut found = Vec::new();
+    for (feed_index, encrypted) in enc.iter().enumerate() {
+        for (key_index, key) in keys.iter().enumerate() {
+            if let Ok(note) = decrypt_note_with(key, encrypted) {
+                found.push((key_index, DecryptedNote { feed_index, note }));
+                break;
+            }
+        }
+    }
+    found
+}
+
+pub(crate) fn parse_bytes32_field(value: &str, field: &str) -> Result<[u8; 32], ClientError> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    if hex.len() != 64 {
+        return Err(ClientError::InvalidInput(format!(
+            "{field}: expected 32-byte hex string"
+        )));
+    }
+    // Checked up front: a multi-byte character would make the byte-offset slices below panic.
+    if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(ClientError::InvalidInput(format!("{field}: invalid hex")));
+    }
+    let mut out = [0u8; 32];
+    for (idx, slot) in out.iter_mut().enumerate() {
+        *slot = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16)
+            .map_err(|_| ClientError::InvalidInput(format!("{field}: invalid hex")))?;
+    }
+    Ok(out)
+}
+
+pub(crate) fn parse_amount_field(value: &str) -> Result<u128, ClientError> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u128::from_str_radix(hex, 16),
+        None => value.parse::<u128>(),
+    };
+    parsed.map_err(|err| ClientError::InvalidInput(format!("amount: {err}")))
+}
+
 fn serialize_note(note: &Note) -> Result<Vec<u8>, ClientError> {
     let mut out = Vec::with_capacity(112);
     out.extend_from_slice(&note.secret);
@@ -335,9 +713,93 @@ fn encode_i32_twos_complement(value: i32) -> u128 {
 #[cfg(test)]
 #[allow(clippy::items_after_test_module)]
 mod tests {
-    use super::{compute_position_commitment, generate_position_nullifier_hash, PositionNote};
+    use super::{
+        compute_commitment, compute_position_commitment, decrypt_note, decrypt_notes, encrypt_note,
+        generate_note, generate_note_deterministic, generate_position_note_deterministic,
+        generate_position_nullifier_hash, scan_feed_multi, split_amount_into_notes, split_amounts,
+        viewing_public_key, EncryptedNote, Note, PositionNote,
+    };
+    use crate::error::ClientError;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
     use starknet::core::types::{Felt, U256};
 
+    #[test]
+    fn scan_feed_multi_reports_matching_key() {
+        let key_a = [1u8; 32];
+        let key_b = [2u8; 32];
+        let foreign = [3u8; 32];
+        let note_a = generate_note(10, Felt::from(1u8)).expect("note");
+        let note_b = generate_note(20, Felt::from(2u8)).expect("note");
+        let feed = vec![
+            encrypt_note(&note_b, &viewing_public_key(&key_b)).expect("encrypt"),
+            encrypt_note(&note_a, &viewing_public_key(&foreign)).expect("encrypt"),
+            encrypt_note(&note_a, &viewing_public_key(&key_a)).expect("encrypt"),
+        ];
+        let found = scan_feed_multi(&feed, &[key_a, key_b]);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, 1);
+        assert_eq!(found[0].1.feed_index, 0);
+        assert_eq!(found[0].1.note.amount, 20);
+        assert_eq!(found[1].0, 0);
+        assert_eq!(found[1].1.feed_index, 2);
+        assert_eq!(found[1].1.note.secret, note_a.secret);
+    }
+
+    #[test]
+    fn encrypted_note_is_bound_to_recipient() {
+        let key = [4u8; 32];
+        let other = [5u8; 32];
+        let note = generate_note(77, Felt::from(3u8)).expect("note");
+        let encrypted = encrypt_note(&note, &viewing_public_key(&key)).expect("encrypt");
+
+        let opened = decrypt_note(&encrypted, &key).expect("decrypt");
+        assert_eq!(opened.secret, note.secret);
+        assert_eq!(opened.amount, 77);
+        assert!(decrypt_note(&encrypted, &other).is_err());
+
+        let mut swapped = encrypted.clone();
+        swapped.ephemeral_pubkey = viewing_public_key(&other);
+        assert!(decrypt_note(&swapped, &key).is_err());
+    }
+
+    #[test]
+    fn legacy_ciphertexts_report_migration_error() {
+        use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+        use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+        let key = [6u8; 32];
+        let note = generate_note(5, Felt::from(1u8)).expect("note");
+        let nonce = [1u8; 24];
+        let mut sealed = XChaCha20Poly1305::new_from_slice(&key)
+            .expect("cipher")
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &super::serialize_note(&note).expect("serialize"),
+                    aad: b"zylith-note-v1",
+                },
+            )
+            .expect("seal");
+        let tag: [u8; 16] = sealed.split_off(sealed.len() - 16).try_into().expect("tag");
+        let legacy = EncryptedNote {
+            ephemeral_pubkey: viewing_public_key(&[9u8; 32]),
+            nonce,
+            tag,
+            ciphertext: zeroize::Zeroizing::new(sealed),
+        };
+        let err = decrypt_note(&legacy, &key).expect_err("legacy");
+        assert!(matches!(err, ClientError::Crypto(msg) if msg.contains("legacy v1")));
+
+        let unbound = EncryptedNote {
+            ephemeral_pubkey: [0u8; 32],
+            ..legacy
+        };
+        let err = decrypt_note(&unbound, &key).expect_err("legacy");
+        assert!(matches!(err, ClientError::Crypto(msg) if msg.contains("legacy v1")));
+    }
+
     #[test]
     fn position_commitment_is_nonzero() {
         let note = PositionNote {
@@ -360,6 +822,181 @@ mod tests {
         assert!(commitment != Felt::ZERO);
         assert!(nullifier != Felt::ZERO);
     }
+
+    #[test]
+    fn decrypt_notes_batch_preserves_order_and_isolates_errors() {
+        let key = [7u8; 32];
+        let notes: Vec<Note> = (0..1000u32)
+            .map(|idx| {
+                let mut secret = [0u8; 32];
+                secret[..4].copy_from_slice(&idx.to_be_bytes());
+                Note {
+                    secret,
+                    nullifier: [9u8; 32],
+                    amount: idx as u128,
+                    token: Felt::from(1u8),
+                }
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..notes.len()).collect();
+        order.shuffle(&mut StdRng::seed_from_u64(42));
+        let recipient = viewing_public_key(&key);
+        let mut feed: Vec<_> = order
+            .iter()
+            .map(|idx| encrypt_note(&notes[*idx], &recipient).expect("encrypt"))
+            .collect();
+        feed[3].tag[0] ^= 1;
+
+        let results = decrypt_notes(&key, &feed);
+        assert_eq!(results.len(), feed.len());
+        for (pos, (result, idx)) in results.iter().zip(order.iter()).enumerate() {
+            if pos == 3 {
+                assert!(result.is_err());
+                continue;
+            }
+            let note = result.as_ref().expect("decrypt");
+            assert_eq!(note.secret, notes[*idx].secret);
+            assert_eq!(note.amount, notes[*idx].amount);
+            assert_eq!(note.token, notes[*idx].token);
+        }
+    }
+
+    #[test]
+    fn note_from_hex_fields() {
+        let secret = format!("0x{}", "01".repeat(32));
+        let nullifier = "ab".repeat(32);
+        let note = Note::try_from_hex_fields(&secret, &nullifier, "0x10", "0x5").expect("note");
+        assert_eq!(note.secret, [1u8; 32]);
+        assert_eq!(note.nullifier, [0xabu8; 32]);
+        assert_eq!(note.amount, 16);
+        assert_eq!(note.token, Felt::from(5u8));
+        assert_eq!(
+            Note::try_from_hex_fields(&secret, &nullifier, "42", "0x5")
+                .expect("note")
+                .amount,
+            42
+        );
+
+        let short = "01".repeat(31);
+        assert!(matches!(
+            Note::try_from_hex_fields(&short, &nullifier, "1", "0x5"),
+            Err(ClientError::InvalidInput(_))
+        ));
+        let overflow = format!("{}0", u128::MAX);
+        assert!(Note::try_from_hex_fields(&secret, &nullifier, &overflow, "0x5").is_err());
+        assert!(Note::try_from_hex_fields(&secret, &"zz".repeat(32), "1", "0x5").is_err());
+        // 64 bytes, but "é" straddles a digit-pair boundary.
+        let non_ascii = format!("0{}{}", "é", "0".repeat(61));
+        assert_eq!(non_ascii.len(), 64);
+        assert!(matches!(
+            Note::try_from_hex_fields(&secret, &non_ascii, "1", "0x5"),
+            Err(ClientError::InvalidInput(_))
+        ));
+        assert!(
+            Note::try_from_hex_fields(&secret, &format!("+f{}", "0".repeat(62)), "1", "0x5")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn deterministic_notes_are_reproducible_and_index_separated() {
+        let master_key = [7u8; 32];
+        let token = Felt::from(0x1234u64);
+        let note = generate_note_deterministic(&master_key, token, 1, 500, 3).expect("note");
+        let again = generate_note_deterministic(&master_key, token, 1, 500, 3).expect("note");
+        let commitment = compute_commitment(&note, 1).expect("commitment");
+        assert_eq!(
+            commitment,
+            compute_commitment(&again, 1).expect("commitment")
+        );
+        assert_ne!(note.secret, note.nullifier);
+
+        let next = generate_note_deterministic(&master_key, token, 1, 500, 4).expect("note");
+        assert_ne!(
+            commitment,
+            compute_commitment(&next, 1).expect("commitment")
+        );
+
+        let other_token_id =
+            generate_note_deterministic(&master_key, token, 0, 500, 3).expect("note");
+        assert_ne!(note.secret, other_token_id.secret);
+
+        let other_key = generate_note_deterministic(&[8u8; 32], token, 1, 500, 3).expect("note");
+        assert_ne!(
+            commitment,
+            compute_commitment(&other_key, 1).expect("commitment")
+        );
+
+        assert!(generate_note_deterministic(&master_key, token, 2, 500, 3).is_err());
+    }
+
+    #[test]
+    fn deterministic_position_notes_are_reproducible() {
+        let master_key = [9u8; 32];
+        let fee0 = U256::from(5u128);
+        let fee1 = U256::from(6u128);
+        let derive = |tick_lower, tick_upper, index| {
+            let note = generate_position_note_deterministic(
+                &master_key,
+                tick_lower,
+                tick_upper,
+                1_000,
+                fee0,
+                fee1,
+                index,
+            )
+            .expect("position note");
+            compute_position_commitment(&note).expect("commitment")
+        };
+        let commitment = derive(-60, 60, 0);
+        assert_eq!(commitment, derive(-60, 60, 0));
+        assert_ne!(commitment, derive(-60, 60, 1));
+        assert_ne!(commitment, derive(-120, 60, 0));
+        assert_ne!(commitment, derive(-60, 120, 0));
+
+        let note = generate_position_note_deterministic(&master_key, -60, 60, 1_000, fee0, fee1, 0)
+            .expect("position note");
+        let fungible =
+            generate_note_deterministic(&master_key, Felt::ZERO, 0, 1_000, 0).expect("note");
+        assert_ne!(note.secret, fungible.secret);
+        assert_ne!(note.nullifier, fungible.nullifier);
+    }
+
+    #[test]
+    fn split_exact_partition_uses_only_denominations() {
+        assert_eq!(
+            split_amounts(1_600, &[100, 500, 1_000]).expect("split"),
+            vec![1_000, 500, 100]
+        );
+        assert_eq!(
+            split_amounts(300, &[100, 100]).expect("split"),
+            vec![100, 100, 100]
+        );
+
+        let token = Felt::from(0x1234u64);
+        let notes = split_amount_into_notes(1_600, &[100, 500, 1_000], token, 0).expect("notes");
+        assert_eq!(notes.iter().map(|n| n.amount).sum::<u128>(), 1_600);
+        assert!(notes.iter().all(|n| n.token == token));
+        assert_ne!(notes[0].secret, notes[1].secret);
+    }
+
+    #[test]
+    fn split_puts_remainder_in_last_note() {
+        assert_eq!(
+            split_amounts(1_234, &[1_000, 100]).expect("split"),
+            vec![1_000, 100, 100, 34]
+        );
+        assert_eq!(split_amounts(42, &[100]).expect("split"), vec![42]);
+    }
+
+    #[test]
+    fn split_rejects_bad_input() {
+        assert!(split_amounts(0, &[100]).is_err());
+        assert!(split_amounts(100, &[]).is_err());
+        assert!(split_amounts(100, &[0, 10]).is_err());
+        assert!(split_amounts(1_000, &[1]).is_err());
+        assert!(split_amount_into_notes(100, &[10], Felt::ONE, 2).is_err());
+    }
 }
 
 fn biguint_from_u8(value: u8) -> num_bigint::BigUint {
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::ClientError;
+use crate::utils::Address;
+
+// Per-pool cache of contract reads. With no TTL every lookup goes straight to `fetch`, so
+// proofs are always built against the latest state unless the caller opts in.
+#[derive(Debug)]
+pub(crate) struct TtlCache<T> {
+    ttl: Option<Duration>,
+    entries: Mutex<HashMap<Address, (Instant, T)>>,
+}
+
+impl<T: Clone> TtlCache<T> {
+    pub(crate) fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) async fn get_or_fetch<F, Fut>(
+        &self,
+        key: Address,
+        fetch: F,
+    ) -> Result<T, ClientError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let Some(ttl) = self.ttl else {
+            return fetch().await;
+        };
+        if let Some(value) = self.fresh(key, ttl) {
+            return Ok(value);
+        }
+        let value = fetch().await?;
+        self.lock().insert(key, (Instant::now(), value.clone()));
+        Ok(value)
+    }
+
+    pub(crate) fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn fresh(&self, key: Address, ttl: Duration) -> Option<T> {
+        let entries = self.lock();
+        let (fetched_at, value) = entries.get(&key)?;
+        (fetched_at.elapsed() < ttl).then(|| value.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Address, (Instant, T)>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TtlCache;
+    use starknet::core::types::Felt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    async fn read_n_times(cache: &TtlCache<u64>, pool: Felt, n: usize) -> usize {
+        let calls = AtomicUsize::new(0);
+        for _ in 0..n {
+            let value = cache
+                .get_or_fetch(pool, || async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(42u64)
+                })
+                .await
+                .expect("fetch");
+            assert_eq!(value, 42);
+        }
+        calls.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn uncached_reads_hit_provider_every_time() {
+        let cache = TtlCache::new(None);
+        assert_eq!(read_n_times(&cache, Felt::from(1u8), 3).await, 3);
+    }
+
+    #[tokio::test]
+    async fn cached_reads_hit_provider_once_per_pool() {
+        let cache = TtlCache::new(Some(Duration::from_secs(60)));
+        assert_eq!(read_n_times(&cache, Felt::from(1u8), 3).await, 1);
+        assert_eq!(read_n_times(&cache, Felt::from(2u8), 3).await, 1);
+        assert_eq!(read_n_times(&cache, Felt::from(1u8), 3).await, 0);
+
+        cache.clear();
+        assert_eq!(read_n_times(&cache, Felt::from(1u8), 3).await, 1);
+    }
+
+    #[tokio::test]
+    async fn stale_entries_are_refreshed() {
+        let cache = TtlCache::new(Some(Duration::ZERO));
+        assert_eq!(read_n_times(&cache, Felt::from(1u8), 2).await, 2);
+    }
+
+    #[tokio::test]
+    async fn failed_fetch_is_not_cached() {
+        let cache: TtlCache<u64> = TtlCache::new(Some(Duration::from_secs(60)));
+        let err = cache
+            .get_or_fetch(Felt::from(1u8), || async {
+                Err(crate::error::ClientError::Rpc("down".to_string()))
+            })
+            .await;
+        assert!(err.is_err());
+        assert_eq!(read_n_times(&cache, Felt::from(1u8), 1).await, 1);
+    }
+}
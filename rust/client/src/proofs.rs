@@ -10,6 +10,7 @@ use starknet::core::utils::get_selector_from_name;
 use starknet::providers::Provider;
 use tokio::time::{sleep, Duration};
 
+use crate::amount::SignedAmount;
 use crate::client::{PoolConfig, ZylithClient};
 use crate::error::ClientError;
 use crate::generated_constants;
@@ -18,15 +19,13 @@ use crate::notes::{
     generate_nullifier_hash, generate_position_note, generate_position_nullifier_hash, Note,
     PositionNote,
 };
+use crate::prover_backend::LiquidityCircuit;
 use crate::swap::{
-    asp_client, with_retry, MerklePath, SignedAmount, SwapClient, SwapQuoteRequest, SwapStepsQuote,
-    TxHash,
-};
-use crate::utils::felt_to_u128;
-use zylith_prover::{
-    prove_lp_add, prove_lp_claim, prove_lp_remove, prove_swap, prove_swap_exact_out,
-    LpWitnessInputs, ProofCalldata, SwapWitnessInputs, WitnessValue,
+    asp_client, with_deadline, with_retry, InsertionTree, MerklePath, SwapClient, SwapDirection,
+    SwapQuoteRequest, SwapRecord, SwapStepsQuote, TxHash,
 };
+use crate::utils::{felt_to_u128, tick_to_felt};
+use zylith_prover::{LpWitnessInputs, ProofCalldata, SwapWitnessInputs, WitnessValue};
 
 const VK_SWAP_DEC: &str = "1398227280";
 const VK_SWAP_EXACT_OUT_DEC: &str = "1690353060931437599809942001243476";
@@ -73,13 +72,18 @@ const TICK_SQRT_RATIO_MULTIPLIERS: [u128; 27] = [
 #[derive(Debug, Clone)]
 pub struct SwapProveRequest {
     pub notes: Vec<Note>,
-    pub zero_for_one: bool,
+    pub direction: SwapDirection,
     pub exact_out: bool,
     pub amount_out: Option<u128>,
+    // Slippage bounds: the proof is refused if the quote consumes more input than
+    // `amount_in_max` or yields less output than `amount_out_min`.
+    pub amount_in_max: Option<u128>,
+    pub amount_out_min: Option<u128>,
     pub sqrt_ratio_limit: Option<U256>,
     pub output_note: Option<Note>,
     pub change_note: Option<Note>,
     pub circuit_dir: Option<PathBuf>,
+    pub require_active_pool: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -94,10 +98,48 @@ pub struct SwapProveResult {
     pub selected_swap_steps: usize,
 }
 
+impl SwapProveResult {
+    // The root every input proof was built against; the swap must be submitted while it is still known.
+    pub fn required_root(&self) -> Result<Felt, ClientError> {
+        let first = self.input_proofs.first().ok_or_else(|| {
+            ClientError::InvalidInput("swap proof has no input proofs".to_string())
+        })?;
+        if self
+            .input_proofs
+            .iter()
+            .any(|proof| proof.root != first.root)
+        {
+            return Err(ClientError::InvalidInput(
+                "input proofs reference different roots".to_string(),
+            ));
+        }
+        Ok(first.root)
+    }
+
+    pub fn to_record(&self, direction: SwapDirection) -> SwapRecord {
+        let change = self.change_note.as_ref().map_or(0, |note| note.amount);
+        let mut record = SwapRecord::new(
+            direction,
+            self.amount_in_consumed.saturating_add(change),
+            self.amount_in_consumed,
+            self.amount_out,
+        );
+        record.token_in = self
+            .input_proofs
+            .first()
+            .map(|proof| format!("0x{:x}", proof.token));
+        record.token_out = self
+            .output_note
+            .as_ref()
+            .map(|note| format!("0x{:x}", note.token));
+        record
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ChunkedSwapExecuteRequest {
     pub notes: Vec<Note>,
-    pub zero_for_one: bool,
+    pub direction: SwapDirection,
     pub sqrt_ratio_limit: Option<U256>,
     pub circuit_dir: Option<PathBuf>,
 }
@@ -131,6 +173,7 @@ pub struct LiquidityAddProveRequest {
     pub output_note_token0: Option<Note>,
     pub output_note_token1: Option<Note>,
     pub circuit_dir: Option<PathBuf>,
+    pub require_active_pool: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -141,6 +184,7 @@ pub struct LiquidityRemoveProveRequest {
     pub output_note_token0: Option<Note>,
     pub output_note_token1: Option<Note>,
     pub circuit_dir: Option<PathBuf>,
+    pub require_active_pool: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -150,6 +194,7 @@ pub struct LiquidityClaimProveRequest {
     pub output_note_token0: Option<Note>,
     pub output_note_token1: Option<Note>,
     pub circuit_dir: Option<PathBuf>,
+    pub require_active_pool: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -166,12 +211,56 @@ pub struct LiquidityProveResult {
     pub output_position_note: Option<PositionNote>,
 }
 
+// Token amounts a liquidity removal would release: principal, protocol fee on the principal,
+// accrued position fees, and the resulting output note amounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiquidityRemoveQuote {
+    pub amount0: u128,
+    pub amount1: u128,
+    pub protocol_fee_0: u128,
+    pub protocol_fee_1: u128,
+    pub fee_amount0: u128,
+    pub fee_amount1: u128,
+    pub out_amount0: u128,
+    pub out_amount1: u128,
+}
+
+// Token amounts a liquidity add would consume, what the given notes provide, and the change
+// that would come back as output notes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiquidityAddPlan {
+    pub amount0: u128,
+    pub amount1: u128,
+    pub provided0: u128,
+    pub provided1: u128,
+    pub change0: u128,
+    pub change1: u128,
+}
+
 impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
+    // Fails with "note already spent" when the ASP has seen any of `nullifiers`, all from notes
+    // in `tree`; a no-op when `check_nullifiers` is off. Callers that prove outside this client
+    // (withdraw) use it too.
+    pub async fn ensure_nullifiers_unspent(
+        &self,
+        tree: InsertionTree,
+        nullifiers: &[Felt],
+    ) -> Result<(), ClientError> {
+        if !self.check_nullifiers {
+            return Ok(());
+        }
+        ensure_nullifiers_unspent(&self.swap_client(), tree, nullifiers).await
+    }
+
     pub async fn prove_swap(
         &self,
         request: SwapProveRequest,
     ) -> Result<SwapProveResult, ClientError> {
+        let zero_for_one = request.direction.is_zero_for_one();
         let swap_client = self.swap_client();
+        if request.require_active_pool {
+            ensure_pool_active(&swap_client).await?;
+        }
         let pool_config = swap_client.get_pool_config().await?;
         if request.notes.len() > generated_constants::MAX_INPUT_NOTES {
             return Err(ClientError::InvalidInput(
@@ -179,7 +268,16 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             ));
         }
         let (token_id_in, input_token, output_token) =
-            resolve_swap_tokens(&request.notes, &pool_config, request.zero_for_one)?;
+            resolve_swap_tokens(&request.notes, &pool_config, zero_for_one)?;
+        if self.check_nullifiers {
+            let nullifiers = request
+                .notes
+                .iter()
+                .map(|note| generate_nullifier_hash(note, token_id_in))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.ensure_nullifiers_unspent(InsertionTree::Token(input_token), &nullifiers)
+                .await?;
+        }
 
         let total_amount_in = sum_note_amounts(&request.notes)?;
         if total_amount_in == 0 {
@@ -199,13 +297,9 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         }
         let sqrt_ratio_limit = request
             .sqrt_ratio_limit
-            .unwrap_or_else(|| default_sqrt_ratio_limit(&pool_config, request.zero_for_one));
+            .unwrap_or_else(|| default_sqrt_ratio_limit(&pool_config, zero_for_one));
 
-        let is_token1 = if request.exact_out {
-            request.zero_for_one
-        } else {
-            !request.zero_for_one
-        };
+        let is_token1 = request.direction.quote_is_token1(request.exact_out);
         let quote_amount = if request.exact_out {
             SignedAmount {
                 mag: amount_out_requested,
@@ -225,28 +319,9 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
                 skip_ahead: 0,
             })
             .await?;
-        if quote.sqrt_price_start == U256::from(0u128) {
-            return Err(ClientError::InvalidInput(
-                "swap quote sqrt_price_start is zero".to_string(),
-            ));
-        }
-        if quote
-            .steps
-            .iter()
-            .any(|step| step.sqrt_price_limit == U256::from(0u128))
-        {
-            return Err(ClientError::InvalidInput(
-                "swap quote sqrt_price_limit is zero".to_string(),
-            ));
-        }
+        quote.validate(generated_constants::MAX_SWAP_STEPS)?;
 
-        let max_steps = generated_constants::MAX_SWAP_STEPS;
-        if quote.steps.len() != max_steps {
-            return Err(ClientError::Rpc("unexpected swap steps length".to_string()));
-        }
-
-        let mut step_liquidity =
-            compute_step_liquidity(&swap_client, &quote, request.zero_for_one).await?;
+        let mut step_liquidity = compute_step_liquidity(&swap_client, &quote, zero_for_one).await?;
         let remaining_start = if request.exact_out {
             amount_out_requested
         } else {
@@ -257,18 +332,17 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             &step_liquidity,
             remaining_start,
             request.exact_out,
-            request.zero_for_one,
+            zero_for_one,
         )? {
             quote = adjusted;
-            step_liquidity =
-                compute_step_liquidity(&swap_client, &quote, request.zero_for_one).await?;
+            step_liquidity = compute_step_liquidity(&swap_client, &quote, zero_for_one).await?;
         }
 
         let active_steps = count_active_swap_steps(&quote);
         let circuit_plan = select_swap_circuit_plan(
             request.circuit_dir.clone(),
             request.exact_out,
-            request.zero_for_one,
+            zero_for_one,
             active_steps,
         )?;
         if request.exact_out && active_steps > circuit_plan.max_steps {
@@ -283,11 +357,22 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
 
         let (_step_amount_in, _step_amount_out, amount_out_total, amount_in_consumed) =
             summarize_swap_amounts(&quote_for_circuit.steps)?;
+        if request.exact_out && amount_out_total < amount_out_requested {
+            return Err(ClientError::InvalidInput(format!(
+                "amount_out unreachable within price limit: max achievable {amount_out_total}"
+            )));
+        }
         if request.exact_out && amount_out_total != amount_out_requested {
             return Err(ClientError::InvalidInput(
                 "quoted amount_out mismatch".to_string(),
             ));
         }
+        check_swap_slippage(
+            amount_in_consumed,
+            amount_out_total,
+            request.amount_in_max,
+            request.amount_out_min,
+        )?;
 
         let change_amount = total_amount_in
             .checked_sub(amount_in_consumed)
@@ -353,16 +438,11 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             )?
         };
 
-        let mut proof = if request.exact_out {
-            prove_swap_exact_out(swap_witness, &circuit_plan.dir)
-                .await
-                .map_err(|e| ClientError::Prover(e.to_string()))?
-        } else {
-            prove_swap(swap_witness, &circuit_plan.dir)
-                .await
-                .map_err(|e| ClientError::Prover(e.to_string()))?
-        };
-        prefix_swap_variant_selector(&mut proof, circuit_plan.max_steps, request.zero_for_one);
+        let mut proof = self
+            .prover
+            .prove_swap(swap_witness, &circuit_plan.dir, request.exact_out)
+            .await?;
+        prefix_swap_variant_selector(&mut proof, circuit_plan.max_steps, zero_for_one);
 
         Ok(SwapProveResult {
             proof,
@@ -376,6 +456,25 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         })
     }
 
+    // Proves and submits in one call so the `exact_out` flag used for the proof is the one the
+    // pool entrypoint is selected with. Use `prove_swap` + `swap` to inspect the proof first.
+    pub async fn prove_and_submit_swap(
+        &self,
+        request: SwapProveRequest,
+    ) -> Result<(SwapProveResult, TxHash), ClientError> {
+        let exact_out = request.exact_out;
+        let result = self.prove_swap(request).await?;
+        let tx_hash = self
+            .swap(
+                result.proof.clone(),
+                &result.input_proofs,
+                &result.output_proofs,
+                exact_out,
+            )
+            .await?;
+        Ok((result, tx_hash))
+    }
+
     // Executes an exact-in swap across as many proof chunks as needed. This is sequential and
     // non-atomic because each follow-up chunk must spend the previous chunk's change note after it
     // is finalized on-chain and indexed by the ASP.
@@ -389,11 +488,12 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             ));
         }
 
+        let zero_for_one = request.direction.is_zero_for_one();
         let swap_client = self.swap_client();
         let pool_config = swap_client.get_pool_config().await?;
         let sqrt_ratio_limit = request
             .sqrt_ratio_limit
-            .unwrap_or_else(|| default_sqrt_ratio_limit(&pool_config, request.zero_for_one));
+            .unwrap_or_else(|| default_sqrt_ratio_limit(&pool_config, zero_for_one));
 
         let mut current_notes = request.notes;
         let mut output_notes = Vec::new();
@@ -405,13 +505,16 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             let prove_result = self
                 .prove_swap(SwapProveRequest {
                     notes: current_notes.clone(),
-                    zero_for_one: request.zero_for_one,
+                    direction: request.direction,
                     exact_out: false,
                     amount_out: None,
+                    amount_in_max: None,
+                    amount_out_min: None,
                     sqrt_ratio_limit: Some(sqrt_ratio_limit),
                     output_note: None,
                     change_note: None,
                     circuit_dir: request.circuit_dir.clone(),
+                    require_active_pool: false,
                 })
                 .await?;
 
@@ -458,13 +561,13 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
                 });
             };
 
-            let token_id_in = input_token_id(request.zero_for_one);
+            let token_id_in = request.direction.input_token_id();
             wait_for_note_indexed(&swap_client, &change_note, token_id_in).await?;
 
             if !chunk_can_progress(
                 &swap_client,
                 std::slice::from_ref(&change_note),
-                request.zero_for_one,
+                zero_for_one,
                 sqrt_ratio_limit,
             )
             .await?
@@ -487,7 +590,9 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         request: LiquidityAddProveRequest,
     ) -> Result<LiquidityProveResult, ClientError> {
         let swap_client = self.swap_client();
-        let pool_config = swap_client.get_pool_config().await?;
+        if request.require_active_pool {
+            ensure_pool_active(&swap_client).await?;
+        }
         if request.token0_notes.len() > generated_constants::MAX_INPUT_NOTES {
             return Err(ClientError::InvalidInput(
                 "too many token0 notes".to_string(),
@@ -515,7 +620,13 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             }
         }
 
-        let pool_state = self.get_pool_state().await?;
+        let context = swap_client
+            .fetch_liquidity_context(tick_lower, tick_upper)
+            .await?;
+        check_local_tick_math(tick_lower, context.sqrt_ratio_lower)?;
+        check_local_tick_math(tick_upper, context.sqrt_ratio_upper)?;
+        let pool_config = context.pool_config;
+        let pool_state = context.pool_state;
         let sqrt_price_start = pool_state.sqrt_price;
         let tick_start = pool_state.tick;
         let liquidity_before = pool_state.liquidity;
@@ -529,9 +640,9 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         );
 
         let (sqrt_ratio_lower, sqrt_ratio_upper) =
-            fetch_tick_sqrt_ratios(&swap_client, tick_lower, tick_upper).await?;
+            (context.sqrt_ratio_lower, context.sqrt_ratio_upper);
         let (fee_growth_inside_0_after, fee_growth_inside_1_after) =
-            fetch_fee_growth_inside(&swap_client, tick_lower, tick_upper).await?;
+            (context.fee_growth_inside_0, context.fee_growth_inside_1);
 
         let (
             position_commitment_in,
@@ -602,17 +713,14 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
                 liquidity_delta,
                 true,
             )?;
-        let (token0_total, token1_total) = (
-            sum_note_amounts(&request.token0_notes)?,
-            sum_note_amounts(&request.token1_notes)?,
-        );
-        if token0_total < amount0 || token1_total < amount1 {
-            return Err(ClientError::InvalidInput(
-                "input notes do not cover liquidity".to_string(),
-            ));
-        }
-        let change0 = token0_total - amount0;
-        let change1 = token1_total - amount1;
+        let LiquidityAddPlan {
+            change0, change1, ..
+        } = plan_liquidity_add_notes(
+            amount0,
+            amount1,
+            &request.token0_notes,
+            &request.token1_notes,
+        )?;
 
         let output_note_token0 =
             build_output_note(request.output_note_token0, change0, pool_config.token0, 0)?;
@@ -627,6 +735,11 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             Some(note) => compute_commitment(note, 1)?,
             None => Felt::ZERO,
         };
+        ensure_unique_output_commitments(&[
+            output_commitment_token0,
+            output_commitment_token1,
+            new_position_commitment,
+        ])?;
         let (proofs_token0, root_token0) =
             fetch_input_proofs(&swap_client, &request.token0_notes, 0, pool_config.token0).await?;
         let (proofs_token1, root_token1) =
@@ -701,9 +814,10 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         let circuit_dir = request
             .circuit_dir
             .unwrap_or_else(|| default_circuit_dir("private_liquidity"));
-        let proof = prove_lp_add(witness, &circuit_dir)
-            .await
-            .map_err(|e| ClientError::Prover(e.to_string()))?;
+        let proof = self
+            .prover
+            .prove_liquidity(LiquidityCircuit::Add, witness, &circuit_dir)
+            .await?;
 
         Ok(LiquidityProveResult {
             proof,
@@ -719,12 +833,72 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         })
     }
 
+    // Checks that the notes cover a liquidity add at current pool state and reports the change,
+    // so callers can assemble inputs before `prove_liquidity_add`.
+    pub async fn plan_liquidity_add(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: u128,
+        token0_notes: &[Note],
+        token1_notes: &[Note],
+    ) -> Result<LiquidityAddPlan, ClientError> {
+        if liquidity_delta == 0 {
+            return Err(ClientError::InvalidInput(
+                "liquidity_delta is zero".to_string(),
+            ));
+        }
+        if tick_lower >= tick_upper {
+            return Err(ClientError::InvalidInput(
+                "tick_lower must be less than tick_upper".to_string(),
+            ));
+        }
+        let swap_client = self.swap_client();
+        let pool_state = self.get_pool_state().await?;
+        let (sqrt_ratio_lower, sqrt_ratio_upper) =
+            fetch_tick_sqrt_ratios(&swap_client, tick_lower, tick_upper).await?;
+        let (amount0, amount1, _div_q_below, _div_q_inside) = compute_liquidity_amounts(
+            pool_state.sqrt_price,
+            sqrt_ratio_lower,
+            sqrt_ratio_upper,
+            liquidity_delta,
+            true,
+        )?;
+        plan_liquidity_add_notes(amount0, amount1, token0_notes, token1_notes)
+    }
+
+    // Previews `prove_liquidity_remove` against current pool state without proving.
+    pub async fn quote_liquidity_remove(
+        &self,
+        note: &PositionNote,
+        liquidity_delta: u128,
+    ) -> Result<LiquidityRemoveQuote, ClientError> {
+        let swap_client = self.swap_client();
+        let pool_config = self.get_pool_config().await?;
+        let pool_state = self.get_pool_state().await?;
+        let sqrt_ratios =
+            fetch_tick_sqrt_ratios(&swap_client, note.tick_lower, note.tick_upper).await?;
+        let fee_growth_inside_after =
+            fetch_fee_growth_inside(&swap_client, note.tick_lower, note.tick_upper).await?;
+        compute_liquidity_remove_quote(
+            note,
+            liquidity_delta,
+            pool_config.fee,
+            pool_state.sqrt_price,
+            sqrt_ratios,
+            fee_growth_inside_after,
+        )
+    }
+
     pub async fn prove_liquidity_remove(
         &self,
         request: LiquidityRemoveProveRequest,
     ) -> Result<LiquidityProveResult, ClientError> {
         let swap_client = self.swap_client();
-        let pool_config = swap_client.get_pool_config().await?;
+        if request.require_active_pool {
+            ensure_pool_active(&swap_client).await?;
+        }
+        let pool_config = self.get_pool_config().await?;
         let position_note = request.position_note;
         let liquidity_delta = request.liquidity_delta;
         if liquidity_delta == 0 {
@@ -765,6 +939,8 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
 
         let position_commitment_in = compute_position_commitment(&position_note)?;
         let nullifier_position = generate_position_nullifier_hash(&position_note)?;
+        self.ensure_nullifiers_unspent(InsertionTree::Position, &[nullifier_position])
+            .await?;
         let remaining_liquidity = position_note
             .liquidity
             .checked_sub(liquidity_delta)
@@ -849,6 +1025,11 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             Some(note) => compute_commitment(note, 1)?,
             None => Felt::ZERO,
         };
+        ensure_unique_output_commitments(&[
+            output_commitment_token0,
+            output_commitment_token1,
+            new_position_commitment,
+        ])?;
 
         let root_token0 = fetch_latest_root(&swap_client, pool_config.token0).await?;
         let root_token1 = fetch_latest_root(&swap_client, pool_config.token1).await?;
@@ -921,9 +1102,10 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         let circuit_dir = request
             .circuit_dir
             .unwrap_or_else(|| default_circuit_dir("private_liquidity"));
-        let proof = prove_lp_remove(witness, &circuit_dir)
-            .await
-            .map_err(|e| ClientError::Prover(e.to_string()))?;
+        let proof = self
+            .prover
+            .prove_liquidity(LiquidityCircuit::Remove, witness, &circuit_dir)
+            .await?;
 
         Ok(LiquidityProveResult {
             proof,
@@ -939,12 +1121,31 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         })
     }
 
+    // Fees a claim would pay out right now, without building a witness or proof.
+    pub async fn preview_claimable_fees(
+        &self,
+        note: &PositionNote,
+    ) -> Result<(u128, u128), ClientError> {
+        let (fee_growth_inside_0, fee_growth_inside_1) =
+            fetch_fee_growth_inside(&self.swap_client(), note.tick_lower, note.tick_upper).await?;
+        compute_fee_amounts(
+            note.liquidity,
+            note.fee_growth_inside_0,
+            note.fee_growth_inside_1,
+            fee_growth_inside_0,
+            fee_growth_inside_1,
+        )
+    }
+
     pub async fn prove_liquidity_claim(
         &self,
         request: LiquidityClaimProveRequest,
     ) -> Result<LiquidityProveResult, ClientError> {
         let swap_client = self.swap_client();
-        let pool_config = swap_client.get_pool_config().await?;
+        if request.require_active_pool {
+            ensure_pool_active(&swap_client).await?;
+        }
+        let pool_config = self.get_pool_config().await?;
         let position_note = request.position_note;
 
         let pool_state = self.get_pool_state().await?;
@@ -974,6 +1175,8 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
 
         let position_commitment_in = compute_position_commitment(&position_note)?;
         let nullifier_position = generate_position_nullifier_hash(&position_note)?;
+        self.ensure_nullifiers_unspent(InsertionTree::Position, &[nullifier_position])
+            .await?;
 
         let output_position_note = build_position_note(
             request.output_position_note,
@@ -1013,6 +1216,11 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
             Some(note) => compute_commitment(note, 1)?,
             None => Felt::ZERO,
         };
+        ensure_unique_output_commitments(&[
+            output_commitment_token0,
+            output_commitment_token1,
+            new_position_commitment,
+        ])?;
 
         let root_token0 = fetch_latest_root(&swap_client, pool_config.token0).await?;
         let root_token1 = fetch_latest_root(&swap_client, pool_config.token1).await?;
@@ -1081,9 +1289,10 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
         let circuit_dir = request
             .circuit_dir
             .unwrap_or_else(|| default_circuit_dir("private_liquidity"));
-        let proof = prove_lp_claim(witness, &circuit_dir)
-            .await
-            .map_err(|e| ClientError::Prover(e.to_string()))?;
+        let proof = self
+            .prover
+            .prove_liquidity(LiquidityCircuit::Claim, witness, &circuit_dir)
+            .await?;
 
         Ok(LiquidityProveResult {
             proof,
@@ -1100,14 +1309,6 @@ impl<A: ConnectedAccount + Sync + Send> ZylithClient<A> {
     }
 }
 
-fn input_token_id(zero_for_one: bool) -> u8 {
-    if zero_for_one {
-        0
-    } else {
-        1
-    }
-}
-
 async fn wait_for_note_indexed<A: ConnectedAccount + Sync>(
     swap_client: &SwapClient<A>,
     note: &Note,
@@ -1144,7 +1345,7 @@ async fn chunk_can_progress<A: ConnectedAccount + Sync>(
                 mag: amount_in,
                 sign: false,
             },
-            is_token1: !zero_for_one,
+            is_token1: SwapDirection::from(zero_for_one).quote_is_token1(false),
             sqrt_ratio_limit,
             skip_ahead: 0,
         })
@@ -1240,6 +1441,29 @@ fn sum_note_amounts(notes: &[Note]) -> Result<u128, ClientError> {
     Ok(total)
 }
 
+fn plan_liquidity_add_notes(
+    amount0: u128,
+    amount1: u128,
+    token0_notes: &[Note],
+    token1_notes: &[Note],
+) -> Result<LiquidityAddPlan, ClientError> {
+    let provided0 = sum_note_amounts(token0_notes)?;
+    let provided1 = sum_note_amounts(token1_notes)?;
+    if provided0 < amount0 || provided1 < amount1 {
+        return Err(ClientError::InvalidInput(
+            "input notes do not cover liquidity".to_string(),
+        ));
+    }
+    Ok(LiquidityAddPlan {
+        amount0,
+        amount1,
+        provided0,
+        provided1,
+        change0: provided0 - amount0,
+        change1: provided1 - amount1,
+    })
+}
+
 fn summarize_swap_amounts(
     steps: &[crate::swap::SwapStepQuote],
 ) -> Result<(Vec<u128>, Vec<u128>, u128, u128), ClientError> {
@@ -1436,19 +1660,20 @@ async fn fetch_input_proofs<A: ConnectedAccount + Sync>(
         let root = fetch_latest_root(swap_client, token).await?;
         return Ok((Vec::new(), root));
     }
-    let mut proofs = Vec::with_capacity(notes.len());
-    let mut merkle_root = Felt::ZERO;
-    for (idx, note) in notes.iter().enumerate() {
-        let commitment = compute_commitment(note, token_id)?;
-        let proof = swap_client
-            .fetch_merkle_path(commitment, None, None)
-            .await?;
-        if idx == 0 {
-            merkle_root = proof.root;
-        } else if proof.root != merkle_root {
-            return Err(ClientError::Asp("input roots mismatch".to_string()));
-        }
-        proofs.push(proof);
+    // One `/paths` request resolves a single root for the token, so inputs cannot straddle a
+    // tree update the way separate `/path` calls could. Entries come back in note order.
+    let commitments = notes
+        .iter()
+        .map(|note| compute_commitment(note, token_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let proofs = swap_client
+        .fetch_merkle_paths(&commitments, None, None)
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    let merkle_root = proofs[0].root;
+    if proofs.iter().any(|proof| proof.root != merkle_root) {
+        return Err(ClientError::Asp("input roots mismatch".to_string()));
     }
     Ok((proofs, merkle_root))
 }
@@ -1458,44 +1683,11 @@ async fn fetch_latest_root<A: ConnectedAccount + Sync>(
     token: Felt,
 ) -> Result<Felt, ClientError> {
     let token_label = format!("0x{:x}", token);
-    let url = format!(
-        "{}/root/latest?token={}",
-        swap_client.asp_url.trim_end_matches('/'),
-        token_label
-    );
-    let client = asp_client()?;
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|err| ClientError::Asp(err.to_string()))?;
-    if !response.status().is_success() {
-        return Err(ClientError::Asp(format!(
-            "asp root error: {}",
-            response.status()
-        )));
-    }
-    let body: RootAtResponse = response.json().await.map_err(ClientError::from)?;
-    if body.token != token_label {
-        return Err(ClientError::Asp("token mismatch".to_string()));
-    }
-    let root = parse_hex_felt(&body.root)?;
-    Ok(root)
-}
-
-async fn fetch_position_root<A: ConnectedAccount + Sync>(
-    swap_client: &SwapClient<A>,
-    position_commitment: Felt,
-) -> Result<Felt, ClientError> {
-    if position_commitment != Felt::ZERO {
-        let proof = swap_client
-            .fetch_merkle_path(position_commitment, None, None)
-            .await?;
-        Ok(proof.root)
-    } else {
+    with_deadline(swap_client.retry.deadline, "asp root request", async {
         let url = format!(
-            "{}/root/latest?token=position",
-            swap_client.asp_url.trim_end_matches('/')
+            "{}/root/latest?token={}",
+            swap_client.asp_url.trim_end_matches('/'),
+            token_label
         );
         let client = asp_client()?;
         let response = client
@@ -1510,10 +1702,49 @@ async fn fetch_position_root<A: ConnectedAccount + Sync>(
             )));
         }
         let body: RootAtResponse = response.json().await.map_err(ClientError::from)?;
-        if body.token != "position" {
+        if body.token != token_label {
             return Err(ClientError::Asp("token mismatch".to_string()));
         }
-        parse_hex_felt(&body.root)
+        let root = parse_hex_felt(&body.root)?;
+        Ok(root)
+    })
+    .await
+}
+
+async fn fetch_position_root<A: ConnectedAccount + Sync>(
+    swap_client: &SwapClient<A>,
+    position_commitment: Felt,
+) -> Result<Felt, ClientError> {
+    if position_commitment != Felt::ZERO {
+        let proof = swap_client
+            .fetch_merkle_path(position_commitment, None, None)
+            .await?;
+        Ok(proof.root)
+    } else {
+        with_deadline(swap_client.retry.deadline, "asp root request", async {
+            let url = format!(
+                "{}/root/latest?token=position",
+                swap_client.asp_url.trim_end_matches('/')
+            );
+            let client = asp_client()?;
+            let response = client
+                .get(url)
+                .send()
+                .await
+                .map_err(|err| ClientError::Asp(err.to_string()))?;
+            if !response.status().is_success() {
+                return Err(ClientError::Asp(format!(
+                    "asp root error: {}",
+                    response.status()
+                )));
+            }
+            let body: RootAtResponse = response.json().await.map_err(ClientError::from)?;
+            if body.token != "position" {
+                return Err(ClientError::Asp("token mismatch".to_string()));
+            }
+            parse_hex_felt(&body.root)
+        })
+        .await
     }
 }
 
@@ -1554,7 +1785,7 @@ fn build_swap_witness_exact_in(
         let step_limit = effective_step_limit(
             quote.sqrt_price_end,
             step.sqrt_price_limit,
-            request.zero_for_one,
+            request.direction.is_zero_for_one(),
         );
         let sqrt_limit = u256_to_big(&step_limit);
         let (div_before_fee, amt0_limit, amt0_calc, amt0_out, next0_floor, next0_ceil, next1_floor) =
@@ -1564,7 +1795,7 @@ fn build_swap_witness_exact_in(
                 liquidity,
                 amount_step,
                 pool_config.fee,
-                request.zero_for_one,
+                request.direction.is_zero_for_one(),
             )?;
         step_amount_before_fee_div_q.push(div_before_fee);
         step_amount0_limit_div_q.push(amt0_limit);
@@ -1586,8 +1817,11 @@ fn build_swap_witness_exact_in(
         }
     }
 
-    let (token_id_in, input_token, output_token) =
-        resolve_swap_tokens(&request.notes, pool_config, request.zero_for_one)?;
+    let (token_id_in, input_token, output_token) = resolve_swap_tokens(
+        &request.notes,
+        pool_config,
+        request.direction.is_zero_for_one(),
+    )?;
     let (commitment_in, nullifier, note_count, commitment_extra, nullifier_extra) =
         build_note_public_inputs(&request.notes, token_id_in, max_notes)?;
 
@@ -1666,7 +1900,7 @@ fn build_swap_witness_exact_in(
     );
     values.insert(
         "zero_for_one".to_string(),
-        WitnessValue::Bool(request.zero_for_one),
+        WitnessValue::Bool(request.direction.is_zero_for_one()),
     );
     values.insert(
         "step_sqrt_price_next".to_string(),
@@ -1867,7 +2101,7 @@ fn build_swap_witness_exact_out(
         let step_limit = effective_step_limit(
             quote.sqrt_price_end,
             step.sqrt_price_limit,
-            request.zero_for_one,
+            request.direction.is_zero_for_one(),
         );
         let sqrt_limit = u256_to_big(&step_limit);
         let (div_before_fee, amt0_limit, amt0_calc, amt0_out, next0_ceil, next1_floor) =
@@ -1877,7 +2111,7 @@ fn build_swap_witness_exact_out(
                 liquidity,
                 amount_step,
                 pool_config.fee,
-                request.zero_for_one,
+                request.direction.is_zero_for_one(),
             )?;
         step_amount_before_fee_div_q.push(div_before_fee);
         step_amount0_limit_div_q.push(amt0_limit);
@@ -1898,8 +2132,11 @@ fn build_swap_witness_exact_out(
         }
     }
 
-    let (token_id_in, input_token, output_token) =
-        resolve_swap_tokens(&request.notes, pool_config, request.zero_for_one)?;
+    let (token_id_in, input_token, output_token) = resolve_swap_tokens(
+        &request.notes,
+        pool_config,
+        request.direction.is_zero_for_one(),
+    )?;
     let (commitment_in, nullifier, note_count, commitment_extra, nullifier_extra) =
         build_note_public_inputs(&request.notes, token_id_in, max_notes)?;
 
@@ -1978,7 +2215,7 @@ fn build_swap_witness_exact_out(
     );
     values.insert(
         "zero_for_one".to_string(),
-        WitnessValue::Bool(request.zero_for_one),
+        WitnessValue::Bool(request.direction.is_zero_for_one()),
     );
     values.insert(
         "step_sqrt_price_next".to_string(),
@@ -2931,12 +3168,8 @@ fn compute_fee_amounts(
     fee_growth_inside_0_after: U256,
     fee_growth_inside_1_after: U256,
 ) -> Result<(u128, u128), ClientError> {
-    let diff0 = u256_to_big(&fee_growth_inside_0_after)
-        .checked_sub(&u256_to_big(&fee_growth_inside_0_before))
-        .ok_or_else(|| ClientError::InvalidInput("fee growth decreased".to_string()))?;
-    let diff1 = u256_to_big(&fee_growth_inside_1_after)
-        .checked_sub(&u256_to_big(&fee_growth_inside_1_before))
-        .ok_or_else(|| ClientError::InvalidInput("fee growth decreased".to_string()))?;
+    let diff0 = fee_growth_diff(0, &fee_growth_inside_0_before, &fee_growth_inside_0_after)?;
+    let diff1 = fee_growth_diff(1, &fee_growth_inside_1_before, &fee_growth_inside_1_after)?;
     let fee0 = (diff0 * BigUint::from(position_liquidity)) >> 128u32;
     let fee1 = (diff1 * BigUint::from(position_liquidity)) >> 128u32;
     let fee0_u128 = fee0
@@ -2948,6 +3181,173 @@ fn compute_fee_amounts(
     Ok((fee0_u128, fee1_u128))
 }
 
+fn compute_liquidity_remove_quote(
+    note: &PositionNote,
+    liquidity_delta: u128,
+    pool_fee: u128,
+    sqrt_price: U256,
+    (sqrt_ratio_lower, sqrt_ratio_upper): (U256, U256),
+    (fee_growth_inside_0_after, fee_growth_inside_1_after): (U256, U256),
+) -> Result<LiquidityRemoveQuote, ClientError> {
+    if liquidity_delta == 0 {
+        return Err(ClientError::InvalidInput(
+            "liquidity_delta is zero".to_string(),
+        ));
+    }
+    if liquidity_delta > note.liquidity {
+        return Err(ClientError::InvalidInput(
+            "liquidity_delta exceeds position".to_string(),
+        ));
+    }
+    let (amount0, amount1, _div_q_below, _div_q_inside) = compute_liquidity_amounts(
+        sqrt_price,
+        sqrt_ratio_lower,
+        sqrt_ratio_upper,
+        liquidity_delta,
+        false,
+    )?;
+    let (fee_amount0, fee_amount1) = compute_fee_amounts(
+        note.liquidity,
+        note.fee_growth_inside_0,
+        note.fee_growth_inside_1,
+        fee_growth_inside_0_after,
+        fee_growth_inside_1_after,
+    )?;
+    let protocol_fee_0 = compute_fee(amount0, pool_fee);
+    let protocol_fee_1 = compute_fee(amount1, pool_fee);
+    let out_amount0 = amount0
+        .checked_sub(protocol_fee_0)
+        .and_then(|val| val.checked_add(fee_amount0))
+        .ok_or_else(|| ClientError::InvalidInput("token0 output underflow".to_string()))?;
+    let out_amount1 = amount1
+        .checked_sub(protocol_fee_1)
+        .and_then(|val| val.checked_add(fee_amount1))
+        .ok_or_else(|| ClientError::InvalidInput("token1 output underflow".to_string()))?;
+    Ok(LiquidityRemoveQuote {
+        amount0,
+        amount1,
+        protocol_fee_0,
+        protocol_fee_1,
+        fee_amount0,
+        fee_amount1,
+        out_amount0,
+        out_amount1,
+    })
+}
+
+// Growth inside a range only moves forward, so a lower pool value means the note was recorded
+// against a different pool or range, or the RPC is serving stale state.
+fn fee_growth_diff(token_index: u8, before: &U256, after: &U256) -> Result<BigUint, ClientError> {
+    let (before, after) = (u256_to_big(before), u256_to_big(after));
+    after.checked_sub(&before).ok_or_else(|| {
+        ClientError::InvalidInput(format!(
+            "fee growth decreased for token{token_index}: note records {before}, pool reports {after}"
+        ))
+    })
+}
+
+#[cfg(test)]
+mod fee_tests {
+    use super::*;
+
+    #[test]
+    fn fee_amounts_scale_growth_by_liquidity() {
+        let one_q128 = U256::from_words(0, 1);
+        let (fee0, fee1) = compute_fee_amounts(
+            1_000,
+            U256::from(0u8),
+            one_q128,
+            U256::from_words(0, 3),
+            U256::from_words(0, 2),
+        )
+        .expect("fees");
+        assert_eq!((fee0, fee1), (3_000, 1_000));
+    }
+
+    #[test]
+    fn decreased_fee_growth_is_reported_per_token() {
+        let err = compute_fee_amounts(
+            1,
+            U256::from(0u8),
+            U256::from(9u8),
+            U256::from(0u8),
+            U256::from(4u8),
+        )
+        .expect_err("decrease");
+        assert!(err
+            .to_string()
+            .contains("fee growth decreased for token1: note records 9, pool reports 4"));
+    }
+
+    #[test]
+    fn remove_quote_above_range_pays_token1_plus_fees() {
+        let one_q128 = U256::from_words(0, 1);
+        let note =
+            generate_position_note(-10, 10, 1_000, U256::from(0u8), U256::from(0u8)).expect("note");
+        // Price sits above the range, so the position is entirely token1.
+        let quote = compute_liquidity_remove_quote(
+            &note,
+            1_000,
+            u128::MAX / 100,
+            U256::from_words(0, 3),
+            (one_q128, U256::from_words(0, 2)),
+            (U256::from(0u8), U256::from_words(0, 2)),
+        )
+        .expect("quote");
+        assert_eq!(
+            quote,
+            LiquidityRemoveQuote {
+                amount0: 0,
+                amount1: 1_000,
+                protocol_fee_0: 0,
+                protocol_fee_1: 10,
+                fee_amount0: 0,
+                fee_amount1: 2_000,
+                out_amount0: 0,
+                out_amount1: 2_990,
+            }
+        );
+
+        assert!(compute_liquidity_remove_quote(
+            &note,
+            1_001,
+            0,
+            U256::from_words(0, 3),
+            (one_q128, U256::from_words(0, 2)),
+            (U256::from(0u8), U256::from(0u8)),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn add_plan_reports_change_and_rejects_short_notes() {
+        let token0 = Felt::from(0x10u64);
+        let token1 = Felt::from(0x11u64);
+        let notes0 = vec![
+            generate_note_with_token_id(600, token0, 0).expect("note"),
+            generate_note_with_token_id(500, token0, 0).expect("note"),
+        ];
+        let notes1 = vec![generate_note_with_token_id(300, token1, 1).expect("note")];
+        let plan = plan_liquidity_add_notes(1_000, 300, &notes0, &notes1).expect("plan");
+        assert_eq!(
+            plan,
+            LiquidityAddPlan {
+                amount0: 1_000,
+                amount1: 300,
+                provided0: 1_100,
+                provided1: 300,
+                change0: 100,
+                change1: 0,
+            }
+        );
+
+        let err = plan_liquidity_add_notes(1_000, 301, &notes0, &notes1).expect_err("short");
+        assert!(err
+            .to_string()
+            .contains("input notes do not cover liquidity"));
+    }
+}
+
 fn encode_signed_u256(value: i128) -> Result<U256, ClientError> {
     if value >= 0 {
         Ok(U256::from_words(value as u128, 0))
@@ -2972,7 +3372,7 @@ fn decode_signed_u256(value: U256) -> Result<(bool, u128), ClientError> {
     }
 }
 
-fn u256_to_big(value: &U256) -> BigUint {
+pub(crate) fn u256_to_big(value: &U256) -> BigUint {
     let mut out = BigUint::from(value.high());
     out <<= 128u32;
     out + BigUint::from(value.low())
@@ -3065,6 +3465,11 @@ async fn fetch_sqrt_ratio_at_tick<A: ConnectedAccount + Sync>(
     tick: i32,
 ) -> Result<U256, ClientError> {
     let onchain = swap_client.get_sqrt_ratio_at_tick(tick).await?;
+    check_local_tick_math(tick, onchain)?;
+    Ok(onchain)
+}
+
+fn check_local_tick_math(tick: i32, onchain: U256) -> Result<(), ClientError> {
     if should_validate_local_tick_math() {
         let local = tick_to_sqrt_ratio_local(tick)?;
         if onchain != local {
@@ -3073,7 +3478,7 @@ async fn fetch_sqrt_ratio_at_tick<A: ConnectedAccount + Sync>(
             )));
         }
     }
-    Ok(onchain)
+    Ok(())
 }
 
 async fn fetch_fee_growth_inside<A: ConnectedAccount + Sync>(
@@ -3083,7 +3488,7 @@ async fn fetch_fee_growth_inside<A: ConnectedAccount + Sync>(
 ) -> Result<(U256, U256), ClientError> {
     let selector = get_selector_from_name("get_fee_growth_inside")
         .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
-    let calldata = vec![i32_to_felt(tick_lower)?, i32_to_felt(tick_upper)?];
+    let calldata = vec![tick_to_felt(tick_lower), tick_to_felt(tick_upper)];
     let call = FunctionCall {
         contract_address: swap_client.pool_address,
         entry_point_selector: selector,
@@ -3124,6 +3529,17 @@ struct SwapCircuitPlan {
     max_steps: usize,
 }
 
+pub fn plan_swap_circuit_steps(
+    circuit_dir: Option<PathBuf>,
+    exact_out: bool,
+    zero_for_one: bool,
+    quote: &SwapStepsQuote,
+) -> Result<(usize, usize), ClientError> {
+    let active_steps = count_active_swap_steps(quote);
+    let plan = select_swap_circuit_plan(circuit_dir, exact_out, zero_for_one, active_steps)?;
+    Ok((active_steps, plan.max_steps))
+}
+
 fn count_active_swap_steps(quote: &SwapStepsQuote) -> usize {
     let mut last_non_idle = 0usize;
     for (idx, step) in quote.steps.iter().enumerate() {
@@ -3271,24 +3687,6 @@ fn parse_hex_felt(value: &str) -> Result<Felt, ClientError> {
     }
 }
 
-fn i32_to_felt(value: i32) -> Result<Felt, ClientError> {
-    if value >= 0 {
-        Ok(Felt::from(value as u64))
-    } else {
-        let modulus = BigUint::parse_bytes(
-            b"800000000000011000000000000000000000000000000000000000000000001",
-            16,
-        )
-        .ok_or_else(|| ClientError::Crypto("invalid modulus".to_string()))?;
-        let mag = BigUint::from((-value) as u32);
-        let result = modulus - mag;
-        let bytes = result.to_bytes_be();
-        let mut out = [0u8; 32];
-        out[32 - bytes.len()..].copy_from_slice(&bytes);
-        Ok(Felt::from_bytes_be(&out))
-    }
-}
-
 fn tick_to_sqrt_ratio_local(tick: i32) -> Result<U256, ClientError> {
     let tick_i64 = i64::from(tick);
     let tick_mag = tick_i64.unsigned_abs() as u128;
@@ -3338,6 +3736,202 @@ fn biguint_to_u256(value: &BigUint) -> Result<U256, ClientError> {
     Ok(U256::from_words(low, high))
 }
 
+#[cfg(test)]
+mod slippage_tests {
+    use super::check_swap_slippage;
+
+    #[test]
+    fn amount_in_max_allows_exact_bound() {
+        check_swap_slippage(1_000, 990, Some(1_000), None).expect("bound met");
+        let err = check_swap_slippage(1_001, 990, Some(1_000), None).expect_err("exceeded");
+        assert!(err.to_string().contains("above amount_in_max 1000"));
+    }
+
+    #[test]
+    fn amount_out_min_allows_exact_bound() {
+        check_swap_slippage(1_000, 990, None, Some(990)).expect("bound met");
+        let err = check_swap_slippage(1_000, 989, None, Some(990)).expect_err("exceeded");
+        assert!(err.to_string().contains("below amount_out_min 990"));
+    }
+
+    #[test]
+    fn unset_bounds_are_ignored() {
+        check_swap_slippage(u128::MAX, 0, None, None).expect("no bounds");
+    }
+}
+
+#[cfg(test)]
+mod nullifier_tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use starknet::core::types::Felt;
+
+    use super::ensure_nullifiers_unspent;
+    use crate::client::{ZylithClient, ZylithConfig};
+    use crate::error::ClientError;
+    use crate::notes::{generate_note_with_token_id, generate_nullifier_hash};
+    use crate::swap::{InsertionTree, SwapClient};
+    use crate::test_support::{dummy_account, spawn_json_server, UNREACHABLE_URL};
+
+    // Answers `GET /nullifier/<hex>?token=<tree>` as spent only for `spent` in `tree`, reporting
+    // it as finalized or, with `pending`, as indexed but not yet final.
+    fn spawn_asp(spent: Felt, tree: InsertionTree, pending: bool) -> String {
+        let spent_path = format!("/nullifier/0x{:x}?token={}", spent, tree.label());
+        let (url, _) = spawn_json_server(Arc::new(move |request| {
+            let is_spent = request.path == spent_path;
+            serde_json::json!({
+                "spent": is_spent && !pending,
+                "block_number": null,
+                "pending": is_spent && pending,
+            })
+        }));
+        url
+    }
+
+    #[tokio::test]
+    async fn spent_input_note_short_circuits_before_proving() {
+        let spent = Felt::from(0x5eedu64);
+        let tree = InsertionTree::Token(Felt::from(0x7u8));
+        for pending in [false, true] {
+            let asp_url = spawn_asp(spent, tree, pending);
+            let client = SwapClient::new(dummy_account(UNREACHABLE_URL), Felt::ONE, asp_url);
+
+            ensure_nullifiers_unspent(&client, tree, &[Felt::from(1u8), Felt::from(2u8)])
+                .await
+                .expect("unspent notes pass");
+            let err = ensure_nullifiers_unspent(&client, tree, &[Felt::from(1u8), spent])
+                .await
+                .expect_err("spent note");
+            assert!(matches!(err, ClientError::InvalidInput(msg) if msg == "note already spent"));
+        }
+    }
+
+    #[tokio::test]
+    async fn withdraw_preflight_follows_check_nullifiers() {
+        let note = generate_note_with_token_id(250, Felt::from(0x7u8), 0).expect("note");
+        let spent = generate_nullifier_hash(&note, 0).expect("nullifier");
+        let tree = InsertionTree::Token(note.token);
+        let asp_url = spawn_asp(spent, tree, false);
+        let client = |check_nullifiers| {
+            ZylithClient::new(ZylithConfig {
+                account: dummy_account(UNREACHABLE_URL),
+                asp_url: asp_url.clone(),
+                pool_address: Felt::ONE,
+                shielded_notes_address: Felt::ONE,
+                token0: Felt::ZERO,
+                token1: Felt::ZERO,
+                rpc_timeout: None,
+                request_timeout: Duration::from_secs(5),
+                pool_state_ttl: None,
+                check_nullifiers,
+            })
+        };
+
+        let err = client(true)
+            .ensure_nullifiers_unspent(tree, &[spent])
+            .await
+            .expect_err("spent note");
+        assert!(matches!(err, ClientError::InvalidInput(msg) if msg == "note already spent"));
+        client(false)
+            .ensure_nullifiers_unspent(tree, &[spent])
+            .await
+            .expect("check disabled");
+    }
+}
+
+#[cfg(test)]
+mod input_proof_tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use starknet::core::types::Felt;
+
+    use super::fetch_input_proofs;
+    use crate::notes::{compute_commitment, generate_note_with_token_id};
+    use crate::swap::SwapClient;
+    use crate::test_support::{dummy_account, spawn_json_server, UNREACHABLE_URL};
+
+    // Serves `POST /paths` from `paths` (commitment hex -> sibling hex and index bit); any
+    // other commitment is reported as not found.
+    fn spawn_asp(paths: HashMap<String, (String, bool)>) -> (String, Arc<AtomicUsize>) {
+        spawn_json_server(Arc::new(move |request| {
+            assert_eq!(request.path, "/paths");
+            let entries: Vec<serde_json::Value> = request.body["commitments"]
+                .as_array()
+                .expect("commitments")
+                .iter()
+                .map(|commitment| {
+                    let commitment = commitment.as_str().expect("commitment");
+                    match paths.get(commitment) {
+                        Some((sibling, index)) => serde_json::json!({
+                            "commitment": commitment,
+                            "status": "ok",
+                            "path": {
+                                "token": "0x1",
+                                "leaf_index": u64::from(*index),
+                                "path": [sibling],
+                                "indices": [index],
+                            },
+                        }),
+                        None => serde_json::json!({
+                            "commitment": commitment,
+                            "status": "not_found",
+                            "path": null,
+                        }),
+                    }
+                })
+                .collect();
+            serde_json::json!(entries)
+        }))
+    }
+
+    #[tokio::test]
+    async fn input_paths_come_from_one_paths_request_in_note_order() {
+        let token = Felt::ONE;
+        let notes = [10u128, 20, 30, 40]
+            .map(|amount| generate_note_with_token_id(amount, token, 0).expect("note"));
+        let [a, b, stray, _unknown] = notes
+            .each_ref()
+            .map(|note| format!("0x{:x}", compute_commitment(note, 0).expect("commitment")));
+        // `a` and `b` are the two leaves of one tree; `stray` hashes up to a different root.
+        let paths = HashMap::from([
+            (a.clone(), (b.clone(), false)),
+            (b.clone(), (a.clone(), true)),
+            (stray.clone(), ("0x0".to_string(), false)),
+        ]);
+        let (asp_url, served) = spawn_asp(paths);
+        let client = SwapClient::new(dummy_account(UNREACHABLE_URL), Felt::ONE, asp_url);
+
+        let (proofs, root) =
+            fetch_input_proofs(&client, &[notes[1].clone(), notes[0].clone()], 0, token)
+                .await
+                .expect("proofs");
+        assert_eq!(
+            served.load(Ordering::SeqCst),
+            1,
+            "one request for every input"
+        );
+        let commitments: Vec<String> = proofs
+            .iter()
+            .map(|proof| format!("0x{:x}", proof.commitment))
+            .collect();
+        assert_eq!(commitments, [b, a]);
+        assert!(proofs.iter().all(|proof| proof.root == root));
+
+        let err = fetch_input_proofs(&client, &[notes[0].clone(), notes[2].clone()], 0, token)
+            .await
+            .expect_err("mismatched roots");
+        assert!(err.to_string().contains("input roots mismatch"));
+
+        let err = fetch_input_proofs(&client, &[notes[0].clone(), notes[3].clone()], 0, token)
+            .await
+            .expect_err("unknown commitment");
+        assert!(err.to_string().contains("not_found"));
+    }
+}
+
 #[cfg(test)]
 mod tick_math_tests {
     use super::*;
@@ -3364,6 +3958,63 @@ mod tick_math_tests {
     }
 }
 
+async fn ensure_pool_active<A: ConnectedAccount + Sync>(
+    swap_client: &SwapClient<A>,
+) -> Result<(), ClientError> {
+    if !swap_client.is_pool_active().await? {
+        return Err(ClientError::InvalidInput("pool is not active".to_string()));
+    }
+    Ok(())
+}
+
+// Proving takes seconds and a spent note only fails on chain, so ask the ASP first.
+async fn ensure_nullifiers_unspent<A: ConnectedAccount + Sync>(
+    swap_client: &SwapClient<A>,
+    tree: InsertionTree,
+    nullifiers: &[Felt],
+) -> Result<(), ClientError> {
+    for nullifier in nullifiers {
+        if swap_client.is_nullifier_spent(*nullifier, tree).await? {
+            return Err(ClientError::InvalidInput("note already spent".to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn check_swap_slippage(
+    amount_in_consumed: u128,
+    amount_out_total: u128,
+    amount_in_max: Option<u128>,
+    amount_out_min: Option<u128>,
+) -> Result<(), ClientError> {
+    if let Some(max) = amount_in_max {
+        if amount_in_consumed > max {
+            return Err(ClientError::InvalidInput(format!(
+                "swap would consume {amount_in_consumed} input, above amount_in_max {max}"
+            )));
+        }
+    }
+    if let Some(min) = amount_out_min {
+        if amount_out_total < min {
+            return Err(ClientError::InvalidInput(format!(
+                "swap would output {amount_out_total}, below amount_out_min {min}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn ensure_unique_output_commitments(commitments: &[Felt]) -> Result<(), ClientError> {
+    for (idx, commitment) in commitments.iter().enumerate() {
+        if *commitment != Felt::ZERO && commitments[idx + 1..].contains(commitment) {
+            return Err(ClientError::InvalidInput(
+                "duplicate output commitment".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
 fn prefix_swap_variant_selector(proof: &mut ProofCalldata, max_steps: usize, zero_for_one: bool) {
     proof.prepend_tokens(&[
         SWAP_STEPS_PREFIX_HEX.to_string(),
@@ -3406,7 +4057,7 @@ mod vector_gen {
     };
 
     use crate::client::PoolState;
-    use crate::utils::{felt_to_i32, parse_felt};
+    use crate::utils::{felt_to_tick, parse_felt};
 
     type Account = SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>;
 
@@ -3641,13 +4292,16 @@ mod vector_gen {
 
         let request = SwapProveRequest {
             notes: notes.clone(),
-            zero_for_one,
+            direction: zero_for_one.into(),
             exact_out: false,
             amount_out: None,
+            amount_in_max: None,
+            amount_out_min: None,
             sqrt_ratio_limit: Some(sqrt_ratio_limit),
             output_note,
             change_note,
             circuit_dir: None,
+            require_active_pool: false,
         };
         let witness = build_swap_witness_exact_in(
             &request,
@@ -3697,13 +4351,16 @@ mod vector_gen {
             .unwrap_or(Felt::ZERO);
         let request = SwapProveRequest {
             notes,
-            zero_for_one,
+            direction: zero_for_one.into(),
             exact_out: true,
             amount_out: Some(amount_out_total),
+            amount_in_max: None,
+            amount_out_min: None,
             sqrt_ratio_limit: Some(sqrt_ratio_limit),
             output_note,
             change_note: None,
             circuit_dir: None,
+            require_active_pool: false,
         };
         let witness = build_swap_witness_exact_out(
             &request,
@@ -3772,13 +4429,16 @@ mod vector_gen {
         let notes = split_notes(&mut rng, 0, token0, 0)?;
         let request = SwapProveRequest {
             notes,
-            zero_for_one: true,
+            direction: SwapDirection::ZeroForOne,
             exact_out: true,
             amount_out: Some(0),
+            amount_in_max: None,
+            amount_out_min: None,
             sqrt_ratio_limit: Some(U256::from(1u8)),
             output_note: None,
             change_note: None,
             circuit_dir: None,
+            require_active_pool: false,
         };
         let quote = synthetic_exact_out_quote();
         let step_liquidity = vec![1u128; generated_constants::MAX_SWAP_STEPS];
@@ -4435,7 +5095,7 @@ mod vector_gen {
             return Err(ClientError::Rpc("invalid pool state".to_string()));
         }
         let sqrt_price = U256::from_words(felt_to_u128(&result[0])?, felt_to_u128(&result[1])?);
-        let tick = felt_to_i32(&result[2])?;
+        let tick = felt_to_tick(&result[2])?;
         let liquidity = felt_to_u128(&result[3])?;
         let fee0_low = felt_to_u128(&result[4])?;
         let fee0_high = felt_to_u128(&result[5])?;
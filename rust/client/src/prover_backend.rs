@@ -0,0 +1,133 @@
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+use zylith_prover::{LpWitnessInputs, ProofCalldata, SwapWitnessInputs};
+
+use crate::error::ClientError;
+
+pub type ProofFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<ProofCalldata, ClientError>> + Send + 'a>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidityCircuit {
+    Add,
+    Remove,
+    Claim,
+}
+
+// Turns witness inputs into proof calldata. The client only needs these two entry points;
+// witness building, quoting and path fetching stay in the client.
+pub trait ProverBackend: Send + Sync {
+    fn prove_swap<'a>(
+        &'a self,
+        witness: SwapWitnessInputs,
+        circuit_dir: &'a Path,
+        exact_out: bool,
+    ) -> ProofFuture<'a>;
+
+    fn prove_liquidity<'a>(
+        &'a self,
+        circuit: LiquidityCircuit,
+        witness: LpWitnessInputs,
+        circuit_dir: &'a Path,
+    ) -> ProofFuture<'a>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SnarkjsProver;
+
+impl ProverBackend for SnarkjsProver {
+    fn prove_swap<'a>(
+        &'a self,
+        witness: SwapWitnessInputs,
+        circuit_dir: &'a Path,
+        exact_out: bool,
+    ) -> ProofFuture<'a> {
+        Box::pin(async move {
+            let result = if exact_out {
+                zylith_prover::prove_swap_exact_out(witness, circuit_dir).await
+            } else {
+                zylith_prover::prove_swap(witness, circuit_dir).await
+            };
+            result.map_err(|e| ClientError::Prover(e.to_string()))
+        })
+    }
+
+    fn prove_liquidity<'a>(
+        &'a self,
+        circuit: LiquidityCircuit,
+        witness: LpWitnessInputs,
+        circuit_dir: &'a Path,
+    ) -> ProofFuture<'a> {
+        Box::pin(async move {
+            let result = match circuit {
+                LiquidityCircuit::Add => zylith_prover::prove_lp_add(witness, circuit_dir).await,
+                LiquidityCircuit::Remove => {
+                    zylith_prover::prove_lp_remove(witness, circuit_dir).await
+                }
+                LiquidityCircuit::Claim => {
+                    zylith_prover::prove_lp_claim(witness, circuit_dir).await
+                }
+            };
+            result.map_err(|e| ClientError::Prover(e.to_string()))
+        })
+    }
+}
+
+// Returns the same canned calldata for every circuit, so the surrounding orchestration can be
+// exercised without artifacts or snarkjs.
+#[cfg(any(test, feature = "test-stubs"))]
+#[derive(Debug, Clone)]
+pub struct StubProver {
+    pub calldata: ProofCalldata,
+}
+
+#[cfg(any(test, feature = "test-stubs"))]
+impl StubProver {
+    pub fn new(calldata: ProofCalldata) -> Self {
+        Self { calldata }
+    }
+}
+
+#[cfg(any(test, feature = "test-stubs"))]
+impl ProverBackend for StubProver {
+    fn prove_swap<'a>(
+        &'a self,
+        _witness: SwapWitnessInputs,
+        _circuit_dir: &'a Path,
+        _exact_out: bool,
+    ) -> ProofFuture<'a> {
+        let calldata = self.calldata.clone();
+        Box::pin(async move { Ok(calldata) })
+    }
+
+    fn prove_liquidity<'a>(
+        &'a self,
+        _circuit: LiquidityCircuit,
+        _witness: LpWitnessInputs,
+        _circuit_dir: &'a Path,
+    ) -> ProofFuture<'a> {
+        let calldata = self.calldata.clone();
+        Box::pin(async move { Ok(calldata) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stub_prover_returns_canned_calldata() {
+        let stub = StubProver::new(ProofCalldata::new(vec!["0x1".to_string()]));
+        let proof = stub
+            .prove_liquidity(
+                LiquidityCircuit::Claim,
+                LpWitnessInputs::default(),
+                Path::new("unused"),
+            )
+            .await
+            .expect("stub proof");
+        assert_eq!(proof.full_proof, vec!["0x1".to_string()]);
+    }
+}
@@ -1,18 +1,26 @@
-use num_bigint::BigUint;
-use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
 use starknet::accounts::ConnectedAccount;
+use starknet::core::types::requests::CallRequest;
 use starknet::core::types::{
-    BlockId, BlockTag, Call, ExecutionResult, Felt, FunctionCall, TransactionStatus, U256,
+    BlockId, BlockTag, Call, ExecutionResult, FeeEstimate, Felt, FunctionCall, ReceiptBlock,
+    TransactionStatus, U256,
 };
-use starknet::core::utils::get_selector_from_name;
-use starknet::providers::Provider;
-use tokio::time::{sleep, Duration};
+use starknet::core::utils::{get_selector_from_name, parse_cairo_short_string};
+use starknet::providers::{Provider, ProviderError, ProviderRequestData, ProviderResponseData};
+use tokio::time::{sleep, Duration, Instant};
 
-use crate::client::{PoolConfig, RetryConfig};
+use crate::amount::SignedAmount;
+use crate::client::{decode_pool_config, decode_pool_state, PoolConfig, PoolState, RetryConfig};
 use crate::error::ClientError;
 use crate::generated_constants;
 use crate::notes::{compute_commitment, Note};
-use crate::utils::{felt_to_i32, felt_to_u128, parse_felt, Address};
+use crate::pool_cache::TtlCache;
+use crate::proofs::{u256_to_big, SwapProveResult};
+use crate::utils::{felt_to_tick, felt_to_u128, parse_felt, tick_to_felt, Address};
 use starknet_crypto::poseidon_hash;
 use zylith_prover::ProofCalldata;
 
@@ -21,6 +29,9 @@ pub type TxHash = Felt;
 const ASP_TIMEOUT_SECS: u64 = 60;
 const TX_POLL_MAX_ATTEMPTS: usize = 240;
 const TX_POLL_DELAY_MS: u64 = 500;
+// How long receipt helpers without an explicit timeout wait, matching the status poller.
+pub(crate) const TX_RECEIPT_TIMEOUT: Duration =
+    Duration::from_millis(TX_POLL_DELAY_MS * TX_POLL_MAX_ATTEMPTS as u64);
 
 fn asp_timeout() -> Result<Duration, ClientError> {
     if let Ok(value) = std::env::var("ZYLITH_ASP_TIMEOUT_SECS") {
@@ -46,18 +57,61 @@ pub(crate) fn asp_client() -> Result<reqwest::Client, ClientError> {
         .map_err(|err| ClientError::Asp(err.to_string()))
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapDirection {
+    ZeroForOne,
+    OneForZero,
+}
+
+impl SwapDirection {
+    pub fn is_zero_for_one(self) -> bool {
+        matches!(self, SwapDirection::ZeroForOne)
+    }
+
+    pub fn input_token_id(self) -> u8 {
+        match self {
+            SwapDirection::ZeroForOne => 0,
+            SwapDirection::OneForZero => 1,
+        }
+    }
+
+    pub fn output_token_id(self) -> u8 {
+        1 - self.input_token_id()
+    }
+
+    // The pool quotes against the specified token: the input for exact-in, the output for exact-out.
+    pub fn quote_is_token1(self, exact_out: bool) -> bool {
+        let specified = if exact_out {
+            self.output_token_id()
+        } else {
+            self.input_token_id()
+        };
+        specified == 1
+    }
+}
+
+impl From<bool> for SwapDirection {
+    fn from(zero_for_one: bool) -> Self {
+        if zero_for_one {
+            SwapDirection::ZeroForOne
+        } else {
+            SwapDirection::OneForZero
+        }
+    }
+}
+
+impl From<SwapDirection> for bool {
+    fn from(direction: SwapDirection) -> Self {
+        direction.is_zero_for_one()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SwapRequest {
     pub notes: Vec<Note>,
     pub token_id: u8,
     pub amount_in: u128,
-    pub zero_for_one: bool,
-}
-
-#[derive(Debug, Clone)]
-pub struct SignedAmount {
-    pub mag: u128,
-    pub sign: bool,
+    pub direction: SwapDirection,
 }
 
 #[derive(Debug, Clone)]
@@ -77,6 +131,141 @@ pub struct SwapResult {
     pub liquidity_after: u128,
 }
 
+impl SwapResult {
+    // Record of a quoted swap; amounts come from the signed pool deltas (input positive,
+    // output negative). `tx_hash` is left empty until the swap is submitted.
+    pub fn to_record(&self, direction: SwapDirection) -> SwapRecord {
+        let (delta_in, delta_out) = if direction.is_zero_for_one() {
+            (self.delta_amount0, self.delta_amount1)
+        } else {
+            (self.delta_amount1, self.delta_amount0)
+        };
+        let mut record = SwapRecord::new(direction, delta_in.mag, delta_in.mag, delta_out.mag);
+        record.tick_after = Some(self.tick_after);
+        record
+    }
+}
+
+// Price summary for a quoted swap. Mid-prices are token1 per token0 in raw units, derived
+// from the pool's Q128.128 sqrt price; the effective price is amount_out per amount_in.
+#[derive(Debug, Clone)]
+pub struct SwapReport {
+    pub quote: SwapResult,
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub effective_price: f64,
+    pub mid_price_before: f64,
+    pub mid_price_after: f64,
+    // Absolute move of the mid-price relative to `mid_price_before`, in percent.
+    pub price_impact_pct: f64,
+}
+
+impl SwapReport {
+    pub fn new(sqrt_price_before: U256, quote: SwapResult) -> Self {
+        let (delta_in, delta_out) = if quote.delta_amount0.is_negative() {
+            (quote.delta_amount1, quote.delta_amount0)
+        } else {
+            (quote.delta_amount0, quote.delta_amount1)
+        };
+        let effective_price = if delta_in.mag == 0 {
+            0.0
+        } else {
+            delta_out.mag as f64 / delta_in.mag as f64
+        };
+        let mid_price_before = sqrt_price_to_price(&sqrt_price_before);
+        let mid_price_after = sqrt_price_to_price(&quote.sqrt_price_after);
+        let price_impact_pct = if mid_price_before == 0.0 {
+            0.0
+        } else {
+            (mid_price_after - mid_price_before).abs() / mid_price_before * 100.0
+        };
+        Self {
+            amount_in: delta_in.mag,
+            amount_out: delta_out.mag,
+            effective_price,
+            mid_price_before,
+            mid_price_after,
+            price_impact_pct,
+            quote,
+        }
+    }
+}
+
+fn sqrt_price_to_price(sqrt_price: &U256) -> f64 {
+    let sqrt = u256_to_big(sqrt_price).to_f64().unwrap_or(f64::INFINITY) / 2f64.powi(128);
+    sqrt * sqrt
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapRecord {
+    pub timestamp: u64,
+    pub tx_hash: Option<String>,
+    pub zero_for_one: bool,
+    pub token_in: Option<String>,
+    pub token_out: Option<String>,
+    pub input_total: u128,
+    pub amount_in_consumed: u128,
+    pub amount_out: u128,
+    // amount_out per unit of amount_in_consumed, in raw token units.
+    pub effective_price: f64,
+    pub tick_after: Option<i32>,
+}
+
+impl SwapRecord {
+    pub const CSV_HEADER: &'static str = "timestamp,tx_hash,zero_for_one,token_in,token_out,input_total,amount_in_consumed,amount_out,effective_price,tick_after";
+
+    pub(crate) fn new(
+        direction: SwapDirection,
+        input_total: u128,
+        amount_in_consumed: u128,
+        amount_out: u128,
+    ) -> Self {
+        let effective_price = if amount_in_consumed == 0 {
+            0.0
+        } else {
+            amount_out as f64 / amount_in_consumed as f64
+        };
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0),
+            tx_hash: None,
+            zero_for_one: direction.is_zero_for_one(),
+            token_in: None,
+            token_out: None,
+            input_total,
+            amount_in_consumed,
+            amount_out,
+            effective_price,
+            tick_after: None,
+        }
+    }
+
+    pub fn with_tx_hash(mut self, tx_hash: TxHash) -> Self {
+        self.tx_hash = Some(felt_to_hex(tx_hash));
+        self
+    }
+
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            self.timestamp,
+            self.tx_hash.as_deref().unwrap_or(""),
+            self.zero_for_one,
+            self.token_in.as_deref().unwrap_or(""),
+            self.token_out.as_deref().unwrap_or(""),
+            self.input_total,
+            self.amount_in_consumed,
+            self.amount_out,
+            self.effective_price,
+            self.tick_after
+                .map(|tick| tick.to_string())
+                .unwrap_or_default(),
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SwapStepQuote {
     pub sqrt_price_next: U256,
@@ -106,6 +295,68 @@ pub struct SwapStepsQuote {
     pub steps: Vec<SwapStepQuote>,
 }
 
+impl SwapStepsQuote {
+    // The checks `prove_swap` runs before building a witness; `max_steps` is the circuit's
+    // fixed step count (`MAX_SWAP_STEPS` for the bundled artifacts).
+    pub fn validate(&self, max_steps: usize) -> Result<(), ClientError> {
+        if self.sqrt_price_start == U256::from(0u128) {
+            return Err(ClientError::InvalidInput(
+                "swap quote sqrt_price_start is zero".to_string(),
+            ));
+        }
+        if self
+            .steps
+            .iter()
+            .any(|step| step.sqrt_price_limit == U256::from(0u128))
+        {
+            return Err(ClientError::InvalidInput(
+                "swap quote sqrt_price_limit is zero".to_string(),
+            ));
+        }
+        if self.steps.len() != max_steps {
+            return Err(ClientError::Rpc(format!(
+                "unexpected swap steps length: expected {max_steps}, got {}",
+                self.steps.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Checks that a swap quote carries exactly `MAX_SWAP_STEPS` steps, the fixed length the
+/// bundled swap circuits are compiled for. Shorter quotes must be padded before proving.
+///
+/// ```
+/// use zylith_client::{assert_swap_steps_len, MAX_SWAP_STEPS};
+///
+/// assert_eq!(MAX_SWAP_STEPS, 16);
+/// assert!(assert_swap_steps_len(MAX_SWAP_STEPS).is_ok());
+/// assert!(assert_swap_steps_len(MAX_SWAP_STEPS - 1).is_err());
+/// ```
+pub fn assert_swap_steps_len(len: usize) -> Result<(), ClientError> {
+    if len != generated_constants::MAX_SWAP_STEPS {
+        return Err(ClientError::InvalidInput(format!(
+            "unexpected swap steps length: expected MAX_SWAP_STEPS ({}), got {len}",
+            generated_constants::MAX_SWAP_STEPS
+        )));
+    }
+    Ok(())
+}
+
+// Where a submitted transaction landed and whether its execution succeeded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxReceipt {
+    pub tx_hash: TxHash,
+    pub block_number: u64,
+    pub revert_reason: Option<String>,
+}
+
+impl TxReceipt {
+    pub fn succeeded(&self) -> bool {
+        self.revert_reason.is_none()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MerklePath {
     pub token: Address,
@@ -116,12 +367,71 @@ pub struct MerklePath {
     pub indices: Vec<bool>,
 }
 
+impl MerklePath {
+    // The root reached by folding `path` up from `commitment`, whatever `root` claims.
+    pub fn compute_root(&self) -> Result<Felt, ClientError> {
+        compute_merkle_root(self.commitment, &self.path, &self.indices)
+    }
+
+    // Whether folding `path` up from `commitment` along `indices` reaches `root`, with the
+    // orientation bits matching `leaf_index`.
+    pub fn verify(&self) -> bool {
+        verify_merkle_path(
+            self.commitment,
+            self.leaf_index,
+            &self.path,
+            &self.indices,
+            self.root,
+        )
+    }
+}
+
+// Every pool read a liquidity add depends on, fetched together by `fetch_liquidity_context`.
+#[derive(Debug, Clone)]
+pub struct LiquidityContext {
+    pub sqrt_ratio_lower: U256,
+    pub sqrt_ratio_upper: U256,
+    pub fee_growth_inside_0: U256,
+    pub fee_growth_inside_1: U256,
+    pub pool_config: PoolConfig,
+    pub pool_state: PoolState,
+}
+
 #[derive(Clone)]
 pub struct SwapClient<A: ConnectedAccount + Sync> {
     pub account: A,
     pub pool_address: Address,
     pub asp_url: String,
     pub retry: RetryConfig,
+    pub(crate) token_metadata: Arc<TtlCache<TokenMetadata>>,
+}
+
+// ERC20 metadata for rendering amounts. `symbol` and `name` are optional views, so tokens that
+// do not implement them (or return something undecodable) report `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub address: Address,
+    pub decimals: u8,
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+}
+
+impl TokenMetadata {
+    // Raw base units to a decimal string in whole tokens, e.g. 1_500_000 at 6 decimals is "1.5".
+    pub fn format_amount(&self, amount: u128) -> String {
+        let decimals = usize::from(self.decimals);
+        if decimals == 0 {
+            return amount.to_string();
+        }
+        let digits = format!("{amount:0>width$}", width = decimals + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - decimals);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            whole.to_string()
+        } else {
+            format!("{whole}.{fraction}")
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -132,6 +442,13 @@ struct PathResponse {
     indices: Vec<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+struct PathsEntry {
+    commitment: String,
+    status: String,
+    path: Option<PathResponse>,
+}
+
 #[derive(Debug, Deserialize)]
 struct InsertPathResponse {
     token: String,
@@ -142,6 +459,20 @@ struct InsertPathResponse {
     indices: Vec<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+struct CommitmentResponse {
+    token: String,
+    leaf_index: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct NullifierResponse {
+    spent: bool,
+    // Older ASPs only report finalized spends.
+    #[serde(default)]
+    pending: bool,
+}
+
 #[derive(Debug, Deserialize)]
 struct RootAtResponse {
     token: String,
@@ -149,6 +480,23 @@ struct RootAtResponse {
     root: String,
 }
 
+/// Which ASP-maintained tree an insertion path refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertionTree {
+    Token(Address),
+    Position,
+}
+
+impl InsertionTree {
+    /// Tree key used by the ASP (`/insert_path`, `/root/latest?token=`, `/nullifier/:hash?token=`).
+    pub fn label(&self) -> String {
+        match self {
+            InsertionTree::Token(token) => felt_to_hex(*token),
+            InsertionTree::Position => "position".to_string(),
+        }
+    }
+}
+
 impl<A: ConnectedAccount + Sync> SwapClient<A> {
     pub fn new(account: A, pool_address: Address, asp_url: impl Into<String>) -> Self {
         Self {
@@ -156,6 +504,8 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
             pool_address,
             asp_url: asp_url.into(),
             retry: RetryConfig::default(),
+            // Token metadata is immutable, so entries never expire.
+            token_metadata: Arc::new(TtlCache::new(Some(Duration::MAX))),
         }
     }
 
@@ -163,7 +513,7 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         &self,
         notes: Vec<Note>,
         token_id: u8,
-        zero_for_one: bool,
+        direction: impl Into<SwapDirection>,
     ) -> Result<SwapRequest, ClientError> {
         if token_id > 1 {
             return Err(ClientError::InvalidInput("invalid token id".to_string()));
@@ -194,7 +544,7 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
             notes,
             token_id,
             amount_in,
-            zero_for_one,
+            direction: direction.into(),
         })
     }
 
@@ -204,90 +554,141 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         root_index: Option<u64>,
         root_hash: Option<Felt>,
     ) -> Result<MerklePath, ClientError> {
+        with_deadline(
+            self.retry.deadline,
+            "asp path request",
+            fetch_merkle_path(&self.asp_url, commitment, root_index, root_hash),
+        )
+        .await
+    }
+
+    // `tree` is the spent note's token, or `Position`. Spends the ASP has indexed but not yet
+    // finalized count too: wrongly refusing a note costs a retry, wrongly accepting one wastes a
+    // proof and a transaction. `false` still only covers what the ASP has indexed.
+    pub async fn is_nullifier_spent(
+        &self,
+        nullifier: Felt,
+        tree: InsertionTree,
+    ) -> Result<bool, ClientError> {
+        with_deadline(
+            self.retry.deadline,
+            "asp nullifier request",
+            fetch_nullifier_spent(&self.asp_url, nullifier, tree),
+        )
+        .await
+    }
+
+    // One ASP round-trip for several commitments, all proven against the same root. The outer
+    // error covers the request itself; a commitment the ASP cannot serve fails only its entry.
+    pub async fn fetch_merkle_paths(
+        &self,
+        commitments: &[Felt],
+        root_index: Option<u64>,
+        root_hash: Option<Felt>,
+    ) -> Result<Vec<Result<MerklePath, ClientError>>, ClientError> {
         if root_index.is_some() && root_hash.is_some() {
             return Err(ClientError::InvalidInput(
                 "root_index and root_hash are mutually exclusive".to_string(),
             ));
         }
-        let mut payload = serde_json::Map::new();
-        payload.insert(
-            "commitment".to_string(),
-            serde_json::Value::String(felt_to_hex(commitment)),
-        );
-        if let Some(index) = root_index {
-            payload.insert(
-                "root_index".to_string(),
-                serde_json::Value::Number(serde_json::Number::from(index)),
-            );
-        }
-        if let Some(hash) = root_hash {
-            payload.insert(
-                "root_hash".to_string(),
-                serde_json::Value::String(felt_to_hex(hash)),
-            );
-        }
-
-        let url = format!("{}/path", self.asp_url.trim_end_matches('/'));
-        let client = asp_client()?;
-        let response = client
-            .post(url)
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|err| ClientError::Asp(err.to_string()))?;
+        let payload = serde_json::json!({
+            "commitments": commitments.iter().map(|c| felt_to_hex(*c)).collect::<Vec<_>>(),
+            "root_index": root_index,
+            "root_hash": root_hash.map(felt_to_hex),
+        });
 
-        if !response.status().is_success() {
-            return Err(ClientError::Asp(format!(
-                "asp path error: {}",
-                response.status()
-            )));
-        }
-        let body: PathResponse = response.json().await.map_err(ClientError::from)?;
-        let token_label = body.token;
-        let token = match parse_felt(&token_label) {
-            Ok(token) => token,
-            Err(_) => {
-                if token_label == "position" {
-                    Felt::ZERO
-                } else {
-                    return Err(ClientError::Asp("invalid token".to_string()));
-                }
+        with_deadline(self.retry.deadline, "asp paths request", async {
+            let url = format!("{}/paths", self.asp_url.trim_end_matches('/'));
+            let client = asp_client()?;
+            let response = client
+                .post(url)
+                .json(&payload)
+                .send()
+                .await
+                .map_err(|err| ClientError::Asp(err.to_string()))?;
+            if !response.status().is_success() {
+                return Err(ClientError::Asp(format!(
+                    "asp paths error: {}",
+                    response.status()
+                )));
+            }
+            let entries: Vec<PathsEntry> = response.json().await.map_err(ClientError::from)?;
+            if entries.len() != commitments.len() {
+                return Err(ClientError::Asp("asp paths length mismatch".to_string()));
             }
-        };
-        let path = parse_hex_vec(&body.path)?;
-
-        let computed_root = compute_merkle_root(commitment, &path, &body.indices)?;
-        let root = if let Some(hash) = root_hash {
-            hash
-        } else if let Some(index) = root_index {
-            fetch_root_at(&self.asp_url, &token_label, index).await?
-        } else {
-            computed_root
-        };
-        if (root_hash.is_some() || root_index.is_some()) && root != computed_root {
-            return Err(ClientError::Asp("merkle path root mismatch".to_string()));
-        }
 
-        Ok(MerklePath {
-            token,
-            root,
-            commitment,
-            leaf_index: body.leaf_index,
-            path,
-            indices: body.indices,
+            let mut roots: HashMap<String, Felt> = HashMap::new();
+            let mut paths = Vec::with_capacity(entries.len());
+            for (commitment, entry) in commitments.iter().zip(entries) {
+                let body = match entry.path {
+                    Some(body) if entry.status == "ok" => body,
+                    _ => {
+                        paths.push(Err(ClientError::Asp(format!(
+                            "asp path error for {}: {}",
+                            entry.commitment, entry.status
+                        ))));
+                        continue;
+                    }
+                };
+                let root = match (root_hash, root_index) {
+                    (Some(hash), _) => Some(hash),
+                    (None, Some(index)) => match roots.get(&body.token) {
+                        Some(root) => Some(*root),
+                        None => {
+                            let root = fetch_root_at(&self.asp_url, &body.token, index).await?;
+                            roots.insert(body.token.clone(), root);
+                            Some(root)
+                        }
+                    },
+                    (None, None) => None,
+                };
+                paths.push(merkle_path_from_response(*commitment, body, root));
+            }
+            Ok(paths)
         })
+        .await
+    }
+
+    /// Path for the next free leaf of `tree`, i.e. where the next inserted commitment will land.
+    /// `commitment` is the current (empty) leaf value and `root` the tree root before insertion;
+    /// the ASP answers 409 while it is still catching up, which is retried per `self.retry`.
+    pub async fn fetch_tree_insertion_path(
+        &self,
+        tree: InsertionTree,
+    ) -> Result<MerklePath, ClientError> {
+        let token = match tree {
+            InsertionTree::Token(token) => token,
+            InsertionTree::Position => Felt::ZERO,
+        };
+        self.fetch_insertion_path_label(&tree.label(), token).await
     }
 
+    /// Insertion path in the note tree of `token` (swap outputs, change and liquidity outputs).
     pub async fn fetch_insertion_path(&self, token: Address) -> Result<MerklePath, ClientError> {
-        self.fetch_insertion_path_label(&felt_to_hex(token), token)
+        self.fetch_tree_insertion_path(InsertionTree::Token(token))
             .await
     }
 
+    /// Insertion path in the position tree (new position notes); `MerklePath::token` is zero.
     pub async fn fetch_position_insertion_path(&self) -> Result<MerklePath, ClientError> {
-        self.fetch_insertion_path_label("position", Felt::ZERO)
+        self.fetch_tree_insertion_path(InsertionTree::Position)
             .await
     }
 
+    pub async fn resolve_root_index(&self, token: Address, root: Felt) -> Result<u64, ClientError> {
+        let token_label = if token == Felt::ZERO {
+            "position".to_string()
+        } else {
+            felt_to_hex(token)
+        };
+        with_deadline(
+            self.retry.deadline,
+            "asp root lookup",
+            fetch_root_index(&self.asp_url, &token_label, root),
+        )
+        .await
+    }
+
     pub async fn simulate_swap(
         &self,
         request: SwapQuoteRequest,
@@ -329,7 +730,7 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         let amount1 = decode_signed_amount(&result[2], &result[3])?;
         let sqrt_price_after =
             U256::from_words(felt_to_u128(&result[4])?, felt_to_u128(&result[5])?);
-        let tick_after = felt_to_i32(&result[6])?;
+        let tick_after = felt_to_tick(&result[6])?;
         let liquidity_after = felt_to_u128(&result[7])?;
         Ok(SwapResult {
             delta_amount0: amount0,
@@ -340,9 +741,26 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         })
     }
 
+    pub async fn swap_report(&self, request: SwapQuoteRequest) -> Result<SwapReport, ClientError> {
+        let (sqrt_price_before, _tick, _liquidity) = self.get_pool_state().await?;
+        let quote = self.simulate_swap(request).await?;
+        Ok(SwapReport::new(sqrt_price_before, quote))
+    }
+
     pub async fn quote_swap_steps(
         &self,
         request: SwapQuoteRequest,
+    ) -> Result<SwapStepsQuote, ClientError> {
+        self.quote_swap_steps_at_block(request, BlockId::Tag(BlockTag::Latest))
+            .await
+    }
+
+    // The `_at_block` variants pin a read to one block, so a quote that combines pool config,
+    // state and tick ratios never mixes reads from different blocks.
+    pub async fn quote_swap_steps_at_block(
+        &self,
+        request: SwapQuoteRequest,
+        block_id: BlockId,
     ) -> Result<SwapStepsQuote, ClientError> {
         if self.pool_address == Felt::ZERO {
             return Err(ClientError::InvalidInput(
@@ -368,7 +786,7 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         let provider = self.account.provider();
         let result = with_retry(self.retry.clone(), || async {
             provider
-                .call(call.clone(), BlockId::Tag(BlockTag::Latest))
+                .call(call.clone(), block_id)
                 .await
                 .map_err(|err| ClientError::Rpc(err.to_string()))
         })
@@ -377,6 +795,15 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
     }
 
     pub async fn get_sqrt_ratio_at_tick(&self, tick: i32) -> Result<U256, ClientError> {
+        self.get_sqrt_ratio_at_tick_at_block(tick, BlockId::Tag(BlockTag::Latest))
+            .await
+    }
+
+    pub async fn get_sqrt_ratio_at_tick_at_block(
+        &self,
+        tick: i32,
+        block_id: BlockId,
+    ) -> Result<U256, ClientError> {
         if self.pool_address == Felt::ZERO {
             return Err(ClientError::InvalidInput(
                 "pool address is zero".to_string(),
@@ -387,12 +814,12 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         let call = FunctionCall {
             contract_address: self.pool_address,
             entry_point_selector: selector,
-            calldata: vec![i32_to_felt(tick)?],
+            calldata: vec![tick_to_felt(tick)],
         };
         let provider = self.account.provider();
         let result = with_retry(self.retry.clone(), || async {
             provider
-                .call(call.clone(), BlockId::Tag(BlockTag::Latest))
+                .call(call.clone(), block_id)
                 .await
                 .map_err(|err| ClientError::Rpc(err.to_string()))
         })
@@ -412,6 +839,18 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         output_proofs: &[MerklePath],
         exact_out: bool,
     ) -> Result<TxHash, ClientError> {
+        let call = self.swap_call(&proof, proofs, output_proofs, exact_out)?;
+        execute_with_retry(&self.account, call, self.retry.clone()).await
+    }
+
+    // The call `execute_swap` submits, for callers that want to inspect or batch it.
+    pub fn swap_call(
+        &self,
+        proof: &ProofCalldata,
+        proofs: &[MerklePath],
+        output_proofs: &[MerklePath],
+        exact_out: bool,
+    ) -> Result<Call, ClientError> {
         if proofs.is_empty() {
             return Err(ClientError::InvalidInput(
                 "missing merkle proofs".to_string(),
@@ -445,13 +884,29 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         }
         .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
 
-        let call = Call {
+        Ok(Call {
             to: self.pool_address,
             selector,
             calldata: full_calldata,
-        };
+        })
+    }
 
-        execute_with_retry(&self.account, call, self.retry.clone()).await
+    // Submits a proven swap and returns its accounting record with the tx hash filled in.
+    pub async fn execute_swap_recorded(
+        &self,
+        result: &SwapProveResult,
+        direction: SwapDirection,
+        exact_out: bool,
+    ) -> Result<SwapRecord, ClientError> {
+        let tx_hash = self
+            .execute_swap(
+                result.proof.clone(),
+                &result.input_proofs,
+                &result.output_proofs,
+                exact_out,
+            )
+            .await?;
+        Ok(result.to_record(direction).with_tx_hash(tx_hash))
     }
 
     pub async fn wait_for_transaction_success(&self, tx_hash: TxHash) -> Result<(), ClientError> {
@@ -479,7 +934,7 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
             }
 
             if attempt >= TX_POLL_MAX_ATTEMPTS {
-                return Err(ClientError::Rpc(
+                return Err(ClientError::Timeout(
                     "timed out waiting for transaction acceptance".to_string(),
                 ));
             }
@@ -488,6 +943,14 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
     }
 
     pub async fn get_pool_state(&self) -> Result<(U256, i32, u128), ClientError> {
+        self.get_pool_state_at_block(BlockId::Tag(BlockTag::Latest))
+            .await
+    }
+
+    pub async fn get_pool_state_at_block(
+        &self,
+        block_id: BlockId,
+    ) -> Result<(U256, i32, u128), ClientError> {
         let provider = self.account.provider();
         let selector = get_selector_from_name("get_pool_state")
             .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
@@ -498,7 +961,7 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         };
         let result = with_retry(self.retry.clone(), || async {
             provider
-                .call(call.clone(), BlockId::Tag(BlockTag::Latest))
+                .call(call.clone(), block_id)
                 .await
                 .map_err(|err| ClientError::Rpc(err.to_string()))
         })
@@ -508,12 +971,27 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
             return Err(ClientError::Rpc("invalid pool state".to_string()));
         }
         let sqrt_price = U256::from_words(felt_to_u128(&result[0])?, felt_to_u128(&result[1])?);
-        let tick = felt_to_i32(&result[2])?;
+        let tick = felt_to_tick(&result[2])?;
         let liquidity = felt_to_u128(&result[3])?;
         Ok((sqrt_price, tick, liquidity))
     }
 
+    // The pool has no pause switch; the only state in which it rejects every operation is
+    // before initialization, when the sqrt price is still zero.
+    pub async fn is_pool_active(&self) -> Result<bool, ClientError> {
+        let (sqrt_price, _tick, _liquidity) = self.get_pool_state().await?;
+        Ok(sqrt_price != U256::from(0u8))
+    }
+
     pub async fn get_pool_config(&self) -> Result<PoolConfig, ClientError> {
+        self.get_pool_config_at_block(BlockId::Tag(BlockTag::Latest))
+            .await
+    }
+
+    pub async fn get_pool_config_at_block(
+        &self,
+        block_id: BlockId,
+    ) -> Result<PoolConfig, ClientError> {
         let selector = get_selector_from_name("get_pool_config")
             .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
         let call = FunctionCall {
@@ -524,7 +1002,7 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
         let provider = self.account.provider();
         let result = with_retry(self.retry.clone(), || async {
             provider
-                .call(call.clone(), BlockId::Tag(BlockTag::Latest))
+                .call(call.clone(), block_id)
                 .await
                 .map_err(|err| ClientError::Rpc(err.to_string()))
         })
@@ -547,68 +1025,274 @@ impl<A: ConnectedAccount + Sync> SwapClient<A> {
             max_sqrt_ratio,
         })
     }
-}
 
-impl<A: ConnectedAccount + Sync> SwapClient<A> {
-    async fn fetch_insertion_path_label(
+    pub async fn get_token_metadata(&self, token: Address) -> Result<TokenMetadata, ClientError> {
+        self.token_metadata
+            .get_or_fetch(token, || self.fetch_token_metadata(token))
+            .await
+    }
+
+    async fn fetch_token_metadata(&self, token: Address) -> Result<TokenMetadata, ClientError> {
+        let decimals_call = FunctionCall {
+            contract_address: token,
+            entry_point_selector: get_selector_from_name("decimals")
+                .map_err(|err| ClientError::InvalidInput(err.to_string()))?,
+            calldata: Vec::new(),
+        };
+        let (decimals, symbol, name) = tokio::try_join!(
+            self.call_view(&decimals_call, BlockId::Tag(BlockTag::Latest)),
+            self.optional_string_view(token, "symbol"),
+            self.optional_string_view(token, "name"),
+        )?;
+        let decimals = decimals
+            .first()
+            .map(felt_to_u128)
+            .transpose()?
+            .and_then(|value| u8::try_from(value).ok())
+            .ok_or_else(|| {
+                ClientError::Rpc(format!("token {token:#x} returned invalid decimals"))
+            })?;
+        Ok(TokenMetadata {
+            address: token,
+            decimals,
+            symbol,
+            name,
+        })
+    }
+
+    // A contract-level error (missing entrypoint, revert) means the token has no such view; only
+    // transport failures are errors, so they are not cached as a missing value.
+    async fn optional_string_view(
         &self,
-        token_label: &str,
         token: Address,
-    ) -> Result<MerklePath, ClientError> {
-        let mut payload = serde_json::Map::new();
-        payload.insert(
-            "token".to_string(),
-            serde_json::Value::String(token_label.to_string()),
-        );
+        name: &str,
+    ) -> Result<Option<String>, ClientError> {
+        let call = FunctionCall {
+            contract_address: token,
+            entry_point_selector: get_selector_from_name(name)
+                .map_err(|err| ClientError::InvalidInput(err.to_string()))?,
+            calldata: Vec::new(),
+        };
+        match self
+            .account
+            .provider()
+            .call(call, BlockId::Tag(BlockTag::Latest))
+            .await
+        {
+            Ok(result) => Ok(decode_cairo_string(&result)),
+            Err(ProviderError::StarknetError(_)) => Ok(None),
+            Err(err) => Err(ClientError::Rpc(err.to_string())),
+        }
+    }
+}
 
-        let url = format!("{}/insert_path", self.asp_url.trim_end_matches('/'));
-        let mut attempt = 0usize;
-        loop {
-            let client = asp_client()?;
-            let response = client
-                .post(&url)
-                .json(&payload)
-                .send()
-                .await
-                .map_err(|err| ClientError::Asp(err.to_string()))?;
+// Decodes either a legacy felt252 short string or a serialized `ByteArray`
+// (`[full_words_len, ..full_words, pending_word, pending_word_len]`, 31 bytes per word).
+fn decode_cairo_string(felts: &[Felt]) -> Option<String> {
+    let value = match felts {
+        [short] => parse_cairo_short_string(short).ok()?,
+        [count, rest @ ..] => {
+            let count = usize::try_from(felt_to_u128(count).ok()?).ok()?;
+            if rest.len() != count + 2 {
+                return None;
+            }
+            let pending_len = usize::try_from(felt_to_u128(&rest[count + 1]).ok()?).ok()?;
+            if pending_len > 31 {
+                return None;
+            }
+            let mut bytes = Vec::with_capacity(count * 31 + pending_len);
+            for word in &rest[..count] {
+                bytes.extend_from_slice(&word.to_bytes_be()[1..]);
+            }
+            bytes.extend_from_slice(&rest[count].to_bytes_be()[32 - pending_len..]);
+            String::from_utf8(bytes).ok()?
+        }
+        [] => return None,
+    };
+    (!value.is_empty()).then_some(value)
+}
 
-            if response.status().as_u16() == 409 {
-                attempt += 1;
-                if attempt >= self.retry.max_attempts {
+impl<A: ConnectedAccount + Sync> SwapClient<A> {
+    // Sends the five reads as one JSON-RPC batch; RPCs that reject batches get the same calls
+    // issued concurrently instead.
+    pub async fn fetch_liquidity_context(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<LiquidityContext, ClientError> {
+        if self.pool_address == Felt::ZERO {
+            return Err(ClientError::InvalidInput(
+                "pool address is zero".to_string(),
+            ));
+        }
+        let view = |name: &str, calldata: Vec<Felt>| -> Result<FunctionCall, ClientError> {
+            Ok(FunctionCall {
+                contract_address: self.pool_address,
+                entry_point_selector: get_selector_from_name(name)
+                    .map_err(|err| ClientError::InvalidInput(err.to_string()))?,
+                calldata,
+            })
+        };
+        let calls = [
+            view("get_sqrt_ratio_at_tick", vec![tick_to_felt(tick_lower)])?,
+            view("get_sqrt_ratio_at_tick", vec![tick_to_felt(tick_upper)])?,
+            view(
+                "get_fee_growth_inside",
+                vec![tick_to_felt(tick_lower), tick_to_felt(tick_upper)],
+            )?,
+            view("get_pool_config", Vec::new())?,
+            view("get_pool_state", Vec::new())?,
+        ];
+        let block_id = BlockId::Tag(BlockTag::Latest);
+        let results = match self.batch_calls(&calls, block_id).await {
+            Ok(results) => results,
+            Err(_) => {
+                let (lower, upper, fee_growth, config, state) = tokio::try_join!(
+                    self.call_view(&calls[0], block_id),
+                    self.call_view(&calls[1], block_id),
+                    self.call_view(&calls[2], block_id),
+                    self.call_view(&calls[3], block_id),
+                    self.call_view(&calls[4], block_id),
+                )?;
+                vec![lower, upper, fee_growth, config, state]
+            }
+        };
+        let [lower, upper, fee_growth, config, state]: [Vec<Felt>; 5] = results
+            .try_into()
+            .map_err(|_| ClientError::Rpc("invalid batch response".to_string()))?;
+        if lower.len() < 2 || upper.len() < 2 {
+            return Err(ClientError::Rpc("invalid sqrt ratio response".to_string()));
+        }
+        if fee_growth.len() < 4 {
+            return Err(ClientError::Rpc("invalid fee growth response".to_string()));
+        }
+        Ok(LiquidityContext {
+            sqrt_ratio_lower: U256::from_words(felt_to_u128(&lower[0])?, felt_to_u128(&lower[1])?),
+            sqrt_ratio_upper: U256::from_words(felt_to_u128(&upper[0])?, felt_to_u128(&upper[1])?),
+            fee_growth_inside_0: U256::from_words(
+                felt_to_u128(&fee_growth[0])?,
+                felt_to_u128(&fee_growth[1])?,
+            ),
+            fee_growth_inside_1: U256::from_words(
+                felt_to_u128(&fee_growth[2])?,
+                felt_to_u128(&fee_growth[3])?,
+            ),
+            pool_config: decode_pool_config(&config)?,
+            pool_state: decode_pool_state(&state)?,
+        })
+    }
+
+    // Single attempt: a failure here usually means the RPC does not accept batches, and the
+    // caller falls back to individual calls that carry their own retries.
+    async fn batch_calls(
+        &self,
+        calls: &[FunctionCall],
+        block_id: BlockId,
+    ) -> Result<Vec<Vec<Felt>>, ClientError> {
+        let requests: Vec<ProviderRequestData> = calls
+            .iter()
+            .map(|call| {
+                ProviderRequestData::Call(CallRequest {
+                    request: call.clone(),
+                    block_id,
+                })
+            })
+            .collect();
+        let responses = self
+            .account
+            .provider()
+            .batch_requests(&requests)
+            .await
+            .map_err(|err| ClientError::Rpc(err.to_string()))?;
+        responses
+            .into_iter()
+            .map(|response| match response {
+                ProviderResponseData::Call(result) => Ok(result),
+                _ => Err(ClientError::Rpc("unexpected batch response".to_string())),
+            })
+            .collect()
+    }
+
+    async fn call_view(
+        &self,
+        call: &FunctionCall,
+        block_id: BlockId,
+    ) -> Result<Vec<Felt>, ClientError> {
+        let provider = self.account.provider();
+        with_retry(self.retry.clone(), || async {
+            provider
+                .call(call.clone(), block_id)
+                .await
+                .map_err(|err| ClientError::Rpc(err.to_string()))
+        })
+        .await
+    }
+
+    async fn fetch_insertion_path_label(
+        &self,
+        token_label: &str,
+        token: Address,
+    ) -> Result<MerklePath, ClientError> {
+        let mut payload = serde_json::Map::new();
+        payload.insert(
+            "token".to_string(),
+            serde_json::Value::String(token_label.to_string()),
+        );
+
+        let url = format!("{}/insert_path", self.asp_url.trim_end_matches('/'));
+        with_deadline(self.retry.deadline, "asp insert path request", async {
+            let mut attempt = 0usize;
+            loop {
+                let client = asp_client()?;
+                let response = client
+                    .post(&url)
+                    .json(&payload)
+                    .send()
+                    .await
+                    .map_err(|err| ClientError::Asp(err.to_string()))?;
+
+                if response.status().as_u16() == 409 {
+                    attempt += 1;
+                    if attempt >= self.retry.max_attempts {
+                        return Err(ClientError::Asp(format!(
+                            "asp insert path error: {}",
+                            response.status()
+                        )));
+                    }
+                    let delay = self
+                        .retry
+                        .delay_for_attempt(attempt, &mut rand::thread_rng());
+                    sleep(delay).await;
+                    continue;
+                }
+
+                if !response.status().is_success() {
                     return Err(ClientError::Asp(format!(
                         "asp insert path error: {}",
                         response.status()
                     )));
                 }
-                sleep(Duration::from_millis(self.retry.delay_ms)).await;
-                continue;
-            }
+                let body: InsertPathResponse = response.json().await.map_err(ClientError::from)?;
+                if body.token != token_label {
+                    return Err(ClientError::Asp("token mismatch".to_string()));
+                }
+                let root = parse_felt(&body.root)
+                    .map_err(|_| ClientError::Asp("invalid root".to_string()))?;
+                let commitment = parse_felt(&body.commitment)
+                    .map_err(|_| ClientError::Asp("invalid commitment".to_string()))?;
+                let path = parse_hex_vec(&body.path)?;
 
-            if !response.status().is_success() {
-                return Err(ClientError::Asp(format!(
-                    "asp insert path error: {}",
-                    response.status()
-                )));
+                return Ok(MerklePath {
+                    token,
+                    root,
+                    commitment,
+                    leaf_index: body.leaf_index,
+                    path,
+                    indices: body.indices,
+                });
             }
-            let body: InsertPathResponse = response.json().await.map_err(ClientError::from)?;
-            if body.token != token_label {
-                return Err(ClientError::Asp("token mismatch".to_string()));
-            }
-            let root =
-                parse_felt(&body.root).map_err(|_| ClientError::Asp("invalid root".to_string()))?;
-            let commitment = parse_felt(&body.commitment)
-                .map_err(|_| ClientError::Asp("invalid commitment".to_string()))?;
-            let path = parse_hex_vec(&body.path)?;
-
-            return Ok(MerklePath {
-                token,
-                root,
-                commitment,
-                leaf_index: body.leaf_index,
-                path,
-                indices: body.indices,
-            });
-        }
+        })
+        .await
     }
 }
 
@@ -616,9 +1300,18 @@ pub(crate) async fn execute_with_retry<A: ConnectedAccount + Sync>(
     account: &A,
     call: Call,
     retry: RetryConfig,
+) -> Result<TxHash, ClientError> {
+    execute_calls_with_retry(account, vec![call], retry).await
+}
+
+// Submits `calls` as a single multicall transaction.
+pub(crate) async fn execute_calls_with_retry<A: ConnectedAccount + Sync>(
+    account: &A,
+    calls: Vec<Call>,
+    retry: RetryConfig,
 ) -> Result<TxHash, ClientError> {
     with_retry(retry, || async {
-        let mut exec = account.execute_v3(vec![call.clone()]);
+        let mut exec = account.execute_v3(calls.clone());
         if let Ok(l1_gas) = std::env::var("ZYLITH_L1_GAS") {
             let parsed = l1_gas
                 .parse::<u64>()
@@ -663,21 +1356,68 @@ pub(crate) async fn execute_with_retry<A: ConnectedAccount + Sync>(
     .await
 }
 
-pub(crate) async fn with_retry<F, Fut, T>(retry: RetryConfig, mut f: F) -> Result<T, ClientError>
+// Estimates `calls` as a single transaction; the `ZYLITH_*_GAS` overrides only apply to
+// submission.
+pub(crate) async fn estimate_fee_with_retry<A: ConnectedAccount + Sync>(
+    account: &A,
+    calls: Vec<Call>,
+    retry: RetryConfig,
+) -> Result<FeeEstimate, ClientError> {
+    with_retry(retry, || async {
+        account
+            .execute_v3(calls.clone())
+            .estimate_fee()
+            .await
+            .map_err(|err| ClientError::Rpc(err.to_string()))
+    })
+    .await
+}
+
+pub(crate) async fn with_retry<F, Fut, T>(retry: RetryConfig, f: F) -> Result<T, ClientError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, ClientError>>,
+{
+    let deadline = retry.deadline;
+    with_deadline(deadline, "request", retry_attempts(retry, f)).await
+}
+
+pub(crate) async fn with_deadline<Fut, T>(
+    deadline: Option<Duration>,
+    operation: &str,
+    fut: Fut,
+) -> Result<T, ClientError>
+where
+    Fut: std::future::Future<Output = Result<T, ClientError>>,
+{
+    match deadline {
+        Some(limit) => tokio::time::timeout(limit, fut).await.unwrap_or_else(|_| {
+            Err(ClientError::Timeout(format!(
+                "{operation} timed out after {}ms",
+                limit.as_millis()
+            )))
+        }),
+        None => fut.await,
+    }
+}
+
+async fn retry_attempts<F, Fut, T>(retry: RetryConfig, mut f: F) -> Result<T, ClientError>
 where
     F: FnMut() -> Fut,
     Fut: std::future::Future<Output = Result<T, ClientError>>,
 {
     let mut attempt = 0usize;
     loop {
-        match f().await {
+        let result = with_deadline(retry.attempt_timeout, "attempt", f()).await;
+        match result {
             Ok(value) => return Ok(value),
             Err(err) => {
                 attempt += 1;
-                if attempt >= retry.max_attempts {
+                if !err.is_retryable() || attempt >= retry.max_attempts {
                     return Err(err);
                 }
-                sleep(Duration::from_millis(retry.delay_ms)).await;
+                let delay = retry.delay_for_attempt(attempt, &mut rand::thread_rng());
+                sleep(delay).await;
             }
         }
     }
@@ -727,6 +1467,26 @@ fn parse_hex_vec(values: &[String]) -> Result<Vec<Felt>, ClientError> {
         .collect()
 }
 
+// Client-side check of an ASP path before it goes into a witness; mirrors the ASP's
+// `MerkleTree::verify_path`, including that the orientation bits spell out `leaf_index`.
+pub fn verify_merkle_path(
+    leaf: Felt,
+    leaf_index: u64,
+    path: &[Felt],
+    indices: &[bool],
+    expected_root: Felt,
+) -> bool {
+    if path.len() < 64 && leaf_index >> path.len() != 0 {
+        return false;
+    }
+    let orientation_matches = indices
+        .iter()
+        .enumerate()
+        .all(|(level, is_right)| (level < 64 && (leaf_index >> level) & 1 == 1) == *is_right);
+    orientation_matches
+        && compute_merkle_root(leaf, path, indices).is_ok_and(|root| root == expected_root)
+}
+
 fn compute_merkle_root(leaf: Felt, path: &[Felt], indices: &[bool]) -> Result<Felt, ClientError> {
     if path.len() != indices.len() {
         return Err(ClientError::InvalidInput(
@@ -745,6 +1505,137 @@ fn compute_merkle_root(leaf: Felt, path: &[Felt], indices: &[bool]) -> Result<Fe
     Ok(hash)
 }
 
+// Without an expected root the path is trusted to prove the root it hashes to.
+fn merkle_path_from_response(
+    commitment: Felt,
+    body: PathResponse,
+    root: Option<Felt>,
+) -> Result<MerklePath, ClientError> {
+    let token = match parse_felt(&body.token) {
+        Ok(token) => token,
+        Err(_) => {
+            if body.token == "position" {
+                Felt::ZERO
+            } else {
+                return Err(ClientError::Asp("invalid token".to_string()));
+            }
+        }
+    };
+    let path = parse_hex_vec(&body.path)?;
+    let computed_root = compute_merkle_root(commitment, &path, &body.indices)?;
+    if root.is_some_and(|root| root != computed_root) {
+        return Err(ClientError::Asp("merkle path root mismatch".to_string()));
+    }
+
+    let path = MerklePath {
+        token,
+        root: computed_root,
+        commitment,
+        leaf_index: body.leaf_index,
+        path,
+        indices: body.indices,
+    };
+    // Folding the path always reproduces `computed_root`, but a path whose bits disagree with
+    // `leaf_index` would prove a different leaf than the one the ASP claims.
+    if !path.verify() {
+        return Err(ClientError::Asp("invalid merkle path".to_string()));
+    }
+    Ok(path)
+}
+
+// Polls until the transaction is included in a block; pre-confirmed receipts and lookup
+// failures (the node may not have seen the hash yet) keep polling until `timeout`.
+pub(crate) async fn wait_for_receipt<P: Provider + Sync>(
+    provider: &P,
+    tx_hash: TxHash,
+    timeout: Duration,
+) -> Result<TxReceipt, ClientError> {
+    let deadline = Instant::now() + timeout;
+    let mut last_error = None;
+    loop {
+        match provider.get_transaction_receipt(tx_hash).await {
+            Ok(receipt) => {
+                if let ReceiptBlock::Block { block_number, .. } = receipt.block {
+                    let revert_reason = match receipt.receipt.execution_result() {
+                        ExecutionResult::Succeeded => None,
+                        ExecutionResult::Reverted { reason } => Some(reason.clone()),
+                    };
+                    return Ok(TxReceipt {
+                        tx_hash,
+                        block_number,
+                        revert_reason,
+                    });
+                }
+                last_error = None;
+            }
+            Err(err) => last_error = Some(err.to_string()),
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            let message = match last_error {
+                Some(err) => format!("timed out waiting for transaction receipt: {err}"),
+                None => "timed out waiting for transaction receipt".to_string(),
+            };
+            return Err(ClientError::Timeout(message));
+        }
+        sleep(Duration::from_millis(TX_POLL_DELAY_MS).min(deadline - now)).await;
+    }
+}
+
+pub(crate) async fn fetch_merkle_path(
+    asp_url: &str,
+    commitment: Felt,
+    root_index: Option<u64>,
+    root_hash: Option<Felt>,
+) -> Result<MerklePath, ClientError> {
+    if root_index.is_some() && root_hash.is_some() {
+        return Err(ClientError::InvalidInput(
+            "root_index and root_hash are mutually exclusive".to_string(),
+        ));
+    }
+    let mut payload = serde_json::Map::new();
+    payload.insert(
+        "commitment".to_string(),
+        serde_json::Value::String(felt_to_hex(commitment)),
+    );
+    if let Some(index) = root_index {
+        payload.insert(
+            "root_index".to_string(),
+            serde_json::Value::Number(serde_json::Number::from(index)),
+        );
+    }
+    if let Some(hash) = root_hash {
+        payload.insert(
+            "root_hash".to_string(),
+            serde_json::Value::String(felt_to_hex(hash)),
+        );
+    }
+
+    let url = format!("{}/path", asp_url.trim_end_matches('/'));
+    let client = asp_client()?;
+    let response = client
+        .post(url)
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|err| ClientError::Asp(err.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(ClientError::Asp(format!(
+            "asp path error: {}",
+            response.status()
+        )));
+    }
+    let body: PathResponse = response.json().await.map_err(ClientError::from)?;
+    let root = match (root_hash, root_index) {
+        (Some(hash), _) => Some(hash),
+        (None, Some(index)) => Some(fetch_root_at(asp_url, &body.token, index).await?),
+        (None, None) => None,
+    };
+    merkle_path_from_response(commitment, body, root)
+}
+
 async fn fetch_root_at(asp_url: &str, token: &str, index: u64) -> Result<Felt, ClientError> {
     let url = format!(
         "{}/root/{}?token={}",
@@ -774,6 +1665,97 @@ async fn fetch_root_at(asp_url: &str, token: &str, index: u64) -> Result<Felt, C
     parse_felt(&body.root).map_err(|_| ClientError::Asp("invalid root".to_string()))
 }
 
+// The ASP looks the root up by hash; 404 means unknown or not yet final for this token.
+async fn fetch_root_index(asp_url: &str, token: &str, root: Felt) -> Result<u64, ClientError> {
+    let url = format!(
+        "{}/root/hash/{}?token={}",
+        asp_url.trim_end_matches('/'),
+        felt_to_hex(root),
+        token
+    );
+    let client = asp_client()?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| ClientError::Asp(err.to_string()))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(ClientError::Asp(format!(
+            "root {} not found",
+            felt_to_hex(root)
+        )));
+    }
+    if !response.status().is_success() {
+        return Err(ClientError::Asp(format!(
+            "asp root error: {}",
+            response.status()
+        )));
+    }
+    let body: RootAtResponse = response.json().await.map_err(ClientError::from)?;
+    if body.token != token {
+        return Err(ClientError::Asp("token mismatch".to_string()));
+    }
+    if parse_felt(&body.root).ok() != Some(root) {
+        return Err(ClientError::Asp("root mismatch".to_string()));
+    }
+    Ok(body.root_index)
+}
+
+pub(crate) async fn fetch_commitment_leaf(
+    asp_url: &str,
+    commitment: Felt,
+) -> Result<Option<(String, u64)>, ClientError> {
+    let url = format!(
+        "{}/commitment/{}",
+        asp_url.trim_end_matches('/'),
+        felt_to_hex(commitment)
+    );
+    let client = asp_client()?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| ClientError::Asp(err.to_string()))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(ClientError::Asp(format!(
+            "asp commitment error: {}",
+            response.status()
+        )));
+    }
+    let body: CommitmentResponse = response.json().await.map_err(ClientError::from)?;
+    Ok(Some((body.token, body.leaf_index)))
+}
+
+pub(crate) async fn fetch_nullifier_spent(
+    asp_url: &str,
+    nullifier: Felt,
+    tree: InsertionTree,
+) -> Result<bool, ClientError> {
+    let url = format!(
+        "{}/nullifier/{}?token={}",
+        asp_url.trim_end_matches('/'),
+        felt_to_hex(nullifier),
+        tree.label()
+    );
+    let client = asp_client()?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| ClientError::Asp(err.to_string()))?;
+    if !response.status().is_success() {
+        return Err(ClientError::Asp(format!(
+            "asp nullifier error: {}",
+            response.status()
+        )));
+    }
+    let body: NullifierResponse = response.json().await.map_err(ClientError::from)?;
+    Ok(body.spent || body.pending)
+}
+
 fn felt_to_hex(value: Felt) -> String {
     format!("0x{:x}", value)
 }
@@ -806,24 +1788,6 @@ fn u256_to_felts(value: &U256) -> (Felt, Felt) {
     (Felt::from(value.low()), Felt::from(value.high()))
 }
 
-fn i32_to_felt(value: i32) -> Result<Felt, ClientError> {
-    if value >= 0 {
-        Ok(Felt::from(value as u64))
-    } else {
-        let modulus = BigUint::parse_bytes(
-            b"800000000000011000000000000000000000000000000000000000000000001",
-            16,
-        )
-        .ok_or_else(|| ClientError::Crypto("invalid modulus".to_string()))?;
-        let mag = BigUint::from((-value) as u32);
-        let result = modulus - mag;
-        let bytes = result.to_bytes_be();
-        let mut out = [0u8; 32];
-        out[32 - bytes.len()..].copy_from_slice(&bytes);
-        Ok(Felt::from_bytes_be(&out))
-    }
-}
-
 fn parse_swap_steps_quote(result: &[Felt]) -> Result<SwapStepsQuote, ClientError> {
     let min_len = 18;
     if result.len() < min_len {
@@ -831,8 +1795,8 @@ fn parse_swap_steps_quote(result: &[Felt]) -> Result<SwapStepsQuote, ClientError
     }
     let sqrt_price_start = U256::from_words(felt_to_u128(&result[0])?, felt_to_u128(&result[1])?);
     let sqrt_price_end = U256::from_words(felt_to_u128(&result[2])?, felt_to_u128(&result[3])?);
-    let tick_start = felt_to_i32(&result[4])?;
-    let tick_end = felt_to_i32(&result[5])?;
+    let tick_start = felt_to_tick(&result[4])?;
+    let tick_end = felt_to_tick(&result[5])?;
     let liquidity_start = felt_to_u128(&result[6])?;
     let liquidity_end = felt_to_u128(&result[7])?;
     let fee_growth_global_0_before =
@@ -866,7 +1830,7 @@ fn parse_swap_steps_quote(result: &[Felt]) -> Result<SwapStepsQuote, ClientError
             felt_to_u128(&result[idx + 2])?,
             felt_to_u128(&result[idx + 3])?,
         );
-        let tick_next = felt_to_i32(&result[idx + 4])?;
+        let tick_next = felt_to_tick(&result[idx + 4])?;
         let liquidity_net = U256::from_words(
             felt_to_u128(&result[idx + 5])?,
             felt_to_u128(&result[idx + 6])?,
@@ -914,9 +1878,29 @@ fn parse_swap_steps_quote(result: &[Felt]) -> Result<SwapStepsQuote, ClientError
 
 #[cfg(test)]
 mod tests {
-    use super::{serialize_merkle_proof, serialize_merkle_proofs, MerklePath};
+    use super::{
+        compute_merkle_root, decode_cairo_string, merkle_path_from_response,
+        serialize_merkle_proof, serialize_merkle_proofs, verify_merkle_path, wait_for_receipt,
+        with_retry, InsertionTree, MerklePath, PathResponse, SwapClient, SwapDirection, SwapRecord,
+        SwapReport, SwapResult, SwapStepQuote, SwapStepsQuote, TokenMetadata, TxReceipt,
+    };
+    use crate::amount::SignedAmount;
+    use crate::client::{RetryConfig, ZylithClient, ZylithConfig};
+    use crate::deposit::DepositRequest;
+    use crate::error::ClientError;
     use crate::generated_constants;
+    use crate::notes::generate_note_with_token_id;
+    use crate::proofs::SwapProveResult;
+    use crate::test_support::{dummy_account, spawn_json_server, UNREACHABLE_URL};
     use starknet::core::types::Felt;
+    use starknet::core::types::U256;
+    use starknet::core::utils::get_selector_from_name;
+    use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClient};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use url::Url;
+    use zylith_prover::ProofCalldata;
 
     #[test]
     fn serialize_merkle_proof_layout() {
@@ -957,4 +1941,721 @@ mod tests {
         let data = serialize_merkle_proofs(&[proof]).expect("serialize");
         assert_eq!(data[0], Felt::ONE);
     }
+
+    #[test]
+    fn required_root_rejects_mixed_roots() {
+        let proof = |root: u8| MerklePath {
+            token: Felt::from(1u8),
+            root: Felt::from(root),
+            commitment: Felt::from(20u8),
+            leaf_index: 0,
+            path: Vec::new(),
+            indices: Vec::new(),
+        };
+        let mut result = SwapProveResult {
+            proof: ProofCalldata::new(Vec::new()),
+            input_proofs: vec![proof(10), proof(10)],
+            output_proofs: Vec::new(),
+            output_note: None,
+            change_note: None,
+            amount_out: 0,
+            amount_in_consumed: 0,
+            selected_swap_steps: 0,
+        };
+        assert_eq!(result.required_root().expect("root"), Felt::from(10u8));
+        result.input_proofs.push(proof(11));
+        assert!(result.required_root().is_err());
+        result.input_proofs.clear();
+        assert!(result.required_root().is_err());
+    }
+
+    #[test]
+    fn insertion_tree_labels_match_asp_keys() {
+        assert_eq!(InsertionTree::Position.label(), "position");
+        assert_eq!(InsertionTree::Token(Felt::from(0xabu8)).label(), "0xab");
+    }
+
+    #[test]
+    fn swap_record_from_quote() {
+        let quote = SwapResult {
+            delta_amount0: SignedAmount::new(100, false),
+            delta_amount1: SignedAmount::new(250, true),
+            sqrt_price_after: U256::from(1u8),
+            tick_after: -5,
+            liquidity_after: 1,
+        };
+        let record = quote
+            .to_record(SwapDirection::ZeroForOne)
+            .with_tx_hash(Felt::from(0x1234u64));
+        assert_eq!(record.amount_in_consumed, 100);
+        assert_eq!(record.amount_out, 250);
+        assert_eq!(record.effective_price, 2.5);
+        let row = record.to_csv_row();
+        assert_eq!(
+            row.split(',').count(),
+            SwapRecord::CSV_HEADER.split(',').count()
+        );
+        assert!(row.ends_with(",100,100,250,2.5,-5"));
+        assert!(row.contains(",0x1234,true,"));
+    }
+
+    #[tokio::test]
+    async fn with_retry_stops_after_max_attempts() {
+        let retry = RetryConfig {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+            jitter: true,
+            ..RetryConfig::default()
+        };
+        let calls = AtomicUsize::new(0);
+        let result: Result<(), ClientError> = with_retry(retry.clone(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ClientError::Rpc("down".to_string()))
+        })
+        .await;
+        assert!(matches!(result, Err(ClientError::Rpc(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        calls.store(0, Ordering::SeqCst);
+        let value = with_retry(retry, || async {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(ClientError::Rpc("flaky".to_string()))
+            } else {
+                Ok(7u8)
+            }
+        })
+        .await
+        .expect("eventually succeeds");
+        assert_eq!(value, 7);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn with_retry_short_circuits_non_retryable_errors() {
+        let retry = RetryConfig {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+            ..RetryConfig::default()
+        };
+        let calls = AtomicUsize::new(0);
+        let result: Result<(), ClientError> = with_retry(retry, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ClientError::InvalidInput("bad request".to_string()))
+        })
+        .await;
+        assert!(matches!(result, Err(ClientError::InvalidInput(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn with_retry_deadline_bounds_all_attempts() {
+        let retry = RetryConfig {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_millis(50),
+            deadline: Some(Duration::from_millis(80)),
+            ..RetryConfig::default()
+        };
+        let result: Result<(), ClientError> = with_retry(retry, || async {
+            Err(ClientError::Rpc("down".to_string()))
+        })
+        .await;
+        assert!(matches!(result, Err(ClientError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn asp_fetch_times_out_against_slow_server() {
+        // Accepts the connection and never answers.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        std::thread::spawn(move || {
+            if let Ok((socket, _)) = listener.accept() {
+                std::thread::sleep(Duration::from_secs(5));
+                drop(socket);
+            }
+        });
+
+        let account = dummy_account(UNREACHABLE_URL);
+        let mut client = SwapClient::new(account, Felt::ONE, format!("http://{addr}"));
+        client.retry.deadline = Some(Duration::from_millis(100));
+        let result = client.fetch_merkle_path(Felt::from(7u8), None, None).await;
+        assert!(matches!(result, Err(ClientError::Timeout(_))));
+    }
+
+    #[test]
+    fn merkle_path_response_checks_expected_root() {
+        let commitment = Felt::from(11u8);
+        let siblings = vec![Felt::from(1u8), Felt::from(2u8)];
+        let indices = vec![false, true];
+        let root = compute_merkle_root(commitment, &siblings, &indices).expect("root");
+        let body = || PathResponse {
+            token: "position".to_string(),
+            leaf_index: 2,
+            path: vec!["0x1".to_string(), "0x2".to_string()],
+            indices: indices.clone(),
+        };
+
+        let path = merkle_path_from_response(commitment, body(), Some(root)).expect("path");
+        assert_eq!(path.token, Felt::ZERO);
+        assert_eq!(path.root, root);
+        assert_eq!(path.leaf_index, 2);
+
+        let unpinned = merkle_path_from_response(commitment, body(), None).expect("path");
+        assert_eq!(unpinned.root, root);
+
+        let err = merkle_path_from_response(commitment, body(), Some(root + Felt::ONE))
+            .expect_err("mismatch");
+        assert!(err.to_string().contains("root mismatch"));
+    }
+
+    #[test]
+    fn merkle_path_verify_rejects_tampering() {
+        let commitment = Felt::from(11u8);
+        let siblings = vec![Felt::from(1u8), Felt::from(2u8)];
+        let indices = vec![false, true];
+        let path = MerklePath {
+            token: Felt::ONE,
+            root: compute_merkle_root(commitment, &siblings, &indices).expect("root"),
+            commitment,
+            leaf_index: 2,
+            path: siblings,
+            indices,
+        };
+        assert!(path.verify());
+
+        assert_eq!(path.compute_root().expect("root"), path.root);
+
+        let mut tampered = path.clone();
+        tampered.path[1] += Felt::ONE;
+        assert!(!tampered.verify());
+        assert_ne!(tampered.compute_root().expect("root"), path.root);
+        let mut tampered = path.clone();
+        tampered.commitment += Felt::ONE;
+        assert!(!tampered.verify());
+        let mut tampered = path;
+        tampered.leaf_index = 3;
+        assert!(!tampered.verify());
+
+        // The ASP claims leaf 3, but the bits fold the commitment in as leaf 2.
+        let body = PathResponse {
+            token: "position".to_string(),
+            leaf_index: 3,
+            path: vec!["0x1".to_string(), "0x2".to_string()],
+            indices: vec![false, true],
+        };
+        let err = merkle_path_from_response(commitment, body, None).expect_err("tampered");
+        assert!(matches!(err, ClientError::Asp(msg) if msg == "invalid merkle path"));
+    }
+
+    #[test]
+    fn verify_merkle_path_checks_siblings_orientation_and_length() {
+        let leaf = Felt::from(5u8);
+        let siblings = vec![Felt::from(1u8), Felt::from(2u8), Felt::from(3u8)];
+        let indices = vec![true, false, true];
+        let root = compute_merkle_root(leaf, &siblings, &indices).expect("root");
+        assert!(verify_merkle_path(leaf, 5, &siblings, &indices, root));
+
+        let mut wrong_sibling = siblings.clone();
+        wrong_sibling[1] = Felt::from(9u8);
+        assert!(!verify_merkle_path(leaf, 5, &wrong_sibling, &indices, root));
+        assert!(!verify_merkle_path(leaf, 5, &siblings[..2], &indices, root));
+        assert!(!verify_merkle_path(leaf, 4, &siblings, &indices, root));
+        assert!(!verify_merkle_path(leaf, 13, &siblings, &indices, root));
+    }
+
+    fn quote_with_steps(count: usize) -> SwapStepsQuote {
+        let step = SwapStepQuote {
+            sqrt_price_next: U256::from(2u128),
+            sqrt_price_limit: U256::from(3u128),
+            tick_next: 0,
+            liquidity_net: U256::from(0u128),
+            fee_growth_global_0: U256::from(0u128),
+            fee_growth_global_1: U256::from(0u128),
+            amount_in: 0,
+            amount_out: 0,
+            fee_amount: 0,
+        };
+        SwapStepsQuote {
+            sqrt_price_start: U256::from(1u128),
+            sqrt_price_end: U256::from(2u128),
+            tick_start: 0,
+            tick_end: 0,
+            liquidity_start: 1,
+            liquidity_end: 1,
+            fee_growth_global_0_before: U256::from(0u128),
+            fee_growth_global_1_before: U256::from(0u128),
+            fee_growth_global_0_after: U256::from(0u128),
+            fee_growth_global_1_after: U256::from(0u128),
+            is_limited: false,
+            steps: vec![step; count],
+        }
+    }
+
+    #[test]
+    fn quote_validate_accepts_well_formed_quote() {
+        let max_steps = generated_constants::MAX_SWAP_STEPS;
+        quote_with_steps(max_steps)
+            .validate(max_steps)
+            .expect("valid quote");
+    }
+
+    #[test]
+    fn quote_validate_rejects_zero_start_price() {
+        let mut quote = quote_with_steps(4);
+        quote.sqrt_price_start = U256::from(0u128);
+        let err = quote.validate(4).expect_err("zero start");
+        assert!(matches!(err, ClientError::InvalidInput(msg) if msg.contains("sqrt_price_start")));
+    }
+
+    #[test]
+    fn quote_validate_rejects_zero_step_limit() {
+        let mut quote = quote_with_steps(4);
+        quote.steps[2].sqrt_price_limit = U256::from(0u128);
+        let err = quote.validate(4).expect_err("zero limit");
+        assert!(matches!(err, ClientError::InvalidInput(msg) if msg.contains("sqrt_price_limit")));
+    }
+
+    #[test]
+    fn quote_validate_rejects_wrong_step_count() {
+        let err = quote_with_steps(3).validate(4).expect_err("short quote");
+        assert!(matches!(err, ClientError::Rpc(msg) if msg.contains("expected 4, got 3")));
+    }
+
+    #[test]
+    fn swap_report_computes_price_impact() {
+        // Mid-price moves from 1.0 to 1.1^2 = 1.21 token1 per token0.
+        let quote = SwapResult {
+            delta_amount0: SignedAmount::new(990, true),
+            delta_amount1: SignedAmount::new(1_000, false),
+            sqrt_price_after: U256::from_words(u128::MAX / 10, 1),
+            tick_after: 1_906,
+            liquidity_after: 1_000_000,
+        };
+        let report = SwapReport::new(U256::from_words(0, 1), quote);
+        assert_eq!(report.amount_in, 1_000);
+        assert_eq!(report.amount_out, 990);
+        assert!((report.effective_price - 0.99).abs() < 1e-12);
+        assert!((report.mid_price_before - 1.0).abs() < 1e-12);
+        assert!((report.mid_price_after - 1.21).abs() < 1e-9);
+        assert!((report.price_impact_pct - 21.0).abs() < 1e-6);
+
+        let flat = SwapResult {
+            delta_amount0: SignedAmount::default(),
+            delta_amount1: SignedAmount::default(),
+            sqrt_price_after: U256::from_words(0, 0),
+            tick_after: 0,
+            liquidity_after: 0,
+        };
+        let report = SwapReport::new(U256::from_words(0, 0), flat);
+        assert_eq!(report.effective_price, 0.0);
+        assert_eq!(report.price_impact_pct, 0.0);
+    }
+
+    type RpcResponder = Arc<dyn Fn(serde_json::Value) -> serde_json::Value + Send + Sync>;
+
+    // Minimal keep-alive JSON-RPC endpoint answering pool views; counts HTTP round trips.
+    fn spawn_pool_rpc(accept_batches: bool) -> (String, Arc<AtomicUsize>) {
+        spawn_rpc(Arc::new(move |request| match request {
+            serde_json::Value::Array(items) if accept_batches => {
+                serde_json::Value::Array(items.iter().map(pool_rpc_result).collect())
+            }
+            serde_json::Value::Array(_) => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32600, "message": "batch requests are not supported" },
+            }),
+            single => pool_rpc_result(&single),
+        }))
+    }
+
+    fn spawn_rpc(respond: RpcResponder) -> (String, Arc<AtomicUsize>) {
+        spawn_json_server(Arc::new(move |request| respond(request.body)))
+    }
+
+    fn pool_rpc_result(request: &serde_json::Value) -> serde_json::Value {
+        let params = &request["params"];
+        let call = if params["request"].is_null() {
+            &params[0]
+        } else {
+            &params["request"]
+        };
+        let selector = Felt::from_hex(call["entry_point_selector"].as_str().expect("selector"))
+            .expect("selector felt");
+        let selector_of = |name| get_selector_from_name(name).expect("selector");
+        let result: Vec<String> = if selector == selector_of("get_sqrt_ratio_at_tick") {
+            vec![
+                call["calldata"][0].as_str().expect("tick").to_string(),
+                "0x0".to_string(),
+            ]
+        } else if selector == selector_of("get_fee_growth_inside") {
+            ["0x2", "0x0", "0x3", "0x0"].map(String::from).to_vec()
+        } else if selector == selector_of("get_pool_config") {
+            ["0xa", "0xb", "0xbb8", "0x1", "0x1", "0x0", "0x2", "0x0"]
+                .map(String::from)
+                .to_vec()
+        } else if selector == selector_of("get_pool_state") {
+            ["0x7", "0x0", "0x0", "0x64", "0x0", "0x0", "0x0", "0x0"]
+                .map(String::from)
+                .to_vec()
+        } else {
+            panic!("unexpected call {call}");
+        };
+        serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+    }
+
+    async fn liquidity_context_round_trips(accept_batches: bool) -> usize {
+        let (rpc_url, round_trips) = spawn_pool_rpc(accept_batches);
+        let account = dummy_account(&rpc_url);
+        let client = SwapClient::new(account, Felt::ONE, UNREACHABLE_URL.to_string());
+        let context = client
+            .fetch_liquidity_context(10, 20)
+            .await
+            .expect("liquidity context");
+        assert_eq!(context.sqrt_ratio_lower, U256::from_words(10, 0));
+        assert_eq!(context.sqrt_ratio_upper, U256::from_words(20, 0));
+        assert_eq!(context.fee_growth_inside_0, U256::from_words(2, 0));
+        assert_eq!(context.fee_growth_inside_1, U256::from_words(3, 0));
+        assert_eq!(context.pool_config.fee, 3000);
+        assert_eq!(context.pool_state.liquidity, 100);
+        round_trips.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn liquidity_context_batches_pool_reads() {
+        assert_eq!(liquidity_context_round_trips(true).await, 1);
+        // A rejected batch costs one extra round trip before the five individual reads.
+        assert_eq!(liquidity_context_round_trips(false).await, 6);
+    }
+
+    #[tokio::test]
+    async fn root_index_is_resolved_with_one_lookup() {
+        let (asp_url, served) = spawn_json_server(Arc::new(|request| {
+            assert!(request.path.starts_with("/root/hash/"), "{}", request.path);
+            serde_json::json!({
+                "token": "0x5",
+                "root_index": 41,
+                "root": "0x7",
+                "leaf_count": 41,
+            })
+        }));
+        let client = SwapClient::new(dummy_account(UNREACHABLE_URL), Felt::ONE, asp_url);
+
+        let index = client
+            .resolve_root_index(Felt::from(5u8), Felt::from(7u8))
+            .await
+            .expect("root index");
+        assert_eq!(index, 41);
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+
+        let err = client
+            .resolve_root_index(Felt::from(5u8), Felt::from(8u8))
+            .await
+            .expect_err("answer for another root");
+        assert!(err.to_string().contains("root mismatch"));
+    }
+
+    // Answers the first receipt lookup with "hash not found", the second with a pre-confirmed
+    // receipt and every later one with the receipt accepted in block 42.
+    fn receipt_rpc_result(lookup: usize, request: &serde_json::Value) -> serde_json::Value {
+        assert_eq!(request["method"], "starknet_getTransactionReceipt");
+        if lookup == 0 {
+            return serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": 29, "message": "Transaction hash not found" },
+            });
+        }
+        let mut receipt = serde_json::json!({
+            "type": "INVOKE",
+            "transaction_hash": "0x7",
+            "actual_fee": { "amount": "0x1", "unit": "FRI" },
+            "finality_status": "PRE_CONFIRMED",
+            "execution_status": "REVERTED",
+            "revert_reason": "insufficient liquidity",
+            "messages_sent": [],
+            "events": [],
+            "execution_resources": { "l1_gas": 0, "l1_data_gas": 0, "l2_gas": 0 },
+            "block_number": 42,
+        });
+        if lookup > 1 {
+            receipt["finality_status"] = "ACCEPTED_ON_L2".into();
+            receipt["block_hash"] = "0x99".into();
+        }
+        serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": receipt })
+    }
+
+    #[tokio::test]
+    async fn token_metadata_is_cached_and_tolerates_missing_symbol() {
+        let (rpc_url, round_trips) = spawn_rpc(Arc::new(|request| {
+            let params = &request["params"];
+            let call = if params["request"].is_null() {
+                &params[0]
+            } else {
+                &params["request"]
+            };
+            let selector = Felt::from_hex(call["entry_point_selector"].as_str().expect("selector"))
+                .expect("selector felt");
+            let selector_of = |name| get_selector_from_name(name).expect("selector");
+            let result = if selector == selector_of("decimals") {
+                serde_json::json!(["0x6"])
+            } else if selector == selector_of("name") {
+                // ByteArray "Wrapped Ether": no full words, 13 pending bytes.
+                serde_json::json!(["0x0", "0x5772617070656420457468657220", "0xd"])
+            } else {
+                return serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {
+                        "code": 40,
+                        "message": "Contract error",
+                        "data": { "revert_error": "Entry point not found in contract." },
+                    },
+                });
+            };
+            serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+        }));
+        let account = dummy_account(&rpc_url);
+        let client = SwapClient::new(account, Felt::ONE, UNREACHABLE_URL.to_string());
+        let token = Felt::from(0x7u8);
+        let metadata = client.get_token_metadata(token).await.expect("metadata");
+        assert_eq!(
+            metadata,
+            TokenMetadata {
+                address: token,
+                decimals: 6,
+                symbol: None,
+                name: Some("Wrapped Ether".to_string()),
+            }
+        );
+        assert_eq!(round_trips.load(Ordering::SeqCst), 3);
+        client.get_token_metadata(token).await.expect("cached");
+        assert_eq!(round_trips.load(Ordering::SeqCst), 3);
+
+        assert_eq!(metadata.format_amount(1_500_000), "1.5");
+        assert_eq!(metadata.format_amount(42), "0.000042");
+        assert_eq!(metadata.format_amount(7_000_000), "7");
+        assert_eq!(
+            decode_cairo_string(&[Felt::from_hex("0x455448").expect("felt")]),
+            Some("ETH".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_for_receipt_polls_until_included() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let (rpc_url, round_trips) = spawn_rpc(Arc::new(move |request| {
+            receipt_rpc_result(lookups.fetch_add(1, Ordering::SeqCst), &request)
+        }));
+        let provider = JsonRpcClient::new(HttpTransport::new(Url::parse(&rpc_url).expect("url")));
+
+        let receipt = wait_for_receipt(&provider, Felt::from(7u8), Duration::from_secs(10))
+            .await
+            .expect("receipt");
+        assert_eq!(
+            receipt,
+            TxReceipt {
+                tx_hash: Felt::from(7u8),
+                block_number: 42,
+                revert_reason: Some("insufficient liquidity".to_string()),
+            }
+        );
+        assert!(!receipt.succeeded());
+        assert_eq!(round_trips.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn swap_fee_is_estimated_for_the_submitted_call() {
+        let estimated_calldata = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = estimated_calldata.clone();
+        let (rpc_url, _) = spawn_rpc(Arc::new(move |request| {
+            let result = match request["method"].as_str() {
+                Some("starknet_getNonce") => serde_json::json!("0x0"),
+                Some("starknet_estimateFee") => {
+                    let params = &request["params"];
+                    let txs = if params["request"].is_null() {
+                        &params[0]
+                    } else {
+                        &params["request"]
+                    };
+                    *seen.lock().expect("lock") = txs[0]["calldata"]
+                        .as_array()
+                        .expect("calldata")
+                        .iter()
+                        .map(|value| Felt::from_hex(value.as_str().expect("hex")).expect("felt"))
+                        .collect::<Vec<_>>();
+                    serde_json::json!([{
+                        "l1_gas_consumed": "0x1",
+                        "l1_gas_price": "0x2",
+                        "l2_gas_consumed": "0x3",
+                        "l2_gas_price": "0x4",
+                        "l1_data_gas_consumed": "0x5",
+                        "l1_data_gas_price": "0x6",
+                        "overall_fee": "0x7d0",
+                        "unit": "FRI",
+                    }])
+                }
+                other => {
+                    return serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": -32601, "message": format!("unexpected {other:?}") },
+                    });
+                }
+            };
+            serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+        }));
+        let account = dummy_account(&rpc_url);
+        let pool = Felt::from(0x9u8);
+        let client = ZylithClient::new(ZylithConfig {
+            account,
+            asp_url: UNREACHABLE_URL.to_string(),
+            pool_address: pool,
+            shielded_notes_address: Felt::ZERO,
+            token0: Felt::ZERO,
+            token1: Felt::ZERO,
+            rpc_timeout: None,
+            request_timeout: Duration::from_secs(5),
+            pool_state_ttl: None,
+            check_nullifiers: false,
+        });
+        let input = MerklePath {
+            token: Felt::ONE,
+            root: Felt::ZERO,
+            commitment: Felt::ZERO,
+            leaf_index: 0,
+            path: vec![Felt::ZERO; generated_constants::TREE_HEIGHT],
+            indices: vec![false; generated_constants::TREE_HEIGHT],
+        };
+        let proof = ProofCalldata::new(vec!["0x1".to_string()]);
+
+        let estimate = client
+            .estimate_fee_for_swap(&proof, &[input], &[], false)
+            .await
+            .expect("estimate");
+        assert_eq!(estimate.overall_fee, 2000u128.into());
+        // Multicall encoding: call count, then the pool and `swap_private` selector.
+        let calldata = estimated_calldata.lock().expect("lock").clone();
+        assert_eq!(
+            calldata[..3],
+            [
+                Felt::ONE,
+                pool,
+                get_selector_from_name("swap_private").expect("selector")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn deposit_fee_is_estimated_for_the_submitted_calls() {
+        // Calldata of every estimated transaction, then of every submitted one.
+        let estimated = Arc::new(std::sync::Mutex::new(Vec::<Vec<Felt>>::new()));
+        let submitted = Arc::new(std::sync::Mutex::new(Vec::<Vec<Felt>>::new()));
+        let (seen_estimated, seen_submitted) = (estimated.clone(), submitted.clone());
+        let (rpc_url, _) = spawn_rpc(Arc::new(move |request| {
+            let params = &request["params"];
+            let calldata = |tx: &serde_json::Value| {
+                tx["calldata"]
+                    .as_array()
+                    .expect("calldata")
+                    .iter()
+                    .map(|value| Felt::from_hex(value.as_str().expect("hex")).expect("felt"))
+                    .collect::<Vec<_>>()
+            };
+            let result = match request["method"].as_str() {
+                Some("starknet_getNonce") => serde_json::json!("0x0"),
+                Some("starknet_estimateFee") => {
+                    let txs = if params["request"].is_null() {
+                        &params[0]
+                    } else {
+                        &params["request"]
+                    };
+                    seen_estimated.lock().expect("lock").push(calldata(&txs[0]));
+                    serde_json::json!([{
+                        "l1_gas_consumed": "0x1",
+                        "l1_gas_price": "0x2",
+                        "l2_gas_consumed": "0x3",
+                        "l2_gas_price": "0x4",
+                        "l1_data_gas_consumed": "0x5",
+                        "l1_data_gas_price": "0x6",
+                        "overall_fee": "0x7d0",
+                        "unit": "FRI",
+                    }])
+                }
+                Some("starknet_addInvokeTransaction") => {
+                    let tx = if params["invoke_transaction"].is_null() {
+                        &params[0]
+                    } else {
+                        &params["invoke_transaction"]
+                    };
+                    seen_submitted.lock().expect("lock").push(calldata(tx));
+                    serde_json::json!({ "transaction_hash": "0x123" })
+                }
+                other => {
+                    return serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": -32601, "message": format!("unexpected {other:?}") },
+                    });
+                }
+            };
+            serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+        }));
+        let account = dummy_account(&rpc_url);
+        let notes_address = Felt::from(0x50u8);
+        let token = Felt::from(0x70u8);
+        let client = ZylithClient::new(ZylithConfig {
+            account,
+            asp_url: UNREACHABLE_URL.to_string(),
+            pool_address: Felt::ONE,
+            shielded_notes_address: notes_address,
+            token0: token,
+            token1: Felt::from(0x71u8),
+            rpc_timeout: None,
+            request_timeout: Duration::from_secs(5),
+            pool_state_ttl: None,
+            check_nullifiers: false,
+        });
+        let request = DepositRequest {
+            note: generate_note_with_token_id(25, token, 0).expect("note"),
+            token_id: 0,
+            token_address: token,
+            proof: ProofCalldata::new(vec!["0x1".to_string(), "0x2".to_string()]),
+            insertion_proof: MerklePath {
+                token,
+                root: Felt::ZERO,
+                commitment: Felt::ZERO,
+                leaf_index: 0,
+                path: vec![Felt::ZERO; generated_constants::TREE_HEIGHT],
+                indices: vec![false; generated_constants::TREE_HEIGHT],
+            },
+            skip_if_deposited: false,
+        };
+
+        client
+            .estimate_fee_for_deposit(&request)
+            .await
+            .expect("estimate");
+        let result = client.deposit(request).await.expect("deposit");
+        assert_eq!(result.tx_hash(), Some(Felt::from(0x123u16)));
+
+        let estimated = estimated.lock().expect("lock").clone();
+        let submitted = submitted.lock().expect("lock").clone();
+        assert_eq!(
+            submitted.len(),
+            1,
+            "approve and deposit go out as one transaction"
+        );
+        assert_eq!(estimated[0], submitted[0]);
+        // Multicall encoding: two calls, the approval first.
+        assert_eq!(
+            submitted[0][..3],
+            [
+                Felt::TWO,
+                token,
+                get_selector_from_name("approve").expect("selector")
+            ]
+        );
+    }
 }
@@ -0,0 +1,106 @@
+// Fixtures shared by the unit tests that talk to a node or the ASP.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use starknet::accounts::{ExecutionEncoding, SingleOwnerAccount};
+use starknet::core::types::Felt;
+use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClient};
+use starknet::signers::{LocalWallet, SigningKey};
+use url::Url;
+
+// Nothing listens on port 1, so anything sent here fails fast.
+pub(crate) const UNREACHABLE_URL: &str = "http://127.0.0.1:1";
+
+pub(crate) type DummyAccount = SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>;
+
+// An account at address 1, signing with key 1, whose provider points at `url`.
+pub(crate) fn dummy_account(url: &str) -> DummyAccount {
+    let provider = JsonRpcClient::new(HttpTransport::new(Url::parse(url).expect("url")));
+    SingleOwnerAccount::new(
+        provider,
+        LocalWallet::from(SigningKey::from_secret_scalar(Felt::ONE)),
+        Felt::ONE,
+        Felt::ONE,
+        ExecutionEncoding::New,
+    )
+}
+
+pub(crate) struct JsonRequest {
+    pub path: String,
+    // `Null` when the request has no body.
+    pub body: serde_json::Value,
+}
+
+pub(crate) type JsonResponder = Arc<dyn Fn(JsonRequest) -> serde_json::Value + Send + Sync>;
+
+// Keep-alive HTTP server answering every request with `respond(request)` as a 200 JSON body.
+// Each connection gets its own thread, so concurrent requests can overlap. Returns the base
+// URL and a count of the requests served.
+pub(crate) fn spawn_json_server(respond: JsonResponder) -> (String, Arc<AtomicUsize>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    let served = Arc::new(AtomicUsize::new(0));
+    let counter = served.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let respond = respond.clone();
+            let counter = counter.clone();
+            std::thread::spawn(move || serve_json(stream, respond, counter));
+        }
+    });
+    (format!("http://{addr}"), served)
+}
+
+fn serve_json(mut stream: TcpStream, respond: JsonResponder, served: Arc<AtomicUsize>) {
+    let mut reader = BufReader::new(stream.try_clone().expect("clone"));
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        let path = line
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or_default()
+            .to_string();
+        let mut content_length = 0usize;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        let mut body = vec![0u8; content_length];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+        served.fetch_add(1, Ordering::SeqCst);
+        let body = if body.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_slice(&body).expect("json")
+        };
+        let response = respond(JsonRequest { path, body }).to_string();
+        let written = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            response.len(),
+            response
+        );
+        if written.is_err() {
+            return;
+        }
+    }
+}
@@ -4,6 +4,9 @@ use std::sync::{Mutex, OnceLock};
 use num_bigint::BigUint;
 use num_traits::{ToPrimitive, Zero};
 use starknet::core::types::{Event, Felt, TransactionReceipt, U256};
+use starknet::core::utils::{cairo_short_string_to_felt, get_selector_from_name};
+
+use zylith_events::CommitmentKind;
 
 use crate::error::ClientError;
 
@@ -46,6 +49,12 @@ pub fn poseidon_hash(inputs: &[Felt]) -> Result<Felt, ClientError> {
     bn254_to_felt(&hashed)
 }
 
+// Starknet's Poseidon sponge over the Stark field (Cairo's `poseidon_hash_span`), as opposed
+// to `poseidon_hash`, which is the BN254 Poseidon used inside the circuits.
+pub fn poseidon_hash_many(inputs: &[Felt]) -> Felt {
+    starknet_crypto::poseidon_hash_many(inputs)
+}
+
 pub fn felt252_to_u256(felt: Felt) -> U256 {
     U256::from(felt)
 }
@@ -64,7 +73,18 @@ pub fn felt_to_u128(value: &Felt) -> Result<u128, ClientError> {
     Ok(u128::from_be_bytes(buf))
 }
 
-pub fn felt_to_i32(value: &Felt) -> Result<i32, ClientError> {
+// Ticks are encoded as two's-complement felts: negative ticks wrap to `P - |tick|`, matching
+// how the contracts serialize `i32` into calldata.
+pub fn tick_to_felt(tick: i32) -> Felt {
+    let magnitude = Felt::from(tick.unsigned_abs());
+    if tick < 0 {
+        Felt::ZERO - magnitude
+    } else {
+        magnitude
+    }
+}
+
+pub fn felt_to_tick(value: &Felt) -> Result<i32, ClientError> {
     let modulus = stark_field_modulus()?;
     let as_big = felt_to_biguint(value);
     let max = BigUint::from(i32::MAX as u32);
@@ -98,21 +118,95 @@ pub fn felt_to_i32(value: &Felt) -> Result<i32, ClientError> {
 }
 
 pub trait StarknetEvent: Sized {
-    fn selector() -> Felt;
-    fn from_event(keys: &[Felt], data: &[Felt]) -> Option<Self>;
+    fn selectors() -> Vec<Felt>;
+    fn from_event(keys: &[Felt], data: &[Felt]) -> Result<Self, ClientError>;
 }
 
-pub fn parse_event<T: StarknetEvent>(receipt: &TransactionReceipt) -> Option<T> {
+// Returns the first event in the receipt whose selector `T` recognizes; a recognized event
+// with a payload that does not decode is an error rather than a miss.
+pub fn parse_event<T: StarknetEvent>(
+    receipt: &TransactionReceipt,
+) -> Result<Option<T>, ClientError> {
+    let selectors = T::selectors();
     for event in receipt_events(receipt) {
         let keys = &event.keys;
-        if keys.first().copied() == Some(T::selector()) {
-            return T::from_event(keys, &event.data);
+        if keys.first().is_some_and(|key| selectors.contains(key)) {
+            return T::from_event(keys, &event.data).map(Some);
         }
     }
-    None
+    Ok(None)
+}
+
+// A leaf appended to one of the shielded-notes Merkle trees. `token` is `None` for the
+// position-note tree, which is shared across tokens. The field layout lives in `zylith-events`,
+// which the ASP indexer decodes with as well; this is its `Felt`-typed view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitmentEvent {
+    pub token: Option<Address>,
+    pub leaf_index: u64,
+    pub commitment: Felt,
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+}
+
+impl CommitmentEvent {
+    pub fn deposit_selector() -> Felt {
+        commitment_selector(CommitmentKind::Deposit)
+    }
+
+    pub fn position_selector() -> Felt {
+        commitment_selector(CommitmentKind::Position)
+    }
+
+    pub fn decode(
+        keys: &[Felt],
+        data: &[Felt],
+        block_number: Option<u64>,
+    ) -> Result<Self, ClientError> {
+        let key = keys
+            .first()
+            .ok_or_else(|| ClientError::InvalidInput("event has no selector".to_string()))?;
+        let kind = CommitmentKind::ALL
+            .into_iter()
+            .find(|kind| commitment_selector(*kind) == *key)
+            .ok_or_else(|| {
+                ClientError::InvalidInput(format!("unexpected commitment event selector {key:#x}"))
+            })?;
+        let data: Vec<[u8; 32]> = data.iter().map(Felt::to_bytes_be).collect();
+        let event = zylith_events::CommitmentEvent::decode(kind, &data, block_number)
+            .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
+        Ok(Self {
+            token: event.token.map(|token| Felt::from_bytes_be(&token)),
+            leaf_index: event.leaf_index,
+            commitment: Felt::from_bytes_be(&event.commitment),
+            timestamp: event.timestamp,
+            block_number: event.block_number,
+        })
+    }
+}
+
+impl StarknetEvent for CommitmentEvent {
+    fn selectors() -> Vec<Felt> {
+        vec![Self::deposit_selector(), Self::position_selector()]
+    }
+
+    fn from_event(keys: &[Felt], data: &[Felt]) -> Result<Self, ClientError> {
+        Self::decode(keys, data, None)
+    }
 }
 
+fn commitment_selector(kind: CommitmentKind) -> Felt {
+    get_selector_from_name(kind.event_name()).expect("selector")
+}
+
+// Hex with `0x`, decimal, or a Cairo short string in single quotes (`'USDC'`).
 pub fn parse_felt(value: &str) -> Result<Felt, ClientError> {
+    if let Some(short) = value
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        return parse_felt_shortstring(short);
+    }
     if value.starts_with("0x") {
         Felt::from_hex(value).map_err(|_| ClientError::InvalidInput("invalid felt".to_string()))
     } else {
@@ -120,6 +214,12 @@ pub fn parse_felt(value: &str) -> Result<Felt, ClientError> {
     }
 }
 
+// ASCII of at most 31 bytes, big-endian packed the way Cairo encodes short string literals.
+pub fn parse_felt_shortstring(value: &str) -> Result<Felt, ClientError> {
+    cairo_short_string_to_felt(value)
+        .map_err(|err| ClientError::InvalidInput(format!("invalid short string: {err}")))
+}
+
 fn receipt_events(receipt: &TransactionReceipt) -> &[Event] {
     match receipt {
         TransactionReceipt::Invoke(inner) => &inner.events,
@@ -414,3 +514,202 @@ fn biguint_to_bytes(value: &BigUint) -> Result<[u8; 32], ClientError> {
     out[32 - bytes.len()..].copy_from_slice(&bytes);
     Ok(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        felt_to_tick, parse_felt, parse_felt_shortstring, poseidon_hash_many, tick_to_felt,
+        CommitmentEvent, StarknetEvent,
+    };
+    use crate::error::ClientError;
+    use crate::generated_constants;
+    use starknet::core::types::Felt;
+    use starknet_crypto::{poseidon_hash, poseidon_permute_comp};
+
+    // Mirrors Cairo's `poseidon_hash_span`: absorb pairs, then pad with a trailing 1.
+    fn cairo_sponge(inputs: &[Felt]) -> Felt {
+        let mut state = [Felt::ZERO; 3];
+        let mut chunks = inputs.chunks_exact(2);
+        for pair in &mut chunks {
+            state[0] += pair[0];
+            state[1] += pair[1];
+            poseidon_permute_comp(&mut state);
+        }
+        match chunks.remainder() {
+            [last] => {
+                state[0] += *last;
+                state[1] += Felt::ONE;
+            }
+            _ => state[0] += Felt::ONE,
+        }
+        poseidon_permute_comp(&mut state);
+        state[0]
+    }
+
+    #[test]
+    fn permutation_matches_contract_zero_leaf() {
+        // ZERO_LEAF_HASH is `hades_permutation(0, 0, 2).0` as evaluated by the contracts.
+        let zero_leaf = parse_felt(generated_constants::ZERO_LEAF_HASH_HEX).expect("constant");
+        assert_eq!(poseidon_hash(Felt::ZERO, Felt::ZERO), zero_leaf);
+        let mut state = [Felt::ZERO, Felt::ZERO, Felt::TWO];
+        poseidon_permute_comp(&mut state);
+        assert_eq!(state[0], zero_leaf);
+    }
+
+    #[test]
+    fn hash_many_matches_cairo_sponge() {
+        let inputs: Vec<Felt> = (1u64..=5).map(Felt::from).collect();
+        for len in 0..=inputs.len() {
+            assert_eq!(
+                poseidon_hash_many(&inputs[..len]),
+                cairo_sponge(&inputs[..len]),
+                "length {len}"
+            );
+        }
+        assert_ne!(
+            poseidon_hash_many(&[Felt::ZERO, Felt::ZERO]),
+            poseidon_hash(Felt::ZERO, Felt::ZERO)
+        );
+    }
+
+    #[test]
+    fn hash_many_matches_cairo_poseidon_hash_span() {
+        // `poseidon_hash_span(array![1, 2, 3].span())` and `[0xaa, 0xbb, 0xcc]` as evaluated
+        // by Cairo; the second vector also appears in the starknet-types-core test suite.
+        let vectors: [(&[u64], &str); 2] = [
+            (
+                &[1, 2, 3],
+                "0x2f0d8840bcf3bc629598d8a6cc80cb7c0d9e52d93dab244bbf9cd0dca0ad082",
+            ),
+            (
+                &[0xaa, 0xbb, 0xcc],
+                "0x2742e049f7e1613e4a014efeec0d742882a798ae0af8b8dd730358c23848775",
+            ),
+        ];
+        for (inputs, expected) in vectors {
+            let inputs: Vec<Felt> = inputs.iter().copied().map(Felt::from).collect();
+            assert_eq!(
+                poseidon_hash_many(&inputs),
+                parse_felt(expected).expect("felt"),
+                "{inputs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn tick_felt_round_trip_edges() {
+        assert_eq!(tick_to_felt(0), Felt::ZERO);
+        assert_eq!(tick_to_felt(-1), Felt::MAX);
+        assert_eq!(tick_to_felt(i32::MAX), Felt::from(i32::MAX as u32));
+        assert_eq!(tick_to_felt(i32::MIN), Felt::ZERO - Felt::from(1u64 << 31));
+        for tick in [
+            i32::MIN,
+            i32::MIN + 1,
+            -887_272,
+            -1,
+            0,
+            1,
+            887_272,
+            i32::MAX,
+        ] {
+            assert_eq!(
+                felt_to_tick(&tick_to_felt(tick)).expect("tick"),
+                tick,
+                "tick {tick}"
+            );
+        }
+    }
+
+    #[test]
+    fn felt_to_tick_rejects_out_of_range() {
+        assert!(felt_to_tick(&Felt::from(1u64 << 31)).is_err());
+        let below_min = Felt::ZERO - Felt::from((1u64 << 31) + 1);
+        assert!(felt_to_tick(&below_min).is_err());
+    }
+
+    #[test]
+    fn short_strings_pack_ascii_big_endian() {
+        assert_eq!(parse_felt_shortstring("").expect("empty"), Felt::ZERO);
+        assert_eq!(
+            parse_felt_shortstring("USDC").expect("usdc"),
+            Felt::from(0x5553_4443u64)
+        );
+        assert_eq!(
+            parse_felt("'USDC'").expect("quoted"),
+            parse_felt_shortstring("USDC").expect("usdc")
+        );
+
+        let max = "a".repeat(31);
+        let mut expected = [0u8; 32];
+        expected[1..].fill(b'a');
+        assert_eq!(
+            parse_felt_shortstring(&max).expect("31 bytes"),
+            Felt::from_bytes_be(&expected)
+        );
+        assert!(parse_felt_shortstring(&"a".repeat(32)).is_err());
+        assert!(parse_felt_shortstring("caf\u{e9}").is_err());
+    }
+
+    #[test]
+    fn commitment_events_decode_both_trees() {
+        let token = Felt::from(0x0494_5432u64);
+        let deposit = CommitmentEvent::decode(
+            &[CommitmentEvent::deposit_selector()],
+            &[
+                Felt::from(0xabcu64),
+                Felt::from(7u64),
+                token,
+                Felt::from(1_700_000_000u64),
+            ],
+            Some(42),
+        )
+        .expect("deposit");
+        assert_eq!(
+            deposit,
+            CommitmentEvent {
+                token: Some(token),
+                leaf_index: 7,
+                commitment: Felt::from(0xabcu64),
+                timestamp: 1_700_000_000,
+                block_number: Some(42),
+            }
+        );
+
+        let position = CommitmentEvent::from_event(
+            &[CommitmentEvent::position_selector()],
+            &[
+                Felt::from(0xdefu64),
+                Felt::from(3u64),
+                Felt::from(1_700_000_001u64),
+            ],
+        )
+        .expect("position");
+        assert_eq!(
+            position,
+            CommitmentEvent {
+                token: None,
+                leaf_index: 3,
+                commitment: Felt::from(0xdefu64),
+                timestamp: 1_700_000_001,
+                block_number: None,
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_commitment_events_are_rejected() {
+        let deposit = CommitmentEvent::deposit_selector();
+        // A position-shaped payload under the deposit selector is missing the timestamp.
+        let err = CommitmentEvent::decode(&[deposit], &[Felt::ONE, Felt::ONE, Felt::ONE], None)
+            .expect_err("short deposit");
+        assert!(matches!(err, ClientError::InvalidInput(msg) if msg.contains("Deposit")));
+        assert!(CommitmentEvent::decode(
+            &[deposit],
+            &[Felt::ONE, Felt::MAX, Felt::ONE, Felt::ONE],
+            None
+        )
+        .is_err());
+        assert!(CommitmentEvent::decode(&[], &[Felt::ONE], None).is_err());
+        assert!(CommitmentEvent::decode(&[Felt::ONE], &[Felt::ONE; 4], None).is_err());
+    }
+}
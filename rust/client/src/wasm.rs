@@ -0,0 +1,236 @@
+//! `wasm-bindgen` surface over the pure note primitives. Every value crosses the JS boundary as
+//! a string: 32-byte fields and felts as `0x` hex, amounts as decimal or `0x` hex.
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Felt, U256};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+use crate::error::ClientError;
+use crate::notes::{
+    self, parse_amount_field, parse_bytes32_field, EncryptedNote, Note, PositionNote,
+};
+use crate::utils::parse_felt;
+
+const ENCRYPTED_HEADER_LEN: usize = 32 + 24 + 16;
+
+#[wasm_bindgen(js_name = Note)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmNote {
+    secret: String,
+    nullifier: String,
+    amount: String,
+    token: String,
+}
+
+#[wasm_bindgen(js_class = Note)]
+impl WasmNote {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        secret: String,
+        nullifier: String,
+        amount: String,
+        token: String,
+    ) -> Result<WasmNote, JsError> {
+        let note = WasmNote {
+            secret,
+            nullifier,
+            amount,
+            token,
+        };
+        note.to_note()?;
+        Ok(note)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn secret(&self) -> String {
+        self.secret.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn nullifier(&self) -> String {
+        self.nullifier.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn amount(&self) -> String {
+        self.amount.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn token(&self) -> String {
+        self.token.clone()
+    }
+
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    #[wasm_bindgen(js_name = fromJSON)]
+    pub fn from_json(json: &str) -> Result<WasmNote, JsError> {
+        let note: WasmNote = serde_json::from_str(json)?;
+        note.to_note()?;
+        Ok(note)
+    }
+}
+
+impl WasmNote {
+    fn to_note(&self) -> Result<Note, ClientError> {
+        Note::try_from_hex_fields(&self.secret, &self.nullifier, &self.amount, &self.token)
+    }
+
+    fn from_note(note: &Note) -> Self {
+        WasmNote {
+            secret: bytes_to_hex(&note.secret),
+            nullifier: bytes_to_hex(&note.nullifier),
+            amount: note.amount.to_string(),
+            token: felt_to_hex(note.token),
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = generateNoteWithTokenId)]
+pub fn generate_note_with_token_id(
+    amount: &str,
+    token: &str,
+    token_id: u8,
+) -> Result<WasmNote, JsError> {
+    let note = notes::generate_note_with_token_id(
+        parse_amount_field(amount)?,
+        parse_felt(token)?,
+        token_id,
+    )?;
+    Ok(WasmNote::from_note(&note))
+}
+
+#[wasm_bindgen(js_name = computeCommitment)]
+pub fn compute_commitment(note: &WasmNote, token_id: u8) -> Result<String, JsError> {
+    let commitment = notes::compute_commitment(&note.to_note()?, token_id)?;
+    Ok(felt_to_hex(commitment))
+}
+
+#[wasm_bindgen(js_name = generateNullifierHash)]
+pub fn generate_nullifier_hash(note: &WasmNote, token_id: u8) -> Result<String, JsError> {
+    let nullifier = notes::generate_nullifier_hash(&note.to_note()?, token_id)?;
+    Ok(felt_to_hex(nullifier))
+}
+
+#[wasm_bindgen(js_name = computePositionCommitment)]
+pub fn compute_position_commitment(
+    secret: &str,
+    nullifier: &str,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: &str,
+    fee_growth_inside_0: &str,
+    fee_growth_inside_1: &str,
+) -> Result<String, JsError> {
+    let note = PositionNote {
+        secret: parse_bytes32_field(secret, "secret")?,
+        nullifier: parse_bytes32_field(nullifier, "nullifier")?,
+        tick_lower,
+        tick_upper,
+        liquidity: parse_amount_field(liquidity)?,
+        fee_growth_inside_0: parse_u256_hex(fee_growth_inside_0)?,
+        fee_growth_inside_1: parse_u256_hex(fee_growth_inside_1)?,
+    };
+    let commitment = notes::compute_position_commitment(&note)?;
+    Ok(felt_to_hex(commitment))
+}
+
+#[wasm_bindgen(js_name = viewingPublicKey)]
+pub fn viewing_public_key(viewing_key: &str) -> Result<String, JsError> {
+    let key = parse_bytes32_field(viewing_key, "viewing_key")?;
+    Ok(bytes_to_hex(&notes::viewing_public_key(&key)))
+}
+
+// Encrypted notes travel as one hex blob: ephemeral pubkey, nonce, tag, then ciphertext.
+#[wasm_bindgen(js_name = encryptNote)]
+pub fn encrypt_note(note: &WasmNote, recipient_pubkey: &str) -> Result<String, JsError> {
+    let recipient = parse_bytes32_field(recipient_pubkey, "recipient_pubkey")?;
+    let encrypted = notes::encrypt_note(&note.to_note()?, &recipient)?;
+    let mut out = Vec::with_capacity(ENCRYPTED_HEADER_LEN + encrypted.ciphertext.len());
+    out.extend_from_slice(&encrypted.ephemeral_pubkey);
+    out.extend_from_slice(&encrypted.nonce);
+    out.extend_from_slice(&encrypted.tag);
+    out.extend_from_slice(&encrypted.ciphertext);
+    Ok(bytes_to_hex(&out))
+}
+
+#[wasm_bindgen(js_name = decryptNote)]
+pub fn decrypt_note(encrypted: &str, viewing_key: &str) -> Result<WasmNote, JsError> {
+    let key = parse_bytes32_field(viewing_key, "viewing_key")?;
+    let bytes = hex_to_bytes(encrypted)?;
+    if bytes.len() < ENCRYPTED_HEADER_LEN {
+        return Err(ClientError::InvalidInput("encrypted note too short".to_string()).into());
+    }
+    let (ephemeral_pubkey, rest) = bytes.split_at(32);
+    let (nonce, rest) = rest.split_at(24);
+    let (tag, ciphertext) = rest.split_at(16);
+    let encrypted = EncryptedNote {
+        ephemeral_pubkey: ephemeral_pubkey.try_into()?,
+        nonce: nonce.try_into()?,
+        tag: tag.try_into()?,
+        ciphertext: Zeroizing::new(ciphertext.to_vec()),
+    };
+    let note = notes::decrypt_note(&encrypted, &key)?;
+    Ok(WasmNote::from_note(&note))
+}
+
+fn parse_u256_hex(value: &str) -> Result<U256, ClientError> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    if hex.is_empty() || hex.len() > 64 {
+        return Err(ClientError::InvalidInput(
+            "expected u256 hex string".to_string(),
+        ));
+    }
+    // The split below is a byte offset; reject anything that is not a plain hex digit first.
+    if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(ClientError::InvalidInput("invalid u256 hex".to_string()));
+    }
+    let split = hex.len().saturating_sub(32);
+    let parse = |part: &str| {
+        if part.is_empty() {
+            Ok(0)
+        } else {
+            u128::from_str_radix(part, 16)
+                .map_err(|_| ClientError::InvalidInput("invalid u256 hex".to_string()))
+        }
+    };
+    Ok(U256::from_words(
+        parse(&hex[split..])?,
+        parse(&hex[..split])?,
+    ))
+}
+
+fn hex_to_bytes(value: &str) -> Result<Vec<u8>, ClientError> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    if hex.len() % 2 != 0 {
+        return Err(ClientError::InvalidInput(
+            "odd-length hex string".to_string(),
+        ));
+    }
+    if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(ClientError::InvalidInput("invalid hex".to_string()));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|start| {
+            u8::from_str_radix(&hex[start..start + 2], 16)
+                .map_err(|_| ClientError::InvalidInput("invalid hex".to_string()))
+        })
+        .collect()
+}
+
+fn bytes_to_hex(value: &[u8]) -> String {
+    let mut out = String::from("0x");
+    for byte in value {
+        out.push_str(&format!("{:02x}", byte));
+    }
+    out
+}
+
+fn felt_to_hex(value: Felt) -> String {
+    format!("0x{:x}", value)
+}
@@ -5,7 +5,9 @@ use starknet::core::utils::get_selector_from_name;
 use crate::client::RetryConfig;
 use crate::error::ClientError;
 use crate::notes::{generate_nullifier_hash, Note};
-use crate::swap::{execute_with_retry, serialize_merkle_proof, MerklePath};
+use crate::swap::{
+    execute_with_retry, fetch_merkle_path, serialize_merkle_proof, with_deadline, MerklePath,
+};
 use crate::utils::{parse_felt, Address};
 use zylith_prover::ProofCalldata;
 
@@ -18,13 +20,18 @@ pub struct WithdrawRequest {
     pub token_address: Address,
     pub recipient: Address,
     pub proof: ProofCalldata,
-    pub merkle_proof: MerklePath,
+    // When None the path is fetched from the ASP, pinned to `root_index` or `root_hash` if set.
+    // A supplied proof can only be pinned with `root_hash`; its root is checked against it.
+    pub merkle_proof: Option<MerklePath>,
+    pub root_index: Option<u64>,
+    pub root_hash: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct WithdrawClient<A: ConnectedAccount + Sync> {
     pub account: A,
     pub shielded_notes_address: Address,
+    pub asp_url: Option<String>,
     pub retry: RetryConfig,
 }
 
@@ -33,11 +40,18 @@ impl<A: ConnectedAccount + Sync> WithdrawClient<A> {
         Self {
             account,
             shielded_notes_address,
+            asp_url: None,
             retry: RetryConfig::default(),
         }
     }
 
     pub async fn withdraw(&self, request: WithdrawRequest) -> Result<TxHash, ClientError> {
+        let call = self.withdraw_call(request).await?;
+        execute_with_retry(&self.account, call, self.retry.clone()).await
+    }
+
+    // The call `withdraw` submits; still async because a missing merkle proof is fetched.
+    pub async fn withdraw_call(&self, request: WithdrawRequest) -> Result<Call, ClientError> {
         if request.token_address == Felt::ZERO {
             return Err(ClientError::InvalidInput(
                 "token address is zero".to_string(),
@@ -51,10 +65,49 @@ impl<A: ConnectedAccount + Sync> WithdrawClient<A> {
                 "shielded notes address is zero".to_string(),
             ));
         }
+        if request.root_index.is_some() && request.root_hash.is_some() {
+            return Err(ClientError::InvalidInput(
+                "root_index and root_hash are mutually exclusive".to_string(),
+            ));
+        }
+        // The index would only steer an ASP fetch, so with a supplied proof it would be ignored.
+        if request.merkle_proof.is_some() && request.root_index.is_some() {
+            return Err(ClientError::InvalidInput(
+                "root_index cannot be combined with merkle_proof; pin it with root_hash"
+                    .to_string(),
+            ));
+        }
+        let root_hash = request
+            .root_hash
+            .as_deref()
+            .map(|value| {
+                parse_felt(value)
+                    .map_err(|_| ClientError::InvalidInput("invalid root_hash".to_string()))
+            })
+            .transpose()?;
         let commitment = crate::notes::compute_commitment(&request.note, request.token_id)?;
-        if commitment != request.merkle_proof.commitment {
+        let merkle_proof = match request.merkle_proof {
+            Some(merkle_proof) => merkle_proof,
+            None => {
+                let asp_url = self.asp_url.as_deref().ok_or_else(|| {
+                    ClientError::InvalidInput("merkle_proof or asp_url is required".to_string())
+                })?;
+                with_deadline(
+                    self.retry.deadline,
+                    "asp path request",
+                    fetch_merkle_path(asp_url, commitment, request.root_index, root_hash),
+                )
+                .await?
+            }
+        };
+        if commitment != merkle_proof.commitment {
             return Err(ClientError::InvalidInput("commitment mismatch".to_string()));
         }
+        if root_hash.is_some_and(|root| root != merkle_proof.root) {
+            return Err(ClientError::InvalidInput(
+                "merkle proof root mismatch".to_string(),
+            ));
+        }
         let _nullifier = generate_nullifier_hash(&request.note, request.token_id)?;
         let mut calldata: Vec<Felt> = request
             .proof
@@ -65,7 +118,7 @@ impl<A: ConnectedAccount + Sync> WithdrawClient<A> {
                     .map_err(|_| ClientError::InvalidInput("invalid proof calldata".to_string()))
             })
             .collect::<Result<Vec<_>, _>>()?;
-        calldata.extend(serialize_merkle_proof(&request.merkle_proof)?);
+        calldata.extend(serialize_merkle_proof(&merkle_proof)?);
 
         let entrypoint = match request.token_id {
             0 => "withdraw_token0",
@@ -74,11 +127,58 @@ impl<A: ConnectedAccount + Sync> WithdrawClient<A> {
         };
         let selector = get_selector_from_name(entrypoint)
             .map_err(|err| ClientError::InvalidInput(err.to_string()))?;
-        let call = Call {
+        Ok(Call {
             to: self.shielded_notes_address,
             selector,
             calldata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated_constants::TREE_HEIGHT;
+    use crate::notes::generate_note_with_token_id;
+    use crate::test_support::{dummy_account, UNREACHABLE_URL};
+
+    #[tokio::test]
+    async fn supplied_proof_cannot_be_pinned_by_index() {
+        let account = dummy_account(UNREACHABLE_URL);
+        let token = Felt::from(0x70u8);
+        let client = WithdrawClient::new(account, Felt::from(0x50u8));
+        let note = generate_note_with_token_id(25, token, 0).unwrap();
+        let request = WithdrawRequest {
+            merkle_proof: Some(MerklePath {
+                token,
+                root: Felt::ZERO,
+                commitment: crate::notes::compute_commitment(&note, 0).unwrap(),
+                leaf_index: 0,
+                path: vec![Felt::ZERO; TREE_HEIGHT],
+                indices: vec![false; TREE_HEIGHT],
+            }),
+            note,
+            token_id: 0,
+            token_address: token,
+            recipient: Felt::ONE,
+            proof: ProofCalldata::new(vec!["0x1".to_string()]),
+            root_index: Some(3),
+            root_hash: None,
         };
-        execute_with_retry(&self.account, call, self.retry.clone()).await
+
+        let err = client
+            .withdraw_call(request.clone())
+            .await
+            .expect_err("index with a supplied proof");
+        assert!(
+            err.to_string().contains("root_index cannot be combined"),
+            "{err}"
+        );
+
+        let unpinned = WithdrawRequest {
+            root_index: None,
+            ..request
+        };
+        assert!(client.withdraw_call(unpinned).await.is_ok());
     }
 }
@@ -78,6 +78,10 @@ async fn e2e_flow() -> Result<(), Box<dyn std::error::Error>> {
         shielded_notes_address: shielded_notes,
         token0,
         token1,
+        rpc_timeout: None,
+        request_timeout: Duration::from_secs(120),
+        pool_state_ttl: None,
+        check_nullifiers: true,
     });
     ensure_pool_initialized(&client, "startup").await?;
     let swap_client = client.swap_client();
@@ -104,6 +108,7 @@ async fn e2e_flow() -> Result<(), Box<dyn std::error::Error>> {
             output_note_token0: None,
             output_note_token1: None,
             circuit_dir: Some(default_circuit_dir("private_liquidity")),
+            require_active_pool: false,
         })
         .await?;
 
@@ -153,6 +158,7 @@ async fn e2e_flow() -> Result<(), Box<dyn std::error::Error>> {
             output_note_token0: None,
             output_note_token1: None,
             circuit_dir: Some(default_circuit_dir("private_liquidity")),
+            require_active_pool: false,
         })
         .await?;
 
@@ -189,6 +195,7 @@ async fn e2e_flow() -> Result<(), Box<dyn std::error::Error>> {
             output_note_token0: None,
             output_note_token1: None,
             circuit_dir: Some(default_circuit_dir("private_liquidity")),
+            require_active_pool: false,
         })
         .await?;
 
@@ -257,13 +264,16 @@ async fn e2e_flow() -> Result<(), Box<dyn std::error::Error>> {
     let swap_result = client
         .prove_swap(SwapProveRequest {
             notes: vec![note_swap0.clone(), note_swap1.clone()],
-            zero_for_one: true,
+            direction: zylith_client::SwapDirection::ZeroForOne,
             exact_out: false,
             amount_out: None,
+            amount_in_max: None,
+            amount_out_min: None,
             sqrt_ratio_limit: None,
             output_note: None,
             change_note: None,
             circuit_dir: Some(default_circuit_dir("private_swap_zero_for_one")),
+            require_active_pool: false,
         })
         .await
         .map_err(|e| format!("prove swap: {e}"))?;
@@ -291,7 +301,9 @@ async fn e2e_flow() -> Result<(), Box<dyn std::error::Error>> {
             token_address: token1,
             recipient: account_address,
             proof: withdraw_proof,
-            merkle_proof: withdraw_path,
+            merkle_proof: Some(withdraw_path),
+            root_index: None,
+            root_hash: None,
         })
         .await
         .map_err(|e| format!("withdraw: {e}"))?;
@@ -345,6 +357,7 @@ async fn deposit_note(
                 token_address: token,
                 proof: proof.clone(),
                 insertion_proof: insertion,
+                skip_if_deposited: false,
             })
             .await
         {
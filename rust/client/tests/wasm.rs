@@ -0,0 +1,39 @@
+//! Run with `wasm-pack test --node -- --no-default-features --features wasm`.
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use wasm_bindgen_test::wasm_bindgen_test;
+use zylith_client::wasm::{
+    compute_commitment, decrypt_note, encrypt_note, generate_note_with_token_id,
+    generate_nullifier_hash, viewing_public_key, WasmNote,
+};
+
+const TOKEN: &str = "0x1234";
+const VIEWING_KEY: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+#[wasm_bindgen_test]
+fn note_json_round_trip_keeps_commitment() {
+    let note = generate_note_with_token_id("1000", TOKEN, 0).expect("note");
+    let restored = WasmNote::from_json(&note.to_json().expect("json")).expect("parse");
+    assert_eq!(restored.amount(), "1000");
+    assert_eq!(
+        compute_commitment(&note, 0).expect("commitment"),
+        compute_commitment(&restored, 0).expect("commitment")
+    );
+    assert_ne!(
+        generate_nullifier_hash(&note, 0).expect("nullifier"),
+        compute_commitment(&note, 0).expect("commitment")
+    );
+}
+
+#[wasm_bindgen_test]
+fn encrypted_note_opens_for_recipient() {
+    let note = generate_note_with_token_id("42", TOKEN, 1).expect("note");
+    let pubkey = viewing_public_key(VIEWING_KEY).expect("pubkey");
+    let encrypted = encrypt_note(&note, &pubkey).expect("encrypt");
+    let decrypted = decrypt_note(&encrypted, VIEWING_KEY).expect("decrypt");
+    assert_eq!(decrypted.secret(), note.secret());
+    assert_eq!(decrypted.amount(), "42");
+
+    let other_key = "0x2222222222222222222222222222222222222222222222222222222222222222";
+    assert!(decrypt_note(&encrypted, other_key).is_err());
+}
@@ -0,0 +1,150 @@
+//! Decoding of the shielded-notes commitment events, shared by the client and the ASP indexer.
+//!
+//! The two sides build on different starknet-rs releases, so everything here works on raw
+//! 32-byte big-endian field elements and leaves selector hashing to the caller.
+
+use std::fmt;
+
+pub type Word = [u8; 32];
+
+pub const DEPOSIT_EVENT: &str = "Deposit";
+pub const POSITION_COMMITMENT_EVENT: &str = "PositionCommitmentInserted";
+
+// Which Merkle tree a commitment event appends to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitmentKind {
+    Deposit,
+    Position,
+}
+
+impl CommitmentKind {
+    pub const ALL: [CommitmentKind; 2] = [CommitmentKind::Deposit, CommitmentKind::Position];
+
+    // Hash this with `get_selector_from_name` to get the event's first key.
+    pub fn event_name(self) -> &'static str {
+        match self {
+            CommitmentKind::Deposit => DEPOSIT_EVENT,
+            CommitmentKind::Position => POSITION_COMMITMENT_EVENT,
+        }
+    }
+}
+
+// A leaf appended to one of the shielded-notes Merkle trees. `token` is `None` for the
+// position-note tree, which is shared across tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitmentEvent {
+    pub token: Option<Word>,
+    pub leaf_index: u64,
+    pub commitment: Word,
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+}
+
+impl CommitmentEvent {
+    // `Deposit { commitment, leaf_index, token, timestamp }` and
+    // `PositionCommitmentInserted { commitment, leaf_index, timestamp }`, with every field in
+    // the data array (none are `#[key]`).
+    pub fn decode(
+        kind: CommitmentKind,
+        data: &[Word],
+        block_number: Option<u64>,
+    ) -> Result<Self, DecodeError> {
+        let field = |index: usize| {
+            data.get(index).copied().ok_or_else(|| {
+                DecodeError(format!(
+                    "malformed {} event: {} data fields",
+                    kind.event_name(),
+                    data.len()
+                ))
+            })
+        };
+        let commitment = field(0)?;
+        let leaf_index = field(1)?;
+        let (token, timestamp) = match kind {
+            CommitmentKind::Deposit => (Some(field(2)?), field(3)?),
+            CommitmentKind::Position => (None, field(2)?),
+        };
+        Ok(Self {
+            token,
+            leaf_index: word_to_u64(&leaf_index)?,
+            commitment,
+            timestamp: word_to_u64(&timestamp)?,
+            block_number,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError(pub String);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn word_to_u64(value: &Word) -> Result<u64, DecodeError> {
+    if value[..24].iter().any(|byte| *byte != 0) {
+        return Err(DecodeError("felt exceeds u64".to_string()));
+    }
+    let mut tail = [0u8; 8];
+    tail.copy_from_slice(&value[24..]);
+    Ok(u64::from_be_bytes(tail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommitmentEvent, CommitmentKind, Word};
+
+    fn word(value: u64) -> Word {
+        let mut out = [0u8; 32];
+        out[24..].copy_from_slice(&value.to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn both_trees_decode() {
+        let deposit = CommitmentEvent::decode(
+            CommitmentKind::Deposit,
+            &[word(0xc0), word(7), word(0x70), word(1_700_000_000)],
+            Some(42),
+        )
+        .expect("deposit");
+        assert_eq!(
+            deposit,
+            CommitmentEvent {
+                token: Some(word(0x70)),
+                leaf_index: 7,
+                commitment: word(0xc0),
+                timestamp: 1_700_000_000,
+                block_number: Some(42),
+            }
+        );
+
+        let position = CommitmentEvent::decode(
+            CommitmentKind::Position,
+            &[word(0xc1), word(3), word(1_700_000_001)],
+            None,
+        )
+        .expect("position");
+        assert_eq!(position.token, None);
+        assert_eq!(position.leaf_index, 3);
+        assert_eq!(position.timestamp, 1_700_000_001);
+    }
+
+    #[test]
+    fn malformed_events_are_rejected() {
+        let err = CommitmentEvent::decode(CommitmentKind::Deposit, &[word(1); 3], None)
+            .expect_err("short deposit");
+        assert!(err.to_string().contains("malformed Deposit event"), "{err}");
+
+        let mut huge = word(0);
+        huge[0] = 1;
+        assert!(
+            CommitmentEvent::decode(CommitmentKind::Position, &[word(1), huge, word(1)], None)
+                .is_err()
+        );
+    }
+}
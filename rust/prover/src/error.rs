@@ -8,6 +8,11 @@ pub enum ProverError {
     Garaga(String),
     Conversion(String),
     InvalidInput(String),
+    MissingWitnessInput(String),
+    InvalidWitnessInput(String),
+    WitnessRejected(String),
+    Transient(String),
+    Verifier(String),
 }
 
 impl fmt::Display for ProverError {
@@ -19,6 +24,11 @@ impl fmt::Display for ProverError {
             ProverError::Garaga(msg) => write!(f, "garaga error: {msg}"),
             ProverError::Conversion(msg) => write!(f, "conversion error: {msg}"),
             ProverError::InvalidInput(msg) => write!(f, "invalid input: {msg}"),
+            ProverError::MissingWitnessInput(name) => write!(f, "missing witness input: {name}"),
+            ProverError::InvalidWitnessInput(msg) => write!(f, "invalid witness input: {msg}"),
+            ProverError::WitnessRejected(msg) => write!(f, "witness rejected: {msg}"),
+            ProverError::Transient(msg) => write!(f, "transient prover failure: {msg}"),
+            ProverError::Verifier(msg) => write!(f, "verifier error: {msg}"),
         }
     }
 }
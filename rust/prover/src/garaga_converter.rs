@@ -4,13 +4,19 @@
 use std::path::Path;
 
 use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::U256;
 use tokio::process::Command;
 use tokio::sync::OnceCell;
 
 use crate::error::ProverError;
+use crate::proof_runner::Groth16Proof;
+use crate::starknet_types::ProofCalldata;
 
 const STARK_FIELD_MODULUS_HEX: &str =
     "800000000000011000000000000000000000000000000000000000000000001";
+const BN254_BASE_FIELD_MODULUS_DEC: &str =
+    "21888242871839275222246405745257275088696311157297823662689037894645226208583";
 const EXPECTED_GARAGA_VERSION: &str = "0.18.2";
 const EXPECTED_GARAGA_SHA256: &str =
     "d9506be4e9f120a4ff6db3140c090ac6371ff502563241295df5153ef07d8345";
@@ -82,6 +88,127 @@ pub async fn generate_garaga_calldata(
     Ok(calldata)
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct G1Point {
+    pub x: String,
+    pub y: String,
+}
+
+// Fp2 coordinates are `[c0, c1]`, matching the snarkjs encoding.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct G2Point {
+    pub x: [String; 2],
+    pub y: [String; 2],
+}
+
+// Affine view of a snarkjs-format Groth16 proof plus its public signals, all as BN254 decimals.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SnarkjsProof {
+    pub a: G1Point,
+    pub b: G2Point,
+    pub c: G1Point,
+    pub public_signals: Vec<String>,
+}
+
+impl SnarkjsProof {
+    pub fn from_groth16(
+        proof: &Groth16Proof,
+        public_signals: Vec<String>,
+    ) -> Result<Self, ProverError> {
+        let b = match proof.pi_b.as_slice() {
+            [x, y, rest @ ..] if rest.len() <= 1 && rest.iter().all(|z| is_projective_one(z)) => {
+                G2Point {
+                    x: fp2_coordinate(x, "pi_b.x")?,
+                    y: fp2_coordinate(y, "pi_b.y")?,
+                }
+            }
+            _ => {
+                return Err(ProverError::Conversion(
+                    "pi_b is not an affine G2 point".to_string(),
+                ))
+            }
+        };
+        for signal in &public_signals {
+            bn254_coordinate(signal, "public signal")?;
+        }
+        Ok(Self {
+            a: g1_point(&proof.pi_a, "pi_a")?,
+            b,
+            c: g1_point(&proof.pi_c, "pi_c")?,
+            public_signals,
+        })
+    }
+
+    pub fn to_groth16(&self) -> Groth16Proof {
+        let one = || "1".to_string();
+        Groth16Proof {
+            pi_a: vec![self.a.x.clone(), self.a.y.clone(), one()],
+            pi_b: vec![
+                self.b.x.to_vec(),
+                self.b.y.to_vec(),
+                vec![one(), "0".to_string()],
+            ],
+            pi_c: vec![self.c.x.clone(), self.c.y.clone(), one()],
+            protocol: "groth16".to_string(),
+            curve: "bn128".to_string(),
+        }
+    }
+}
+
+// Same as `generate_garaga_calldata`, but also returns the proof the calldata was built from so
+// callers verifying off-chain do not have to re-read the prover output files.
+pub async fn generate_garaga_calldata_with_proof(
+    vk_path: &Path,
+    proof_path: &Path,
+    public_inputs_path: &Path,
+) -> Result<(ProofCalldata, SnarkjsProof), ProverError> {
+    let calldata = generate_garaga_calldata(vk_path, proof_path, public_inputs_path).await?;
+    let proof: Groth16Proof = serde_json::from_slice(&std::fs::read(proof_path)?)?;
+    let public_signals: Vec<String> = serde_json::from_slice(&std::fs::read(public_inputs_path)?)?;
+    let proof = SnarkjsProof::from_groth16(&proof, public_signals)?;
+    Ok((ProofCalldata::new(calldata), proof))
+}
+
+fn g1_point(coords: &[String], label: &str) -> Result<G1Point, ProverError> {
+    match coords {
+        [x, y, rest @ ..] if rest.len() <= 1 && rest.iter().all(|z| z == "1") => Ok(G1Point {
+            x: bn254_coordinate(x, label)?,
+            y: bn254_coordinate(y, label)?,
+        }),
+        _ => Err(ProverError::Conversion(format!(
+            "{label} is not an affine G1 point"
+        ))),
+    }
+}
+
+fn fp2_coordinate(coords: &[String], label: &str) -> Result<[String; 2], ProverError> {
+    match coords {
+        [c0, c1] => Ok([bn254_coordinate(c0, label)?, bn254_coordinate(c1, label)?]),
+        _ => Err(ProverError::Conversion(format!(
+            "{label} must have two components, got {}",
+            coords.len()
+        ))),
+    }
+}
+
+fn is_projective_one(z: &[String]) -> bool {
+    z.len() == 2 && z[0] == "1" && z[1] == "0"
+}
+
+// Normalizes to canonical decimal so the same point always encodes the same way.
+fn bn254_coordinate(value: &str, label: &str) -> Result<String, ProverError> {
+    let parsed = BigUint::parse_bytes(value.as_bytes(), 10)
+        .ok_or_else(|| ProverError::Conversion(format!("{label}: invalid decimal {value}")))?;
+    let modulus = BigUint::parse_bytes(BN254_BASE_FIELD_MODULUS_DEC.as_bytes(), 10)
+        .ok_or_else(|| ProverError::Conversion("invalid BN254 modulus".to_string()))?;
+    if parsed >= modulus {
+        return Err(ProverError::Conversion(format!(
+            "{label}: value exceeds BN254 base field"
+        )));
+    }
+    Ok(parsed.to_str_radix(10))
+}
+
 async fn ensure_garaga_pinned_once() -> Result<(), ProverError> {
     GARAGA_PIN_CHECK
         .get_or_try_init(|| async { ensure_garaga_pinned().await })
@@ -96,8 +223,18 @@ pub fn serialize_public_inputs_for_garaga(inputs: Vec<String>) -> Result<Vec<Str
         .collect()
 }
 
-pub fn split_u256_to_u128(value: starknet::core::types::U256) -> (u128, u128) {
-    (value.low(), value.high())
+/// Splits `value` into its 128-bit limbs, returned as `(low, high)` to match the Cairo `u256`
+/// struct layout; `u128_limbs_to_u256` is the inverse.
+pub fn split_u256_to_u128(value: U256) -> (u128, u128) {
+    let (low, high) = (value.low(), value.high());
+    debug_assert!(u128_limbs_to_u256(low, high) == value);
+    (low, high)
+}
+
+/// Rebuilds a `U256` from `(low, high)` limbs as produced by `split_u256_to_u128` and
+/// `bytes32_to_u128_limbs`.
+pub fn u128_limbs_to_u256(low: u128, high: u128) -> U256 {
+    U256::from_words(low, high)
 }
 
 pub fn bn254_to_felt252(bn254_dec: &str) -> Result<String, ProverError> {
@@ -112,18 +249,22 @@ pub fn bn254_to_felt252(bn254_dec: &str) -> Result<String, ProverError> {
     Ok(format!("0x{}", value.to_str_radix(16)))
 }
 
+/// Reads `value` as a big-endian 256-bit integer and returns its `(low, high)` limbs: `high` is
+/// bytes `0..16`, `low` is bytes `16..32`. The order matches `split_u256_to_u128`.
 pub fn bytes32_to_u128_limbs(value: [u8; 32]) -> (u128, u128) {
     let mut low_bytes = [0u8; 16];
     let mut high_bytes = [0u8; 16];
     high_bytes.copy_from_slice(&value[..16]);
     low_bytes.copy_from_slice(&value[16..]);
-    (
+    let (low, high) = (
         u128::from_be_bytes(low_bytes),
         u128::from_be_bytes(high_bytes),
-    )
+    );
+    debug_assert!(high.to_be_bytes() == value[..16] && low.to_be_bytes() == value[16..]);
+    (low, high)
 }
 
-fn stark_field_modulus() -> Result<BigUint, ProverError> {
+pub(crate) fn stark_field_modulus() -> Result<BigUint, ProverError> {
     let modulus = BigUint::parse_bytes(STARK_FIELD_MODULUS_HEX.as_bytes(), 16)
         .ok_or_else(|| ProverError::Conversion("invalid Stark modulus".to_string()))?;
     Ok(modulus)
@@ -217,7 +358,6 @@ fn parse_garaga_version(output: &str) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use starknet::core::types::U256;
 
     #[test]
     fn bn254_to_felt_accepts_small_value() {
@@ -255,6 +395,55 @@ mod tests {
         assert_eq!(high, 0x5678_u128);
     }
 
+    #[test]
+    fn u128_limbs_round_trip_edge_values() {
+        let max = U256::from_words(u128::MAX, u128::MAX);
+        let zero = U256::from_words(0, 0);
+        let high_only = U256::from_words(0, 1);
+        for value in [max, zero, high_only] {
+            let (low, high) = split_u256_to_u128(value);
+            assert_eq!(u128_limbs_to_u256(low, high), value);
+        }
+        assert_eq!(split_u256_to_u128(high_only), (0, 1));
+        assert_eq!(split_u256_to_u128(max), (u128::MAX, u128::MAX));
+
+        assert_eq!(bytes32_to_u128_limbs([0xff; 32]), (u128::MAX, u128::MAX));
+        assert_eq!(bytes32_to_u128_limbs([0; 32]), (0, 0));
+        let mut high_only_bytes = [0u8; 32];
+        high_only_bytes[15] = 1;
+        let (low, high) = bytes32_to_u128_limbs(high_only_bytes);
+        assert_eq!((low, high), (0, 1));
+        assert_eq!(u128_limbs_to_u256(low, high), high_only);
+    }
+
+    #[test]
+    fn snarkjs_proof_round_trips_point_encoding() {
+        let raw = serde_json::json!({
+            "pi_a": ["1", "2", "1"],
+            "pi_b": [["3", "4"], ["5", "6"], ["1", "0"]],
+            "pi_c": ["7", "8", "1"],
+            "protocol": "groth16",
+            "curve": "bn128"
+        });
+        let groth16: Groth16Proof = serde_json::from_value(raw.clone()).expect("proof");
+        let proof =
+            SnarkjsProof::from_groth16(&groth16, vec!["9".to_string()]).expect("structured");
+        assert_eq!(proof.b.x, ["3".to_string(), "4".to_string()]);
+        assert_eq!(proof.c.y, "8");
+        assert_eq!(serde_json::to_value(proof.to_groth16()).expect("json"), raw);
+
+        let encoded = serde_json::to_string(&proof).expect("encode");
+        let decoded: SnarkjsProof = serde_json::from_str(&encoded).expect("decode");
+        assert_eq!(decoded, proof);
+
+        let mut out_of_field = groth16.clone();
+        out_of_field.pi_a[0] = BN254_BASE_FIELD_MODULUS_DEC.to_string();
+        assert!(SnarkjsProof::from_groth16(&out_of_field, Vec::new()).is_err());
+        let mut projective = groth16;
+        projective.pi_c[2] = "2".to_string();
+        assert!(SnarkjsProof::from_groth16(&projective, Vec::new()).is_err());
+    }
+
     #[test]
     fn serialize_public_inputs_converts_bn254() {
         let inputs = vec!["1".to_string(), "2".to_string()];
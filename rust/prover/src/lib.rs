@@ -11,15 +11,20 @@ use std::path::Path;
 pub use crate::error::ProverError;
 pub use crate::garaga_converter::{
     bn254_to_felt252, bytes32_to_u128_limbs, generate_garaga_calldata,
-    serialize_public_inputs_for_garaga, split_u256_to_u128,
+    generate_garaga_calldata_with_proof, serialize_public_inputs_for_garaga, split_u256_to_u128,
+    u128_limbs_to_u256, G1Point, G2Point, SnarkjsProof,
+};
+pub use crate::proof_runner::{
+    generate_proof, generate_proof_streamed, generate_proof_with_rapidsnark, verify_proof,
+    verify_proof_with_snarkjs, Groth16Proof, ProofOutput,
 };
-pub use crate::proof_runner::{generate_proof, Groth16Proof, ProofOutput};
 pub use crate::starknet_types::ProofCalldata;
 pub use crate::witness::{
     generate_deposit_witness_inputs, generate_lp_add_witness_inputs,
     generate_lp_remove_witness_inputs, generate_swap_witness_inputs,
-    generate_withdraw_witness_inputs, DepositWitnessInputs, LpWitnessInputs, SwapWitnessInputs,
-    WithdrawWitnessInputs, WitnessValue,
+    generate_withdraw_witness_inputs, write_lp_witness_inputs, write_swap_witness_inputs,
+    DepositWitnessInputs, LpWitnessInputs, SwapWitnessInputs, WithdrawWitnessInputs, WitnessKind,
+    WitnessValue,
 };
 
 pub async fn prove_swap(
@@ -35,11 +40,18 @@ pub async fn prove_swap_with_vk(
     circuit_dir: &Path,
     vk_path: &Path,
 ) -> Result<ProofCalldata, ProverError> {
-    let witness_json = generate_swap_witness_inputs(witness_inputs)?;
+    witness_inputs.validate(false)?;
     let circuit = swap_circuit_name(circuit_dir)?;
     let wasm = circuit_dir.join(format!("{circuit}.wasm"));
     let zkey = circuit_dir.join(format!("{circuit}_final.zkey"));
-    let proof_output = generate_proof(&circuit, &witness_json, &wasm, &zkey).await?;
+    let proof_output = generate_proof_streamed(
+        &circuit,
+        |writer| write_swap_witness_inputs(witness_inputs, writer),
+        &wasm,
+        &zkey,
+    )
+    .await?;
+    check_proof(vk_path, &proof_output).await?;
     let calldata = generate_garaga_calldata(
         vk_path,
         &proof_output.proof_path,
@@ -62,11 +74,18 @@ pub async fn prove_swap_exact_out_with_vk(
     circuit_dir: &Path,
     vk_path: &Path,
 ) -> Result<ProofCalldata, ProverError> {
-    let witness_json = generate_swap_witness_inputs(witness_inputs)?;
+    witness_inputs.validate(true)?;
     let circuit = swap_circuit_name(circuit_dir)?;
     let wasm = circuit_dir.join(format!("{circuit}.wasm"));
     let zkey = circuit_dir.join(format!("{circuit}_final.zkey"));
-    let proof_output = generate_proof(&circuit, &witness_json, &wasm, &zkey).await?;
+    let proof_output = generate_proof_streamed(
+        &circuit,
+        |writer| write_swap_witness_inputs(witness_inputs, writer),
+        &wasm,
+        &zkey,
+    )
+    .await?;
+    check_proof(vk_path, &proof_output).await?;
     let calldata = generate_garaga_calldata(
         vk_path,
         &proof_output.proof_path,
@@ -89,10 +108,17 @@ pub async fn prove_lp_add_with_vk(
     circuit_dir: &Path,
     vk_path: &Path,
 ) -> Result<ProofCalldata, ProverError> {
-    let witness_json = generate_lp_add_witness_inputs(witness_inputs)?;
+    witness_inputs.validate()?;
     let wasm = circuit_dir.join("private_liquidity.wasm");
     let zkey = circuit_dir.join("private_liquidity_final.zkey");
-    let proof_output = generate_proof("private_liquidity", &witness_json, &wasm, &zkey).await?;
+    let proof_output = generate_proof_streamed(
+        "private_liquidity",
+        |writer| write_lp_witness_inputs(witness_inputs, writer),
+        &wasm,
+        &zkey,
+    )
+    .await?;
+    check_proof(vk_path, &proof_output).await?;
     let calldata = generate_garaga_calldata(
         vk_path,
         &proof_output.proof_path,
@@ -115,10 +141,17 @@ pub async fn prove_lp_remove_with_vk(
     circuit_dir: &Path,
     vk_path: &Path,
 ) -> Result<ProofCalldata, ProverError> {
-    let witness_json = generate_lp_remove_witness_inputs(witness_inputs)?;
+    witness_inputs.validate()?;
     let wasm = circuit_dir.join("private_liquidity.wasm");
     let zkey = circuit_dir.join("private_liquidity_final.zkey");
-    let proof_output = generate_proof("private_liquidity", &witness_json, &wasm, &zkey).await?;
+    let proof_output = generate_proof_streamed(
+        "private_liquidity",
+        |writer| write_lp_witness_inputs(witness_inputs, writer),
+        &wasm,
+        &zkey,
+    )
+    .await?;
+    check_proof(vk_path, &proof_output).await?;
     let calldata = generate_garaga_calldata(
         vk_path,
         &proof_output.proof_path,
@@ -141,10 +174,17 @@ pub async fn prove_lp_claim_with_vk(
     circuit_dir: &Path,
     vk_path: &Path,
 ) -> Result<ProofCalldata, ProverError> {
-    let witness_json = generate_lp_remove_witness_inputs(witness_inputs)?;
+    witness_inputs.validate()?;
     let wasm = circuit_dir.join("private_liquidity.wasm");
     let zkey = circuit_dir.join("private_liquidity_final.zkey");
-    let proof_output = generate_proof("private_liquidity", &witness_json, &wasm, &zkey).await?;
+    let proof_output = generate_proof_streamed(
+        "private_liquidity",
+        |writer| write_lp_witness_inputs(witness_inputs, writer),
+        &wasm,
+        &zkey,
+    )
+    .await?;
+    check_proof(vk_path, &proof_output).await?;
     let calldata = generate_garaga_calldata(
         vk_path,
         &proof_output.proof_path,
@@ -167,11 +207,13 @@ pub async fn prove_deposit_with_vk(
     circuit_dir: &Path,
     vk_path: &Path,
 ) -> Result<ProofCalldata, ProverError> {
+    witness_inputs.validate()?;
     let witness_json = generate_deposit_witness_inputs(witness_inputs)?;
 
     let wasm = circuit_dir.join("private_deposit.wasm");
     let zkey = circuit_dir.join("private_deposit_final.zkey");
     let proof_output = generate_proof("private_deposit", &witness_json, &wasm, &zkey).await?;
+    check_proof(vk_path, &proof_output).await?;
     let calldata = generate_garaga_calldata(
         vk_path,
         &proof_output.proof_path,
@@ -194,11 +236,13 @@ pub async fn prove_withdraw_with_vk(
     circuit_dir: &Path,
     vk_path: &Path,
 ) -> Result<ProofCalldata, ProverError> {
+    witness_inputs.validate()?;
     let witness_json = generate_withdraw_witness_inputs(witness_inputs)?;
 
     let wasm = circuit_dir.join("private_withdraw.wasm");
     let zkey = circuit_dir.join("private_withdraw_final.zkey");
     let proof_output = generate_proof("private_withdraw", &witness_json, &wasm, &zkey).await?;
+    check_proof(vk_path, &proof_output).await?;
     let calldata = generate_garaga_calldata(
         vk_path,
         &proof_output.proof_path,
@@ -208,6 +252,22 @@ pub async fn prove_withdraw_with_vk(
     Ok(ProofCalldata::new(calldata))
 }
 
+// With ZYLITH_PROVER_VERIFY_AFTER_PROVE set, every proof is checked against the verification
+// key before calldata is built, so a corrupted zkey or vk fails here instead of on-chain.
+async fn check_proof(vk_path: &Path, proof_output: &ProofOutput) -> Result<(), ProverError> {
+    if !proof_runner::env_flag("ZYLITH_PROVER_VERIFY_AFTER_PROVE") {
+        return Ok(());
+    }
+    if verify_proof(vk_path, proof_output).await? {
+        Ok(())
+    } else {
+        Err(ProverError::Verifier(format!(
+            "generated proof does not verify against {}",
+            vk_path.display()
+        )))
+    }
+}
+
 fn swap_circuit_name(circuit_dir: &Path) -> Result<String, ProverError> {
     let name = circuit_dir
         .file_name()
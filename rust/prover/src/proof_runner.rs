@@ -1,5 +1,10 @@
 //! Proof generation wrapper for Circom artifacts.
 //! Uses the native Circom witness generator and `rapidsnark` for Groth16 proving.
+//!
+//! Artifacts are never loaded into this process: the witness generator and `rapidsnark` are
+//! spawned per proof and read the `.dat`/`.zkey` files by path, so zkey memory is owned by the
+//! child and released when it exits. There is no resident set to manage here; hosts that want
+//! to keep hot zkeys cached should rely on the OS page cache.
 
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt;
@@ -14,9 +19,11 @@ use crate::error::ProverError;
 
 static PROVER_WORK_ROOT: OnceLock<PathBuf> = OnceLock::new();
 static PROVER_WORK_COUNTER: AtomicU64 = AtomicU64::new(0);
+// Witness generation + proving is re-run once when it fails with `ProverError::Transient`.
+// Witness rejections are deterministic for a given input and are never retried.
+const PROVE_ATTEMPTS: usize = 2;
 
-fn write_private_file(path: &Path, data: &[u8]) -> Result<(), ProverError> {
-    use std::io::Write;
+fn open_private_file(path: &Path) -> Result<std::fs::File, ProverError> {
     let mut options = std::fs::OpenOptions::new();
     options.create(true).write(true).truncate(true);
     #[cfg(unix)]
@@ -24,7 +31,12 @@ fn write_private_file(path: &Path, data: &[u8]) -> Result<(), ProverError> {
         use std::os::unix::fs::OpenOptionsExt;
         options.mode(0o600);
     }
-    let mut file = options.open(path)?;
+    Ok(options.open(path)?)
+}
+
+fn write_private_file(path: &Path, data: &[u8]) -> Result<(), ProverError> {
+    use std::io::Write;
+    let mut file = open_private_file(path)?;
     file.write_all(data)?;
     Ok(())
 }
@@ -40,26 +52,83 @@ pub struct Groth16Proof {
     pub curve: String,
 }
 
+// Proof files live in a per-invocation work directory that is removed when the output is
+// dropped, unless `ZYLITH_PROVER_RETAIN_ARTIFACTS` is set.
 #[derive(Debug)]
 pub struct ProofOutput {
     pub workdir: PathBuf,
-    cleanup_paths: Vec<PathBuf>,
     pub proof_path: PathBuf,
     pub public_inputs_path: PathBuf,
+    _work_dir: WorkDir,
 }
 
-impl Drop for ProofOutput {
-    fn drop(&mut self) {
-        cleanup_paths(&self.cleanup_paths);
-    }
+pub async fn generate_proof(
+    circuit_name: &str,
+    witness_input: &serde_json::Value,
+    wasm_path: &Path,
+    zkey_path: &Path,
+) -> Result<ProofOutput, ProverError> {
+    generate_proof_with_rapidsnark(
+        circuit_name,
+        witness_input,
+        wasm_path,
+        zkey_path,
+        &rapidsnark_bin(),
+    )
+    .await
 }
 
-pub async fn generate_proof(
+pub async fn generate_proof_with_rapidsnark(
     circuit_name: &str,
     witness_input: &serde_json::Value,
     wasm_path: &Path,
     zkey_path: &Path,
+    rapidsnark_bin: &Path,
 ) -> Result<ProofOutput, ProverError> {
+    run_proof(
+        circuit_name,
+        |writer| Ok(serde_json::to_writer(writer, witness_input)?),
+        wasm_path,
+        zkey_path,
+        rapidsnark_bin,
+        &work_config()?,
+    )
+    .await
+}
+
+/// Like `generate_proof`, but `write_input` streams the witness json straight into the
+/// prover's input file instead of materializing it first.
+pub async fn generate_proof_streamed<F>(
+    circuit_name: &str,
+    write_input: F,
+    wasm_path: &Path,
+    zkey_path: &Path,
+) -> Result<ProofOutput, ProverError>
+where
+    F: FnOnce(&mut dyn std::io::Write) -> Result<(), ProverError>,
+{
+    run_proof(
+        circuit_name,
+        write_input,
+        wasm_path,
+        zkey_path,
+        &rapidsnark_bin(),
+        &work_config()?,
+    )
+    .await
+}
+
+async fn run_proof<F>(
+    circuit_name: &str,
+    write_input: F,
+    wasm_path: &Path,
+    zkey_path: &Path,
+    rapidsnark_bin: &Path,
+    work: &WorkConfig,
+) -> Result<ProofOutput, ProverError>
+where
+    F: FnOnce(&mut dyn std::io::Write) -> Result<(), ProverError>,
+{
     let native_witness_path = native_witness_bin_path(wasm_path)?;
     let native_witness_dat_path = native_witness_dat_path(wasm_path)?;
     if !native_witness_path.exists() {
@@ -81,66 +150,144 @@ pub async fn generate_proof(
         )));
     }
 
-    let work_files = create_work_files(circuit_name)?;
-    let workdir = work_files.workdir.clone();
-    let result = async {
-        write_private_file(&work_files.input_path, &serde_json::to_vec(witness_input)?)?;
-
-        run_native_witness(
-            &native_witness_path,
-            &work_files.input_path,
-            &work_files.witness_path,
-            &workdir,
-        )
-        .await?;
-
-        run_rapidsnark(
-            zkey_path,
-            &work_files.witness_path,
-            &work_files.proof_path,
-            &work_files.public_inputs_path,
-            &workdir,
-        )
-        .await?;
-
-        let proof_bytes = std::fs::read(&work_files.proof_path)?;
-        let mut proof: Groth16Proof = serde_json::from_slice(&proof_bytes)?;
-        let mut rewrite_proof = false;
-        if proof.protocol.is_empty() {
-            proof.protocol = "groth16".to_string();
-            rewrite_proof = true;
-        }
-        if proof.curve.is_empty() {
-            proof.curve = "bn128".to_string();
-            rewrite_proof = true;
-        }
-        if rewrite_proof {
-            write_private_file(&work_files.proof_path, &serde_json::to_vec(&proof)?)?;
+    // Any early return below drops `work_files`, which removes the work directory.
+    let work_files = create_work_files(circuit_name, work)?;
+    {
+        use std::io::Write;
+        let mut input = std::io::BufWriter::new(open_private_file(&work_files.input_path)?);
+        write_input(&mut input)?;
+        input.flush()?;
+    }
+
+    let mut attempt = 1;
+    loop {
+        match witness_and_prove(&native_witness_path, rapidsnark_bin, zkey_path, &work_files).await
+        {
+            Err(ProverError::Transient(_)) if attempt < PROVE_ATTEMPTS => attempt += 1,
+            result => break result,
         }
-        Ok(ProofOutput {
-            workdir: work_files.workdir.clone(),
-            cleanup_paths: work_files.cleanup_paths.clone(),
-            proof_path: work_files.proof_path.clone(),
-            public_inputs_path: work_files.public_inputs_path.clone(),
-        })
+    }?;
+
+    let proof_bytes = std::fs::read(&work_files.proof_path)?;
+    let mut proof: Groth16Proof = serde_json::from_slice(&proof_bytes)?;
+    let mut rewrite_proof = false;
+    if proof.protocol.is_empty() {
+        proof.protocol = "groth16".to_string();
+        rewrite_proof = true;
     }
-    .await;
-    if result.is_err() {
-        cleanup_paths(&work_files.cleanup_paths);
+    if proof.curve.is_empty() {
+        proof.curve = "bn128".to_string();
+        rewrite_proof = true;
     }
-    result
+    if rewrite_proof {
+        write_private_file(&work_files.proof_path, &serde_json::to_vec(&proof)?)?;
+    }
+    Ok(ProofOutput {
+        workdir: work_files.dir.path.clone(),
+        proof_path: work_files.proof_path,
+        public_inputs_path: work_files.public_inputs_path,
+        _work_dir: work_files.dir,
+    })
+}
+
+async fn witness_and_prove(
+    native_witness_path: &Path,
+    rapidsnark_bin: &Path,
+    zkey_path: &Path,
+    work_files: &WorkFiles,
+) -> Result<(), ProverError> {
+    run_native_witness(
+        native_witness_path,
+        &work_files.input_path,
+        &work_files.witness_path,
+        &work_files.dir.path,
+    )
+    .await?;
+    run_rapidsnark(
+        rapidsnark_bin,
+        zkey_path,
+        &work_files.witness_path,
+        &work_files.proof_path,
+        &work_files.public_inputs_path,
+        &work_files.dir.path,
+    )
+    .await
+}
+
+pub async fn verify_proof(vk_path: &Path, output: &ProofOutput) -> Result<bool, ProverError> {
+    verify_proof_with_snarkjs(vk_path, output, &snarkjs_bin()).await
+}
+
+// Runs `snarkjs groth16 verify`. `Ok(false)` means the verifier rejected the proof; failing to
+// run it at all is an error.
+pub async fn verify_proof_with_snarkjs(
+    vk_path: &Path,
+    output: &ProofOutput,
+    snarkjs_bin: &Path,
+) -> Result<bool, ProverError> {
+    if !vk_path.exists() {
+        return Err(ProverError::InvalidInput(format!(
+            "missing verification key at {}",
+            vk_path.display()
+        )));
+    }
+    let program = snarkjs_bin.display();
+    let result = Command::new(snarkjs_bin)
+        .arg("groth16")
+        .arg("verify")
+        .arg(vk_path)
+        .arg(&output.public_inputs_path)
+        .arg(&output.proof_path)
+        .current_dir(&output.workdir)
+        .output()
+        .await
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => ProverError::Verifier(format!(
+                "snarkjs binary not found at {program}; set ZYLITH_SNARKJS_BIN"
+            )),
+            _ => ProverError::Verifier(format!("failed to run {program}: {err}")),
+        })?;
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    if result.status.success() && stdout.contains("OK!") {
+        return Ok(true);
+    }
+    if stdout.contains("Invalid proof") || stderr.contains("Invalid proof") {
+        return Ok(false);
+    }
+    Err(ProverError::Verifier(format!(
+        "{program} groth16 verify failed: {stdout} {stderr}"
+    )))
+}
+
+pub(crate) fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|value| !value.is_empty() && value != "0")
+        .unwrap_or(false)
+}
+
+fn snarkjs_bin() -> PathBuf {
+    std::env::var("ZYLITH_SNARKJS_BIN")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("snarkjs"))
+}
+
+fn rapidsnark_bin() -> PathBuf {
+    std::env::var("ZYLITH_RAPIDSNARK_BIN")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("rapidsnark"))
 }
 
 async fn run_rapidsnark(
+    rapidsnark_bin: &Path,
     zkey_path: &Path,
     witness_path: &Path,
     proof_path: &Path,
     public_path: &Path,
     workdir: &Path,
 ) -> Result<(), ProverError> {
-    let program =
-        std::env::var("ZYLITH_RAPIDSNARK_BIN").unwrap_or_else(|_| "rapidsnark".to_string());
-    let output = Command::new(&program)
+    let program = rapidsnark_bin.display();
+    let output = Command::new(rapidsnark_bin)
         .arg(zkey_path)
         .arg(witness_path)
         .arg(proof_path)
@@ -148,7 +295,15 @@ async fn run_rapidsnark(
         .current_dir(workdir)
         .output()
         .await
-        .map_err(|err| ProverError::Rapidsnark(format!("failed to run {program}: {err}")))?;
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => ProverError::Rapidsnark(format!(
+                "rapidsnark binary not found at {program}; set ZYLITH_RAPIDSNARK_BIN"
+            )),
+            _ if is_transient_spawn_error(&err) => {
+                ProverError::Transient(format!("failed to run {program}: {err}"))
+            }
+            _ => ProverError::Rapidsnark(format!("failed to run {program}: {err}")),
+        })?;
     if !output.status.success() {
         let stdout = String::from_utf8_lossy(&output.stdout);
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -157,9 +312,12 @@ async fn run_rapidsnark(
             (None, Some(signal)) => format!("terminated by signal {signal}"),
             (None, None) => "terminated".to_string(),
         };
-        return Err(ProverError::Rapidsnark(format!(
-            "{program} failed ({status}): {stdout} {stderr}"
-        )));
+        let message = format!("{program} failed ({status}): {stdout} {stderr}");
+        // Killed without an exit code (OOM killer, SIGKILL): the zkey and witness are fine.
+        if output.status.code().is_none() {
+            return Err(ProverError::Transient(message));
+        }
+        return Err(ProverError::Rapidsnark(message));
     }
     Ok(())
 }
@@ -177,10 +335,15 @@ async fn run_native_witness(
         .output()
         .await
         .map_err(|err| {
-            ProverError::Io(format!(
+            let message = format!(
                 "failed to run native witness generator {}: {err}",
                 witness_bin.display()
-            ))
+            );
+            if is_transient_spawn_error(&err) {
+                ProverError::Transient(message)
+            } else {
+                ProverError::Io(message)
+            }
         })?;
     if !output.status.success() {
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -190,13 +353,26 @@ async fn run_native_witness(
             (None, Some(signal)) => format!("terminated by signal {signal}"),
             (None, None) => "terminated".to_string(),
         };
-        return Err(ProverError::Io(format!(
-            "native witness generator failed ({status}): {stdout} {stderr}"
-        )));
+        let message = format!("native witness generator failed ({status}): {stdout} {stderr}");
+        // An exit code means the generator ran to a verdict, i.e. a constraint or input check
+        // failed; the same input would fail again.
+        if output.status.code().is_none() {
+            return Err(ProverError::Transient(message));
+        }
+        return Err(ProverError::WitnessRejected(message));
     }
     Ok(())
 }
 
+// Missing or non-executable binaries are configuration errors; anything else at spawn time
+// (ETXTBSY while a binary is being replaced, EAGAIN under memory pressure) is worth a retry.
+fn is_transient_spawn_error(err: &std::io::Error) -> bool {
+    !matches!(
+        err.kind(),
+        std::io::ErrorKind::NotFound | std::io::ErrorKind::PermissionDenied
+    )
+}
+
 fn native_witness_bin_path(wasm_path: &Path) -> Result<PathBuf, ProverError> {
     let circuit_dir = wasm_path.parent().ok_or_else(|| {
         ProverError::InvalidInput(format!(
@@ -221,59 +397,301 @@ fn native_witness_dat_path(wasm_path: &Path) -> Result<PathBuf, ProverError> {
 }
 
 #[derive(Debug, Clone)]
+struct WorkConfig {
+    root: PathBuf,
+    retain: bool,
+}
+
+// Scratch directory for a single proof, removed with its contents on drop.
+#[derive(Debug)]
+struct WorkDir {
+    path: PathBuf,
+    retain: bool,
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        if !self.retain {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+#[derive(Debug)]
 struct WorkFiles {
-    workdir: PathBuf,
-    cleanup_paths: Vec<PathBuf>,
+    dir: WorkDir,
     input_path: PathBuf,
     witness_path: PathBuf,
     proof_path: PathBuf,
     public_inputs_path: PathBuf,
 }
 
+fn work_config() -> Result<WorkConfig, ProverError> {
+    Ok(WorkConfig {
+        root: prover_work_root()?,
+        retain: env_flag("ZYLITH_PROVER_RETAIN_ARTIFACTS"),
+    })
+}
+
 fn prover_work_root() -> Result<PathBuf, ProverError> {
     if let Some(path) = PROVER_WORK_ROOT.get() {
         return Ok(path.clone());
     }
-    let base_dir = std::env::var("ZYLITH_PROVER_TMPDIR")
+    // `temp_dir` already honors TMPDIR; ZYLITH_PROVER_TMPDIR is the older name for the override.
+    let base_dir = std::env::var("ZYLITH_PROVER_WORKDIR")
+        .or_else(|_| std::env::var("ZYLITH_PROVER_TMPDIR"))
         .map(PathBuf::from)
         .unwrap_or_else(|_| std::env::temp_dir());
     let dir = base_dir.join(format!("zylith_prover_{}", std::process::id()));
-    std::fs::create_dir_all(&dir)?;
+    create_private_dir(&dir)?;
+    let _ = PROVER_WORK_ROOT.set(dir.clone());
+    Ok(dir)
+}
+
+fn create_private_dir(dir: &Path) -> Result<(), ProverError> {
+    std::fs::create_dir_all(dir)?;
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
     }
-    let _ = PROVER_WORK_ROOT.set(dir.clone());
-    Ok(dir)
+    Ok(())
 }
 
-fn create_work_files(circuit_name: &str) -> Result<WorkFiles, ProverError> {
-    let workdir = prover_work_root()?;
+fn create_work_files(circuit_name: &str, work: &WorkConfig) -> Result<WorkFiles, ProverError> {
     let request_id = PROVER_WORK_COUNTER.fetch_add(1, Ordering::Relaxed);
-    let prefix = format!("{circuit_name}_{}_{}", std::process::id(), request_id);
-    let input_path = workdir.join(format!("{prefix}_input.json"));
-    let witness_path = workdir.join(format!("{prefix}_witness.wtns"));
-    let proof_path = workdir.join(format!("{prefix}_proof.json"));
-    let public_inputs_path = workdir.join(format!("{prefix}_public.json"));
-    let cleanup_paths = vec![
-        input_path.clone(),
-        witness_path.clone(),
-        proof_path.clone(),
-        public_inputs_path.clone(),
-    ];
+    let path = work.root.join(format!(
+        "{circuit_name}_{}_{}",
+        std::process::id(),
+        request_id
+    ));
+    create_private_dir(&path)?;
+    let dir = WorkDir {
+        path,
+        retain: work.retain,
+    };
     Ok(WorkFiles {
-        workdir,
-        cleanup_paths,
-        input_path,
-        witness_path,
-        proof_path,
-        public_inputs_path,
+        input_path: dir.path.join("input.json"),
+        witness_path: dir.path.join("witness.wtns"),
+        proof_path: dir.path.join("proof.json"),
+        public_inputs_path: dir.path.join("public.json"),
+        dir,
     })
 }
 
-fn cleanup_paths(paths: &[PathBuf]) {
-    for path in paths {
-        let _ = std::fs::remove_file(path);
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn write_script(path: &Path, body: &str) {
+        std::fs::write(path, format!("#!/bin/sh\n{body}\n")).expect("write script");
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .expect("chmod script");
+    }
+
+    // Writes an empty proof and public inputs to the paths rapidsnark is given.
+    const EMPTY_PROOF: &str =
+        "echo '{\"pi_a\":[],\"pi_b\":[],\"pi_c\":[]}' > \"$3\"; echo '[]' > \"$4\"";
+
+    // Stub artifacts for one circuit in a fresh temp directory, removed on drop.
+    struct StubProver {
+        dir: PathBuf,
+        wasm: PathBuf,
+        zkey: PathBuf,
+        rapidsnark: PathBuf,
+    }
+
+    // Empty wasm/zkey/dat for `circuit`, a witness generator that only creates its output
+    // file, and a rapidsnark script running `prove`.
+    fn stub_prover(tag: &str, circuit: &str, prove: &str) -> StubProver {
+        let dir = std::env::temp_dir().join(format!("zylith_{tag}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let wasm = dir.join(format!("{circuit}.wasm"));
+        let zkey = dir.join(format!("{circuit}_final.zkey"));
+        std::fs::write(&wasm, b"").expect("wasm");
+        std::fs::write(&zkey, b"").expect("zkey");
+        std::fs::write(dir.join(format!("{circuit}.dat")), b"").expect("dat");
+        write_script(&dir.join(circuit), "touch \"$2\"");
+        let rapidsnark = dir.join("rapidsnark");
+        write_script(&rapidsnark, prove);
+        StubProver {
+            dir,
+            wasm,
+            zkey,
+            rapidsnark,
+        }
+    }
+
+    impl Drop for StubProver {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn rapidsnark_path_is_respected() {
+        // The default rapidsnark fails, so only the custom path can produce a proof.
+        let stub = stub_prover("rapidsnark", "stub", "exit 1");
+        let (dir, wasm, zkey) = (&stub.dir, &stub.wasm, &stub.zkey);
+        let rapidsnark = dir.join("custom-rapidsnark");
+        write_script(&rapidsnark, EMPTY_PROOF);
+
+        let output =
+            generate_proof_with_rapidsnark("stub", &serde_json::json!({}), wasm, zkey, &rapidsnark)
+                .await
+                .expect("stub proof");
+        let proof: Groth16Proof =
+            serde_json::from_slice(&std::fs::read(&output.proof_path).expect("proof"))
+                .expect("proof json");
+        assert_eq!(proof.protocol, "groth16");
+
+        let missing = dir.join("missing-rapidsnark");
+        let err =
+            generate_proof_with_rapidsnark("stub", &serde_json::json!({}), wasm, zkey, &missing)
+                .await
+                .expect_err("missing binary");
+        assert!(err.to_string().contains("not found"));
+        drop(output);
+    }
+
+    #[tokio::test]
+    async fn transient_witness_failure_is_retried_once() {
+        let stub = stub_prover("retry", "flaky", EMPTY_PROOF);
+        let StubProver {
+            dir,
+            wasm,
+            zkey,
+            rapidsnark,
+        } = &stub;
+
+        // First run is killed mid-flight, the second succeeds.
+        let marker = dir.join("attempted");
+        write_script(
+            &dir.join("flaky"),
+            &format!(
+                "if [ ! -e {marker} ]; then touch {marker}; kill -9 $$; fi; touch \"$2\"",
+                marker = marker.display()
+            ),
+        );
+        let output =
+            generate_proof_with_rapidsnark("flaky", &serde_json::json!({}), wasm, zkey, rapidsnark)
+                .await
+                .expect("retried proof");
+        assert!(output.proof_path.exists());
+        drop(output);
+
+        // A rejected witness is reported once and not re-run.
+        let runs = dir.join("runs");
+        write_script(
+            &dir.join("flaky"),
+            &format!(
+                "echo run >> {runs}; echo 'Assert Failed' >&2; exit 1",
+                runs = runs.display()
+            ),
+        );
+        let err =
+            generate_proof_with_rapidsnark("flaky", &serde_json::json!({}), wasm, zkey, rapidsnark)
+                .await
+                .expect_err("rejected witness");
+        assert!(matches!(err, ProverError::WitnessRejected(_)));
+        let runs = std::fs::read_to_string(&runs).expect("runs");
+        assert_eq!(runs.lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn work_directory_is_removed_after_success_and_failure() {
+        let stub = stub_prover("workdir", "stub", EMPTY_PROOF);
+        let root = stub.dir.join("work");
+        std::fs::create_dir_all(&root).expect("dir");
+        let failing = stub.dir.join("failing-rapidsnark");
+        write_script(&failing, "exit 1");
+        let is_empty = |root: &Path| std::fs::read_dir(root).expect("read root").next().is_none();
+        let prove = |rapidsnark: PathBuf, retain: bool| {
+            let work = WorkConfig {
+                root: root.clone(),
+                retain,
+            };
+            let (wasm, zkey) = (stub.wasm.clone(), stub.zkey.clone());
+            async move {
+                run_proof(
+                    "stub",
+                    |writer| Ok(serde_json::to_writer(writer, &serde_json::json!({}))?),
+                    &wasm,
+                    &zkey,
+                    &rapidsnark,
+                    &work,
+                )
+                .await
+            }
+        };
+
+        let output = prove(stub.rapidsnark.clone(), false).await.expect("proof");
+        assert!(output.proof_path.exists());
+        assert!(!is_empty(&root));
+        drop(output);
+        assert!(is_empty(&root));
+
+        prove(failing, false).await.expect_err("failed proof");
+        assert!(is_empty(&root));
+
+        let output = prove(stub.rapidsnark.clone(), true)
+            .await
+            .expect("retained proof");
+        let workdir = output.workdir.clone();
+        drop(output);
+        assert!(workdir.join("proof.json").exists());
+    }
+
+    #[tokio::test]
+    async fn verify_proof_rejects_tampered_proof() {
+        let good_proof =
+            r#"{"pi_a":["1"],"pi_b":[],"pi_c":[],"protocol":"groth16","curve":"bn128"}"#;
+        let stub = stub_prover(
+            "verify",
+            "stub",
+            &format!("echo '{good_proof}' > \"$3\"; echo '[\"7\"]' > \"$4\""),
+        );
+        let StubProver {
+            dir,
+            wasm,
+            zkey,
+            rapidsnark,
+        } = &stub;
+        let vk = dir.join("verification_key.json");
+        std::fs::write(&vk, b"{}").expect("vk");
+        // Mimics snarkjs output: accepts exactly the proof the stub prover emits.
+        let expected = dir.join("expected_proof.json");
+        std::fs::write(&expected, good_proof).expect("expected proof");
+        let snarkjs = dir.join("snarkjs");
+        write_script(
+            &snarkjs,
+            &format!(
+                "if [ \"$(cat \"$5\")\" = \"$(cat {expected})\" ]; \
+                 then echo '[INFO]  snarkJS: OK!'; \
+                 else echo '[ERROR] snarkJS: Invalid proof'; exit 1; fi",
+                expected = expected.display()
+            ),
+        );
+
+        let output =
+            generate_proof_with_rapidsnark("stub", &serde_json::json!({}), wasm, zkey, rapidsnark)
+                .await
+                .expect("stub proof");
+        assert!(verify_proof_with_snarkjs(&vk, &output, &snarkjs)
+            .await
+            .expect("verify"));
+
+        let tampered = good_proof.replace("\"1\"", "\"2\"");
+        std::fs::write(&output.proof_path, tampered).expect("tamper");
+        assert!(!verify_proof_with_snarkjs(&vk, &output, &snarkjs)
+            .await
+            .expect("verify tampered"));
+
+        let err = verify_proof_with_snarkjs(&vk, &output, &dir.join("missing-snarkjs"))
+            .await
+            .expect_err("missing binary");
+        assert!(matches!(err, ProverError::Verifier(_)));
+        drop(output);
     }
 }
@@ -1,6 +1,11 @@
 //! Helper types for Starknet calldata.
 
-#[derive(Debug, Clone)]
+use num_bigint::BigUint;
+
+use crate::error::ProverError;
+use crate::garaga_converter::stark_field_modulus;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ProofCalldata {
     pub full_proof: Vec<String>,
 }
@@ -35,6 +40,42 @@ impl ProofCalldata {
         calldata.extend(self.full_proof.clone());
         calldata
     }
+
+    // Inverse of `to_calldata`: expects the leading length token and keeps the body verbatim.
+    pub fn from_calldata(values: &[String]) -> Result<ProofCalldata, ProverError> {
+        let (len_token, body) = values
+            .split_first()
+            .ok_or_else(|| ProverError::InvalidInput("calldata is empty".to_string()))?;
+        let len = parse_len_token(Some(len_token)).ok_or_else(|| {
+            ProverError::InvalidInput(format!("invalid calldata length token {len_token}"))
+        })?;
+        if len != body.len() {
+            return Err(ProverError::InvalidInput(format!(
+                "calldata length token {len} does not match {} values",
+                body.len()
+            )));
+        }
+        for (idx, value) in body.iter().enumerate() {
+            if !is_felt(value) {
+                return Err(ProverError::InvalidInput(format!(
+                    "calldata[{}] is not a felt: {value}",
+                    idx + 1
+                )));
+            }
+        }
+        Ok(ProofCalldata::new(body.to_vec()))
+    }
+}
+
+fn is_felt(value: &str) -> bool {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16),
+        None => BigUint::parse_bytes(value.as_bytes(), 10),
+    };
+    match (parsed, stark_field_modulus()) {
+        (Some(value), Ok(modulus)) => value < modulus,
+        _ => false,
+    }
 }
 
 fn parse_len_token(token: Option<&String>) -> Option<usize> {
@@ -45,3 +86,35 @@ fn parse_len_token(token: Option<&String>) -> Option<usize> {
         token.parse::<usize>().ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ProofCalldata;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn from_calldata_round_trips() {
+        let proof = ProofCalldata::new(strings(&["0x1", "42", "0xdeadbeef"]));
+        let calldata = proof.to_calldata();
+        assert_eq!(calldata[0], "0x3");
+        assert_eq!(ProofCalldata::from_calldata(&calldata).unwrap(), proof);
+
+        let mut prefixed = ProofCalldata::new(strings(&["0x1", "0x2"]));
+        prefixed.prepend_tokens(&strings(&["0x2"]));
+        let restored = ProofCalldata::from_calldata(&prefixed.to_calldata()).unwrap();
+        assert_eq!(restored.to_calldata(), prefixed.to_calldata());
+    }
+
+    #[test]
+    fn from_calldata_rejects_malformed_input() {
+        assert!(ProofCalldata::from_calldata(&[]).is_err());
+        assert!(ProofCalldata::from_calldata(&strings(&["0x3", "0x1", "0x2"])).is_err());
+        assert!(ProofCalldata::from_calldata(&strings(&["zz", "0x1"])).is_err());
+        assert!(ProofCalldata::from_calldata(&strings(&["0x1", "0xnothex"])).is_err());
+        let too_big = format!("0x{}", "f".repeat(64));
+        assert!(ProofCalldata::from_calldata(&strings(&["0x1", &too_big])).is_err());
+    }
+}
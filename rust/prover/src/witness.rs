@@ -1,9 +1,11 @@
 //! Witness input formatting for Circom circuits.
 
 use std::collections::HashMap;
+use std::io::Write;
 
 use num_bigint::BigUint;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use starknet::core::types::U256;
 
 use crate::error::ProverError;
@@ -28,6 +30,182 @@ pub enum WitnessValue {
     Raw(Value),
 }
 
+// The `WitnessValue` variant a circuit input is expected to arrive as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WitnessKind {
+    Scalar,
+    U128,
+    I32,
+    Bool,
+    Bytes32,
+    U256,
+    VecU128,
+    VecI32,
+    VecBool,
+    VecBytes32,
+    VecU256,
+    MatrixU128,
+    MatrixU256,
+    TensorU128,
+    TensorU256,
+    Raw,
+}
+
+impl WitnessValue {
+    pub fn kind(&self) -> WitnessKind {
+        match self {
+            WitnessValue::Scalar(_) => WitnessKind::Scalar,
+            WitnessValue::U128(_) => WitnessKind::U128,
+            WitnessValue::I32(_) => WitnessKind::I32,
+            WitnessValue::Bool(_) => WitnessKind::Bool,
+            WitnessValue::Bytes32(_) => WitnessKind::Bytes32,
+            WitnessValue::U256(_) => WitnessKind::U256,
+            WitnessValue::VecU128(_) => WitnessKind::VecU128,
+            WitnessValue::VecI32(_) => WitnessKind::VecI32,
+            WitnessValue::VecBool(_) => WitnessKind::VecBool,
+            WitnessValue::VecBytes32(_) => WitnessKind::VecBytes32,
+            WitnessValue::VecU256(_) => WitnessKind::VecU256,
+            WitnessValue::MatrixU128(_) => WitnessKind::MatrixU128,
+            WitnessValue::MatrixU256(_) => WitnessKind::MatrixU256,
+            WitnessValue::TensorU128(_) => WitnessKind::TensorU128,
+            WitnessValue::TensorU256(_) => WitnessKind::TensorU256,
+            WitnessValue::Raw(_) => WitnessKind::Raw,
+        }
+    }
+}
+
+// Required inputs per circuit, mirroring the `signal input`s in circuits/*.circom.
+const DEPOSIT_INPUTS: &[(&str, WitnessKind)] = &[
+    ("tag", WitnessKind::Scalar),
+    ("commitment", WitnessKind::Scalar),
+    ("amount", WitnessKind::U128),
+    ("token_id", WitnessKind::U128),
+    ("secret", WitnessKind::Scalar),
+    ("nullifier_seed", WitnessKind::Scalar),
+];
+
+const WITHDRAW_INPUTS: &[(&str, WitnessKind)] = &[
+    ("tag", WitnessKind::Scalar),
+    ("commitment", WitnessKind::Scalar),
+    ("nullifier", WitnessKind::Scalar),
+    ("amount", WitnessKind::U128),
+    ("token_id", WitnessKind::U128),
+    ("recipient", WitnessKind::Scalar),
+    ("secret", WitnessKind::Scalar),
+    ("nullifier_seed", WitnessKind::Scalar),
+];
+
+// Shared by the exact-in and exact-out swap circuits.
+const SWAP_INPUTS: &[(&str, WitnessKind)] = &[
+    ("tag", WitnessKind::Scalar),
+    ("merkle_root", WitnessKind::Scalar),
+    ("nullifier", WitnessKind::Scalar),
+    ("sqrt_price_start", WitnessKind::Scalar),
+    ("sqrt_price_end_public", WitnessKind::Scalar),
+    ("liquidity_before", WitnessKind::U128),
+    ("fee", WitnessKind::U128),
+    ("fee_growth_global_0_before", WitnessKind::Scalar),
+    ("fee_growth_global_1_before", WitnessKind::Scalar),
+    ("output_commitment", WitnessKind::Scalar),
+    ("change_commitment", WitnessKind::Scalar),
+    ("is_limited", WitnessKind::Bool),
+    ("zero_for_one", WitnessKind::Bool),
+    ("step_sqrt_price_next", WitnessKind::Raw),
+    ("step_sqrt_price_limit", WitnessKind::Raw),
+    ("step_tick_next", WitnessKind::VecI32),
+    ("step_liquidity_net", WitnessKind::Raw),
+    ("step_fee_growth_global_0", WitnessKind::Raw),
+    ("step_fee_growth_global_1", WitnessKind::Raw),
+    ("commitment_in", WitnessKind::Scalar),
+    ("token_id_in", WitnessKind::U128),
+    ("note_count", WitnessKind::U128),
+    ("nullifier_extra", WitnessKind::Raw),
+    ("commitment_extra", WitnessKind::Raw),
+    ("step_amount_in", WitnessKind::VecU128),
+    ("step_amount_out", WitnessKind::VecU128),
+    ("step_amount_before_fee_div_q", WitnessKind::MatrixU128),
+    ("step_amount0_limit_div_q", WitnessKind::TensorU128),
+    ("step_amount0_calc_div_q", WitnessKind::TensorU128),
+    ("step_amount0_out_div_q", WitnessKind::TensorU128),
+    ("step_next0_div_ceil_q", WitnessKind::MatrixU128),
+    ("step_next1_div_floor_q", WitnessKind::MatrixU128),
+    ("step_fee_div_q", WitnessKind::MatrixU128),
+    ("note_amount_in", WitnessKind::VecU128),
+    ("secret_in", WitnessKind::VecBytes32),
+    ("nullifier_seed_in", WitnessKind::VecBytes32),
+    ("secret_out", WitnessKind::Bytes32),
+    ("nullifier_seed_out", WitnessKind::Bytes32),
+    ("change_secret", WitnessKind::Bytes32),
+    ("change_nullifier_seed", WitnessKind::Bytes32),
+    ("tick_spacing", WitnessKind::U128),
+];
+
+const SWAP_EXACT_IN_INPUTS: &[(&str, WitnessKind)] =
+    &[("step_next0_div_floor_q", WitnessKind::MatrixU128)];
+
+// One liquidity circuit serves add, remove and claim.
+const LP_INPUTS: &[(&str, WitnessKind)] = &[
+    ("tag", WitnessKind::Scalar),
+    ("merkle_root_token0", WitnessKind::Scalar),
+    ("merkle_root_token1", WitnessKind::Scalar),
+    ("merkle_root_position", WitnessKind::Scalar),
+    ("nullifier_position", WitnessKind::Scalar),
+    ("sqrt_price_start", WitnessKind::Scalar),
+    ("tick_start", WitnessKind::I32),
+    ("tick_lower", WitnessKind::I32),
+    ("tick_upper", WitnessKind::I32),
+    ("sqrt_ratio_lower", WitnessKind::Scalar),
+    ("sqrt_ratio_upper", WitnessKind::Scalar),
+    ("liquidity_before", WitnessKind::U128),
+    ("liquidity_delta", WitnessKind::Scalar),
+    ("fee", WitnessKind::U128),
+    ("fee_growth_global_0_before", WitnessKind::Scalar),
+    ("fee_growth_global_1_before", WitnessKind::Scalar),
+    ("fee_growth_global_0", WitnessKind::Scalar),
+    ("fee_growth_global_1", WitnessKind::Scalar),
+    ("prev_position_commitment", WitnessKind::Scalar),
+    ("new_position_commitment", WitnessKind::Scalar),
+    ("liquidity_commitment", WitnessKind::Scalar),
+    ("fee_growth_inside_0_before", WitnessKind::Scalar),
+    ("fee_growth_inside_1_before", WitnessKind::Scalar),
+    ("fee_growth_inside_0_after", WitnessKind::Scalar),
+    ("fee_growth_inside_1_after", WitnessKind::Scalar),
+    ("input_commitment_token0", WitnessKind::Scalar),
+    ("input_commitment_token1", WitnessKind::Scalar),
+    ("nullifier_token0", WitnessKind::Scalar),
+    ("nullifier_token1", WitnessKind::Scalar),
+    ("output_commitment_token0", WitnessKind::Scalar),
+    ("output_commitment_token1", WitnessKind::Scalar),
+    ("protocol_fee_0", WitnessKind::U128),
+    ("protocol_fee_1", WitnessKind::U128),
+    ("token0_note_count", WitnessKind::U128),
+    ("token1_note_count", WitnessKind::U128),
+    ("nullifier_token0_extra", WitnessKind::Raw),
+    ("nullifier_token1_extra", WitnessKind::Raw),
+    ("input_commitment_token0_extra", WitnessKind::Raw),
+    ("input_commitment_token1_extra", WitnessKind::Raw),
+    ("token0_note_amount", WitnessKind::VecU128),
+    ("token0_note_secret", WitnessKind::VecBytes32),
+    ("token0_note_nullifier_seed", WitnessKind::VecBytes32),
+    ("token1_note_amount", WitnessKind::VecU128),
+    ("token1_note_secret", WitnessKind::VecBytes32),
+    ("token1_note_nullifier_seed", WitnessKind::VecBytes32),
+    ("position_liquidity", WitnessKind::U128),
+    ("position_secret_in", WitnessKind::Bytes32),
+    ("position_nullifier_seed_in", WitnessKind::Bytes32),
+    ("position_secret_out", WitnessKind::Bytes32),
+    ("position_nullifier_seed_out", WitnessKind::Bytes32),
+    ("out_token0_secret", WitnessKind::Bytes32),
+    ("out_token0_nullifier_seed", WitnessKind::Bytes32),
+    ("out_token1_secret", WitnessKind::Bytes32),
+    ("out_token1_nullifier_seed", WitnessKind::Bytes32),
+    ("tick_spacing", WitnessKind::U128),
+    ("amount0_below_div_q", WitnessKind::MatrixU128),
+    ("amount0_inside_div_q", WitnessKind::MatrixU128),
+    ("tick_lower_inv_div_q", WitnessKind::VecU128),
+    ("tick_upper_inv_div_q", WitnessKind::VecU128),
+];
+
 #[derive(Debug, Clone, Default)]
 pub struct LpWitnessInputs {
     pub values: HashMap<String, WitnessValue>,
@@ -48,6 +226,70 @@ pub struct WithdrawWitnessInputs {
     pub values: HashMap<String, WitnessValue>,
 }
 
+impl LpWitnessInputs {
+    pub fn validate(&self) -> Result<(), ProverError> {
+        validate_witness_values(&self.values, LP_INPUTS)
+    }
+
+    pub fn canonical_hash(&self) -> [u8; 32] {
+        canonical_witness_hash("liquidity", &self.values)
+    }
+}
+
+impl SwapWitnessInputs {
+    pub fn validate(&self, exact_out: bool) -> Result<(), ProverError> {
+        validate_witness_values(&self.values, SWAP_INPUTS)?;
+        if !exact_out {
+            validate_witness_values(&self.values, SWAP_EXACT_IN_INPUTS)?;
+        }
+        Ok(())
+    }
+
+    pub fn canonical_hash(&self) -> [u8; 32] {
+        canonical_witness_hash("swap", &self.values)
+    }
+}
+
+impl DepositWitnessInputs {
+    pub fn validate(&self) -> Result<(), ProverError> {
+        validate_witness_values(&self.values, DEPOSIT_INPUTS)
+    }
+
+    pub fn canonical_hash(&self) -> [u8; 32] {
+        canonical_witness_hash("deposit", &self.values)
+    }
+}
+
+impl WithdrawWitnessInputs {
+    pub fn validate(&self) -> Result<(), ProverError> {
+        validate_witness_values(&self.values, WITHDRAW_INPUTS)
+    }
+
+    pub fn canonical_hash(&self) -> [u8; 32] {
+        canonical_witness_hash("withdraw", &self.values)
+    }
+}
+
+// Reports the first required input that is absent or of the wrong kind, so a dropped key fails
+// before the witness generator runs rather than as an opaque calculation error.
+fn validate_witness_values(
+    values: &HashMap<String, WitnessValue>,
+    required: &[(&str, WitnessKind)],
+) -> Result<(), ProverError> {
+    for (name, expected) in required {
+        let value = values
+            .get(*name)
+            .ok_or_else(|| ProverError::MissingWitnessInput(name.to_string()))?;
+        if value.kind() != *expected {
+            return Err(ProverError::InvalidWitnessInput(format!(
+                "{name}: expected {expected:?}, got {:?}",
+                value.kind()
+            )));
+        }
+    }
+    Ok(())
+}
+
 pub fn generate_swap_witness_inputs(inputs: SwapWitnessInputs) -> Result<Value, ProverError> {
     build_witness_from_values(inputs.values)
 }
@@ -74,18 +316,71 @@ pub fn generate_withdraw_witness_inputs(
     build_witness_from_values(inputs.values)
 }
 
+/// streams the swap witness json to `writer`; same bytes as serializing the in-memory form.
+pub fn write_swap_witness_inputs<W: Write>(
+    inputs: SwapWitnessInputs,
+    writer: W,
+) -> Result<(), ProverError> {
+    write_witness_values(inputs.values, writer)
+}
+
+/// streams a liquidity (add/remove/claim) witness json to `writer`.
+pub fn write_lp_witness_inputs<W: Write>(
+    inputs: LpWitnessInputs,
+    writer: W,
+) -> Result<(), ProverError> {
+    write_witness_values(inputs.values, writer)
+}
+
 fn build_witness_from_map(inputs: LpWitnessInputs) -> Result<Value, ProverError> {
     build_witness_from_values(inputs.values)
 }
 
 fn build_witness_from_values(values: HashMap<String, WitnessValue>) -> Result<Value, ProverError> {
     let mut map = serde_json::Map::new();
-    for (key, value) in values {
+    for (key, value) in sorted_entries(values) {
         map.insert(key, witness_value_to_json(value)?);
     }
     Ok(Value::Object(map))
 }
 
+// Only one field is converted to json at a time; keys are sorted so the output is stable.
+fn write_witness_values<W: Write>(
+    values: HashMap<String, WitnessValue>,
+    mut writer: W,
+) -> Result<(), ProverError> {
+    writer.write_all(b"{")?;
+    for (idx, (key, value)) in sorted_entries(values).into_iter().enumerate() {
+        if idx > 0 {
+            writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut writer, &key)?;
+        writer.write_all(b":")?;
+        serde_json::to_writer(&mut writer, &witness_value_to_json(value)?)?;
+    }
+    writer.write_all(b"}")?;
+    writer.flush()?;
+    Ok(())
+}
+
+// Sha256 over the circuit family and the exact witness json the prover would write. Keys are
+// sorted there, so the hash is independent of map order and equal hashes mean the circuit
+// receives identical inputs, which makes it usable as a proof cache key.
+fn canonical_witness_hash(circuit: &str, values: &HashMap<String, WitnessValue>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(circuit.as_bytes());
+    hasher.update([0u8]);
+    write_witness_values(values.clone(), &mut hasher)
+        .expect("writing witness json into a hasher cannot fail");
+    hasher.finalize().into()
+}
+
+fn sorted_entries(values: HashMap<String, WitnessValue>) -> Vec<(String, WitnessValue)> {
+    let mut entries: Vec<_> = values.into_iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries
+}
+
 fn witness_value_to_json(value: WitnessValue) -> Result<Value, ProverError> {
     match value {
         WitnessValue::Scalar(value) => Ok(Value::String(value)),
@@ -248,3 +543,134 @@ fn u256_to_limbs(value: U256) -> [String; 4] {
         high_limbs[1].clone(),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streamed_witness_matches_in_memory_json() {
+        let mut values = HashMap::new();
+        values.insert("tick".to_string(), WitnessValue::I32(-3));
+        values.insert("amount".to_string(), WitnessValue::U128(42));
+        values.insert(
+            "sqrt_prices".to_string(),
+            WitnessValue::VecU256(vec![U256::from(7u8), U256::from_words(1, 2)]),
+        );
+        values.insert(
+            "flags".to_string(),
+            WitnessValue::VecBool(vec![true, false]),
+        );
+        values.insert(
+            "raw".to_string(),
+            WitnessValue::Raw(serde_json::json!({"b": ["1"], "a": "2"})),
+        );
+        let inputs = LpWitnessInputs { values };
+
+        let in_memory =
+            serde_json::to_vec(&generate_lp_add_witness_inputs(inputs.clone()).expect("json"))
+                .expect("bytes");
+        let path =
+            std::env::temp_dir().join(format!("zylith_witness_stream_{}.json", std::process::id()));
+        let file = std::fs::File::create(&path).expect("create");
+        write_lp_witness_inputs(inputs, std::io::BufWriter::new(file)).expect("stream");
+        let on_disk = std::fs::read(&path).expect("read");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(on_disk, in_memory);
+    }
+
+    fn complete_inputs(schema: &[(&str, WitnessKind)]) -> HashMap<String, WitnessValue> {
+        schema
+            .iter()
+            .map(|(name, kind)| {
+                let value = match kind {
+                    WitnessKind::Scalar => WitnessValue::Scalar("1".to_string()),
+                    WitnessKind::U128 => WitnessValue::U128(1),
+                    WitnessKind::I32 => WitnessValue::I32(1),
+                    WitnessKind::Bool => WitnessValue::Bool(true),
+                    WitnessKind::Bytes32 => WitnessValue::Bytes32([1u8; 32]),
+                    WitnessKind::VecU128 => WitnessValue::VecU128(vec![1]),
+                    WitnessKind::VecI32 => WitnessValue::VecI32(vec![1]),
+                    WitnessKind::VecBytes32 => WitnessValue::VecBytes32(vec![[1u8; 32]]),
+                    WitnessKind::MatrixU128 => WitnessValue::MatrixU128(vec![vec![1]]),
+                    WitnessKind::TensorU128 => WitnessValue::TensorU128(vec![vec![vec![1]]]),
+                    _ => WitnessValue::Raw(serde_json::json!(["1"])),
+                };
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn incomplete_witness_names_the_missing_input() {
+        let mut values = complete_inputs(DEPOSIT_INPUTS);
+        assert!(DepositWitnessInputs {
+            values: values.clone()
+        }
+        .validate()
+        .is_ok());
+
+        values.remove("nullifier_seed");
+        let err = DepositWitnessInputs { values }
+            .validate()
+            .expect_err("missing input");
+        assert!(matches!(err, ProverError::MissingWitnessInput(name) if name == "nullifier_seed"));
+
+        let mut values = complete_inputs(WITHDRAW_INPUTS);
+        values.insert("amount".to_string(), WitnessValue::Scalar("5".to_string()));
+        let err = WithdrawWitnessInputs { values }
+            .validate()
+            .expect_err("mistyped input");
+        assert!(matches!(err, ProverError::InvalidWitnessInput(msg) if msg.starts_with("amount:")));
+    }
+
+    #[test]
+    fn exact_out_swap_witness_omits_floor_quotients() {
+        let inputs = SwapWitnessInputs {
+            values: complete_inputs(SWAP_INPUTS),
+        };
+        assert!(inputs.validate(true).is_ok());
+        match inputs.validate(false) {
+            Err(ProverError::MissingWitnessInput(name)) => {
+                assert_eq!(name, "step_next0_div_floor_q")
+            }
+            other => panic!("expected missing exact-in input, got {other:?}"),
+        }
+
+        let mut values = complete_inputs(LP_INPUTS);
+        values.remove("tick_upper_inv_div_q");
+        assert!(LpWitnessInputs { values }.validate().is_err());
+    }
+
+    #[test]
+    fn canonical_hash_ignores_insertion_order() {
+        let entries = [
+            ("tick", WitnessValue::I32(-3)),
+            ("amount", WitnessValue::U128(42)),
+            ("secret", WitnessValue::Bytes32([7u8; 32])),
+            ("steps", WitnessValue::VecU128(vec![1, 2, 3])),
+        ];
+        let forward = SwapWitnessInputs {
+            values: entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        };
+        let mut reversed = SwapWitnessInputs::default();
+        for (key, value) in entries.iter().rev() {
+            reversed.values.insert(key.to_string(), value.clone());
+        }
+        assert_eq!(forward.canonical_hash(), reversed.canonical_hash());
+
+        let mut changed = reversed.clone();
+        changed
+            .values
+            .insert("amount".to_string(), WitnessValue::U128(43));
+        assert_ne!(forward.canonical_hash(), changed.canonical_hash());
+
+        let lp = LpWitnessInputs {
+            values: forward.values.clone(),
+        };
+        assert_ne!(forward.canonical_hash(), lp.canonical_hash());
+    }
+}